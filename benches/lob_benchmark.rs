@@ -1,5 +1,16 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use lob::{Order, OrderBook, OrderSide};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use lob::{
+    DenseLevels, HashedLevels, LevelStore, Oid, Order, OrderBook, OrderSide, Price, SortedLevels,
+    Timestamp, Volume,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const MID_PRICE: f64 = 100.0;
+const TICK_SIZE: f64 = 0.01;
+const PRICE_LEVELS: usize = 500;
+const RESTING_ORDERS: u64 = 10_000;
+const OPERATIONS: usize = 10_000;
 
 // create num_orders orders
 // buy orders will have even ids, sell orders will have odd ids
@@ -55,36 +66,36 @@ fn setup_orders(num_orders: u64) -> Vec<Order> {
     // create orders that will be matched with the stable list of orders
     // and the result should be empty order book
 
-    // let mut id = num_orders + 1;
-
-    // let buy_volume = orders
-    //     .iter()
-    //     .filter(|o| o.side == OrderSide::Buy)
-    //     .map(|o| o.volume)
-    //     .sum::<Volume>();
-    // let sell_volume = orders
-    //     .iter()
-    //     .filter(|o| o.side == OrderSide::Sell)
-    //     .map(|o| o.volume)
-    //     .sum::<Volume>();
-
-    // // add sell market order that will be matched with all buy orders
-    // orders.push(Order::new_market(
-    //     black_box(id.into()),
-    //     black_box(OrderSide::Sell),
-    //     black_box(chrono::Utc::now().into()),
-    //     black_box(buy_volume),
-    // ));
-
-    // id += 1;
-
-    // // add buy market order that will be matched with all sell orders
-    // orders.push(Order::new_market(
-    //     black_box(id.into()),
-    //     black_box(OrderSide::Buy),
-    //     black_box(chrono::Utc::now().into()),
-    //     black_box(sell_volume),
-    // ));
+    let mut id = num_orders + 1;
+
+    let buy_volume = orders
+        .iter()
+        .filter(|o| o.side == OrderSide::Buy)
+        .map(|o| o.volume)
+        .sum::<Volume>();
+    let sell_volume = orders
+        .iter()
+        .filter(|o| o.side == OrderSide::Sell)
+        .map(|o| o.volume)
+        .sum::<Volume>();
+
+    // add sell market order that will be matched with all buy orders
+    orders.push(Order::new_market(
+        black_box(id.into()),
+        black_box(OrderSide::Sell),
+        black_box(chrono::Utc::now().into()),
+        black_box(buy_volume),
+    ));
+
+    id += 1;
+
+    // add buy market order that will be matched with all sell orders
+    orders.push(Order::new_market(
+        black_box(id.into()),
+        black_box(OrderSide::Buy),
+        black_box(chrono::Utc::now().into()),
+        black_box(sell_volume),
+    ));
 
     orders
 }
@@ -95,12 +106,361 @@ fn bench_order_matching(c: &mut Criterion) {
         b.iter(|| {
             let mut order_book = OrderBook::default();
             for order in orders.iter() {
-                order_book.add_order(order.try_into().unwrap());
-                let _ = order_book.find_and_fill_best_orders();
+                let _ = order_book.execute(order);
+            }
+        })
+    });
+}
+
+fn bench_add_orders(c: &mut Criterion) {
+    // resting orders only, market orders at the end are not added
+    let mut orders = setup_orders(10000);
+    orders.truncate(10000);
+    c.bench_function("add_orders_batch", |b| {
+        b.iter(|| {
+            let mut order_book = OrderBook::default();
+            order_book.add_orders(orders.iter().cloned());
+        })
+    });
+}
+
+/// distance from the mid price in ticks, near levels are far more likely than distant ones
+/// like in real books, weight of the level k is 1/k^s
+struct Zipf {
+    cumulative: Vec<f64>,
+}
+
+impl Zipf {
+    fn new(levels: usize, exponent: f64) -> Self {
+        let mut total = 0.0;
+        let cumulative = (1..=levels)
+            .map(|k| {
+                total += 1.0 / (k as f64).powf(exponent);
+                total
+            })
+            .collect();
+        Zipf { cumulative }
+    }
+
+    /// level from 1 to the number of levels
+    fn sample(&self, rng: &mut StdRng) -> usize {
+        let total = self.cumulative.last().copied().unwrap_or_default();
+        let point = rng.gen::<f64>() * total;
+        self.cumulative.partition_point(|weight| *weight < point) + 1
+    }
+}
+
+/// operation of the mixed workloads
+enum Operation {
+    Add(Order),
+    Cancel(Oid),
+    Amend(Oid, Price, Volume),
+}
+
+struct Workload {
+    rng: StdRng,
+    zipf: Zipf,
+    next_id: u64,
+}
+
+impl Workload {
+    fn new() -> Self {
+        Workload {
+            rng: StdRng::seed_from_u64(42),
+            zipf: Zipf::new(PRICE_LEVELS, 1.1),
+            next_id: 0,
+        }
+    }
+
+    fn price(&mut self, side: OrderSide) -> Price {
+        let ticks = self.zipf.sample(&mut self.rng) as f64 * TICK_SIZE;
+        match side {
+            OrderSide::Buy => Price::new(MID_PRICE - ticks),
+            OrderSide::Sell => Price::new(MID_PRICE + ticks),
+        }
+    }
+
+    /// limit order that does not cross the mid price
+    fn passive_order(&mut self) -> Order {
+        self.next_id += 1;
+        let side = if self.rng.gen_bool(0.5) {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        };
+        let price = self.price(side);
+        let volume = self.rng.gen_range(1..=100);
+        Order::new_limit(
+            Oid::new(self.next_id),
+            side,
+            Timestamp::new(self.next_id),
+            price,
+            volume.into(),
+        )
+    }
+
+    fn passive_orders(&mut self, count: u64) -> Vec<Order> {
+        (0..count).map(|_| self.passive_order()).collect()
+    }
+
+    /// adds and operations on the resting orders, operations are given as the share of all
+    fn operations(
+        &mut self,
+        resting: &[Order],
+        operation: fn(&mut Self, Oid, OrderSide) -> Operation,
+        share: f64,
+    ) -> Vec<Operation> {
+        let mut live = resting
+            .iter()
+            .map(|order| (order.id, order.side))
+            .collect::<Vec<_>>();
+        (0..OPERATIONS)
+            .map(|_| {
+                if live.is_empty() || !self.rng.gen_bool(share) {
+                    let order = self.passive_order();
+                    live.push((order.id, order.side));
+                    return Operation::Add(order);
+                }
+                let index = self.rng.gen_range(0..live.len());
+                let (order_id, side) = live.swap_remove(index);
+                operation(self, order_id, side)
+            })
+            .collect()
+    }
+
+    fn cancel(&mut self, order_id: Oid, _side: OrderSide) -> Operation {
+        Operation::Cancel(order_id)
+    }
+
+    /// amended order is moved to a new level, it is not picked again
+    fn amend(&mut self, order_id: Oid, side: OrderSide) -> Operation {
+        let volume = self.rng.gen_range(1..=100);
+        Operation::Amend(order_id, self.price(side), volume.into())
+    }
+}
+
+fn resting_book(orders: &[Order]) -> OrderBook {
+    fill(OrderBook::empty(), orders)
+}
+
+fn fill<S: LevelStore>(mut order_book: OrderBook<S>, orders: &[Order]) -> OrderBook<S> {
+    for order in orders {
+        let _ = order_book.execute(order);
+    }
+    order_book
+}
+
+fn apply<S: LevelStore>(order_book: &mut OrderBook<S>, operations: &[Operation]) {
+    for operation in operations {
+        match operation {
+            Operation::Add(order) => {
+                let _ = black_box(order_book.execute(order));
+            }
+            Operation::Cancel(order_id) => {
+                let _ = black_box(order_book.cancel_order(*order_id));
             }
+            Operation::Amend(order_id, price, volume) => {
+                let _ = black_box(order_book.amend_order(*order_id, *price, *volume));
+            }
+        }
+    }
+}
+
+fn bench_add_only(c: &mut Criterion) {
+    let orders = Workload::new().passive_orders(RESTING_ORDERS);
+    let mut group = c.benchmark_group("add_only");
+    group.bench_function("execute_passive", |b| b.iter(|| resting_book(&orders)));
+    group.bench_function("add_orders_batch", |b| {
+        b.iter(|| {
+            let mut order_book = OrderBook::default();
+            order_book.add_orders(orders.iter().cloned());
+            order_book
+        })
+    });
+    group.finish();
+}
+
+fn bench_mixed_workloads(c: &mut Criterion) {
+    let mut workload = Workload::new();
+    let resting = workload.passive_orders(RESTING_ORDERS);
+    let cancels = workload.operations(&resting, Workload::cancel, 0.9);
+    let amends = workload.operations(&resting, Workload::amend, 0.9);
+
+    let mut group = c.benchmark_group("mixed");
+    for (name, operations) in [("cancel_heavy", &cancels), ("amend_heavy", &amends)] {
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                || resting_book(&resting),
+                |order_book| apply(order_book, operations),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_market_sweep(c: &mut Criterion) {
+    let resting = Workload::new().passive_orders(RESTING_ORDERS);
+    let book = resting_book(&resting);
+    let mut group = c.benchmark_group("market_sweep");
+    for levels in [1, 10, 100] {
+        let volume = book
+            .depth(levels)
+            .asks
+            .iter()
+            .map(|level| level.volume)
+            .sum::<Volume>();
+        let order = Order::new_market(
+            Oid::new(RESTING_ORDERS + 1),
+            OrderSide::Buy,
+            Timestamp::new(RESTING_ORDERS + 1),
+            volume,
+        );
+        group.bench_function(format!("{levels}_levels"), |b| {
+            b.iter_batched_ref(
+                || resting_book(&resting),
+                |order_book| black_box(order_book.execute(&order)),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_snapshot(c: &mut Criterion) {
+    let book = resting_book(&Workload::new().passive_orders(RESTING_ORDERS));
+    let bytes = book.snapshot();
+    let mut group = c.benchmark_group("snapshot");
+    group.bench_function("book_snapshot", |b| {
+        b.iter(|| black_box(book.book_snapshot()))
+    });
+    group.bench_function("encode", |b| b.iter(|| black_box(book.snapshot())));
+    group.bench_function("restore", |b| {
+        b.iter(|| black_box(OrderBook::restore(&bytes).unwrap()))
+    });
+    group.finish();
+}
+
+fn bench_depth_query(c: &mut Criterion) {
+    let book = resting_book(&Workload::new().passive_orders(RESTING_ORDERS));
+    let mut group = c.benchmark_group("depth_query");
+    group.bench_function("best_bid_ask", |b| {
+        b.iter(|| black_box(book.best_bid_ask()))
+    });
+    for levels in [5, 20] {
+        group.bench_function(format!("depth_{levels}"), |b| {
+            b.iter(|| black_box(book.depth(levels)))
+        });
+    }
+    group.bench_function("volume_at_limit", |b| {
+        b.iter(|| {
+            black_box(book.get_volume_at_limit(Price::new(MID_PRICE - TICK_SIZE), OrderSide::Buy))
         })
     });
+    group.finish();
+}
+
+// same workloads on each level store of the book
+fn bench_level_stores(c: &mut Criterion) {
+    bench_level_store(c, "hashed", HashedLevels::default());
+    bench_level_store(c, "sorted", SortedLevels::default());
+    // range covers every price of the workload
+    let ticks = PRICE_LEVELS as f64 * TICK_SIZE;
+    let dense = DenseLevels::new(
+        Price::new(MID_PRICE - ticks),
+        Price::new(MID_PRICE + ticks),
+        Price::new(TICK_SIZE),
+    );
+    bench_level_store(c, "dense", dense);
+}
+
+fn bench_level_store<S: LevelStore>(c: &mut Criterion, name: &str, store: S) {
+    let book = || OrderBook::<S>::empty().with_level_store(store.clone());
+    let mut workload = Workload::new();
+    let resting = workload.passive_orders(RESTING_ORDERS);
+    let cancels = workload.operations(&resting, Workload::cancel, 0.9);
+    let volume = resting
+        .iter()
+        .filter(|order| order.side == OrderSide::Sell)
+        .map(|order| order.volume)
+        .sum::<Volume>();
+    let sweep = Order::new_market(
+        Oid::new(RESTING_ORDERS + 1),
+        OrderSide::Buy,
+        Timestamp::new(RESTING_ORDERS + 1),
+        volume,
+    );
+
+    let mut group = c.benchmark_group(format!("level_store_{name}"));
+    group.bench_function("add", |b| b.iter(|| fill(book(), &resting)));
+    group.bench_function("cancel_heavy", |b| {
+        b.iter_batched_ref(
+            || fill(book(), &resting),
+            |order_book| apply(order_book, &cancels),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("sweep", |b| {
+        b.iter_batched_ref(
+            || fill(book(), &resting),
+            |order_book| black_box(order_book.execute(&sweep)),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+// prices drift, so each round creates new levels and the emptied levels are compacted
+fn bench_level_churn(c: &mut Criterion) {
+    let rounds = (0..100u64)
+        .map(|round| {
+            (0..100u64)
+                .map(|offset| {
+                    let id = round * 100 + offset + 1;
+                    let ticks = (round + offset) as f64 * TICK_SIZE;
+                    Order::new_limit(
+                        Oid::new(id),
+                        OrderSide::Buy,
+                        Timestamp::new(id),
+                        Price::new(MID_PRICE - ticks),
+                        10.into(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("level_churn");
+    for pool in [0, 256] {
+        group.bench_function(format!("pool_{pool}"), |b| {
+            b.iter(|| {
+                let mut order_book = OrderBook::default().with_level_pool(pool);
+                for orders in &rounds {
+                    for order in orders {
+                        let _ = order_book.execute(order);
+                    }
+                    for order in orders {
+                        let _ = order_book.cancel_order(order.id);
+                    }
+                    order_book.compact();
+                }
+                order_book
+            })
+        });
+    }
+    group.finish();
 }
 
-criterion_group!(benches, bench_order_matching);
+criterion_group!(
+    benches,
+    bench_order_matching,
+    bench_add_orders,
+    bench_add_only,
+    bench_mixed_workloads,
+    bench_market_sweep,
+    bench_snapshot,
+    bench_depth_query,
+    bench_level_stores,
+    bench_level_churn
+);
 criterion_main!(benches);
@@ -1,23 +1,35 @@
-///! Matching engine example
-///
-/// To run the example specify the CPU id to run the matching engine on.
-/// If no cpu is specified the matching engine will run on the first available CPU.
-///
-/// ```bash
-/// RUST_LOG=info cargo run --example matching_engine -- --cpu-id 2
-/// ```
-///
+//! Matching engine example
+//!
+//! To run the example specify the CPU id to run the matching engine on.
+//! If no cpu is specified the matching engine will run on the first available CPU.
+//!
+//! ```bash
+//! RUST_LOG=info cargo run --example matching_engine -- --cpu-id 2
+//! ```
+//!
+//! With the `tracing` feature the book emits spans of the order operations and events of
+//! the matching loop, shown with the debug and trace levels
+//!
+//! ```bash
+//! RUST_LOG=lob=trace cargo run --example matching_engine --features tracing
+//! ```
+//!
+//! With the `metrics` feature counters and gauges of the book are served for Prometheus
+//! on the given port, i.e. http://localhost:9000/metrics
+//!
+//! ```bash
+//! cargo run --example matching_engine --features metrics -- --metrics-port 9000
+//! ```
+//!
 use glommio::prelude::*;
-use std::collections::VecDeque;
-use thiserror::Error;
 use tracing::info;
 
-use clap::{command, Parser};
+use clap::Parser;
 use std::sync::atomic::Ordering;
 use std::sync::{atomic::AtomicBool, LazyLock};
 use tracing_subscriber::EnvFilter;
 
-use lob::{Fill, LimitOrder, Oid, Order, OrderBook, OrderBookError, OrderType, Price, Volume};
+use lob::{Match, MatchingEngine, Oid, Order, OrderBook, OrderSide, Price, PriceBands, Timestamp};
 
 static RUNNING: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::from(true));
 
@@ -30,6 +42,30 @@ fn sig_int_handler() {
 struct Args {
     #[arg(short, long)]
     cpu_id: Option<usize>,
+    /// port of the Prometheus endpoint, requires the metrics feature
+    #[arg(short, long)]
+    metrics_port: Option<u16>,
+}
+
+#[cfg(feature = "metrics")]
+fn order_book(metrics_port: Option<u16>) -> OrderBook {
+    let Some(port) = metrics_port else {
+        return OrderBook::default();
+    };
+    metrics_exporter_prometheus::PrometheusBuilder::new()
+        .with_http_listener(([0, 0, 0, 0], port))
+        .install()
+        .expect("failed to install Prometheus exporter");
+    info!("metrics served on port {port}");
+    OrderBook::default().with_exported_metrics(&lob::Symbol::from("LOB"))
+}
+
+#[cfg(not(feature = "metrics"))]
+fn order_book(metrics_port: Option<u16>) -> OrderBook {
+    if metrics_port.is_some() {
+        info!("metrics are not exported, build with the metrics feature");
+    }
+    OrderBook::default()
 }
 
 pub fn main() -> std::io::Result<()> {
@@ -53,10 +89,51 @@ pub fn main() -> std::io::Result<()> {
 
     let cpu_placement = args.cpu_id.map_or(Placement::Unbound, Placement::Fixed);
 
+    let order_book = order_book(args.metrics_port);
+
     let builder = LocalExecutorBuilder::new(cpu_placement.clone()).name("matching-engine");
     let handle = builder.spawn(|| async move {
-        std::thread::sleep(std::time::Duration::from_secs(10));
-        info!("Done!");
+        let mut engine = MatchingEngine::new(order_book);
+        engine.set_price_bands(PriceBands::fixed(Price::MIN, Price::MAX));
+
+        let mut id = 0;
+        while RUNNING.load(Ordering::SeqCst) && id < 10 {
+            id += 1;
+            let side = if id % 2 == 0 {
+                OrderSide::Buy
+            } else {
+                OrderSide::Sell
+            };
+            let order = if id % 3 == 0 {
+                Order::new_market(Oid::new(id), side, Timestamp::new(id), 5.into())
+            } else {
+                let price = 100.0 + (id % 4) as f64;
+                Order::new_limit(
+                    Oid::new(id),
+                    side,
+                    Timestamp::new(id),
+                    price.into(),
+                    10.into(),
+                )
+            };
+            if let Err(e) = engine.place_order(order) {
+                info!("order {id} rejected: {e}");
+                continue;
+            }
+            match engine.match_all() {
+                Ok(matches) => {
+                    for m in matches {
+                        match m {
+                            Match::Market(fill) => info!("market fill: {fill:?}"),
+                            Match::Limit(fill) => info!("fill: {fill:?}"),
+                        }
+                    }
+                }
+                Err(e) => info!("matching stopped: {e}"),
+            }
+            glommio::yield_if_needed().await;
+        }
+        info!("Done! {:?}", engine.order_book().best_bid_ask());
     })?;
 
     info!("MatchingEngine running on CPU {:?}", cpu_placement);
@@ -67,175 +144,3 @@ pub fn main() -> std::io::Result<()> {
 
     Ok(())
 }
-
-#[derive(Debug, Default)]
-pub struct MatchingEngine {
-    order_book: OrderBook,
-    min_price: Price,
-    max_price: Price,
-    // queue of market orders, that should be matched first in first out
-    market_orders: VecDeque<Order>,
-}
-
-#[derive(Debug, Default)]
-pub struct Exchange {
-    matching_engine: MatchingEngine,
-}
-
-#[derive(Error, Debug)]
-pub enum ExchangeError {
-    #[error("Failed to match error: {0}")]
-    MatchingError(#[from] MatchingEngineError),
-}
-
-#[derive(Error, Debug)]
-pub enum MatchingEngineError {
-    #[error("OrderBook error: {0}")]
-    OrderBookError(#[from] OrderBookError),
-    #[error("Order price is too low")]
-    OrderPriceTooLowError(),
-    #[error("Order price is too high")]
-    OrderPriceTooHighError(),
-    #[error("Limit Order price is required")]
-    MissingPriceError(),
-    #[error("No market orders to match")]
-    NoMarketOrdersError(),
-    #[error("No orders to match")]
-    NoOrdersToMatchError(),
-}
-
-impl Exchange {
-    pub fn initialize(&mut self) {
-        self.matching_engine.set_min_price(Price::MIN);
-        self.matching_engine.set_max_price(Price::MAX);
-    }
-
-    pub fn place_order_single(&mut self, order: Order) -> Result<(), ExchangeError> {
-        // place a single order in a proper matching engine for later matching
-        self.matching_engine.place_order(order)?;
-
-        Ok(())
-    }
-}
-
-impl MatchingEngine {
-    pub fn set_min_price(&mut self, price: Price) {
-        self.min_price = price;
-    }
-
-    pub fn set_max_price(&mut self, price: Price) {
-        self.max_price = price;
-    }
-
-    pub fn has_market_orders(&self) -> bool {
-        !self.market_orders.is_empty()
-    }
-
-    pub fn place_order(&mut self, order: Order) -> Result<(), MatchingEngineError> {
-        if order.kind == OrderType::Limit {
-            if order.price.is_none() {
-                return Err(MatchingEngineError::MissingPriceError());
-            }
-            if order.price.unwrap() < self.min_price {
-                return Err(MatchingEngineError::OrderPriceTooLowError());
-            }
-            if order.price.unwrap() > self.max_price {
-                return Err(MatchingEngineError::OrderPriceTooHighError());
-            }
-            self.order_book
-                .add_order(LimitOrder::try_from(&order).unwrap());
-        } else {
-            // market order
-            self.market_orders.push_back(order);
-        }
-        Ok(())
-    }
-
-    pub fn can_match_orders(&self) -> bool {
-        let best_buy = self.order_book.get_best_buy();
-        let best_sell = self.order_book.get_best_sell();
-        match (best_buy, best_sell) {
-            (Some(buy_price), Some(sell_price)) => buy_price >= sell_price,
-            _ => false,
-        }
-    }
-
-    pub fn match_orders(&mut self) -> Result<Fill, MatchingEngineError> {
-        self.order_book
-            .find_and_fill_best_orders()
-            .map_err(|e| e.into())
-    }
-
-    // fn match_buy_side(&mut self) -> Result<Trade, PlaceOrderError> {
-    //     let trade = self.order_book.fill_buy_order(order)?;
-    //     match order.kind {
-    //         OrderType::Market => {
-    //             // we do not need to add the order to the book
-    //         }
-    //         OrderType::Limit => {
-    //             if trade.filled_volume < order.volume {
-    //                 // add the order to the book
-    //                 let limit_order = LimitOrder::try_from(order).map_err(|_| {
-    //                     PlaceOrderError::OrderCannotBePlaced("not an market order".to_string())
-    //                 })?;
-    //                 self.bids.add_order(&limit_order);
-    //                 self.orders.insert(limit_order.id, limit_order);
-    //             }
-    //         }
-    //     }
-    //     Ok(trade)
-    // }
-}
-
-impl Matching for MatchingEngine {
-    fn match_orders(&mut self) -> Vec<Trade> {
-        todo!("Implement matching engine")
-    }
-}
-
-/// Trade
-#[derive(Debug)]
-#[allow(dead_code)]
-pub struct Trade {
-    order_id: Oid,
-    volume: Volume,
-    filled_volume: Volume,
-    executions: Vec<Execution>,
-}
-
-impl Trade {
-    /// Create a new trade
-    pub fn new(order_id: Oid, volume: Volume) -> Self {
-        Trade {
-            order_id,
-            volume,
-            filled_volume: Volume::ZERO,
-            executions: Vec::new(),
-        }
-    }
-
-    /// Add an execution to the trade
-    pub fn add_execution(&mut self, execution: Execution) {
-        self.filled_volume += execution.volume;
-        self.executions.push(execution)
-    }
-}
-
-/// Execution
-#[derive(Debug, PartialEq, PartialOrd, Clone)]
-pub struct Execution {
-    order_id: Oid,
-    price: Price,
-    volume: Volume,
-}
-
-impl Execution {
-    /// Create a new execution
-    pub fn new(order_id: Oid, price: Price, volume: Volume) -> Self {
-        Execution {
-            order_id,
-            price,
-            volume,
-        }
-    }
-}
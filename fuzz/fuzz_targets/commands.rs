@@ -0,0 +1,113 @@
+//!
+//! Fuzz the book with sequences of add, cancel, amend and match commands decoded from the input,
+//! book invariants are checked after every command.
+//!
+//! ```bash
+//! cargo +nightly fuzz run commands
+//! ```
+//!
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lob::{
+    IntegrityViolation, LimitOrder, Oid, Order, OrderBook, OrderSide, Price, TimeInForce,
+    Timestamp, Volume,
+};
+
+/// every command is encoded in 4 bytes: opcode, order id, price tick and volume
+const COMMAND_LEN: usize = 4;
+
+fn side(byte: u8) -> OrderSide {
+    if byte & 1 == 0 {
+        OrderSide::Buy
+    } else {
+        OrderSide::Sell
+    }
+}
+
+/// prices on a narrow grid, so orders often cross and share levels
+fn price(byte: u8) -> Price {
+    Price::new(100.0 + (byte % 16) as f64 * 0.5)
+}
+
+fn volume(byte: u8) -> Volume {
+    Volume::new(byte as u64 % 32 + 1)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut book = OrderBook::default();
+    for (step, command) in data.chunks_exact(COMMAND_LEN).enumerate() {
+        let timestamp = Timestamp::new(step as u64);
+        // small id space, so cancels and amends hit resting orders
+        let id = Oid::new(command[1] as u64 % 64);
+        let _ = match command[0] % 8 {
+            // add without matching, may leave the book crossed until the next match
+            0 => book
+                .add_order(LimitOrder::new(
+                    id,
+                    side(command[0] >> 3),
+                    timestamp,
+                    price(command[2]),
+                    volume(command[3]),
+                ))
+                .map(|_| ()),
+            1 => book
+                .execute(&Order::new_limit(
+                    id,
+                    side(command[0] >> 3),
+                    timestamp,
+                    price(command[2]),
+                    volume(command[3]),
+                ))
+                .map(|_| ()),
+            2 => book
+                .execute(
+                    &Order::new_limit(
+                        id,
+                        side(command[0] >> 3),
+                        timestamp,
+                        price(command[2]),
+                        volume(command[3]),
+                    )
+                    .with_time_in_force(TimeInForce::ImmediateOrCancel),
+                )
+                .map(|_| ()),
+            3 => book
+                .execute(
+                    &Order::new_limit(
+                        id,
+                        side(command[0] >> 3),
+                        timestamp,
+                        price(command[2]),
+                        volume(command[3]) + volume(command[2]),
+                    )
+                    .with_display_volume(volume(command[3] / 4)),
+                )
+                .map(|_| ()),
+            4 => book
+                .execute(&Order::new_market(
+                    id,
+                    side(command[0] >> 3),
+                    timestamp,
+                    volume(command[3]),
+                ))
+                .map(|_| ()),
+            5 => book.cancel_order(id).map(|_| ()).map_err(Into::into),
+            6 => book
+                .amend_order(id, price(command[2]), volume(command[3]))
+                .map(|_| ()),
+            _ => book.find_and_fill_best_orders().map(|_| ()),
+        };
+
+        let violations = book
+            .check_integrity()
+            .violations
+            .into_iter()
+            .filter(|v| !matches!(v, IntegrityViolation::CrossedBook { .. }))
+            .collect::<Vec<_>>();
+        assert!(
+            violations.is_empty(),
+            "step {step}: {command:?} broke the book: {violations:?}"
+        );
+    }
+});
@@ -0,0 +1,224 @@
+//!
+//! Analytics computed from the levels of the book, such as mid price and microprice.
+//!
+
+use crate::{LevelStore, OrderBook, OrderSide, Price, Volume};
+
+/// price weighted by volumes, computed on the scaled integers so no precision is lost
+fn weighted_price(
+    first: Price,
+    first_volume: Volume,
+    second: Price,
+    second_volume: Volume,
+) -> Option<Price> {
+    let total = *first_volume as i128 + *second_volume as i128;
+    if total == 0 {
+        return None;
+    }
+    let weighted = first.mantissa() as i128 * *first_volume as i128
+        + second.mantissa() as i128 * *second_volume as i128;
+    Some(Price::from_mantissa((weighted / total) as i64))
+}
+
+/// Result of simulating a sweep of one side of the book
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepEstimate {
+    /// volume weighted average price of the fills
+    pub average_price: Price,
+    /// price of the last level that would be touched
+    pub worst_price: Price,
+    /// volume that could be filled, less than requested if the book is not deep enough
+    pub volume: Volume,
+    /// number of levels that would be consumed
+    pub levels: usize,
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// price half way between the best bid and ask
+    pub fn mid_price(&self) -> Option<Price> {
+        let bbo = self.best_bid_ask();
+        let (bid, ask) = (bbo.bid_price?, bbo.ask_price?);
+        weighted_price(bid, Volume::new(1), ask, Volume::new(1))
+    }
+
+    /// best bid and ask weighted by the volume at each of them
+    pub fn weighted_mid(&self) -> Option<Price> {
+        let bbo = self.best_bid_ask();
+        weighted_price(
+            bbo.bid_price?,
+            bbo.bid_volume,
+            bbo.ask_price?,
+            bbo.ask_volume,
+        )
+    }
+
+    /// best bid and ask weighted by the volume on the opposite side,
+    /// so the price leans towards the side that is more likely to be taken out next
+    pub fn microprice(&self) -> Option<Price> {
+        let bbo = self.best_bid_ask();
+        weighted_price(
+            bbo.bid_price?,
+            bbo.ask_volume,
+            bbo.ask_price?,
+            bbo.bid_volume,
+        )
+    }
+
+    /// (bid volume - ask volume) / (bid volume + ask volume) over the top levels of the book
+    /// ranges from -1 when there are only asks to 1 when there are only bids
+    pub fn imbalance(&self, levels: usize) -> Option<f64> {
+        let bid_volume = self.volume_of_top_levels(OrderSide::Buy, levels);
+        let ask_volume = self.volume_of_top_levels(OrderSide::Sell, levels);
+        let total = bid_volume + ask_volume;
+        if total.is_zero() {
+            return None;
+        }
+        Some((*bid_volume as f64 - *ask_volume as f64) / *total as f64)
+    }
+
+    fn volume_of_top_levels(&self, side: OrderSide, levels: usize) -> Volume {
+        self.iter_levels(side)
+            .take(levels)
+            .map(|level| level.total_volume())
+            .sum()
+    }
+
+    /// total volume resting on the side at the price or better
+    pub fn volume_within(&self, side: OrderSide, price: Price) -> Volume {
+        self.iter_levels(side)
+            .take_while(|level| match side {
+                OrderSide::Buy => level.price() >= price,
+                OrderSide::Sell => level.price() <= price,
+            })
+            .map(|level| level.total_volume())
+            .sum()
+    }
+
+    /// simulate an order of the side and volume sweeping the opposite side of the book
+    /// only visible volume is considered, the book is not modified
+    /// returns none if there is nothing to match against
+    pub fn estimate_fill_price(&self, side: OrderSide, volume: Volume) -> Option<SweepEstimate> {
+        let mut remaining = volume;
+        let mut notional: i128 = 0;
+        let mut levels = 0;
+        let mut worst_price = None;
+        for level in self.iter_levels(side.opposite()) {
+            if remaining.is_zero() {
+                break;
+            }
+            let fill = level.total_volume().min(remaining);
+            notional += level.price().mantissa() as i128 * *fill as i128;
+            remaining -= fill;
+            levels += 1;
+            worst_price = Some(level.price());
+        }
+        let filled = volume - remaining;
+        if filled.is_zero() {
+            return None;
+        }
+        Some(SweepEstimate {
+            average_price: Price::from_mantissa((notional / *filled as i128) as i64),
+            worst_price: worst_price?,
+            volume: filled,
+            levels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests_analytics {
+    use crate::*;
+
+    #[test]
+    fn test_mid_and_microprice() {
+        let mut order_book = OrderBook::default();
+        let bid = LimitOrder::new(
+            Oid::new(1),
+            OrderSide::Buy,
+            Timestamp::new(1),
+            10.0.into(),
+            30.into(),
+        );
+        order_book.add_order(bid).unwrap();
+        assert_eq!(order_book.mid_price(), None);
+        assert_eq!(order_book.microprice(), None);
+
+        let ask = LimitOrder::new(
+            Oid::new(2),
+            OrderSide::Sell,
+            Timestamp::new(2),
+            11.0.into(),
+            10.into(),
+        );
+        order_book.add_order(ask).unwrap();
+        assert_eq!(order_book.mid_price(), Some(Price::new(10.5)));
+        assert_eq!(order_book.weighted_mid(), Some(Price::new(10.25)));
+        assert_eq!(order_book.microprice(), Some(Price::new(10.75)));
+    }
+
+    #[test]
+    fn test_imbalance() {
+        let mut order_book = OrderBook::default();
+        assert_eq!(order_book.imbalance(5), None);
+        let orders = [
+            (1, OrderSide::Buy, 10.0, 30),
+            (2, OrderSide::Buy, 9.0, 20),
+            (3, OrderSide::Sell, 11.0, 10),
+            (4, OrderSide::Sell, 12.0, 40),
+        ];
+        for (id, side, price, volume) in orders {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        assert_eq!(order_book.imbalance(1), Some(0.5));
+        assert_eq!(order_book.imbalance(2), Some(0.0));
+    }
+
+    #[test]
+    fn test_sweep_estimate() {
+        let mut order_book = OrderBook::default();
+        let orders = [
+            (1, OrderSide::Sell, 10.0, 10),
+            (2, OrderSide::Sell, 11.0, 10),
+            (3, OrderSide::Sell, 12.0, 10),
+            (4, OrderSide::Buy, 9.0, 5),
+        ];
+        for (id, side, price, volume) in orders {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        assert_eq!(
+            order_book.volume_within(OrderSide::Sell, 11.0.into()),
+            20.into()
+        );
+        assert_eq!(
+            order_book.volume_within(OrderSide::Buy, 9.5.into()),
+            Volume::ZERO
+        );
+
+        let estimate = order_book
+            .estimate_fill_price(OrderSide::Buy, 20.into())
+            .unwrap();
+        assert_eq!(estimate.average_price, Price::new(10.5));
+        assert_eq!(estimate.worst_price, Price::new(11.0));
+        assert_eq!(estimate.levels, 2);
+
+        let estimate = order_book
+            .estimate_fill_price(OrderSide::Sell, 50.into())
+            .unwrap();
+        assert_eq!(estimate.volume, 5.into());
+        assert_eq!(order_book.get_best_sell_volume(), Some(10.into()));
+    }
+}
@@ -0,0 +1,192 @@
+//!
+//! Call auction. While the book is in the pre-open state orders accumulate on the book without
+//! matching. Uncrossing executes all crossing volume at a single equilibrium price, the price that
+//! maximizes the executable volume.
+//!
+
+use alloc::vec::Vec;
+
+use crate::{BookEvent, Fill, LevelStore, OrderBook, OrderBookError, OrderSide, Price, Volume};
+
+/// Result of uncrossing the book
+#[derive(Debug, Clone)]
+pub struct AuctionResult {
+    /// equilibrium price, all fills are executed at this price
+    pub price: Price,
+    /// total executed volume
+    pub volume: Volume,
+    pub fills: Vec<Fill>,
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// price at which the auction would uncross the book if it ended now
+    /// returns none if the book is not crossed
+    pub fn indicative_auction_price(&self) -> Option<Price> {
+        self.equilibrium().map(|(price, _)| price)
+    }
+
+    /// volume that would be executed if the auction ended now
+    pub fn indicative_auction_volume(&self) -> Volume {
+        self.equilibrium()
+            .map_or(Volume::ZERO, |(_, volume)| volume)
+    }
+
+    /// execute all crossing volume at the equilibrium price
+    /// book is uncrossed when it transitions from pre-open to open
+    pub fn uncross(&mut self) -> Result<AuctionResult, OrderBookError> {
+        self.logged(|| BookEvent::Uncross, |book| book.uncross_at_equilibrium())
+    }
+
+    fn uncross_at_equilibrium(&mut self) -> Result<AuctionResult, OrderBookError> {
+        let Some((price, _)) = self.equilibrium() else {
+            return Err(OrderBookError::NoOrderToMatch);
+        };
+
+        let mut fills = Vec::new();
+        let mut volume = Volume::ZERO;
+        loop {
+            if self.bids.best.is_none() {
+                self.update_best_buy();
+            }
+            if self.asks.best.is_none() {
+                self.update_best_sell();
+            }
+            let (Some(best_buy), Some(best_sell)) = (self.get_best_buy(), self.get_best_sell())
+            else {
+                break;
+            };
+            if best_buy < price || best_sell > price {
+                break;
+            }
+            // all fills of the auction are executed at the equilibrium price
+            match self.fill_best_orders(Some(price)) {
+                Ok(fill) => {
+                    volume += fill.volume;
+                    fills.push(fill);
+                }
+                Err(OrderBookError::LevelHasNoValidOrders) => {
+                    // best level is stale, it will be removed and best updated on next iteration
+                    self.remove_filled_levels();
+                }
+                Err(_) => break,
+            }
+        }
+
+        Ok(AuctionResult {
+            price,
+            volume,
+            fills,
+        })
+    }
+
+    /// find the price that maximizes executable volume, on tie the price with the smallest
+    /// surplus (difference between buy and sell volume) is chosen, then the lowest price
+    fn equilibrium(&self) -> Option<(Price, Volume)> {
+        let bids = self
+            .bids
+            .iter_levels(OrderSide::Buy)
+            .map(|l| (l.price, l.total_volume))
+            .collect::<Vec<_>>();
+        let asks = self
+            .asks
+            .iter_levels(OrderSide::Sell)
+            .map(|l| (l.price, l.total_volume))
+            .collect::<Vec<_>>();
+
+        let mut prices = bids
+            .iter()
+            .chain(asks.iter())
+            .map(|l| l.0)
+            .collect::<Vec<_>>();
+        prices.sort();
+        prices.dedup();
+
+        let mut best: Option<(Price, Volume, Volume)> = None;
+        for price in prices {
+            let buy_volume = bids
+                .iter()
+                .take_while(|l| l.0 >= price)
+                .map(|l| l.1)
+                .sum::<Volume>();
+            let sell_volume = asks
+                .iter()
+                .take_while(|l| l.0 <= price)
+                .map(|l| l.1)
+                .sum::<Volume>();
+            let executable = buy_volume.min(sell_volume);
+            if executable.is_zero() {
+                continue;
+            }
+            let surplus = buy_volume.max(sell_volume) - executable;
+            let better = match best {
+                None => true,
+                Some((_, best_executable, best_surplus)) => {
+                    executable > best_executable
+                        || (executable == best_executable && surplus < best_surplus)
+                }
+            };
+            if better {
+                best = Some((price, executable, surplus));
+            }
+        }
+        best.map(|(price, volume, _)| (price, volume))
+    }
+}
+
+#[cfg(test)]
+mod tests_auction {
+    use crate::*;
+
+    fn limit(id: u64, side: OrderSide, price: f64, volume: u64) -> Order {
+        Order::new_limit(
+            Oid::new(id),
+            side,
+            Timestamp::new(id),
+            price.into(),
+            volume.into(),
+        )
+    }
+
+    #[test]
+    fn test_uncross_at_equilibrium_price() {
+        let mut order_book = OrderBook::default();
+        order_book
+            .transition(TradingState::PreOpen, Timestamp::new(0))
+            .unwrap();
+        order_book
+            .execute(&limit(1, OrderSide::Buy, 22.0, 100))
+            .unwrap();
+        order_book
+            .execute(&limit(2, OrderSide::Buy, 21.0, 100))
+            .unwrap();
+        order_book
+            .execute(&limit(3, OrderSide::Sell, 20.0, 50))
+            .unwrap();
+        order_book
+            .execute(&limit(4, OrderSide::Sell, 21.0, 100))
+            .unwrap();
+        assert_eq!(order_book.get_best_buy(), Some(22.0.into()));
+        assert_eq!(order_book.get_best_sell(), Some(20.0.into()));
+
+        assert_eq!(order_book.indicative_auction_price(), Some(21.0.into()));
+        assert_eq!(order_book.indicative_auction_volume(), 150.into());
+
+        let transition = order_book
+            .transition(TradingState::Open, Timestamp::new(10))
+            .unwrap();
+        let result = transition.auction.unwrap();
+        assert_eq!(result.price, 21.0.into());
+        assert_eq!(result.volume, 150.into());
+        // fills are executed at the equilibrium price, not by the trade price policy
+        assert_eq!(result.fills.len(), 3);
+        assert!(result
+            .fills
+            .iter()
+            .all(|fill| fill.trade_price == 21.0.into()));
+        assert_eq!(order_book.last_trade_price(), Some(21.0.into()));
+        assert_eq!(order_book.state(), TradingState::Open);
+        assert_eq!(order_book.get_best_buy(), Some(21.0.into()));
+        assert_eq!(order_book.get_best_buy_volume(), Some(50.into()));
+        assert!(order_book.get_best_sell().is_none());
+    }
+}
@@ -0,0 +1,157 @@
+//!
+//! Audit trail of the orders. When enabled every lifecycle event of an order is appended to
+//! its history with the event sequence of the book, histories are kept until cleared.
+//!
+
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::{LevelStore, Oid, Order, OrderBook, Price, Timestamp, Volume};
+
+/// Lifecycle event of the order
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AuditEvent {
+    /// order was accepted by the book, market orders have no price
+    Accepted {
+        timestamp: Timestamp,
+        price: Option<Price>,
+        volume: Volume,
+    },
+    /// order was filled, leaves is the volume of the order that is still open
+    Filled {
+        price: Price,
+        volume: Volume,
+        leaves: Volume,
+    },
+    /// price and total volume after the amend
+    Amended {
+        price: Price,
+        volume: Volume,
+    },
+    /// order or the remainder that could not be filled was cancelled
+    Cancelled,
+    Expired,
+}
+
+impl AuditEvent {
+    pub(crate) fn accepted(order: &Order) -> Self {
+        AuditEvent::Accepted {
+            timestamp: order.timestamp,
+            price: order.price,
+            volume: order.volume,
+        }
+    }
+}
+
+/// Sequence numbered event in the order history
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditRecord {
+    pub sequence: u64,
+    pub event: AuditEvent,
+}
+
+/// Histories of the orders
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AuditTrail {
+    histories: HashMap<Oid, Vec<AuditRecord>>,
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// record the lifecycle events of every order
+    pub fn with_audit_trail(mut self) -> Self {
+        self.audit_trail = Some(AuditTrail::default());
+        self
+    }
+
+    /// events of the order from the oldest, empty when the audit trail is disabled
+    pub fn order_history(&self, order_id: Oid) -> &[AuditRecord] {
+        self.audit_trail
+            .as_ref()
+            .and_then(|trail| trail.histories.get(&order_id))
+            .map_or(&[], |history| history.as_slice())
+    }
+
+    /// drop the history of the order, i.e. once it has been reported
+    pub fn clear_order_history(&mut self, order_id: Oid) -> Vec<AuditRecord> {
+        self.audit_trail
+            .as_mut()
+            .and_then(|trail| trail.histories.remove(&order_id))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn audit(&mut self, order_id: Oid, sequence: u64, event: AuditEvent) {
+        if let Some(trail) = &mut self.audit_trail {
+            trail
+                .histories
+                .entry(order_id)
+                .or_default()
+                .push(AuditRecord { sequence, event });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_audit {
+    use crate::*;
+
+    #[test]
+    fn test_order_history() {
+        let mut order_book = OrderBook::default().with_audit_trail();
+        let sell = LimitOrder::new(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            10.0.into(),
+            10.into(),
+        );
+        order_book.add_order(sell).unwrap();
+        let buy = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Buy,
+            Timestamp::new(2),
+            10.0.into(),
+            4.into(),
+        )
+        .with_time_in_force(TimeInForce::ImmediateOrCancel);
+        order_book.execute(&buy).unwrap();
+        order_book
+            .amend_order(Oid::new(1), 10.5.into(), 8.into())
+            .unwrap();
+        order_book.cancel_order(Oid::new(1)).unwrap();
+
+        let events = order_book
+            .order_history(Oid::new(1))
+            .iter()
+            .map(|record| record.event.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                AuditEvent::Accepted {
+                    timestamp: Timestamp::new(1),
+                    price: Some(Price::new(10.0)),
+                    volume: 10.into()
+                },
+                AuditEvent::Filled {
+                    price: Price::new(10.0),
+                    volume: 4.into(),
+                    leaves: 6.into()
+                },
+                AuditEvent::Amended {
+                    price: Price::new(10.5),
+                    volume: 8.into()
+                },
+                AuditEvent::Cancelled,
+            ]
+        );
+        let history = order_book.order_history(Oid::new(1));
+        assert!(history.windows(2).all(|w| w[0].sequence < w[1].sequence));
+        assert_eq!(order_book.order_history(Oid::new(2)).len(), 2);
+
+        assert_eq!(order_book.clear_order_history(Oid::new(1)).len(), 4);
+        assert!(order_book.order_history(Oid::new(1)).is_empty());
+    }
+}
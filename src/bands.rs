@@ -0,0 +1,61 @@
+//!
+//! Price bands (circuit breaker). Limit orders priced outside of the bands are rejected and
+//! matching is halted when a fill would be executed outside of the bands.
+//!
+
+use crate::Price;
+
+/// Price bands
+/// static bands limit the price to fixed min and max, dynamic bands allow the price to move
+/// by a percentage around the reference price. When both are set the price must be within both.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PriceBands {
+    pub min_price: Option<Price>,
+    pub max_price: Option<Price>,
+    pub reference_price: Option<Price>,
+    /// allowed move from the reference price in percent, i.e. 5.0 is 5%
+    pub percentage: Option<f64>,
+}
+
+impl PriceBands {
+    /// static bands
+    pub fn fixed(min_price: Price, max_price: Price) -> Self {
+        PriceBands {
+            min_price: Some(min_price),
+            max_price: Some(max_price),
+            ..Default::default()
+        }
+    }
+
+    /// dynamic bands around the reference price
+    pub fn percentage(reference_price: Price, percentage: f64) -> Self {
+        PriceBands {
+            reference_price: Some(reference_price),
+            percentage: Some(percentage),
+            ..Default::default()
+        }
+    }
+
+    /// move the reference price of the dynamic bands
+    pub fn set_reference_price(&mut self, reference_price: Price) {
+        self.reference_price = Some(reference_price);
+    }
+
+    /// lowest and highest allowed price
+    pub fn limits(&self) -> (Price, Price) {
+        let mut min = self.min_price.unwrap_or(Price::MIN);
+        let mut max = self.max_price.unwrap_or(Price::MAX);
+        if let (Some(reference), Some(percentage)) = (self.reference_price, self.percentage) {
+            let band = Price::new(reference.to_f64() * percentage / 100.0);
+            min = min.max(reference - band);
+            max = max.min(reference + band);
+        }
+        (min, max)
+    }
+
+    /// check if the price is within the bands
+    pub fn contains(&self, price: Price) -> bool {
+        let (min, max) = self.limits();
+        price >= min && price <= max
+    }
+}
@@ -0,0 +1,155 @@
+//!
+//! Builder of the order book with pre-allocation hints.
+//! Orders and levels are kept in slabs and hash maps that grow as the book fills up,
+//! with the expected number of orders and levels given up front they are allocated once,
+//! so the book does not rehash or reallocate in steady state.
+//!
+
+use crate::{HashedLevels, LevelStore, OrderBook, TradePricePolicy};
+
+/// Builds the book with its storage allocated for the expected size
+#[derive(Debug, Clone, Default)]
+pub struct OrderBookBuilder<S = HashedLevels> {
+    orders: usize,
+    levels: usize,
+    level_pool: usize,
+    trade_price_policy: TradePricePolicy,
+    level_store: S,
+}
+
+impl OrderBookBuilder {
+    /// builder of the book with the default level store and no pre-allocation
+    pub fn new() -> Self {
+        OrderBookBuilder::default()
+    }
+}
+
+impl<S: LevelStore> OrderBookBuilder<S> {
+    /// number of orders expected to rest on the book at the same time
+    pub fn with_orders(mut self, orders: usize) -> Self {
+        self.orders = orders;
+        self
+    }
+
+    /// number of price levels expected on each side
+    pub fn with_levels(mut self, levels: usize) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    /// order queues of compacted levels kept for new levels, see `OrderBook::with_level_pool`
+    pub fn with_level_pool(mut self, size: usize) -> Self {
+        self.level_pool = size;
+        self
+    }
+
+    /// price of the trade between the passive and the aggressive order
+    pub fn with_trade_price_policy(mut self, trade_price_policy: TradePricePolicy) -> Self {
+        self.trade_price_policy = trade_price_policy;
+        self
+    }
+
+    /// keep the levels of both sides in the given store
+    pub fn with_level_store<T: LevelStore>(self, level_store: T) -> OrderBookBuilder<T> {
+        OrderBookBuilder {
+            orders: self.orders,
+            levels: self.levels,
+            level_pool: self.level_pool,
+            trade_price_policy: self.trade_price_policy,
+            level_store,
+        }
+    }
+
+    pub fn build(self) -> OrderBook<S> {
+        let mut order_book = OrderBook::<S>::empty()
+            .with_level_store(self.level_store)
+            .with_level_pool(self.level_pool)
+            .with_trade_price_policy(self.trade_price_policy);
+        order_book.orders.reserve(self.orders);
+        order_book.bids.reserve(self.levels);
+        order_book.asks.reserve(self.levels);
+        order_book
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// number of resting orders the book holds without reallocating
+    pub fn order_capacity(&self) -> usize {
+        self.orders.capacity()
+    }
+}
+
+impl OrderBook {
+    /// builder of the book with pre-allocation hints
+    pub fn builder() -> OrderBookBuilder {
+        OrderBookBuilder::new()
+    }
+
+    /// empty book with room for the number of resting orders and of levels on each side
+    pub fn with_capacity(orders: usize, levels: usize) -> Self {
+        OrderBookBuilder::new()
+            .with_orders(orders)
+            .with_levels(levels)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests_builder {
+    use crate::*;
+
+    #[test]
+    fn test_book_is_preallocated() {
+        let mut order_book = OrderBook::builder()
+            .with_orders(200)
+            .with_levels(20)
+            .with_trade_price_policy(TradePricePolicy::Midpoint)
+            .build();
+        assert_eq!(order_book.trade_price_policy(), TradePricePolicy::Midpoint);
+        let order_capacity = order_book.order_capacity();
+        let level_capacity = order_book.bids.levels.capacity();
+        assert!(order_capacity >= 200);
+        assert!(level_capacity >= 20);
+
+        for id in 0..200 {
+            let (side, price) = match id % 2 {
+                0 => (OrderSide::Buy, 10.0 - (id % 20) as f64),
+                _ => (OrderSide::Sell, 11.0 + (id % 20) as f64),
+            };
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+        assert_eq!(order_book.order_count(), 200);
+        // nothing was reallocated
+        assert_eq!(order_book.order_capacity(), order_capacity);
+        assert_eq!(order_book.bids.levels.capacity(), level_capacity);
+    }
+
+    #[test]
+    fn test_with_capacity_and_level_store() {
+        let order_book = OrderBook::with_capacity(100, 10);
+        assert!(order_book.order_capacity() >= 100);
+        assert!(order_book.asks.levels.capacity() >= 10);
+
+        let store = DenseLevels::new(Price::new(1.0), Price::new(2.0), Price::new(0.01));
+        let mut order_book = OrderBookBuilder::new()
+            .with_levels(101)
+            .with_level_store(store)
+            .build();
+        let order = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Buy,
+            Timestamp::new(1),
+            1.5.into(),
+            5.into(),
+        );
+        order_book.execute(&order).unwrap();
+        assert_eq!(order_book.get_best_buy(), Some(1.5.into()));
+    }
+}
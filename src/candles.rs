@@ -0,0 +1,190 @@
+//!
+//! OHLCV candles built from the trades of the book. Trades are grouped into bars of a fixed
+//! interval aligned to multiples of the interval, intervals without trades have no bar.
+//! Time is taken from the trade timestamps, the interval is in the same units.
+//! At most capacity most recent bars are kept.
+//!
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::{LevelStore, OrderBook, Price, TapeEntry, Timestamp, Volume};
+
+/// Open, high, low, close and volume of the trades within the interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candle {
+    /// start of the interval
+    pub start: Timestamp,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: Volume,
+    /// number of trades
+    pub trades: usize,
+}
+
+impl Candle {
+    fn new(start: Timestamp, price: Price, volume: Volume) -> Self {
+        Candle {
+            start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume,
+            trades: 1,
+        }
+    }
+
+    fn add(&mut self, price: Price, volume: Volume) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += volume;
+        self.trades += 1;
+    }
+}
+
+/// Rolling bars of the most recent trades
+#[derive(Debug, Clone)]
+pub struct CandleBuilder {
+    interval: u64,
+    capacity: usize,
+    candles: VecDeque<Candle>,
+}
+
+impl CandleBuilder {
+    /// bars of the interval, interval of zero is treated as one
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        CandleBuilder {
+            interval: interval.max(1),
+            capacity,
+            candles: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// add the trade to the bar of its interval, trade older than the kept bars is ignored
+    pub fn update(&mut self, timestamp: Timestamp, price: Price, volume: Volume) {
+        if self.capacity == 0 {
+            return;
+        }
+        let time = u64::from(timestamp);
+        let start = Timestamp::new(time - time % self.interval);
+        match self.candles.back() {
+            Some(last) if last.start < start => {}
+            None => {}
+            // trade reported late belongs to one of the kept bars
+            Some(_) => {
+                if let Some(candle) = self.candles.iter_mut().rev().find(|c| c.start == start) {
+                    candle.add(price, volume);
+                }
+                return;
+            }
+        }
+        if self.candles.len() == self.capacity {
+            self.candles.pop_front();
+        }
+        self.candles.push_back(Candle::new(start, price, volume));
+    }
+
+    /// add the trade printed on the tape
+    pub fn record(&mut self, entry: &TapeEntry) {
+        self.update(entry.timestamp, entry.price, entry.volume);
+    }
+
+    /// bar of the most recent trade
+    pub fn current(&self) -> Option<&Candle> {
+        self.candles.back()
+    }
+
+    /// at most n most recent bars, from the oldest to the most recent
+    pub fn recent(&self, n: usize) -> Vec<&Candle> {
+        self.candles
+            .iter()
+            .skip(self.candles.len().saturating_sub(n))
+            .collect()
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// build candles of the interval from the trades, keeping at most capacity bars
+    pub fn with_candles(mut self, interval: u64, capacity: usize) -> Self {
+        self.candles = Some(CandleBuilder::new(interval, capacity));
+        self
+    }
+
+    pub fn candles(&self) -> Option<&CandleBuilder> {
+        self.candles.as_ref()
+    }
+
+    /// at most n most recent bars, from the oldest to the most recent
+    pub fn recent_candles(&self, n: usize) -> Vec<&Candle> {
+        self.candles
+            .as_ref()
+            .map(|candles| candles.recent(n))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests_candles {
+    use crate::*;
+
+    #[test]
+    fn test_candle_builder() {
+        let mut candles = CandleBuilder::new(60, 2);
+        candles.update(Timestamp::new(61), 10.0.into(), 1.into());
+        candles.update(Timestamp::new(70), 12.0.into(), 2.into());
+        candles.update(Timestamp::new(119), 9.0.into(), 3.into());
+        assert_eq!(
+            candles.current(),
+            Some(&Candle {
+                start: Timestamp::new(60),
+                open: 10.0.into(),
+                high: 12.0.into(),
+                low: 9.0.into(),
+                close: 9.0.into(),
+                volume: 6.into(),
+                trades: 3,
+            })
+        );
+
+        candles.update(Timestamp::new(240), 11.0.into(), 1.into());
+        // late trade updates its bar
+        candles.update(Timestamp::new(100), 8.0.into(), 1.into());
+        candles.update(Timestamp::new(300), 11.5.into(), 1.into());
+        let recent = candles.recent(5);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].start, Timestamp::new(240));
+        assert_eq!(recent[1].close, 11.5.into());
+    }
+
+    #[test]
+    fn test_candles_from_book_trades() {
+        let mut order_book = OrderBook::default().with_candles(1_000, 10);
+        for (id, price) in [(1, 10.0), (2, 11.0)] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        let order = Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(500), 7.into());
+        order_book.execute(&order).unwrap();
+
+        let candles = order_book.recent_candles(1);
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].open, 10.0.into());
+        assert_eq!(candles[0].close, 11.0.into());
+        assert_eq!(candles[0].volume, 7.into());
+        assert_eq!(candles[0].trades, 2);
+    }
+}
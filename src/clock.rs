@@ -0,0 +1,211 @@
+//!
+//! Time source of the book. Without a clock orders keep the timestamps given by the caller.
+//! With a clock the book stamps every executed, added or replaced order with the current time
+//! and orders that expired by then are removed first, so good till date orders never trade late.
+//! `ManualClock` is moved by hand, which makes time dependent behaviour testable.
+//!
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{CancellationReport, LevelStore, Order, OrderBook, OrderBookError, Timestamp};
+
+/// Source of the current time in milliseconds
+pub trait Clock: core::fmt::Debug + Send + Sync {
+    fn now(&self) -> Timestamp;
+}
+
+/// Wall clock, milliseconds since the unix epoch
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        let elapsed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        Timestamp::new(elapsed.as_millis() as u64)
+    }
+}
+
+/// Clock moved by hand, clones share the same time
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(now: Timestamp) -> Self {
+        ManualClock {
+            now: Arc::new(AtomicU64::new(now.into())),
+        }
+    }
+
+    pub fn set(&self, now: Timestamp) {
+        self.now.store(now.into(), Ordering::Relaxed);
+    }
+
+    /// move the clock forward by the milliseconds
+    pub fn advance(&self, millis: u64) {
+        self.now.fetch_add(millis, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::new(self.now.load(Ordering::Relaxed))
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// stamp accepted orders and check expiry with the clock
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.set_clock(clock);
+        self
+    }
+
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Some(Arc::new(clock));
+    }
+
+    /// current time of the clock, none without a clock
+    pub fn now(&self) -> Option<Timestamp> {
+        self.clock.as_ref().map(|clock| clock.now())
+    }
+
+    /// remove orders expired at the current time of the clock
+    pub fn purge_expired_now(&mut self) -> Vec<CancellationReport> {
+        match self.now() {
+            Some(now) => self.purge_expired(now),
+            None => Vec::new(),
+        }
+    }
+
+    /// current time of the clock for orders put on the book without matching,
+    /// resting orders expired by then are removed, none without a clock
+    pub(crate) fn accept_now(&mut self) -> Option<Timestamp> {
+        let now = self.now()?;
+        if self.orders.values().any(|order| order.is_expired(now)) {
+            self.purge_expired(now);
+        }
+        Some(now)
+    }
+
+    /// order stamped with the current time, resting orders expired by then are removed
+    /// and the order is rejected if it has already expired, none without a clock
+    pub(crate) fn stamp(&mut self, order: &Order) -> Result<Option<Order>, OrderBookError> {
+        let now = self.now();
+        self.stamp_at(order, now)
+    }
+
+    /// order stamped with the given time of the clock
+    pub(crate) fn stamp_at(
+        &mut self,
+        order: &Order,
+        now: Option<Timestamp>,
+    ) -> Result<Option<Order>, OrderBookError> {
+        let Some(now) = now else {
+            return Ok(None);
+        };
+        if order.expiry.is_some_and(|expiry| expiry <= now) {
+            return Err(OrderBookError::OrderExpired(order.id));
+        }
+        self.purge_expired(now);
+        Ok(Some(Order {
+            timestamp: now,
+            ..order.clone()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests_clock {
+    use crate::*;
+
+    #[test]
+    fn test_book_assigned_timestamps() {
+        let clock = ManualClock::new(Timestamp::new(100));
+        let mut order_book = OrderBook::default().with_clock(clock.clone());
+        let sell = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            10.0.into(),
+            5.into(),
+        )
+        .with_expiry(Timestamp::new(150));
+        order_book.execute(&sell).unwrap();
+        assert_eq!(
+            order_book.orders.get(&Oid::new(1)).unwrap().timestamp,
+            Timestamp::new(100)
+        );
+
+        // expired order is removed before the next order is matched
+        clock.advance(50);
+        let buy = Order::new_market(Oid::new(2), OrderSide::Buy, Timestamp::new(2), 5.into());
+        let trade = order_book.execute(&buy).unwrap();
+        assert!(trade.executions.is_empty());
+        assert_eq!(
+            order_book.get_order(Oid::new(1)).unwrap().state,
+            OrderState::Cancelled
+        );
+
+        // incoming order that has already expired is rejected
+        let expired = sell.clone().with_expiry(Timestamp::new(120));
+        assert!(order_book.execute(&expired).is_err());
+        assert!(order_book.purge_expired_now().is_empty());
+    }
+
+    #[test]
+    fn test_orders_added_without_matching_use_the_clock() {
+        let clock = ManualClock::new(Timestamp::new(100));
+        let mut order_book = OrderBook::default().with_clock(clock.clone());
+        let order = |id: u64, price: f64, expiry: u64| {
+            Order::new_limit(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            )
+            .with_expiry(Timestamp::new(expiry))
+        };
+        let limit_order = |order: Order| LimitOrder::try_from(&order).unwrap();
+
+        order_book
+            .add_order(limit_order(order(1, 10.0, 150)))
+            .unwrap();
+        let results = order_book.add_orders([order(2, 9.0, 200), order(3, 8.0, 100)]);
+        assert_eq!(results[0], Ok(()));
+        assert_eq!(results[1], Err(OrderBookError::OrderExpired(Oid::new(3))));
+        for id in [1, 2] {
+            assert_eq!(
+                order_book.orders.get(&Oid::new(id)).unwrap().timestamp,
+                Timestamp::new(100)
+            );
+        }
+
+        // expired orders are removed before the order is added or amended
+        clock.advance(50);
+        assert_eq!(
+            order_book.add_order(limit_order(order(4, 10.0, 140))),
+            Err(OrderBookError::OrderExpired(Oid::new(4)))
+        );
+        assert_eq!(
+            order_book.get_order(Oid::new(1)).unwrap().state,
+            OrderState::Cancelled
+        );
+        let report = order_book
+            .amend_order(Oid::new(2), 9.0.into(), 6.into())
+            .unwrap();
+        assert_eq!(report.timestamp, Timestamp::new(150));
+        clock.advance(50);
+        assert!(order_book
+            .amend_order(Oid::new(2), 9.0.into(), 5.into())
+            .is_err());
+        assert_eq!(order_book.order_count(), 0);
+    }
+}
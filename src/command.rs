@@ -0,0 +1,309 @@
+//!
+//! Commands accepted by the book and the reports sent back, used by front-ends that drive
+//! the book from another task or thread.
+//! Every mutating call of the book reports the events of the orders it touched: accepts,
+//! fills, amends, cancels, expiries and rejects. Reports are collected with
+//! `drain_execution_reports` once enabled, `apply` passes the reports of the command
+//! to the callback.
+//!
+
+use alloc::vec::Vec;
+
+use crate::{
+    AuditEvent, LevelStore, Oid, Order, OrderBook, OrderBookError, Price, Timestamp, Volume,
+};
+
+/// Command to the book
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Command {
+    /// execute the order, remainder of a limit order rests on the book
+    Execute(Order),
+    Cancel(Oid),
+    Amend {
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+    },
+    /// remove orders expired at the given time
+    PurgeExpired(Timestamp),
+}
+
+/// Event of the order, accepted events carry the event sequence of the book
+#[derive(Debug, Clone)]
+pub enum ExecutionReport {
+    Accepted {
+        order_id: Oid,
+        sequence: u64,
+    },
+    /// order was filled and still has open volume
+    PartialFill {
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+        leaves: Volume,
+        sequence: u64,
+    },
+    /// order was completely filled
+    Filled {
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+        sequence: u64,
+    },
+    /// price and total volume after the amend
+    Amended {
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+        sequence: u64,
+    },
+    /// order or the remainder that could not be filled was cancelled
+    Cancelled {
+        order_id: Oid,
+        sequence: u64,
+    },
+    Expired {
+        order_id: Oid,
+        sequence: u64,
+    },
+    /// command for the order was rejected by the book
+    Rejected {
+        order_id: Oid,
+        error: OrderBookError,
+    },
+}
+
+impl ExecutionReport {
+    fn from_event(order_id: Oid, sequence: u64, event: &AuditEvent) -> Self {
+        match *event {
+            AuditEvent::Accepted { .. } => ExecutionReport::Accepted { order_id, sequence },
+            AuditEvent::Filled {
+                price,
+                volume,
+                leaves,
+            } if leaves.is_zero() => ExecutionReport::Filled {
+                order_id,
+                price,
+                volume,
+                sequence,
+            },
+            AuditEvent::Filled {
+                price,
+                volume,
+                leaves,
+            } => ExecutionReport::PartialFill {
+                order_id,
+                price,
+                volume,
+                leaves,
+                sequence,
+            },
+            AuditEvent::Amended { price, volume } => ExecutionReport::Amended {
+                order_id,
+                price,
+                volume,
+                sequence,
+            },
+            AuditEvent::Cancelled => ExecutionReport::Cancelled { order_id, sequence },
+            AuditEvent::Expired => ExecutionReport::Expired { order_id, sequence },
+        }
+    }
+
+    pub fn order_id(&self) -> Oid {
+        match self {
+            ExecutionReport::Accepted { order_id, .. }
+            | ExecutionReport::PartialFill { order_id, .. }
+            | ExecutionReport::Filled { order_id, .. }
+            | ExecutionReport::Amended { order_id, .. }
+            | ExecutionReport::Cancelled { order_id, .. }
+            | ExecutionReport::Expired { order_id, .. }
+            | ExecutionReport::Rejected { order_id, .. } => *order_id,
+        }
+    }
+
+    /// event sequence of the book, rejects are not sequenced
+    pub fn sequence(&self) -> Option<u64> {
+        match self {
+            ExecutionReport::Accepted { sequence, .. }
+            | ExecutionReport::PartialFill { sequence, .. }
+            | ExecutionReport::Filled { sequence, .. }
+            | ExecutionReport::Amended { sequence, .. }
+            | ExecutionReport::Cancelled { sequence, .. }
+            | ExecutionReport::Expired { sequence, .. } => Some(*sequence),
+            ExecutionReport::Rejected { .. } => None,
+        }
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// collect the execution reports of every mutating call
+    pub fn with_execution_reports(mut self) -> Self {
+        self.execution_reports = Some(Vec::new());
+        self
+    }
+
+    /// take the execution reports since the last call
+    pub fn drain_execution_reports(&mut self) -> Vec<ExecutionReport> {
+        self.execution_reports
+            .as_mut()
+            .map(core::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// apply the command to the book and pass its reports to the callback,
+    /// reports of the command are not added to the collected execution reports
+    pub fn apply(&mut self, command: Command, report: impl FnMut(ExecutionReport)) {
+        let collected = self.execution_reports.replace(Vec::new());
+        match command {
+            Command::Execute(order) => {
+                let _ = self.execute(&order);
+            }
+            Command::Cancel(order_id) => {
+                let _ = self.cancel_order(order_id);
+            }
+            Command::Amend {
+                order_id,
+                price,
+                volume,
+            } => {
+                let _ = self.amend_order(order_id, price, volume);
+            }
+            Command::PurgeExpired(now) => {
+                self.purge_expired(now);
+            }
+        }
+        let reports = core::mem::replace(&mut self.execution_reports, collected);
+        reports.unwrap_or_default().into_iter().for_each(report);
+    }
+
+    /// record the event in the order history and report it
+    pub(crate) fn record_event(&mut self, order_id: Oid, sequence: u64, event: AuditEvent) {
+        if let Some(reports) = &mut self.execution_reports {
+            reports.push(ExecutionReport::from_event(order_id, sequence, &event));
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(order_id = %order_id, sequence, event = ?event, "order event");
+        #[cfg(feature = "metrics")]
+        self.export_event(&event);
+        self.audit(order_id, sequence, event);
+    }
+
+    pub(crate) fn record_fill(
+        &mut self,
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+        leaves: Volume,
+        sequence: u64,
+    ) {
+        let filled = AuditEvent::Filled {
+            price,
+            volume,
+            leaves,
+        };
+        self.record_event(order_id, sequence, filled);
+    }
+
+    pub(crate) fn record_rejected(&mut self, order_id: Oid, error: &OrderBookError) {
+        if let Some(reports) = &mut self.execution_reports {
+            reports.push(ExecutionReport::Rejected {
+                order_id,
+                error: error.clone(),
+            });
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(order_id = %order_id, error = %error, "order rejected");
+        #[cfg(feature = "metrics")]
+        self.export_rejected(error);
+    }
+}
+
+#[cfg(test)]
+mod tests_command {
+    use crate::*;
+
+    #[test]
+    fn test_apply_commands() {
+        let mut order_book = OrderBook::default();
+        let mut reports = Vec::new();
+        for id in 1..=2 {
+            let order = Order::new_limit(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                10.0.into(),
+                5.into(),
+            )
+            .with_expiry(Timestamp::new(10));
+            order_book.apply(Command::Execute(order), |r| reports.push(r));
+        }
+        order_book.apply(
+            Command::Amend {
+                order_id: Oid::new(3),
+                price: 10.0.into(),
+                volume: 1.into(),
+            },
+            |r| reports.push(r),
+        );
+        order_book.apply(Command::PurgeExpired(Timestamp::new(10)), |r| {
+            reports.push(r)
+        });
+
+        assert_eq!(reports.len(), 5);
+        assert!(matches!(
+            reports[2],
+            ExecutionReport::Rejected { order_id, .. } if order_id == Oid::new(3)
+        ));
+        assert!(reports[3..]
+            .iter()
+            .all(|r| matches!(r, ExecutionReport::Expired { .. })));
+        assert!(order_book.is_empty());
+    }
+
+    #[test]
+    fn test_execution_report_stream() {
+        let mut order_book = OrderBook::default().with_execution_reports();
+        let sell = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            10.0.into(),
+            5.into(),
+        );
+        order_book.execute(&sell).unwrap();
+        let buy = Order::new_market(Oid::new(2), OrderSide::Buy, Timestamp::new(2), 8.into());
+        order_book.execute(&buy).unwrap();
+        assert!(order_book.cancel_order(Oid::new(1)).is_err());
+
+        let reports = order_book.drain_execution_reports();
+        let kinds = reports
+            .iter()
+            .map(|r| match r {
+                ExecutionReport::Accepted { .. } => "accepted",
+                ExecutionReport::PartialFill { .. } => "partial",
+                ExecutionReport::Filled { .. } => "filled",
+                ExecutionReport::Cancelled { .. } => "cancelled",
+                ExecutionReport::Rejected { .. } => "rejected",
+                _ => "other",
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                "accepted",
+                "accepted",
+                "filled",
+                "partial",
+                "cancelled",
+                "rejected"
+            ]
+        );
+        assert!(matches!(
+            reports[3],
+            ExecutionReport::PartialFill { order_id, leaves, .. }
+                if order_id == Oid::new(2) && leaves == 3.into()
+        ));
+        assert!(order_book.drain_execution_reports().is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+//!
+//! Conflated market data for slow consumers. Deltas received within the interval are
+//! coalesced, each changed level is published once with its latest state and the top of
+//! the book only with its latest value. Trades are not conflated, every trade of the interval
+//! is part of the batch. A batch is emitted once the interval has elapsed since the previous one.
+//!
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use itertools::Either;
+
+use crate::{
+    Bbo, BookDelta, DeltaEvent, LevelStore, OrderBook, OrderSide, Price, Timestamp, Volume,
+};
+
+/// Changes of the book during one interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflatedBatch {
+    /// sequence of the last delta included in the batch,
+    /// the batch applies on top of the snapshot at the sequence of the previous batch
+    pub sequence: u64,
+    /// latest state of each changed level, bids and asks from the best price
+    pub levels: Vec<DeltaEvent>,
+    /// trades of the interval in the order they happened
+    pub trades: Vec<DeltaEvent>,
+    /// top of the book, none if it has not changed since the previous batch
+    pub bbo: Option<Bbo>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LevelChange {
+    // consumer knew the level before the interval
+    published: bool,
+    // volume and order count, none once removed
+    level: Option<(Volume, usize)>,
+}
+
+/// Coalesces deltas and top of the book changes into batches
+#[derive(Debug, Clone)]
+pub struct Conflator {
+    interval: u64,
+    last_batch: Option<Timestamp>,
+    sequence: u64,
+    bids: BTreeMap<Price, LevelChange>,
+    asks: BTreeMap<Price, LevelChange>,
+    trades: Vec<DeltaEvent>,
+    bbo: Bbo,
+    published_bbo: Bbo,
+}
+
+impl Conflator {
+    /// batches are emitted at most once per interval in milliseconds
+    pub fn new(interval: u64) -> Self {
+        Conflator {
+            interval,
+            last_batch: None,
+            sequence: 0,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            trades: Vec::new(),
+            bbo: Bbo::default(),
+            published_bbo: Bbo::default(),
+        }
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// add the delta to the pending batch
+    pub fn push(&mut self, delta: &BookDelta) {
+        self.sequence = self.sequence.max(delta.sequence);
+        let (side, price, level, added) = match delta.event {
+            DeltaEvent::LevelAdded {
+                side,
+                price,
+                volume,
+                order_count,
+            } => (side, price, Some((volume, order_count)), true),
+            DeltaEvent::LevelUpdated {
+                side,
+                price,
+                volume,
+                order_count,
+            } => (side, price, Some((volume, order_count)), false),
+            DeltaEvent::LevelRemoved { side, price } => (side, price, None, false),
+            DeltaEvent::Trade { .. } => {
+                self.trades.push(delta.event.clone());
+                return;
+            }
+        };
+        let levels = match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        };
+        levels
+            .entry(price)
+            .or_insert(LevelChange {
+                // first change of the level in the interval tells if the consumer knows it
+                published: !added,
+                level,
+            })
+            .level = level;
+    }
+
+    /// latest top of the book
+    pub fn update_bbo(&mut self, bbo: Bbo) {
+        self.bbo = bbo;
+    }
+
+    /// true if the next batch would not be empty
+    pub fn has_pending(&self) -> bool {
+        !self.bids.is_empty()
+            || !self.asks.is_empty()
+            || !self.trades.is_empty()
+            || self.bbo != self.published_bbo
+    }
+
+    /// batch of the pending changes if the interval has elapsed since the previous batch
+    pub fn poll(&mut self, now: Timestamp) -> Option<ConflatedBatch> {
+        let due = self
+            .last_batch
+            .is_none_or(|last| u64::from(now).saturating_sub(last.into()) >= self.interval);
+        if !due || !self.has_pending() {
+            return None;
+        }
+        self.last_batch = Some(now);
+        self.flush()
+    }
+
+    /// batch of the pending changes regardless of the interval
+    pub fn flush(&mut self) -> Option<ConflatedBatch> {
+        if !self.has_pending() {
+            return None;
+        }
+        let mut levels = Vec::with_capacity(self.bids.len() + self.asks.len());
+        let bids = core::mem::take(&mut self.bids);
+        let asks = core::mem::take(&mut self.asks);
+        for (side, changes) in [
+            (OrderSide::Buy, Either::Left(bids.into_iter().rev())),
+            (OrderSide::Sell, Either::Right(asks.into_iter())),
+        ] {
+            levels.extend(changes.filter_map(|(price, change)| {
+                match (change.published, change.level) {
+                    (true, Some((volume, order_count))) => Some(DeltaEvent::LevelUpdated {
+                        side,
+                        price,
+                        volume,
+                        order_count,
+                    }),
+                    (false, Some((volume, order_count))) => Some(DeltaEvent::LevelAdded {
+                        side,
+                        price,
+                        volume,
+                        order_count,
+                    }),
+                    (true, None) => Some(DeltaEvent::LevelRemoved { side, price }),
+                    // level was added and removed within the interval
+                    (false, None) => None,
+                }
+            }));
+        }
+        let bbo = (self.bbo != self.published_bbo).then_some(self.bbo);
+        self.published_bbo = self.bbo;
+        Some(ConflatedBatch {
+            sequence: self.sequence,
+            levels,
+            trades: core::mem::take(&mut self.trades),
+            bbo,
+        })
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// conflate the deltas of the book into batches emitted at most once per interval
+    /// in milliseconds, deltas are no longer returned by `drain_deltas`
+    pub fn with_conflation(mut self, interval: u64) -> Self {
+        if self.delta_feed.is_none() {
+            self = self.with_delta_feed();
+        }
+        let mut conflator = Conflator::new(interval);
+        // top of the book is part of the snapshot the consumer starts from
+        conflator.update_bbo(self.bbo);
+        conflator.published_bbo = self.bbo;
+        self.conflator = Some(conflator);
+        self
+    }
+
+    /// conflated changes since the previous batch, once the interval has elapsed
+    pub fn poll_conflated(&mut self, now: Timestamp) -> Option<ConflatedBatch> {
+        self.conflate_deltas();
+        self.conflator.as_mut()?.poll(now)
+    }
+
+    /// conflated changes since the previous batch regardless of the interval
+    pub fn flush_conflated(&mut self) -> Option<ConflatedBatch> {
+        self.conflate_deltas();
+        self.conflator.as_mut()?.flush()
+    }
+
+    pub(crate) fn conflate_deltas(&mut self) {
+        let Some(conflator) = &mut self.conflator else {
+            return;
+        };
+        if let Some(feed) = &mut self.delta_feed {
+            for delta in feed.take_pending() {
+                conflator.push(&delta);
+            }
+        }
+        conflator.update_bbo(self.bbo);
+    }
+}
+
+#[cfg(test)]
+mod tests_conflation {
+    use crate::*;
+
+    fn limit(id: u64, side: OrderSide, price: f64, volume: u64) -> Order {
+        Order::new_limit(
+            Oid::new(id),
+            side,
+            Timestamp::new(id),
+            price.into(),
+            volume.into(),
+        )
+    }
+
+    #[test]
+    fn test_level_updates_are_coalesced() {
+        let mut order_book = OrderBook::default();
+        order_book
+            .execute(&limit(1, OrderSide::Buy, 9.0, 5))
+            .unwrap();
+        let mut order_book = order_book.with_conflation(100);
+
+        order_book
+            .execute(&limit(2, OrderSide::Buy, 9.0, 5))
+            .unwrap();
+        order_book
+            .execute(&limit(3, OrderSide::Buy, 9.0, 5))
+            .unwrap();
+        order_book
+            .execute(&limit(4, OrderSide::Sell, 11.0, 5))
+            .unwrap();
+        order_book
+            .execute(&limit(5, OrderSide::Sell, 12.0, 5))
+            .unwrap();
+        order_book.cancel_order(Oid::new(5)).unwrap();
+        let batch = order_book.poll_conflated(Timestamp::new(1_000)).unwrap();
+        assert_eq!(batch.sequence, order_book.sequence());
+        assert_eq!(
+            batch.levels,
+            vec![
+                DeltaEvent::LevelUpdated {
+                    side: OrderSide::Buy,
+                    price: 9.0.into(),
+                    volume: 15.into(),
+                    order_count: 3
+                },
+                DeltaEvent::LevelAdded {
+                    side: OrderSide::Sell,
+                    price: 11.0.into(),
+                    volume: 5.into(),
+                    order_count: 1
+                },
+            ]
+        );
+        assert_eq!(batch.bbo, Some(order_book.best_bid_ask()));
+        assert!(order_book.drain_deltas().is_empty());
+
+        // next batch waits for the interval
+        let market = Order::new_market(Oid::new(6), OrderSide::Sell, Timestamp::new(6), 15.into());
+        order_book.execute(&market).unwrap();
+        assert_eq!(order_book.poll_conflated(Timestamp::new(1_050)), None);
+        let batch = order_book.poll_conflated(Timestamp::new(1_100)).unwrap();
+        assert_eq!(batch.trades.len(), 3);
+        assert_eq!(
+            batch.levels,
+            vec![DeltaEvent::LevelRemoved {
+                side: OrderSide::Buy,
+                price: 9.0.into()
+            }]
+        );
+        assert_eq!(batch.bbo.unwrap().bid_price, None);
+        assert_eq!(order_book.flush_conflated(), None);
+    }
+}
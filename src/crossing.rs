@@ -0,0 +1,111 @@
+//!
+//! Crossed and locked market detection. The book is crossed when the best bid is above
+//! the best ask and locked when they are equal. Books that match on arrival are never crossed
+//! while open, but books in auction, halted books and mirrors of external feeds can be.
+//! An event is recorded whenever the book becomes crossed or is no longer crossed,
+//! events are collected with `drain_crossing_events`.
+//!
+
+use alloc::vec::Vec;
+
+use crate::{LevelStore, OrderBook, Price, Spread};
+
+/// Change of the crossed state of the book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossingEvent {
+    /// best bid went above the best ask
+    Crossed { bid: Price, ask: Price },
+    /// best bid is no longer above the best ask
+    Uncrossed,
+}
+
+/// Crossed state of the book and its changes
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Crossing {
+    crossed: bool,
+    events: Vec<CrossingEvent>,
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// difference between the best ask and the best bid, negative when the book is crossed
+    pub fn spread(&self) -> Option<Spread> {
+        self.spread
+    }
+
+    /// best bid is above the best ask
+    pub fn is_crossed(&self) -> bool {
+        self.spread.is_some_and(|spread| spread.0 < Price::ZERO)
+    }
+
+    /// best bid is equal to the best ask
+    pub fn is_locked(&self) -> bool {
+        self.spread.is_some_and(|spread| spread.0 == Price::ZERO)
+    }
+
+    /// take the crossing events since the last call
+    pub fn drain_crossing_events(&mut self) -> Vec<CrossingEvent> {
+        core::mem::take(&mut self.crossing.events)
+    }
+
+    /// record the event if the crossed state changed with the last update of the top of book
+    pub(crate) fn track_crossing(&mut self) {
+        let crossed = self.is_crossed();
+        if crossed == self.crossing.crossed {
+            return;
+        }
+        self.crossing.crossed = crossed;
+        let event = match (self.bbo.bid_price, self.bbo.ask_price) {
+            (Some(bid), Some(ask)) if crossed => CrossingEvent::Crossed { bid, ask },
+            _ => CrossingEvent::Uncrossed,
+        };
+        self.crossing.events.push(event);
+    }
+}
+
+#[cfg(test)]
+mod tests_crossing {
+    use crate::*;
+
+    #[test]
+    fn test_crossed_and_locked_book() {
+        // orders added to the book are not matched
+        let mut order_book = OrderBook::default();
+        let order = |id: u64, side: OrderSide, price: f64| {
+            LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            )
+        };
+        order_book
+            .add_order(order(1, OrderSide::Buy, 10.0))
+            .unwrap();
+        order_book
+            .add_order(order(2, OrderSide::Sell, 10.0))
+            .unwrap();
+        assert!(order_book.is_locked());
+        assert!(!order_book.is_crossed());
+        assert_eq!(order_book.spread(), Some(Spread(Price::ZERO)));
+        assert!(order_book.drain_crossing_events().is_empty());
+
+        order_book
+            .add_order(order(3, OrderSide::Buy, 10.5))
+            .unwrap();
+        assert!(order_book.is_crossed());
+        assert_eq!(
+            order_book.drain_crossing_events(),
+            vec![CrossingEvent::Crossed {
+                bid: 10.5.into(),
+                ask: 10.0.into()
+            }]
+        );
+
+        order_book.cancel_order(Oid::new(3)).unwrap();
+        assert_eq!(
+            order_book.drain_crossing_events(),
+            vec![CrossingEvent::Uncrossed]
+        );
+    }
+}
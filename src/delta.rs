@@ -0,0 +1,298 @@
+//!
+//! Incremental market data. Every change of the book is published as a sequence numbered
+//! delta, so the L2 book can be rebuilt by applying the deltas to a depth snapshot.
+//!
+
+use alloc::vec::Vec;
+
+use hashbrown::HashSet;
+
+use crate::{LevelStore, OrderBook, OrderSide, Price, TapeEntry, Volume};
+
+/// Change of the book
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeltaEvent {
+    /// New price level
+    LevelAdded {
+        side: OrderSide,
+        price: Price,
+        volume: Volume,
+        order_count: usize,
+    },
+    /// Volume or number of orders at the price level changed
+    LevelUpdated {
+        side: OrderSide,
+        price: Price,
+        volume: Volume,
+        order_count: usize,
+    },
+    /// Price level has no volume left
+    LevelRemoved { side: OrderSide, price: Price },
+    /// Trade between two orders
+    Trade {
+        aggressor_side: OrderSide,
+        price: Price,
+        volume: Volume,
+    },
+}
+
+/// Sequence numbered change of the book
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BookDelta {
+    /// sequence numbers start at 1 and increase by one with every delta
+    pub sequence: u64,
+    /// event sequence of the book when the delta was published,
+    /// the delta follows the report with the same event sequence
+    pub event_sequence: u64,
+    pub event: DeltaEvent,
+}
+
+/// Deltas waiting to be drained, with levels that are known to the consumers
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DeltaFeed {
+    sequence: u64,
+    pending: Vec<BookDelta>,
+    published_bids: HashSet<Price>,
+    published_asks: HashSet<Price>,
+}
+
+impl DeltaFeed {
+    pub(crate) fn take_pending(&mut self) -> Vec<BookDelta> {
+        core::mem::take(&mut self.pending)
+    }
+
+    fn push(&mut self, event: DeltaEvent, event_sequence: u64) {
+        self.sequence += 1;
+        self.pending.push(BookDelta {
+            sequence: self.sequence,
+            event_sequence,
+            event,
+        });
+    }
+
+    pub(crate) fn record_trade(&mut self, entry: &TapeEntry) {
+        self.push(
+            DeltaEvent::Trade {
+                aggressor_side: entry.aggressor_side,
+                price: entry.price,
+                volume: entry.volume,
+            },
+            entry.sequence,
+        );
+    }
+
+    /// publish the current state of the level, level is none if it has been removed
+    fn publish_level(
+        &mut self,
+        side: OrderSide,
+        price: Price,
+        level: Option<(Volume, usize)>,
+        event_sequence: u64,
+    ) {
+        let published = match side {
+            OrderSide::Buy => &mut self.published_bids,
+            OrderSide::Sell => &mut self.published_asks,
+        };
+        match level {
+            Some((volume, order_count)) if !volume.is_zero() => {
+                let event = if published.insert(price) {
+                    DeltaEvent::LevelAdded {
+                        side,
+                        price,
+                        volume,
+                        order_count,
+                    }
+                } else {
+                    DeltaEvent::LevelUpdated {
+                        side,
+                        price,
+                        volume,
+                        order_count,
+                    }
+                };
+                self.push(event, event_sequence);
+            }
+            _ => {
+                if published.remove(&price) {
+                    self.push(DeltaEvent::LevelRemoved { side, price }, event_sequence);
+                }
+            }
+        }
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// publish a delta for every change of the book, deltas are collected with drain_deltas
+    pub fn with_delta_feed(mut self) -> Self {
+        // levels already on the book are part of the snapshot the consumer starts from
+        let feed = DeltaFeed {
+            published_bids: self.iter_levels(OrderSide::Buy).map(|l| l.price).collect(),
+            published_asks: self.iter_levels(OrderSide::Sell).map(|l| l.price).collect(),
+            ..DeltaFeed::default()
+        };
+        self.delta_feed = Some(feed);
+        self
+    }
+
+    /// sequence number of the last published delta,
+    /// deltas with higher sequence numbers apply on top of the current depth snapshot
+    pub fn sequence(&self) -> u64 {
+        self.delta_feed.as_ref().map_or(0, |feed| feed.sequence)
+    }
+
+    /// take the deltas published since the last call,
+    /// deltas of a conflated book are only published in batches
+    pub fn drain_deltas(&mut self) -> Vec<BookDelta> {
+        if self.conflator.is_some() {
+            self.conflate_deltas();
+            return Vec::new();
+        }
+        self.delta_feed
+            .as_mut()
+            .map(|feed| core::mem::take(&mut feed.pending))
+            .unwrap_or_default()
+    }
+
+    /// publish deltas for the levels that changed since the last call
+    pub(crate) fn publish_level_deltas(&mut self) {
+        for side in [OrderSide::Buy, OrderSide::Sell] {
+            let limits = match side {
+                OrderSide::Buy => &mut self.bids,
+                OrderSide::Sell => &mut self.asks,
+            };
+            let mut touched = core::mem::take(&mut limits.touched);
+            if let Some(feed) = &mut self.delta_feed {
+                touched.sort();
+                touched.dedup();
+                for price in touched.drain(..) {
+                    let level = limits
+                        .level_at(&price)
+                        .map(|level| (level.total_volume, level.order_count()));
+                    feed.publish_level(side, price, level, self.event_sequence);
+                }
+            }
+            touched.clear();
+            // keep the allocation for the next change
+            limits.touched = touched;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_delta {
+    use std::collections::BTreeMap;
+
+    use crate::*;
+
+    #[test]
+    fn test_deltas_rebuild_the_book() {
+        let mut order_book = OrderBook::default();
+        let order = LimitOrder::new(
+            Oid::new(1),
+            OrderSide::Buy,
+            Timestamp::new(1),
+            9.0.into(),
+            5.into(),
+        );
+        order_book.add_order(order).unwrap();
+
+        let mut order_book = order_book.with_delta_feed();
+        let mut bids = order_book
+            .depth(usize::MAX)
+            .bids
+            .into_iter()
+            .map(|l| (l.price, l.volume))
+            .collect::<BTreeMap<_, _>>();
+        let mut asks = BTreeMap::new();
+        let mut last_sequence = order_book.sequence();
+
+        let orders = [
+            Order::new_limit(
+                Oid::new(2),
+                OrderSide::Sell,
+                Timestamp::new(2),
+                10.0.into(),
+                5.into(),
+            ),
+            Order::new_limit(
+                Oid::new(3),
+                OrderSide::Sell,
+                Timestamp::new(3),
+                11.0.into(),
+                5.into(),
+            ),
+            Order::new_limit(
+                Oid::new(4),
+                OrderSide::Buy,
+                Timestamp::new(4),
+                9.0.into(),
+                3.into(),
+            ),
+            Order::new_limit(
+                Oid::new(5),
+                OrderSide::Buy,
+                Timestamp::new(5),
+                10.5.into(),
+                7.into(),
+            ),
+        ];
+        for order in &orders {
+            order_book.execute(order).unwrap();
+        }
+        order_book.cancel_order(Oid::new(1)).unwrap();
+
+        let mut trades = 0;
+        for delta in order_book.drain_deltas() {
+            assert_eq!(delta.sequence, last_sequence + 1);
+            last_sequence = delta.sequence;
+            match delta.event {
+                DeltaEvent::LevelAdded {
+                    side,
+                    price,
+                    volume,
+                    ..
+                }
+                | DeltaEvent::LevelUpdated {
+                    side,
+                    price,
+                    volume,
+                    ..
+                } => {
+                    let levels = if side == OrderSide::Buy {
+                        &mut bids
+                    } else {
+                        &mut asks
+                    };
+                    levels.insert(price, volume);
+                }
+                DeltaEvent::LevelRemoved { side, price } => {
+                    let levels = if side == OrderSide::Buy {
+                        &mut bids
+                    } else {
+                        &mut asks
+                    };
+                    levels.remove(&price);
+                }
+                DeltaEvent::Trade { .. } => trades += 1,
+            }
+        }
+        assert_eq!(trades, 1);
+
+        let depth = order_book.depth(usize::MAX);
+        let expected_bids = depth
+            .bids
+            .iter()
+            .map(|l| (l.price, l.volume))
+            .collect::<BTreeMap<_, _>>();
+        let expected_asks = depth
+            .asks
+            .iter()
+            .map(|l| (l.price, l.volume))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(bids, expected_bids);
+        assert_eq!(asks, expected_asks);
+        assert!(order_book.drain_deltas().is_empty());
+    }
+}
@@ -0,0 +1,324 @@
+//!
+//! Market data snapshots of the book.
+//! L2 depth aggregates the orders at each price level,
+//! L3 lists every resting order with its position in the level queue.
+//!
+
+use alloc::vec::Vec;
+
+use crate::{Level, LevelStore, LimitOrder, Oid, OrderBook, OrderSide, Price, Timestamp, Volume};
+
+/// Top of the book
+/// best bid and ask with the volume visible at those prices
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bbo {
+    pub bid_price: Option<Price>,
+    pub bid_volume: Volume,
+    pub ask_price: Option<Price>,
+    pub ask_volume: Volume,
+}
+
+/// Aggregated price level
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthLevel {
+    pub price: Price,
+    /// total visible volume at the level
+    pub volume: Volume,
+    pub order_count: usize,
+}
+
+impl From<&Level> for DepthLevel {
+    fn from(level: &Level) -> Self {
+        DepthLevel {
+            price: level.price(),
+            volume: level.total_volume(),
+            order_count: level.order_count(),
+        }
+    }
+}
+
+/// Top levels of both sides of the book, ordered from the best price
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DepthSnapshot {
+    /// bid levels, prices are descending
+    pub bids: Vec<DepthLevel>,
+    /// ask levels, prices are ascending
+    pub asks: Vec<DepthLevel>,
+}
+
+/// Resting order and its position in the level queue
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderEntry {
+    pub id: Oid,
+    pub price: Price,
+    /// volume visible on the book
+    pub volume: Volume,
+    pub timestamp: Timestamp,
+    /// 0 is the front of the level queue
+    pub queue_position: usize,
+}
+
+/// Every resting order of both sides of the book in price-time priority
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct L3Snapshot {
+    pub bids: Vec<OrderEntry>,
+    pub asks: Vec<OrderEntry>,
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// best bid and ask, consistent with each other after every change of the book
+    pub fn best_bid_ask(&self) -> Bbo {
+        self.bbo
+    }
+
+    /// top n levels of each side of the book
+    pub fn depth(&self, n: usize) -> DepthSnapshot {
+        DepthSnapshot {
+            bids: self.depth_levels(OrderSide::Buy, n),
+            asks: self.depth_levels(OrderSide::Sell, n),
+        }
+    }
+
+    fn depth_levels(&self, side: OrderSide, n: usize) -> Vec<DepthLevel> {
+        self.iter_levels(side)
+            .take(n)
+            .map(DepthLevel::from)
+            .collect()
+    }
+
+    /// top n price buckets of each side of the book, levels are grouped into buckets
+    /// of the given size, bids are rounded down and asks up to the bucket price.
+    /// Bucket that is not positive leaves the levels as they are.
+    pub fn aggregate_depth(&self, bucket: Price, n: usize) -> DepthSnapshot {
+        DepthSnapshot {
+            bids: self.bucket_levels(OrderSide::Buy, bucket, n),
+            asks: self.bucket_levels(OrderSide::Sell, bucket, n),
+        }
+    }
+
+    fn bucket_levels(&self, side: OrderSide, bucket: Price, n: usize) -> Vec<DepthLevel> {
+        let size = bucket.mantissa();
+        if size <= 0 {
+            return self.depth_levels(side, n);
+        }
+        let mut buckets: Vec<DepthLevel> = Vec::new();
+        for level in self.iter_levels(side) {
+            let bucket_price = bucket_price(level.price(), bucket, side == OrderSide::Sell);
+            // levels come from the best price, so each bucket is filled before the next one
+            match buckets.last_mut() {
+                Some(last) if last.price == bucket_price => {
+                    last.volume += level.total_volume();
+                    last.order_count += level.order_count();
+                }
+                _ => {
+                    if buckets.len() == n {
+                        break;
+                    }
+                    buckets.push(DepthLevel {
+                        price: bucket_price,
+                        ..DepthLevel::from(level)
+                    });
+                }
+            }
+        }
+        buckets
+    }
+
+    /// resting orders of one side of the book in price-time priority
+    pub fn iter_orders(&self, side: OrderSide) -> impl Iterator<Item = &LimitOrder> {
+        self.iter_levels(side)
+            .flat_map(|level| level.orders.iter())
+            .filter_map(|handle| self.orders.get_by_handle(handle))
+    }
+
+    /// every resting order of both sides of the book
+    pub fn l3_snapshot(&self) -> L3Snapshot {
+        L3Snapshot {
+            bids: self.order_entries(OrderSide::Buy),
+            asks: self.order_entries(OrderSide::Sell),
+        }
+    }
+
+    fn order_entries(&self, side: OrderSide) -> Vec<OrderEntry> {
+        self.iter_levels(side)
+            .flat_map(|level| {
+                level
+                    .orders
+                    .iter()
+                    .filter_map(|handle| self.orders.get_by_handle(handle))
+                    .enumerate()
+                    .map(|(queue_position, order)| OrderEntry {
+                        id: order.id,
+                        price: order.price,
+                        volume: order.visible_volume(),
+                        timestamp: order.timestamp,
+                        queue_position,
+                    })
+            })
+            .collect()
+    }
+
+    /// levels of one side of the book, starting from the best price
+    pub(crate) fn iter_levels(&self, side: OrderSide) -> impl Iterator<Item = &Level> {
+        self.limits(side).iter_levels(side)
+    }
+}
+
+/// price rounded down to a multiple of the bucket size, or up when round_up is set,
+/// bucket size must be positive
+pub(crate) fn bucket_price(price: Price, bucket: Price, round_up: bool) -> Price {
+    let (price, size) = (price.mantissa(), bucket.mantissa());
+    Price::from_mantissa(if round_up {
+        -(-price).div_euclid(size) * size
+    } else {
+        price.div_euclid(size) * size
+    })
+}
+
+#[cfg(test)]
+mod tests_depth {
+    use crate::*;
+
+    #[test]
+    fn test_best_bid_ask() {
+        let mut order_book = OrderBook::default();
+        assert_eq!(order_book.best_bid_ask(), Bbo::default());
+
+        for (id, side, price) in [
+            (1, OrderSide::Buy, 10.0),
+            (2, OrderSide::Sell, 11.0),
+            (3, OrderSide::Sell, 12.0),
+        ] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        let bbo = order_book.best_bid_ask();
+        assert_eq!(bbo.bid_price, Some(Price::new(10.0)));
+        assert_eq!(bbo.ask_price, Some(Price::new(11.0)));
+        assert_eq!(bbo.ask_volume, 5.into());
+
+        order_book.cancel_order(Oid::new(2)).unwrap();
+        assert_eq!(order_book.best_bid_ask().ask_price, Some(Price::new(12.0)));
+
+        let order = Order::new_market(Oid::new(4), OrderSide::Sell, Timestamp::new(4), 2.into());
+        order_book.execute(&order).unwrap();
+        assert_eq!(order_book.best_bid_ask().bid_volume, 3.into());
+    }
+
+    #[test]
+    fn test_depth() {
+        let mut order_book = OrderBook::default();
+        let orders = [
+            (1, OrderSide::Buy, 10.0, 5),
+            (2, OrderSide::Buy, 10.0, 7),
+            (3, OrderSide::Buy, 9.0, 1),
+            (4, OrderSide::Buy, 11.0, 2),
+            (5, OrderSide::Sell, 12.0, 3),
+            (6, OrderSide::Sell, 13.0, 4),
+        ];
+        for (id, side, price, volume) in orders {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+
+        let depth = order_book.depth(2);
+        assert_eq!(
+            depth.bids,
+            vec![
+                DepthLevel {
+                    price: 11.0.into(),
+                    volume: 2.into(),
+                    order_count: 1
+                },
+                DepthLevel {
+                    price: 10.0.into(),
+                    volume: 12.into(),
+                    order_count: 2
+                },
+            ]
+        );
+        assert_eq!(
+            depth.asks.iter().map(|l| l.price).collect::<Vec<_>>(),
+            vec![Price::new(12.0), Price::new(13.0)]
+        );
+
+        order_book.cancel_order(Oid::new(4)).unwrap();
+        assert_eq!(order_book.depth(1).bids[0].price, Price::new(10.0));
+
+        let bids = order_book
+            .iter_orders(OrderSide::Buy)
+            .map(|o| o.id)
+            .collect::<Vec<_>>();
+        assert_eq!(bids, vec![Oid::new(1), Oid::new(2), Oid::new(3)]);
+
+        let snapshot = order_book.l3_snapshot();
+        assert_eq!(
+            snapshot
+                .bids
+                .iter()
+                .map(|o| (o.id, o.queue_position))
+                .collect::<Vec<_>>(),
+            vec![(Oid::new(1), 0), (Oid::new(2), 1), (Oid::new(3), 0)]
+        );
+        assert_eq!(snapshot.asks[1].id, Oid::new(6));
+    }
+
+    #[test]
+    fn test_aggregate_depth() {
+        let mut order_book = OrderBook::default();
+        for (id, side, price, volume) in [
+            (1, OrderSide::Buy, 10.04, 1),
+            (2, OrderSide::Buy, 10.01, 2),
+            (3, OrderSide::Buy, 9.99, 3),
+            (4, OrderSide::Buy, 9.90, 4),
+            (5, OrderSide::Sell, 10.06, 5),
+            (6, OrderSide::Sell, 10.10, 6),
+        ] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+
+        let depth = order_book.aggregate_depth(0.05.into(), 2);
+        assert_eq!(
+            depth
+                .bids
+                .iter()
+                .map(|l| (l.price, l.volume, l.order_count))
+                .collect::<Vec<_>>(),
+            vec![
+                (Price::new(10.0), 3.into(), 2),
+                (Price::new(9.95), 3.into(), 1)
+            ]
+        );
+        assert_eq!(
+            depth
+                .asks
+                .iter()
+                .map(|l| (l.price, l.volume))
+                .collect::<Vec<_>>(),
+            vec![(Price::new(10.10), 11.into())]
+        );
+        assert_eq!(
+            order_book.aggregate_depth(Price::ZERO, 3),
+            order_book.depth(3)
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//!
+//! Depth limited book. The book keeps only the given number of best price levels on each side,
+//! orders at worse prices are cancelled as soon as they fall outside of the kept levels.
+//! Memory stays bounded when only the top of a deep book matters, e.g. for mirrors of
+//! external feeds.
+//!
+
+use alloc::vec::Vec;
+
+use crate::{CancellationStatus, LevelStore, OrderBook, OrderSide};
+
+/// Levels kept on each side and the number of orders dropped so far
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DepthLimit {
+    levels: usize,
+    dropped: u64,
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// keep at most the given number of best price levels on each side, at least one level is kept
+    pub fn with_max_depth(mut self, levels: usize) -> Self {
+        self.depth_limit = Some(DepthLimit {
+            levels: levels.max(1),
+            dropped: 0,
+        });
+        self
+    }
+
+    /// number of price levels kept on each side
+    pub fn max_depth(&self) -> Option<usize> {
+        self.depth_limit.map(|limit| limit.levels)
+    }
+
+    /// number of orders dropped since they were outside of the kept levels
+    pub fn dropped_orders(&self) -> u64 {
+        self.depth_limit.map_or(0, |limit| limit.dropped)
+    }
+
+    /// cancel the orders at levels beyond the depth limit
+    pub(crate) fn trim_depth(&mut self, side: OrderSide) {
+        let Some(limit) = self.depth_limit else {
+            return;
+        };
+        if self.level_count(side) <= limit.levels {
+            return;
+        }
+        let order_ids = self
+            .iter_levels(side)
+            .skip(limit.levels)
+            .flat_map(|level| level.orders.iter())
+            .filter_map(|handle| self.orders.get_by_handle(handle))
+            .map(|order| order.id)
+            .collect::<Vec<_>>();
+        let dropped = self
+            .remove_orders(order_ids, CancellationStatus::Cancelled)
+            .len();
+        if let Some(limit) = self.depth_limit.as_mut() {
+            limit.dropped += dropped as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_depth_limit {
+    use crate::*;
+
+    #[test]
+    fn test_book_keeps_best_levels() {
+        let mut order_book = OrderBook::default().with_max_depth(2);
+        assert_eq!(order_book.max_depth(), Some(2));
+        for (id, price) in [(1, 10.0), (2, 11.0), (3, 12.0), (4, 10.5)] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+
+        assert_eq!(order_book.level_count(OrderSide::Sell), 2);
+        assert_eq!(order_book.worst_price(OrderSide::Sell), Some(10.5.into()));
+        assert_eq!(order_book.dropped_orders(), 2);
+        assert_eq!(
+            order_book.get_order(Oid::new(2)).map(|o| o.state),
+            Some(OrderState::Cancelled)
+        );
+    }
+}
@@ -0,0 +1,515 @@
+//!
+//! Matching engine around the order book.
+//! Limit orders are added to the book without matching, market orders are queued and matched
+//! first in first out against the resting liquidity. Matching is driven by the caller, either
+//! one fill at a time or by draining everything that can be matched with `match_all`.
+//! Order entry of each owner can be rate limited, orders above the rate are rejected.
+//! Positions of the owners can be tracked from the fills made by the engine.
+//!
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use thiserror::Error;
+
+use crate::{
+    AuditEvent, CancellationReport, CancellationStatus, Clock, Fill, FillAtMarket, LimitOrder, Oid,
+    Order, OrderBook, OrderBookError, OrderSide, OrderType, OwnerId, Positions, Price, PriceBands,
+    RateLimit, RateLimiter, SnapshotError, Symbol, TradingState, Volume,
+};
+
+/// Matching engine error
+#[derive(Error, Debug, PartialEq, PartialOrd, Clone)]
+pub enum MatchingEngineError {
+    #[error("OrderBook error: {0}")]
+    OrderBookError(#[from] OrderBookError),
+    /// Limit order has no price
+    #[error("Limit order {0} price is required")]
+    MissingPrice(Oid),
+    #[error("Order {0} volume is zero")]
+    ZeroVolume(Oid),
+    /// Order with the same id is resting on the book or queued
+    #[error("Order {0} is already placed")]
+    DuplicateOrderId(Oid),
+    #[error("No market orders to match")]
+    NoMarketOrders,
+    #[error("No orders to match")]
+    NoOrdersToMatch,
+    /// Owner sent more orders than allowed by the rate limit
+    #[error("Owner {0} is throttled")]
+    Throttled(OwnerId),
+}
+
+/// Single match made by the engine
+#[derive(Debug, Clone)]
+pub enum Match {
+    /// queued market order matched against the order at the front of the best level
+    Market(FillAtMarket),
+    /// crossed limit orders matched against each other
+    Limit(Fill),
+}
+
+/// Matching engine
+#[derive(Debug, Default)]
+pub struct MatchingEngine {
+    order_book: OrderBook,
+    // queue of market orders, that should be matched first in first out
+    // volume of the queued order is the volume that has not been filled yet
+    market_orders: VecDeque<Order>,
+    // orders of owners above the rate limit are rejected
+    rate_limiter: Option<RateLimiter>,
+    // positions of the owners in the symbol of the book, tracked only when enabled
+    positions: Option<(Symbol, Positions)>,
+}
+
+impl MatchingEngine {
+    /// engine matching orders of the given book
+    pub fn new(order_book: OrderBook) -> Self {
+        MatchingEngine {
+            order_book,
+            market_orders: VecDeque::new(),
+            rate_limiter: None,
+            positions: None,
+        }
+    }
+
+    /// track positions of the owners from the fills, the book trades the symbol
+    /// owners of filled resting orders are looked up in the book's finished orders history
+    pub fn with_positions(mut self, symbol: Symbol) -> Self {
+        self.positions = Some((symbol, Positions::new()));
+        self
+    }
+
+    pub fn positions(&self) -> Option<&Positions> {
+        self.positions.as_ref().map(|(_, positions)| positions)
+    }
+
+    /// limit the rate of orders of each owner, orders without an owner are not limited
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(rate_limit));
+        self
+    }
+
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+
+    /// stamp placed orders with the clock, rate limits and expiry use its time as well
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.order_book.set_clock(clock);
+        self
+    }
+
+    pub fn order_book(&self) -> &OrderBook {
+        &self.order_book
+    }
+
+    pub fn into_order_book(self) -> OrderBook {
+        self.order_book
+    }
+
+    pub fn set_price_bands(&mut self, price_bands: PriceBands) {
+        // order book rejects limit orders outside of the bands
+        self.order_book.set_price_bands(Some(price_bands));
+    }
+
+    /// save the book so the engine can be warm restarted, queued market orders are not saved
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.order_book.snapshot()
+    }
+
+    /// warm restart of the engine from the saved book, price bands are configuration
+    /// so they need to be set again
+    pub fn restore(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Ok(MatchingEngine::new(OrderBook::restore(bytes)?))
+    }
+
+    pub fn has_market_orders(&self) -> bool {
+        !self.market_orders.is_empty()
+    }
+
+    /// market orders waiting for liquidity, in the order they will be matched
+    pub fn market_orders(&self) -> impl Iterator<Item = &Order> {
+        self.market_orders.iter()
+    }
+
+    /// validate the order, limit order is added to the book, market order is queued
+    pub fn place_order(&mut self, order: Order) -> Result<(), MatchingEngineError> {
+        let order = self.order_book.stamp(&order)?.unwrap_or(order);
+        if let (Some(limiter), Some(owner)) = (&mut self.rate_limiter, order.owner) {
+            if !limiter.try_acquire(owner, order.timestamp) {
+                return Err(MatchingEngineError::Throttled(owner));
+            }
+        }
+        if order.volume.is_zero() {
+            return Err(MatchingEngineError::ZeroVolume(order.id));
+        }
+        let resting = self
+            .order_book
+            .get_order(order.id)
+            .is_some_and(|view| !view.remaining_volume.is_zero());
+        if resting || self.market_orders.iter().any(|o| o.id == order.id) {
+            return Err(MatchingEngineError::DuplicateOrderId(order.id));
+        }
+
+        // market to limit order is placed as a limit order at the best opposite price
+        let order = match order.kind {
+            OrderType::MarketToLimit => self
+                .order_book
+                .market_to_limit(&order)
+                .ok_or(MatchingEngineError::NoOrdersToMatch)?,
+            _ => order,
+        };
+
+        match order.kind {
+            OrderType::Limit | OrderType::MarketToLimit => {
+                if order.price.is_none() {
+                    return Err(MatchingEngineError::MissingPrice(order.id));
+                }
+                let limit_order = LimitOrder::try_from(&order)
+                    .map_err(|_| MatchingEngineError::MissingPrice(order.id))?;
+                self.order_book.add_order(limit_order)?;
+            }
+            OrderType::Market => {
+                let state = self.order_book.state();
+                if matches!(state, TradingState::Closed | TradingState::Suspended) {
+                    return Err(OrderBookError::InvalidState(state).into());
+                }
+                let sequence = self.order_book.next_event_sequence();
+                self.order_book
+                    .record_event(order.id, sequence, AuditEvent::accepted(&order));
+                self.market_orders.push_back(order)
+            }
+        }
+        Ok(())
+    }
+
+    /// cancel all resting and queued market orders and suspend the book,
+    /// only cancels are accepted until the book is resumed
+    pub fn kill_switch(&mut self) -> Vec<CancellationReport> {
+        let mut reports = self.order_book.kill_switch();
+        for order in self.market_orders.drain(..) {
+            let sequence = self.order_book.next_event_sequence();
+            self.order_book
+                .record_event(order.id, sequence, AuditEvent::Cancelled);
+            reports.push(CancellationReport {
+                order_id: order.id,
+                status: CancellationStatus::Cancelled,
+                sequence,
+            });
+        }
+        reports
+    }
+
+    /// true if the best bid is at or above the best ask
+    pub fn can_match_orders(&self) -> bool {
+        let best_buy = self.order_book.get_best_buy();
+        let best_sell = self.order_book.get_best_sell();
+        match (best_buy, best_sell) {
+            (Some(buy_price), Some(sell_price)) => buy_price >= sell_price,
+            _ => false,
+        }
+    }
+
+    /// match the orders at the front of the best crossed levels
+    pub fn match_orders(&mut self) -> Result<Fill, MatchingEngineError> {
+        let fill = self
+            .order_book
+            .find_and_fill_best_orders()
+            .map_err(|e| match e {
+                OrderBookError::NoOrderToMatch => MatchingEngineError::NoOrdersToMatch,
+                e => e.into(),
+            })?;
+        self.record_fill(&fill);
+        Ok(fill)
+    }
+
+    /// update positions of both owners of the fill
+    fn record_fill(&mut self, fill: &Fill) {
+        for (order_id, side) in [
+            (fill.buy_order_id, OrderSide::Buy),
+            (fill.sell_order_id, OrderSide::Sell),
+        ] {
+            let owner = self.resting_owner(order_id);
+            self.record_position(owner, side, fill.trade_price, fill.volume);
+        }
+    }
+
+    fn resting_owner(&self, order_id: Oid) -> Option<OwnerId> {
+        self.order_book
+            .get_order(order_id)
+            .and_then(|view| view.owner)
+    }
+
+    fn record_position(
+        &mut self,
+        owner: Option<OwnerId>,
+        side: OrderSide,
+        price: Price,
+        volume: Volume,
+    ) {
+        if let (Some((symbol, positions)), Some(owner)) = (&mut self.positions, owner) {
+            positions.apply(symbol, owner, side, price, volume);
+        }
+    }
+
+    /// match the market order at the front of the queue against the best resting order,
+    /// order is removed from the queue once it is completely filled
+    pub fn match_market_order(&mut self) -> Result<FillAtMarket, MatchingEngineError> {
+        let Some(order) = self.market_orders.front_mut() else {
+            return Err(MatchingEngineError::NoMarketOrders);
+        };
+        let (owner, side) = (order.owner, order.side);
+        let fill = self
+            .order_book
+            .fill_market_order(order)
+            .map_err(|e| match e {
+                OrderBookError::NoOrderToMatch => MatchingEngineError::NoOrdersToMatch,
+                e => e.into(),
+            })?;
+        order.volume -= fill.filled_volume;
+        if order.volume.is_zero() {
+            self.market_orders.pop_front();
+        }
+        let resting_owner = self.resting_owner(fill.order_id);
+        for (owner, side) in [(owner, side), (resting_owner, side.opposite())] {
+            self.record_position(owner, side, fill.order_price, fill.filled_volume);
+        }
+        Ok(fill)
+    }
+
+    /// match queued market orders and then crossed limit orders until nothing more can be matched
+    /// market orders that could not be filled stay queued until liquidity arrives
+    pub fn match_all(&mut self) -> Result<Vec<Match>, MatchingEngineError> {
+        let mut matches = Vec::new();
+        self.match_all_into(&mut matches)?;
+        Ok(matches)
+    }
+
+    /// same as `match_all`, matches are appended to the buffer, so a buffer reused
+    /// across calls lets hot loops match without allocating
+    /// matches made before an error are kept in the buffer
+    pub fn match_all_into(&mut self, matches: &mut Vec<Match>) -> Result<(), MatchingEngineError> {
+        while self.has_market_orders() {
+            match self.match_market_order() {
+                Ok(fill) => matches.push(Match::Market(fill)),
+                Err(MatchingEngineError::NoOrdersToMatch) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let start = matches.len();
+        self.order_book
+            .match_all_into(|fill| matches.push(Match::Limit(fill)));
+        for limit in &matches[start..] {
+            if let Match::Limit(fill) = limit {
+                self.record_fill(fill);
+            }
+        }
+        if self.can_match_orders() {
+            // book is still crossed, so matching was stopped by an error
+            matches.push(Match::Limit(self.match_orders()?));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_engine {
+    use crate::*;
+
+    fn limit(id: u64, side: OrderSide, price: f64, volume: u64) -> Order {
+        Order::new_limit(
+            Oid::new(id),
+            side,
+            Timestamp::new(id),
+            price.into(),
+            volume.into(),
+        )
+    }
+
+    fn market(id: u64, side: OrderSide, volume: u64) -> Order {
+        Order::new_market(Oid::new(id), side, Timestamp::new(id), volume.into())
+    }
+
+    #[test]
+    fn test_place_order_validation() {
+        let mut engine = MatchingEngine::default();
+        engine
+            .place_order(limit(1, OrderSide::Buy, 10.0, 5))
+            .unwrap();
+
+        let mut no_price = limit(2, OrderSide::Buy, 10.0, 5);
+        no_price.price = None;
+        assert_eq!(
+            engine.place_order(no_price).unwrap_err(),
+            MatchingEngineError::MissingPrice(Oid::new(2))
+        );
+        assert_eq!(
+            engine
+                .place_order(market(3, OrderSide::Sell, 0))
+                .unwrap_err(),
+            MatchingEngineError::ZeroVolume(Oid::new(3))
+        );
+        assert_eq!(
+            engine
+                .place_order(limit(1, OrderSide::Sell, 11.0, 5))
+                .unwrap_err(),
+            MatchingEngineError::DuplicateOrderId(Oid::new(1))
+        );
+        engine.place_order(market(4, OrderSide::Sell, 1)).unwrap();
+        assert_eq!(
+            engine
+                .place_order(market(4, OrderSide::Sell, 1))
+                .unwrap_err(),
+            MatchingEngineError::DuplicateOrderId(Oid::new(4))
+        );
+        assert_eq!(engine.market_orders().count(), 1);
+    }
+
+    #[test]
+    fn test_match_all() {
+        let mut engine = MatchingEngine::default();
+        assert_eq!(
+            engine.match_market_order().unwrap_err(),
+            MatchingEngineError::NoMarketOrders
+        );
+
+        engine
+            .place_order(limit(1, OrderSide::Sell, 10.0, 3))
+            .unwrap();
+        engine
+            .place_order(limit(2, OrderSide::Sell, 11.0, 3))
+            .unwrap();
+        engine.place_order(market(3, OrderSide::Buy, 4)).unwrap();
+        engine.place_order(market(4, OrderSide::Buy, 5)).unwrap();
+        engine
+            .place_order(limit(5, OrderSide::Buy, 12.0, 2))
+            .unwrap();
+        assert!(engine.can_match_orders());
+
+        let matches = engine.match_all().unwrap();
+        // market orders are matched first, the second one takes the rest of the liquidity
+        let market_fills = matches
+            .iter()
+            .filter_map(|m| match m {
+                Match::Market(fill) => Some((fill.market_order_id, fill.filled_volume)),
+                Match::Limit(_) => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            market_fills,
+            vec![
+                (Oid::new(3), 3.into()),
+                (Oid::new(3), 1.into()),
+                (Oid::new(4), 2.into()),
+            ]
+        );
+        assert!(!matches.iter().any(|m| matches!(m, Match::Limit(_))));
+
+        // rest of the market order waits for liquidity
+        assert_eq!(
+            engine.market_orders().map(|o| o.volume).collect::<Vec<_>>(),
+            vec![3.into()]
+        );
+        assert!(!engine.can_match_orders());
+        assert_eq!(
+            engine.match_orders().unwrap_err(),
+            MatchingEngineError::NoOrdersToMatch
+        );
+
+        engine
+            .place_order(limit(6, OrderSide::Sell, 12.0, 4))
+            .unwrap();
+        let matches = engine.match_all().unwrap();
+        assert_eq!(matches.len(), 2);
+        assert!(matches!(&matches[1], Match::Limit(fill) if fill.volume == 1.into()));
+        assert!(!engine.has_market_orders());
+        assert_eq!(engine.order_book().get_best_buy_volume(), Some(1.into()));
+    }
+
+    #[test]
+    fn test_kill_switch() {
+        let mut engine = MatchingEngine::default();
+        engine
+            .place_order(limit(1, OrderSide::Buy, 10.0, 5))
+            .unwrap();
+        engine
+            .place_order(limit(2, OrderSide::Sell, 11.0, 5))
+            .unwrap();
+        engine.place_order(market(3, OrderSide::Sell, 9)).unwrap();
+        engine.place_order(market(4, OrderSide::Buy, 1)).unwrap();
+
+        let mut cancelled = engine
+            .kill_switch()
+            .iter()
+            .map(|r| u64::from(r.order_id()))
+            .collect::<Vec<_>>();
+        cancelled.sort();
+        assert_eq!(cancelled, vec![1, 2, 3, 4]);
+        assert!(engine.order_book().is_empty());
+        assert!(!engine.has_market_orders());
+        assert_eq!(engine.order_book().state(), TradingState::Suspended);
+
+        let suspended = MatchingEngineError::OrderBookError(OrderBookError::InvalidState(
+            TradingState::Suspended,
+        ));
+        assert_eq!(
+            engine
+                .place_order(limit(5, OrderSide::Buy, 10.0, 5))
+                .unwrap_err(),
+            suspended
+        );
+        assert_eq!(
+            engine
+                .place_order(market(6, OrderSide::Buy, 5))
+                .unwrap_err(),
+            suspended
+        );
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let owner = OwnerId::new(1);
+        let mut engine = MatchingEngine::default().with_rate_limit(RateLimit::new(1, 2));
+        for id in 1..=3 {
+            let result = engine.place_order(limit(id, OrderSide::Buy, 10.0, 1).with_owner(owner));
+            assert_eq!(result.is_ok(), id < 3);
+        }
+        assert_eq!(
+            engine
+                .place_order(limit(4, OrderSide::Buy, 10.0, 1).with_owner(owner))
+                .unwrap_err(),
+            MatchingEngineError::Throttled(owner)
+        );
+        // orders without owner are not limited
+        engine
+            .place_order(limit(5, OrderSide::Buy, 10.0, 1))
+            .unwrap();
+        assert_eq!(engine.rate_limiter().unwrap().throttled(owner), 2);
+        assert_eq!(engine.order_book().order_count(), 3);
+    }
+
+    #[test]
+    fn test_positions_from_fills() {
+        let symbol = Symbol::new("AAPL");
+        let (alice, bob) = (OwnerId::new(1), OwnerId::new(2));
+        let mut engine = MatchingEngine::default().with_positions(symbol.clone());
+        engine
+            .place_order(limit(1, OrderSide::Sell, 10.0, 5).with_owner(alice))
+            .unwrap();
+        engine
+            .place_order(market(2, OrderSide::Buy, 2).with_owner(bob))
+            .unwrap();
+        engine
+            .place_order(limit(3, OrderSide::Buy, 12.0, 3).with_owner(bob))
+            .unwrap();
+        engine.match_all().unwrap();
+
+        let positions = engine.positions().unwrap();
+        let position = positions.position(&symbol, alice);
+        let units = |units: i64| units * utils::VOLUME_SCALE as i64;
+        assert_eq!(position.quantity, units(-5));
+        assert_eq!(position.average_price, Price::new(10.0));
+        assert_eq!(positions.position(&symbol, bob).quantity, units(5));
+    }
+}
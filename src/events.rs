@@ -0,0 +1,340 @@
+//!
+//! Event log of the book. Every mutating call of the book is recorded as a `BookEvent`
+//! once the log is enabled, applying the events in order to an empty book rebuilds it,
+//! so the state of the book at any point of a session can be inspected from its log.
+//! Calls made by the book itself, i.e. the cancel of the previous quote, are part of the
+//! event of the outer call and are not logged separately. Configuration set with the
+//! `with_*` builders, clocks and risk validators are not logged, events are replayed on a book
+//! built with the same configuration.
+//!
+
+use alloc::vec::Vec;
+
+use crate::{
+    LevelStore, LimitOrder, OcoTrigger, Oid, Order, OrderBook, OrderSide, OwnerId, Price,
+    PriceBands, Quote, Timestamp, TradingState, Volume,
+};
+
+/// Mutating call of the book
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookEvent {
+    AddOrder(LimitOrder),
+    AddOrders(Vec<Order>),
+    /// order is stamped with the time of the clock if the book had one
+    Execute {
+        order: Order,
+        now: Option<Timestamp>,
+    },
+    Cancel(Oid),
+    /// replaced order takes the timestamp if given
+    Amend {
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+        timestamp: Option<Timestamp>,
+    },
+    PurgeExpired(Timestamp),
+    FillBestOrders,
+    MatchAll,
+    FillMarketOrder(Order),
+    Uncross,
+    Transition {
+        to: TradingState,
+        now: Timestamp,
+    },
+    KillSwitch,
+    SubmitQuote {
+        owner: OwnerId,
+        quote: Quote,
+        order_ids: (Oid, Oid),
+        timestamp: Timestamp,
+    },
+    CancelQuote(OwnerId),
+    CancelAll(OwnerId),
+    CancelSide(OrderSide),
+    CancelRange {
+        side: OrderSide,
+        from_price: Price,
+        to_price: Price,
+    },
+    LinkOco {
+        first: Oid,
+        second: Oid,
+        trigger: OcoTrigger,
+    },
+    SetPriceBands(Option<PriceBands>),
+    SetReferencePrice(Option<Price>),
+    Compact,
+    GarbageCollect {
+        budget: usize,
+    },
+}
+
+impl BookEvent {
+    /// time carried by the event, none for events without one
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        match self {
+            BookEvent::AddOrder(order) => Some(order.timestamp),
+            BookEvent::AddOrders(orders) => orders
+                .iter()
+                .map(|order| order.timestamp)
+                .max_by_key(|timestamp| u64::from(*timestamp)),
+            BookEvent::Execute { order, now } => Some(now.unwrap_or(order.timestamp)),
+            BookEvent::Amend { timestamp, .. } => *timestamp,
+            BookEvent::PurgeExpired(now) | BookEvent::Transition { now, .. } => Some(*now),
+            BookEvent::FillMarketOrder(order) => Some(order.timestamp),
+            BookEvent::SubmitQuote { timestamp, .. } => Some(*timestamp),
+            _ => None,
+        }
+    }
+}
+
+impl OrderBook {
+    /// rebuild the book by applying the events to an empty book,
+    /// books with other level stores replay the events on `OrderBook::empty`
+    pub fn from_events(events: impl IntoIterator<Item = BookEvent>) -> Self {
+        let mut order_book = OrderBook::empty();
+        order_book.replay_events(events);
+        order_book
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// record every mutating call in the event log
+    pub fn with_event_log(mut self) -> Self {
+        self.event_log = Some(Vec::new());
+        self
+    }
+
+    /// events logged since the log was enabled or last drained
+    pub fn book_events(&self) -> &[BookEvent] {
+        self.event_log.as_deref().unwrap_or_default()
+    }
+
+    /// take the logged events since the last call
+    pub fn drain_book_events(&mut self) -> Vec<BookEvent> {
+        self.event_log
+            .as_mut()
+            .map(core::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// apply the events in order, results of the calls are dropped
+    /// as they are the same as the results of the recorded calls
+    pub fn replay_events(&mut self, events: impl IntoIterator<Item = BookEvent>) {
+        for event in events {
+            self.apply_event(event);
+        }
+    }
+
+    /// make the call recorded by the event
+    pub fn apply_event(&mut self, event: BookEvent) {
+        match event {
+            BookEvent::AddOrder(order) => {
+                let _ = self.add_order(order);
+            }
+            BookEvent::AddOrders(orders) => {
+                self.add_orders(orders);
+            }
+            BookEvent::Execute { order, now } => {
+                let _ = self.execute_at(&order, now);
+            }
+            BookEvent::Cancel(order_id) => {
+                let _ = self.cancel_order(order_id);
+            }
+            BookEvent::Amend {
+                order_id,
+                price,
+                volume,
+                timestamp: None,
+            } => {
+                let _ = self.amend_order(order_id, price, volume);
+            }
+            BookEvent::Amend {
+                order_id,
+                price,
+                volume,
+                timestamp: Some(now),
+            } => {
+                let _ = self.amend_order_at(order_id, price, volume, now);
+            }
+            BookEvent::PurgeExpired(now) => {
+                self.purge_expired(now);
+            }
+            BookEvent::FillBestOrders => {
+                let _ = self.find_and_fill_best_orders();
+            }
+            BookEvent::MatchAll => {
+                self.match_all();
+            }
+            BookEvent::FillMarketOrder(order) => {
+                let _ = self.fill_market_order(&order);
+            }
+            BookEvent::Uncross => {
+                let _ = self.uncross();
+            }
+            BookEvent::Transition { to, now } => {
+                let _ = self.transition(to, now);
+            }
+            BookEvent::KillSwitch => {
+                self.kill_switch();
+            }
+            BookEvent::SubmitQuote {
+                owner,
+                quote,
+                order_ids,
+                timestamp,
+            } => {
+                let _ = self.submit_quote(owner, quote, order_ids, timestamp);
+            }
+            BookEvent::CancelQuote(owner) => {
+                self.cancel_quote(owner);
+            }
+            BookEvent::CancelAll(owner) => {
+                self.cancel_all(owner);
+            }
+            BookEvent::CancelSide(side) => {
+                self.cancel_side(side);
+            }
+            BookEvent::CancelRange {
+                side,
+                from_price,
+                to_price,
+            } => {
+                self.cancel_range(side, from_price, to_price);
+            }
+            BookEvent::LinkOco {
+                first,
+                second,
+                trigger,
+            } => {
+                let _ = self.link_oco(first, second, trigger);
+            }
+            BookEvent::SetPriceBands(price_bands) => self.set_price_bands(price_bands),
+            BookEvent::SetReferencePrice(price) => self.set_reference_price(price),
+            BookEvent::Compact => {
+                self.compact();
+            }
+            BookEvent::GarbageCollect { budget } => {
+                self.garbage_collect_bounded(budget);
+            }
+        }
+    }
+
+    /// log the event of the call, calls made while applying it are not logged
+    pub(crate) fn logged<T>(
+        &mut self,
+        event: impl FnOnce() -> BookEvent,
+        apply: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let Some(mut log) = self.event_log.take() else {
+            return apply(self);
+        };
+        log.push(event());
+        let result = apply(self);
+        self.event_log = Some(log);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests_events {
+    use crate::*;
+
+    #[test]
+    fn test_book_is_rebuilt_from_events() {
+        let mut order_book = OrderBook::default().with_event_log();
+        let order = |id: u64, side: OrderSide, price: f64, volume: u64| {
+            Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            )
+        };
+        order_book
+            .execute(&order(1, OrderSide::Sell, 10.0, 5))
+            .unwrap();
+        order_book
+            .execute(&order(2, OrderSide::Sell, 11.0, 5))
+            .unwrap();
+        order_book
+            .execute(&order(3, OrderSide::Buy, 9.0, 5))
+            .unwrap();
+        order_book
+            .execute(&order(4, OrderSide::Buy, 10.0, 2))
+            .unwrap();
+        order_book
+            .amend_order(Oid::new(3), 9.5.into(), 8.into())
+            .unwrap();
+        order_book.cancel_order(Oid::new(2)).unwrap();
+        // rejected calls are logged as well
+        assert!(order_book.cancel_order(Oid::new(2)).is_err());
+        let quote = Quote::new(8.0.into(), 3.into(), 12.0.into(), 3.into());
+        order_book
+            .submit_quote(
+                OwnerId::new(1),
+                quote,
+                (Oid::new(5), Oid::new(6)),
+                Timestamp::new(5),
+            )
+            .unwrap();
+        order_book.halt(Timestamp::new(6)).unwrap();
+
+        let events = order_book.book_events().to_vec();
+        // cancel of the previous quote and the quote orders are part of the quote event
+        assert_eq!(events.len(), 9);
+        assert_eq!(events[5], BookEvent::Cancel(Oid::new(2)));
+
+        let rebuilt = OrderBook::from_events(events.clone());
+        assert_eq!(rebuilt.depth(usize::MAX), order_book.depth(usize::MAX));
+        assert_eq!(rebuilt.order_count(), order_book.order_count());
+        assert_eq!(rebuilt.state(), TradingState::Halted);
+        assert_eq!(rebuilt.event_sequence(), order_book.event_sequence());
+        assert_eq!(rebuilt.quote(OwnerId::new(1)), Some(quote));
+
+        // book as it was before the amend
+        let before = OrderBook::from_events(events.into_iter().take(4));
+        assert_eq!(before.get_best_buy(), Some(Price::new(9.0)));
+        assert_eq!(before.get_best_sell_volume(), Some(3.into()));
+
+        assert_eq!(order_book.drain_book_events().len(), 9);
+        assert!(order_book.book_events().is_empty());
+    }
+
+    #[test]
+    fn test_clock_time_is_replayed() {
+        let clock = ManualClock::new(Timestamp::new(100));
+        let mut order_book = OrderBook::default()
+            .with_clock(clock.clone())
+            .with_event_log();
+        let sell = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            10.0.into(),
+            5.into(),
+        )
+        .with_expiry(Timestamp::new(150));
+        order_book.execute(&sell).unwrap();
+        clock.advance(50);
+        let buy = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Buy,
+            Timestamp::new(2),
+            10.0.into(),
+            5.into(),
+        );
+        order_book.execute(&buy).unwrap();
+
+        // replayed book has no clock, the orders are stamped with the logged time
+        let rebuilt = OrderBook::from_events(order_book.drain_book_events());
+        assert_eq!(rebuilt.get_best_sell(), None);
+        assert_eq!(rebuilt.get_best_buy(), Some(Price::new(10.0)));
+        assert_eq!(
+            rebuilt.orders.get(&Oid::new(2)).unwrap().timestamp,
+            Timestamp::new(150)
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//!
+//! Adapters of the depth feeds of external venues. The adapter parses the depth snapshot and
+//! the incremental updates of the venue, the feed book validates their sequence numbers and
+//! applies them to a mirror book. Updates received before the snapshot are buffered and
+//! replayed on top of it, as the venues recommend for building a local book.
+//! Snapshots are fetched by the caller from the url given by the adapter, or sent on the stream.
+//! Venues without sequence numbers publish checksums of their book instead, the feed book
+//! compares them with the checksum of the mirror after each update.
+//!
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use thiserror::Error;
+
+use crate::{
+    BookDelta, DeltaEvent, DepthLevel, DepthSnapshot, MirrorBook, MirrorError, OrderBook,
+    OrderSide, Price, Volume,
+};
+
+/// Price levels of the venue at the sequence
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedSnapshot {
+    pub sequence: u64,
+    pub bids: Vec<(Price, Volume)>,
+    pub asks: Vec<(Price, Volume)>,
+}
+
+/// New volume of the levels changed by the range of the sequence numbers of the venue,
+/// level with zero volume is removed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedUpdate {
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    pub bids: Vec<(Price, Volume)>,
+    pub asks: Vec<(Price, Volume)>,
+    /// checksum of the venue book after the update
+    pub checksum: Option<u32>,
+}
+
+/// Depth message of the stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedMessage {
+    Snapshot(FeedSnapshot),
+    Update(FeedUpdate),
+}
+
+/// Feed parsing or applying error
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum FeedError {
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+    /// Updates were lost, the book waits for a new snapshot
+    #[error("Sequence gap, expected {expected} received {received}")]
+    SequenceGap { expected: u64, received: u64 },
+    /// Book differs from the book of the venue, the book waits for a new snapshot
+    #[error("Checksum mismatch, expected {expected} computed {computed}")]
+    ChecksumMismatch { expected: u32, computed: u32 },
+    #[error("Mirror error: {0}")]
+    MirrorError(#[from] MirrorError),
+}
+
+/// Parser of the depth messages of a venue
+pub trait FeedAdapter {
+    /// url of the depth snapshot fetched by the caller,
+    /// none if the venue sends the snapshot on the stream
+    fn snapshot_url(&self) -> Option<String>;
+
+    /// parse the depth snapshot response
+    fn parse_snapshot(&self, message: &str) -> Result<FeedSnapshot, FeedError>;
+
+    /// parse the message of the stream, none if it is not a depth message of the instrument,
+    /// i.e. subscription confirmations and heartbeats
+    fn parse_message(&self, message: &str) -> Result<Option<FeedMessage>, FeedError>;
+
+    /// updates carry sequence numbers of the venue,
+    /// updates of venues without them are applied in the order they are received
+    fn is_sequenced(&self) -> bool {
+        true
+    }
+
+    /// number of levels of each side kept by the venue, levels pushed beyond it are dropped
+    fn depth(&self) -> Option<usize> {
+        None
+    }
+
+    /// checksum of the book as computed by the venue, none if the venue has no checksums
+    fn checksum(&self, _book: &OrderBook) -> Option<u32> {
+        None
+    }
+}
+
+/// Book of a venue built from its depth feed
+#[derive(Debug)]
+pub struct FeedBook<A> {
+    adapter: A,
+    mirror: Option<MirrorBook>,
+    // last applied sequence of the venue
+    sequence: u64,
+    // updates received while there is no snapshot
+    pending: Vec<FeedUpdate>,
+}
+
+impl<A: FeedAdapter> FeedBook<A> {
+    /// book waiting for its first snapshot
+    pub fn new(adapter: A) -> Self {
+        FeedBook {
+            adapter,
+            mirror: None,
+            sequence: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn adapter(&self) -> &A {
+        &self.adapter
+    }
+
+    /// book of the venue, none until the snapshot is applied and after a sequence gap
+    pub fn book(&self) -> Option<&OrderBook> {
+        self.mirror.as_ref().map(MirrorBook::book)
+    }
+
+    /// mirror of the venue book, i.e. to uncross it
+    pub fn mirror_mut(&mut self) -> Option<&mut MirrorBook> {
+        self.mirror.as_mut()
+    }
+
+    /// last applied sequence number of the venue
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// snapshot is applied and no updates were lost since
+    pub fn is_synced(&self) -> bool {
+        self.mirror.is_some()
+    }
+
+    /// rebuild the book from the snapshot response and apply the buffered updates on top
+    pub fn on_snapshot(&mut self, message: &str) -> Result<(), FeedError> {
+        let snapshot = self.adapter.parse_snapshot(message)?;
+        self.load(snapshot)
+    }
+
+    /// apply the message of the stream, updates are buffered until the snapshot is applied
+    /// returns true if the message changed the book
+    pub fn on_message(&mut self, message: &str) -> Result<bool, FeedError> {
+        let update = match self.adapter.parse_message(message)? {
+            None => return Ok(false),
+            Some(FeedMessage::Snapshot(snapshot)) => {
+                self.load(snapshot)?;
+                return Ok(true);
+            }
+            Some(FeedMessage::Update(update)) => update,
+        };
+        if self.mirror.is_none() {
+            // without sequence numbers it is not known which updates the snapshot includes
+            if self.adapter.is_sequenced() {
+                self.pending.push(update);
+            }
+            return Ok(false);
+        }
+        self.apply(update)
+    }
+
+    fn load(&mut self, snapshot: FeedSnapshot) -> Result<(), FeedError> {
+        let depth = |levels: Vec<(Price, Volume)>| {
+            levels
+                .into_iter()
+                .map(|(price, volume)| DepthLevel {
+                    price,
+                    volume,
+                    order_count: 1,
+                })
+                .collect()
+        };
+        let depth = DepthSnapshot {
+            bids: depth(snapshot.bids),
+            asks: depth(snapshot.asks),
+        };
+        let mut mirror = MirrorBook::from_snapshot(&depth, 0)?;
+        if let Some(levels) = self.adapter.depth() {
+            mirror = mirror.with_max_depth(levels);
+        }
+        self.mirror = Some(mirror);
+        self.sequence = snapshot.sequence;
+        for update in core::mem::take(&mut self.pending) {
+            self.apply(update)?;
+        }
+        Ok(())
+    }
+
+    /// update covering the sequence after the last applied one is applied, older updates
+    /// are already part of the book, newer ones mean updates were lost
+    fn apply(&mut self, update: FeedUpdate) -> Result<bool, FeedError> {
+        let sequenced = self.adapter.is_sequenced();
+        if sequenced && update.last_sequence <= self.sequence {
+            return Ok(false);
+        }
+        let expected = self.sequence + 1;
+        if sequenced && update.first_sequence > expected {
+            self.mirror = None;
+            self.pending.clear();
+            return Err(FeedError::SequenceGap {
+                expected,
+                received: update.first_sequence,
+            });
+        }
+        let Some(mirror) = &mut self.mirror else {
+            return Ok(false);
+        };
+        let levels = update
+            .bids
+            .into_iter()
+            .map(|level| (OrderSide::Buy, level))
+            .chain(
+                update
+                    .asks
+                    .into_iter()
+                    .map(|level| (OrderSide::Sell, level)),
+            );
+        for (side, (price, volume)) in levels {
+            let delta = BookDelta {
+                sequence: mirror.sequence() + 1,
+                event_sequence: 0,
+                event: DeltaEvent::LevelUpdated {
+                    side,
+                    price,
+                    volume,
+                    order_count: 1,
+                },
+            };
+            mirror.apply_delta(&delta)?;
+        }
+        self.sequence = match sequenced {
+            true => update.last_sequence,
+            false => expected,
+        };
+        let checksum = update.checksum.zip(self.adapter.checksum(mirror.book()));
+        if let Some((expected, computed)) = checksum {
+            if expected != computed {
+                self.mirror = None;
+                return Err(FeedError::ChecksumMismatch { expected, computed });
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// price and volume of the level given as decimal strings, for use by adapters,
+/// venues pad the fraction with zeros, so they are trimmed before parsing
+pub fn parse_level(price: &str, volume: &str) -> Result<(Price, Volume), FeedError> {
+    let invalid = |value: &str| FeedError::InvalidMessage(value.to_string());
+    let price = trim_zeros(price).parse().map_err(|_| invalid(price))?;
+    let volume = trim_zeros(volume).parse().map_err(|_| invalid(volume))?;
+    Ok((price, volume))
+}
+
+fn trim_zeros(value: &str) -> &str {
+    match value.contains('.') {
+        true => value.trim_end_matches('0').trim_end_matches('.'),
+        false => value,
+    }
+}
+
+#[cfg(test)]
+mod tests_adapter {
+    use crate::feed::{
+        parse_level, FeedAdapter, FeedBook, FeedError, FeedMessage, FeedSnapshot, FeedUpdate,
+    };
+    use crate::*;
+
+    /// messages are "sequence bid_price bid_volume" or "first last bid_price bid_volume"
+    struct TextAdapter;
+
+    impl FeedAdapter for TextAdapter {
+        fn snapshot_url(&self) -> Option<String> {
+            Some("memory://snapshot".to_string())
+        }
+
+        fn parse_snapshot(&self, message: &str) -> Result<FeedSnapshot, FeedError> {
+            let fields = message.split(' ').collect::<Vec<_>>();
+            Ok(FeedSnapshot {
+                sequence: fields[0].parse().unwrap(),
+                bids: vec![parse_level(fields[1], fields[2])?],
+                asks: Vec::new(),
+            })
+        }
+
+        fn parse_message(&self, message: &str) -> Result<Option<FeedMessage>, FeedError> {
+            let fields = message.split(' ').collect::<Vec<_>>();
+            if fields.len() != 4 {
+                return Ok(None);
+            }
+            Ok(Some(FeedMessage::Update(FeedUpdate {
+                first_sequence: fields[0].parse().unwrap(),
+                last_sequence: fields[1].parse().unwrap(),
+                bids: vec![parse_level(fields[2], fields[3])?],
+                asks: Vec::new(),
+                checksum: None,
+            })))
+        }
+    }
+
+    #[test]
+    fn test_feed_book_validates_sequence() {
+        let mut feed = FeedBook::new(TextAdapter);
+        // buffered until the snapshot, the first one is already part of it
+        assert!(!feed.on_message("8 10 9.0 1").unwrap());
+        assert!(!feed.on_message("11 12 9.5 2.000").unwrap());
+        assert!(!feed.on_message("heartbeat").unwrap());
+        assert!(feed.book().is_none());
+
+        feed.on_snapshot("10 9.00000000 5").unwrap();
+        assert_eq!(feed.sequence(), 12);
+        let book = feed.book().unwrap();
+        assert_eq!(book.get_best_buy(), Some(9.5.into()));
+        assert_eq!(
+            book.get_volume_at_limit(9.0.into(), OrderSide::Buy),
+            Some(5.into())
+        );
+
+        assert!(feed.on_message("13 13 9.5 0").unwrap());
+        assert_eq!(feed.book().unwrap().get_best_buy(), Some(9.0.into()));
+        assert!(!feed.on_message("12 13 9.5 7").unwrap());
+
+        // lost updates need a new snapshot
+        assert_eq!(
+            feed.on_message("15 15 9.0 1"),
+            Err(FeedError::SequenceGap {
+                expected: 14,
+                received: 15
+            })
+        );
+        assert!(!feed.is_synced());
+        assert_eq!(
+            feed.on_message("16 16 x 1"),
+            Err(FeedError::InvalidMessage("x".into()))
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//!
+//! Binance spot depth stream. Snapshot is the response of the `/api/v3/depth` endpoint,
+//! updates are the `depthUpdate` events of the `<symbol>@depth` stream, each covering
+//! the update ids from `U` to `u`. Quantities are given with 8 decimal places and are parsed
+//! into the volume precision of the build, fractional quantities need `fractional-volume`.
+//!
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde::Deserialize;
+
+use crate::feed::{parse_level, FeedAdapter, FeedError, FeedMessage, FeedSnapshot, FeedUpdate};
+use crate::{Price, Volume};
+
+const REST_URL: &str = "https://api.binance.com/api/v3/depth";
+
+#[derive(Deserialize)]
+struct DepthSnapshotMessage {
+    #[serde(rename = "lastUpdateId")]
+    last_update_id: u64,
+    bids: Vec<[String; 2]>,
+    asks: Vec<[String; 2]>,
+}
+
+#[derive(Deserialize)]
+struct DepthUpdateMessage {
+    #[serde(rename = "e")]
+    event: String,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "U")]
+    first_update_id: u64,
+    #[serde(rename = "u")]
+    final_update_id: u64,
+    #[serde(rename = "b")]
+    bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    asks: Vec<[String; 2]>,
+}
+
+/// Adapter of the depth stream of one Binance symbol
+#[derive(Debug, Clone)]
+pub struct BinanceDepthAdapter {
+    symbol: String,
+    limit: usize,
+}
+
+impl BinanceDepthAdapter {
+    /// symbol as listed by Binance, i.e. `BTCUSDT`, snapshot has 1000 levels of each side
+    pub fn new(symbol: &str) -> Self {
+        BinanceDepthAdapter {
+            symbol: symbol.to_uppercase(),
+            limit: 1000,
+        }
+    }
+
+    /// number of levels of each side in the snapshot, up to 5000
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// name of the depth stream to subscribe to
+    pub fn stream(&self) -> String {
+        format!("{}@depth", self.symbol.to_lowercase())
+    }
+}
+
+impl FeedAdapter for BinanceDepthAdapter {
+    fn snapshot_url(&self) -> Option<String> {
+        Some(format!(
+            "{REST_URL}?symbol={}&limit={}",
+            self.symbol, self.limit
+        ))
+    }
+
+    fn parse_snapshot(&self, message: &str) -> Result<FeedSnapshot, FeedError> {
+        let snapshot: DepthSnapshotMessage =
+            serde_json::from_str(message).map_err(|e| FeedError::InvalidMessage(e.to_string()))?;
+        Ok(FeedSnapshot {
+            sequence: snapshot.last_update_id,
+            bids: parse_levels(&snapshot.bids)?,
+            asks: parse_levels(&snapshot.asks)?,
+        })
+    }
+
+    fn parse_message(&self, message: &str) -> Result<Option<FeedMessage>, FeedError> {
+        let value: serde_json::Value =
+            serde_json::from_str(message).map_err(|e| FeedError::InvalidMessage(e.to_string()))?;
+        // combined streams wrap the event in the data field
+        let value = match value.get("data") {
+            Some(data) => data.clone(),
+            None => value,
+        };
+        if value.get("e").and_then(|event| event.as_str()) != Some("depthUpdate") {
+            return Ok(None);
+        }
+        let update: DepthUpdateMessage =
+            serde_json::from_value(value).map_err(|e| FeedError::InvalidMessage(e.to_string()))?;
+        if update.event != "depthUpdate" || update.symbol != self.symbol {
+            return Ok(None);
+        }
+        Ok(Some(FeedMessage::Update(FeedUpdate {
+            first_sequence: update.first_update_id,
+            last_sequence: update.final_update_id,
+            bids: parse_levels(&update.bids)?,
+            asks: parse_levels(&update.asks)?,
+            checksum: None,
+        })))
+    }
+}
+
+fn parse_levels(levels: &[[String; 2]]) -> Result<Vec<(Price, Volume)>, FeedError> {
+    levels
+        .iter()
+        .map(|[price, volume]| parse_level(price, volume))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests_binance {
+    use crate::feed::binance::BinanceDepthAdapter;
+    use crate::feed::{FeedAdapter, FeedBook, FeedError};
+    use crate::*;
+
+    const SNAPSHOT: &str = r#"{
+        "lastUpdateId": 160,
+        "bids": [["0.0024", "10.00000000"], ["0.0023", "4.00000000"]],
+        "asks": [["0.0026", "100.00000000"]]
+    }"#;
+
+    fn update(first: u64, last: u64, bids: &str, asks: &str) -> String {
+        format!(
+            r#"{{"e":"depthUpdate","E":123456789,"s":"BNBBTC","U":{first},"u":{last},"b":{bids},"a":{asks}}}"#
+        )
+    }
+
+    #[test]
+    fn test_binance_depth_stream() {
+        let adapter = BinanceDepthAdapter::new("bnbbtc").with_limit(100);
+        assert_eq!(
+            adapter.snapshot_url().unwrap(),
+            "https://api.binance.com/api/v3/depth?symbol=BNBBTC&limit=100"
+        );
+        assert_eq!(adapter.stream(), "bnbbtc@depth");
+
+        let mut feed = FeedBook::new(adapter);
+        assert!(!feed.on_message(r#"{"result":null,"id":1}"#).unwrap());
+        // first update straddles the snapshot
+        feed.on_message(&update(157, 161, r#"[["0.0024","0"]]"#, "[]"))
+            .unwrap();
+        feed.on_snapshot(SNAPSHOT).unwrap();
+        assert_eq!(feed.sequence(), 161);
+        let book = feed.book().unwrap();
+        assert_eq!(book.get_best_buy(), Some(Price::new(0.0023)));
+        assert_eq!(book.get_best_sell_volume(), Some(100.into()));
+
+        let combined = format!(
+            r#"{{"stream":"bnbbtc@depth","data":{}}}"#,
+            update(162, 163, "[]", r#"[["0.0025","3.00000000"]]"#)
+        );
+        assert!(feed.on_message(&combined).unwrap());
+        assert_eq!(
+            feed.book().unwrap().get_best_sell(),
+            Some(Price::new(0.0025))
+        );
+
+        let fractional = update(164, 164, "[]", r#"[["0.0027","0.00120000"]]"#);
+        #[cfg(feature = "fractional-volume")]
+        {
+            assert!(feed.on_message(&fractional).unwrap());
+            assert_eq!(
+                feed.book()
+                    .unwrap()
+                    .get_volume_at_limit(Price::new(0.0027), OrderSide::Sell),
+                Some("0.0012".parse().unwrap())
+            );
+        }
+        // quantity finer than the volume precision of the build
+        #[cfg(not(feature = "fractional-volume"))]
+        assert_eq!(
+            feed.on_message(&fractional),
+            Err(FeedError::InvalidMessage("0.00120000".to_string()))
+        );
+
+        // updates of other symbols are ignored
+        let other = update(165, 165, "[]", "[]").replace("BNBBTC", "ETHBTC");
+        assert!(!feed.on_message(&other).unwrap());
+        let expected = feed.sequence() + 1;
+        assert_eq!(
+            feed.on_message(&update(170, 171, "[]", "[]")),
+            Err(FeedError::SequenceGap {
+                expected,
+                received: 170
+            })
+        );
+        assert!(matches!(
+            feed.on_message("{not json"),
+            Err(FeedError::InvalidMessage(_))
+        ));
+    }
+}
@@ -0,0 +1,187 @@
+//!
+//! Coinbase Exchange `level2` channel. The snapshot is the first message of the channel,
+//! `l2update` messages carry the new size of the changed levels.
+//! The channel has neither sequence numbers nor checksums, updates are applied in the order
+//! they are received, so the book has to be resubscribed after the connection is lost.
+//! Sizes are parsed into the volume precision of the build, fractional sizes need
+//! `fractional-volume`.
+//!
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde::Deserialize;
+
+use crate::feed::{parse_level, FeedAdapter, FeedError, FeedMessage, FeedSnapshot, FeedUpdate};
+use crate::{Price, Volume};
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum Level2Message {
+    #[serde(rename = "snapshot")]
+    Snapshot {
+        product_id: String,
+        bids: Vec<[String; 2]>,
+        asks: Vec<[String; 2]>,
+    },
+    #[serde(rename = "l2update")]
+    Update {
+        product_id: String,
+        changes: Vec<[String; 3]>,
+    },
+    #[serde(rename = "error")]
+    Error { message: String },
+    #[serde(other)]
+    Other,
+}
+
+/// Adapter of the level2 channel of one Coinbase product
+#[derive(Debug, Clone)]
+pub struct CoinbaseLevel2Adapter {
+    product_id: String,
+}
+
+impl CoinbaseLevel2Adapter {
+    /// product as listed by Coinbase, i.e. `BTC-USD`
+    pub fn new(product_id: &str) -> Self {
+        CoinbaseLevel2Adapter {
+            product_id: product_id.to_uppercase(),
+        }
+    }
+
+    pub fn product_id(&self) -> &str {
+        &self.product_id
+    }
+
+    /// subscribe message of the channel
+    pub fn subscription(&self) -> String {
+        format!(
+            r#"{{"type":"subscribe","product_ids":["{}"],"channels":["level2"]}}"#,
+            self.product_id
+        )
+    }
+}
+
+impl FeedAdapter for CoinbaseLevel2Adapter {
+    fn snapshot_url(&self) -> Option<String> {
+        None
+    }
+
+    fn parse_snapshot(&self, message: &str) -> Result<FeedSnapshot, FeedError> {
+        match self.parse_message(message)? {
+            Some(FeedMessage::Snapshot(snapshot)) => Ok(snapshot),
+            _ => Err(FeedError::InvalidMessage(message.to_string())),
+        }
+    }
+
+    fn parse_message(&self, message: &str) -> Result<Option<FeedMessage>, FeedError> {
+        let message: Level2Message =
+            serde_json::from_str(message).map_err(|e| FeedError::InvalidMessage(e.to_string()))?;
+        match message {
+            Level2Message::Snapshot {
+                product_id,
+                bids,
+                asks,
+            } if product_id == self.product_id => Ok(Some(FeedMessage::Snapshot(FeedSnapshot {
+                sequence: 0,
+                bids: parse_levels(&bids)?,
+                asks: parse_levels(&asks)?,
+            }))),
+            Level2Message::Update {
+                product_id,
+                changes,
+            } if product_id == self.product_id => {
+                let mut update = FeedUpdate::default();
+                for [side, price, size] in &changes {
+                    let level = parse_level(price, size)?;
+                    match side.as_str() {
+                        "buy" => update.bids.push(level),
+                        "sell" => update.asks.push(level),
+                        _ => return Err(FeedError::InvalidMessage(side.clone())),
+                    }
+                }
+                Ok(Some(FeedMessage::Update(update)))
+            }
+            Level2Message::Error { message } => Err(FeedError::InvalidMessage(message)),
+            _ => Ok(None),
+        }
+    }
+
+    fn is_sequenced(&self) -> bool {
+        false
+    }
+}
+
+fn parse_levels(levels: &[[String; 2]]) -> Result<Vec<(Price, Volume)>, FeedError> {
+    levels
+        .iter()
+        .map(|[price, size]| parse_level(price, size))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests_coinbase {
+    use crate::feed::coinbase::CoinbaseLevel2Adapter;
+    use crate::feed::{FeedAdapter, FeedBook, FeedError};
+    #[cfg(feature = "fractional-volume")]
+    use crate::Volume;
+
+    #[test]
+    fn test_coinbase_level2_channel() {
+        let adapter = CoinbaseLevel2Adapter::new("btc-usd");
+        assert_eq!(adapter.snapshot_url(), None);
+        assert_eq!(
+            adapter.subscription(),
+            r#"{"type":"subscribe","product_ids":["BTC-USD"],"channels":["level2"]}"#
+        );
+
+        let mut feed = FeedBook::new(adapter);
+        let subscriptions =
+            r#"{"type":"subscriptions","channels":[{"name":"level2","product_ids":["BTC-USD"]}]}"#;
+        assert!(!feed.on_message(subscriptions).unwrap());
+        // updates before the snapshot are not buffered without sequence numbers
+        let update = r#"{"type":"l2update","product_id":"BTC-USD","time":"2019-08-14T20:42:27.265Z","changes":[["buy","10101.80000000","3"]]}"#;
+        assert!(!feed.on_message(update).unwrap());
+
+        let snapshot = r#"{"type":"snapshot","product_id":"BTC-USD","bids":[["10101.10","4.00000000"]],"asks":[["10102.55","5"],["10103.00","2.00000000"]]}"#;
+        assert!(feed.on_message(snapshot).unwrap());
+        assert_eq!(feed.book().unwrap().get_best_buy(), Some(10101.1.into()));
+        assert!(feed.on_message(update).unwrap());
+        assert_eq!(feed.sequence(), 1);
+        let book = feed.book().unwrap();
+        assert_eq!(book.get_best_buy(), Some(10101.8.into()));
+        assert_eq!(book.get_best_buy_volume(), Some(3.into()));
+
+        let update = r#"{"type":"l2update","product_id":"BTC-USD","time":"2019-08-14T20:42:27.265Z","changes":[["sell","10102.55","0"],["buy","10101.80","0.00"]]}"#;
+        assert!(feed.on_message(update).unwrap());
+        let book = feed.book().unwrap();
+        assert_eq!(book.get_best_sell(), Some(10103.0.into()));
+        assert_eq!(book.get_best_sell_volume(), Some(2.into()));
+        assert_eq!(book.get_best_buy(), Some(10101.1.into()));
+
+        let fractional = r#"{"type":"l2update","product_id":"BTC-USD","time":"2019-08-14T20:42:27.265Z","changes":[["sell","10103.00","0.45054140"]]}"#;
+        #[cfg(feature = "fractional-volume")]
+        {
+            assert!(feed.on_message(fractional).unwrap());
+            assert_eq!(
+                feed.book().unwrap().get_best_sell_volume(),
+                Some("0.4505414".parse::<Volume>().unwrap())
+            );
+        }
+        // size finer than the volume precision of the build
+        #[cfg(not(feature = "fractional-volume"))]
+        assert_eq!(
+            feed.on_message(fractional),
+            Err(FeedError::InvalidMessage("0.45054140".into()))
+        );
+
+        // other products are ignored
+        let other = update.replace("BTC-USD", "ETH-USD");
+        assert!(!feed.on_message(&other).unwrap());
+        assert_eq!(
+            feed.on_message(r#"{"type":"error","message":"Failed to subscribe"}"#),
+            Err(FeedError::InvalidMessage("Failed to subscribe".into()))
+        );
+    }
+}
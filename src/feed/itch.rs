@@ -0,0 +1,393 @@
+//!
+//! Nasdaq TotalView ITCH 5.0 order messages.
+//! Add, Execute, Cancel, Delete and Replace messages of a single instrument are applied to an
+//! order book, other messages are ignored. Messages are passed without the length prefix
+//! used by the SoupBinTCP and binary file framing.
+//!
+
+use thiserror::Error;
+
+use crate::{
+    CancelOrderError, LimitOrder, Oid, OrderBook, OrderBookError, OrderSide, Price, Timestamp,
+    Volume,
+};
+
+/// ITCH prices have 4 decimal places
+const ITCH_PRICE_SCALE: i64 = 10_000;
+
+/// ITCH parsing or applying error
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum ItchError {
+    #[error("Message {0:?} is shorter than expected")]
+    Truncated(char),
+    #[error("Invalid side {0:?}")]
+    InvalidSide(char),
+    #[error("Order {0} is not on the book")]
+    UnknownOrder(u64),
+    #[error("Order book error: {0}")]
+    OrderBookError(#[from] OrderBookError),
+}
+
+/// Order message of the ITCH feed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItchMessage {
+    /// Add Order (A) and Add Order with MPID attribution (F)
+    AddOrder {
+        stock_locate: u16,
+        timestamp: u64,
+        order_reference: u64,
+        side: OrderSide,
+        shares: u32,
+        price: Price,
+    },
+    /// Order Executed (E) and Order Executed With Price (C)
+    OrderExecuted {
+        stock_locate: u16,
+        timestamp: u64,
+        order_reference: u64,
+        executed_shares: u32,
+    },
+    /// Order Cancel (X), part of the order is cancelled
+    OrderCancel {
+        stock_locate: u16,
+        timestamp: u64,
+        order_reference: u64,
+        cancelled_shares: u32,
+    },
+    /// Order Delete (D)
+    OrderDelete {
+        stock_locate: u16,
+        timestamp: u64,
+        order_reference: u64,
+    },
+    /// Order Replace (U), original order is removed and a new one is added with the same side
+    OrderReplace {
+        stock_locate: u16,
+        timestamp: u64,
+        original_order_reference: u64,
+        new_order_reference: u64,
+        shares: u32,
+        price: Price,
+    },
+    /// Message that does not change the order book
+    Other(u8),
+}
+
+impl ItchMessage {
+    /// parse single message, first byte is the message type
+    pub fn parse(bytes: &[u8]) -> Result<Self, ItchError> {
+        let Some(&kind) = bytes.first() else {
+            return Err(ItchError::Truncated(' '));
+        };
+        let expected_len = match kind {
+            b'A' => 36,
+            b'F' => 40,
+            b'E' => 31,
+            b'C' => 36,
+            b'X' => 23,
+            b'D' => 19,
+            b'U' => 35,
+            _ => return Ok(ItchMessage::Other(kind)),
+        };
+        if bytes.len() < expected_len {
+            return Err(ItchError::Truncated(kind as char));
+        }
+        let mut reader = Reader { bytes: &bytes[1..] };
+        let stock_locate = reader.u16();
+        let _tracking_number = reader.u16();
+        let timestamp = reader.u48();
+        let message = match kind {
+            b'A' | b'F' => {
+                let order_reference = reader.u64();
+                let side = match reader.u8() {
+                    b'B' => OrderSide::Buy,
+                    b'S' => OrderSide::Sell,
+                    side => return Err(ItchError::InvalidSide(side as char)),
+                };
+                let shares = reader.u32();
+                let _stock = reader.take(8);
+                let price = reader.price();
+                ItchMessage::AddOrder {
+                    stock_locate,
+                    timestamp,
+                    order_reference,
+                    side,
+                    shares,
+                    price,
+                }
+            }
+            b'E' | b'C' => ItchMessage::OrderExecuted {
+                stock_locate,
+                timestamp,
+                order_reference: reader.u64(),
+                executed_shares: reader.u32(),
+            },
+            b'X' => ItchMessage::OrderCancel {
+                stock_locate,
+                timestamp,
+                order_reference: reader.u64(),
+                cancelled_shares: reader.u32(),
+            },
+            b'D' => ItchMessage::OrderDelete {
+                stock_locate,
+                timestamp,
+                order_reference: reader.u64(),
+            },
+            _ => ItchMessage::OrderReplace {
+                stock_locate,
+                timestamp,
+                original_order_reference: reader.u64(),
+                new_order_reference: reader.u64(),
+                shares: reader.u32(),
+                price: reader.price(),
+            },
+        };
+        Ok(message)
+    }
+
+    fn stock_locate(&self) -> Option<u16> {
+        match self {
+            ItchMessage::AddOrder { stock_locate, .. }
+            | ItchMessage::OrderExecuted { stock_locate, .. }
+            | ItchMessage::OrderCancel { stock_locate, .. }
+            | ItchMessage::OrderDelete { stock_locate, .. }
+            | ItchMessage::OrderReplace { stock_locate, .. } => Some(*stock_locate),
+            ItchMessage::Other(_) => None,
+        }
+    }
+}
+
+// reads big endian fields, length is checked before reading
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> &[u8] {
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        head
+    }
+
+    fn uint(&mut self, len: usize) -> u64 {
+        self.take(len)
+            .iter()
+            .fold(0, |value, byte| (value << 8) | *byte as u64)
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.uint(1) as u8
+    }
+
+    fn u16(&mut self) -> u16 {
+        self.uint(2) as u16
+    }
+
+    fn u32(&mut self) -> u32 {
+        self.uint(4) as u32
+    }
+
+    fn u48(&mut self) -> u64 {
+        self.uint(6)
+    }
+
+    fn u64(&mut self) -> u64 {
+        self.uint(8)
+    }
+
+    fn price(&mut self) -> Price {
+        let price = self.u32() as i64;
+        Price::from_mantissa(price * (crate::utils::PRICE_SCALE / ITCH_PRICE_SCALE))
+    }
+}
+
+/// Order book of one instrument reconstructed from the ITCH feed
+#[derive(Debug, Default)]
+pub struct ItchBook {
+    stock_locate: u16,
+    book: OrderBook,
+}
+
+impl ItchBook {
+    /// book of the instrument with the given stock locate code
+    pub fn new(stock_locate: u16) -> Self {
+        ItchBook {
+            stock_locate,
+            book: OrderBook::default(),
+        }
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// parse and apply single message
+    pub fn apply_bytes(&mut self, bytes: &[u8]) -> Result<(), ItchError> {
+        self.apply(&ItchMessage::parse(bytes)?)
+    }
+
+    /// apply the message, messages of other instruments are ignored
+    /// orders are not matched, executions reduce the resting order
+    pub fn apply(&mut self, message: &ItchMessage) -> Result<(), ItchError> {
+        if message.stock_locate() != Some(self.stock_locate) {
+            return Ok(());
+        }
+        match *message {
+            ItchMessage::AddOrder {
+                timestamp,
+                order_reference,
+                side,
+                shares,
+                price,
+                ..
+            } => self.add(order_reference, side, timestamp, price, shares),
+            ItchMessage::OrderExecuted {
+                order_reference,
+                executed_shares,
+                ..
+            } => self.reduce(order_reference, executed_shares),
+            ItchMessage::OrderCancel {
+                order_reference,
+                cancelled_shares,
+                ..
+            } => self.reduce(order_reference, cancelled_shares),
+            ItchMessage::OrderDelete {
+                order_reference, ..
+            } => self.delete(order_reference),
+            ItchMessage::OrderReplace {
+                timestamp,
+                original_order_reference,
+                new_order_reference,
+                shares,
+                price,
+                ..
+            } => {
+                let side = self
+                    .book
+                    .get_order(Oid::new(original_order_reference))
+                    .map(|order| order.side)
+                    .ok_or(ItchError::UnknownOrder(original_order_reference))?;
+                self.delete(original_order_reference)?;
+                self.add(new_order_reference, side, timestamp, price, shares)
+            }
+            ItchMessage::Other(_) => Ok(()),
+        }
+    }
+
+    fn add(
+        &mut self,
+        order_reference: u64,
+        side: OrderSide,
+        timestamp: u64,
+        price: Price,
+        shares: u32,
+    ) -> Result<(), ItchError> {
+        let order = LimitOrder::new(
+            Oid::new(order_reference),
+            side,
+            Timestamp::new(timestamp),
+            price,
+            Volume::new(shares as u64),
+        );
+        Ok(self.book.add_order(order)?)
+    }
+
+    /// remove part of the order, order keeps its queue position
+    fn reduce(&mut self, order_reference: u64, shares: u32) -> Result<(), ItchError> {
+        let id = Oid::new(order_reference);
+        let order = self
+            .book
+            .get_order(id)
+            .filter(|order| !order.remaining_volume.is_zero())
+            .ok_or(ItchError::UnknownOrder(order_reference))?;
+        let shares = Volume::new(shares as u64);
+        if shares >= order.remaining_volume {
+            return self.delete(order_reference);
+        }
+        self.book
+            .amend_order(id, order.price, order.volume - shares)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, order_reference: u64) -> Result<(), ItchError> {
+        match self.book.cancel_order(Oid::new(order_reference)) {
+            Ok(_) => Ok(()),
+            Err(CancelOrderError::NotFound(_)) => Err(ItchError::UnknownOrder(order_reference)),
+            Err(e) => Err(OrderBookError::from(e).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_itch {
+    use super::*;
+
+    fn header(kind: u8, stock_locate: u16) -> Vec<u8> {
+        let mut bytes = vec![kind];
+        bytes.extend_from_slice(&stock_locate.to_be_bytes());
+        bytes.extend_from_slice(&0u16.to_be_bytes());
+        bytes.extend_from_slice(&1_000u64.to_be_bytes()[2..]);
+        bytes
+    }
+
+    fn add_order(reference: u64, side: u8, shares: u32, price: u32) -> Vec<u8> {
+        let mut bytes = header(b'A', 7);
+        bytes.extend_from_slice(&reference.to_be_bytes());
+        bytes.push(side);
+        bytes.extend_from_slice(&shares.to_be_bytes());
+        bytes.extend_from_slice(b"AAPL    ");
+        bytes.extend_from_slice(&price.to_be_bytes());
+        bytes
+    }
+
+    fn executed(reference: u64, shares: u32) -> Vec<u8> {
+        let mut bytes = header(b'E', 7);
+        bytes.extend_from_slice(&reference.to_be_bytes());
+        bytes.extend_from_slice(&shares.to_be_bytes());
+        bytes.extend_from_slice(&1u64.to_be_bytes());
+        bytes
+    }
+
+    fn replace(original: u64, new: u64, shares: u32, price: u32) -> Vec<u8> {
+        let mut bytes = header(b'U', 7);
+        bytes.extend_from_slice(&original.to_be_bytes());
+        bytes.extend_from_slice(&new.to_be_bytes());
+        bytes.extend_from_slice(&shares.to_be_bytes());
+        bytes.extend_from_slice(&price.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_itch_messages_rebuild_the_book() {
+        let mut book = ItchBook::new(7);
+        book.apply_bytes(&add_order(1, b'B', 100, 1_502_500))
+            .unwrap();
+        book.apply_bytes(&add_order(2, b'S', 50, 1_503_000))
+            .unwrap();
+        assert_eq!(book.book().get_best_buy(), Some(Price::new(150.25)));
+
+        book.apply_bytes(&executed(1, 40)).unwrap();
+        assert_eq!(book.book().get_best_buy_volume(), Some(60.into()));
+
+        book.apply_bytes(&replace(2, 3, 70, 1_502_900)).unwrap();
+        assert_eq!(book.book().get_best_sell(), Some(Price::new(150.29)));
+        assert_eq!(book.book().get_best_sell_volume(), Some(70.into()));
+
+        let mut delete = header(b'D', 7);
+        delete.extend_from_slice(&1u64.to_be_bytes());
+        book.apply_bytes(&delete).unwrap();
+        assert_eq!(book.book().get_best_buy(), None);
+        assert_eq!(book.apply_bytes(&delete), Err(ItchError::UnknownOrder(1)));
+
+        // other instruments and message types are ignored
+        let mut other = add_order(4, b'B', 10, 1_000_000);
+        other[1..3].copy_from_slice(&8u16.to_be_bytes());
+        book.apply_bytes(&other).unwrap();
+        book.apply_bytes(b"S\0\0").unwrap();
+        assert_eq!(book.book().order_count(), 1);
+        assert_eq!(
+            ItchMessage::parse(&executed(1, 1)[..20]),
+            Err(ItchError::Truncated('E'))
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//!
+//! Kraken `book` channel of the websocket api. The snapshot is the first message of the channel,
+//! updates carry the new volume of the changed levels and the CRC32 checksum of the top ten
+//! levels of each side of the venue book after the update. The venue keeps only the subscribed
+//! depth and does not send removals of the levels pushed beyond it, so the book is truncated
+//! to the same depth. Volumes are parsed into the volume precision of the build, fractional
+//! volumes need `fractional-volume`.
+//!
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde::Deserialize;
+
+use crate::feed::{parse_level, FeedAdapter, FeedError, FeedMessage, FeedSnapshot, FeedUpdate};
+use crate::{OrderBook, Price, Volume};
+
+// levels of each side included in the checksum
+const CHECKSUM_LEVELS: usize = 10;
+
+#[derive(Deserialize)]
+struct BookPayload {
+    #[serde(rename = "as")]
+    snapshot_asks: Option<Vec<Vec<String>>>,
+    #[serde(rename = "bs")]
+    snapshot_bids: Option<Vec<Vec<String>>>,
+    #[serde(rename = "a", default)]
+    asks: Vec<Vec<String>>,
+    #[serde(rename = "b", default)]
+    bids: Vec<Vec<String>>,
+    #[serde(rename = "c")]
+    checksum: Option<String>,
+}
+
+/// Adapter of the book channel of one Kraken pair
+#[derive(Debug, Clone)]
+pub struct KrakenBookAdapter {
+    pair: String,
+    depth: usize,
+    price_decimals: usize,
+    volume_decimals: usize,
+}
+
+impl KrakenBookAdapter {
+    /// pair as named by the websocket api, i.e. `XBT/USD`, with the `pair_decimals` and
+    /// `lot_decimals` of the pair, prices and volumes are formatted with them for the checksum,
+    /// the book has 10 levels of each side
+    pub fn new(pair: &str, price_decimals: usize, volume_decimals: usize) -> Self {
+        KrakenBookAdapter {
+            pair: pair.to_string(),
+            depth: CHECKSUM_LEVELS,
+            price_decimals,
+            volume_decimals,
+        }
+    }
+
+    /// subscribed depth of the book, one of 10, 25, 100, 500 or 1000
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn pair(&self) -> &str {
+        &self.pair
+    }
+
+    /// subscribe message of the channel
+    pub fn subscription(&self) -> String {
+        format!(
+            r#"{{"event":"subscribe","pair":["{}"],"subscription":{{"name":"book","depth":{}}}}}"#,
+            self.pair, self.depth
+        )
+    }
+}
+
+impl FeedAdapter for KrakenBookAdapter {
+    fn snapshot_url(&self) -> Option<String> {
+        None
+    }
+
+    fn parse_snapshot(&self, message: &str) -> Result<FeedSnapshot, FeedError> {
+        match self.parse_message(message)? {
+            Some(FeedMessage::Snapshot(snapshot)) => Ok(snapshot),
+            _ => Err(FeedError::InvalidMessage(message.to_string())),
+        }
+    }
+
+    /// channel messages are arrays of the channel id, one or two payloads, channel name and pair,
+    /// events such as heartbeats are objects
+    fn parse_message(&self, message: &str) -> Result<Option<FeedMessage>, FeedError> {
+        let invalid = |e: serde_json::Error| FeedError::InvalidMessage(e.to_string());
+        let value: serde_json::Value = serde_json::from_str(message).map_err(invalid)?;
+        let Some(fields) = value.as_array() else {
+            return Ok(None);
+        };
+        let [_, payloads @ .., channel, pair] = fields.as_slice() else {
+            return Ok(None);
+        };
+        let is_book = channel
+            .as_str()
+            .is_some_and(|name| name.starts_with("book"));
+        if !is_book || pair.as_str() != Some(self.pair.as_str()) {
+            return Ok(None);
+        }
+        let mut update = FeedUpdate::default();
+        for payload in payloads {
+            let payload = BookPayload::deserialize(payload).map_err(invalid)?;
+            if let (Some(asks), Some(bids)) = (&payload.snapshot_asks, &payload.snapshot_bids) {
+                return Ok(Some(FeedMessage::Snapshot(FeedSnapshot {
+                    sequence: 0,
+                    bids: parse_levels(bids)?,
+                    asks: parse_levels(asks)?,
+                })));
+            }
+            update.asks.extend(parse_levels(&payload.asks)?);
+            update.bids.extend(parse_levels(&payload.bids)?);
+            if let Some(checksum) = payload.checksum {
+                let checksum = checksum
+                    .parse()
+                    .map_err(|_| FeedError::InvalidMessage(checksum))?;
+                update.checksum = Some(checksum);
+            }
+        }
+        Ok(Some(FeedMessage::Update(update)))
+    }
+
+    fn is_sequenced(&self) -> bool {
+        false
+    }
+
+    fn depth(&self) -> Option<usize> {
+        Some(self.depth)
+    }
+
+    /// CRC32 of the price and volume of the best asks followed by the best bids,
+    /// each formatted with the decimals of the pair without the decimal point and leading zeros
+    fn checksum(&self, book: &OrderBook) -> Option<u32> {
+        let depth = book.depth(CHECKSUM_LEVELS);
+        let mut text = String::new();
+        for level in depth.asks.iter().chain(depth.bids.iter()) {
+            let price = format!("{:.*}", self.price_decimals, level.price);
+            let volume = format!("{:.*}", self.volume_decimals, level.volume);
+            for value in [price, volume] {
+                text.extend(
+                    value
+                        .chars()
+                        .filter(|c| *c != '.')
+                        .skip_while(|c| *c == '0'),
+                );
+            }
+        }
+        Some(crc32(text.as_bytes()))
+    }
+}
+
+/// price and volume are the first two fields of the level, followed by the timestamp
+/// and the flag of republished updates
+fn parse_levels(levels: &[Vec<String>]) -> Result<Vec<(Price, Volume)>, FeedError> {
+    levels
+        .iter()
+        .map(|level| match level.as_slice() {
+            [price, volume, ..] => parse_level(price, volume),
+            _ => Err(FeedError::InvalidMessage(level.join(","))),
+        })
+        .collect()
+}
+
+/// CRC-32 with the IEEE polynomial, as computed by zlib
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests_kraken {
+    use super::crc32;
+    use crate::feed::kraken::KrakenBookAdapter;
+    use crate::feed::{FeedAdapter, FeedBook, FeedError};
+    use crate::*;
+
+    fn levels(best: f64, step: f64) -> String {
+        (0..10)
+            .map(|i| {
+                let price = best + step * i as f64;
+                format!(r#"["{price:.5}","{}.00000000","1534614057.321597"]"#, i + 1)
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    #[test]
+    fn test_kraken_book_checksum() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let adapter = KrakenBookAdapter::new("XBT/USD", 5, 8);
+        assert_eq!(
+            adapter.subscription(),
+            r#"{"event":"subscribe","pair":["XBT/USD"],"subscription":{"name":"book","depth":10}}"#
+        );
+
+        let mut feed = FeedBook::new(adapter);
+        assert!(!feed.on_message(r#"{"event":"heartbeat"}"#).unwrap());
+        let snapshot = format!(
+            r#"[0,{{"as":[{}],"bs":[{}]}},"book-10","XBT/USD"]"#,
+            levels(5541.3, 0.1),
+            levels(5541.2, -0.1)
+        );
+        assert!(feed.on_message(&snapshot).unwrap());
+        let book = feed.book().unwrap();
+        assert_eq!(book.get_best_sell(), Some(5541.3.into()));
+        assert_eq!(feed.adapter().checksum(book), Some(1210616905));
+
+        // better ask pushes the worst one beyond the depth
+        let update = r#"[0,{"a":[["5541.25000","2.00000000","1534614248.456738"]],"c":"3180308339"},"book-10","XBT/USD"]"#;
+        assert!(feed.on_message(update).unwrap());
+        let book = feed.book().unwrap();
+        assert_eq!(book.get_best_sell(), Some(5541.25.into()));
+        assert_eq!(book.level_count(OrderSide::Sell), 10);
+        assert_eq!(
+            book.get_volume_at_limit(5542.2.into(), OrderSide::Sell),
+            None
+        );
+
+        // both sides in one message, the second payload has the checksum
+        let update = r#"[0,{"a":[["5541.30000","0.00000000","1534614248.456738"]]},{"b":[["5541.20000","5.00000000","1534614248.456738","r"]],"c":"2287997236"},"book-10","XBT/USD"]"#;
+        assert!(feed.on_message(update).unwrap());
+        assert_eq!(feed.book().unwrap().get_best_buy_volume(), Some(5.into()));
+
+        // fractional volume is part of the checksum
+        #[cfg(feature = "fractional-volume")]
+        {
+            let update = r#"[0,{"b":[["5541.15000","0.00120000","1534614248.456738"]],"c":"2400862470"},"book-10","XBT/USD"]"#;
+            assert!(feed.on_message(update).unwrap());
+            assert_eq!(
+                feed.book()
+                    .unwrap()
+                    .get_volume_at_limit(5541.15.into(), OrderSide::Buy),
+                Some("0.0012".parse().unwrap())
+            );
+        }
+
+        let trade =
+            r#"[0,[["5541.20000","1.00000000","1534614248.456738","s","l",""]],"trade","XBT/USD"]"#;
+        assert!(!feed.on_message(trade).unwrap());
+        let update = r#"[0,{"b":[["5541.10000","7.00000000","1534614248.456738"]],"c":"12345"},"book-10","XBT/USD"]"#;
+        assert!(matches!(
+            feed.on_message(update),
+            Err(FeedError::ChecksumMismatch {
+                expected: 12345,
+                ..
+            })
+        ));
+        assert!(!feed.is_synced());
+    }
+
+    #[test]
+    fn test_kraken_checksum_uses_decimals_of_the_pair() {
+        // prices have 2 and volumes 4 decimal places
+        let mut feed = FeedBook::new(KrakenBookAdapter::new("ETH/EUR", 2, 4));
+        let snapshot = r#"[0,{"as":[["2500.10","1.0000","1534614057.321597"],["2500.20","2.0000","1534614057.321597"],["2500.30","3.0000","1534614057.321597"]],"bs":[["2500.00","4.0000","1534614057.321597"],["2499.90","5.0000","1534614057.321597"],["2499.80","6.0000","1534614057.321597"]]},"book-10","ETH/EUR"]"#;
+        assert!(feed.on_message(snapshot).unwrap());
+        assert_eq!(
+            feed.adapter().checksum(feed.book().unwrap()),
+            Some(101201532)
+        );
+
+        let update = r#"[0,{"a":[["2500.15","7.0000","1534614248.456738"]],"c":"2236833728"},"book-10","ETH/EUR"]"#;
+        assert!(feed.on_message(update).unwrap());
+        assert!(feed.is_synced());
+        assert_eq!(feed.book().unwrap().get_best_sell_volume(), Some(1.into()));
+    }
+}
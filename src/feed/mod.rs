@@ -0,0 +1,17 @@
+//!
+//! Ingestion of exchange market data feeds, used to reconstruct exchange books from raw captures.
+//!
+
+mod adapter;
+#[cfg(feature = "binance")]
+pub mod binance;
+#[cfg(feature = "coinbase")]
+pub mod coinbase;
+#[cfg(feature = "itch")]
+pub mod itch;
+#[cfg(feature = "kraken")]
+pub mod kraken;
+
+pub use adapter::{
+    parse_level, FeedAdapter, FeedBook, FeedError, FeedMessage, FeedSnapshot, FeedUpdate,
+};
@@ -0,0 +1,317 @@
+//!
+//! FIX 4.4 adapter.
+//! NewOrderSingle and OrderCancelRequest messages are converted into orders and cancels,
+//! fills, cancellations and rejects are rendered as ExecutionReport messages.
+//! Order ids are numeric, ClOrdID of the request is used as the order id on the book.
+//!
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{
+    CancellationReport, CancellationStatus, Fill, Oid, Order, OrderBookError, OrderSide, Price,
+    RejectCode, TimeInForce, Timestamp, Volume,
+};
+
+const SOH: char = '\x01';
+const BEGIN_STRING: &str = "FIX.4.4";
+
+/// FIX parsing error
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum FixError {
+    #[error("Malformed field {0:?}")]
+    MalformedField(String),
+    #[error("Required tag {0} is missing")]
+    MissingTag(u32),
+    #[error("Tag {0} has invalid value {1:?}")]
+    InvalidValue(u32, String),
+    #[error("Message type {0:?} is not supported")]
+    UnsupportedMessage(String),
+}
+
+/// FIX message as a list of tag value pairs
+/// header fields BeginString, BodyLength and CheckSum are added when the message is encoded
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FixMessage {
+    fields: Vec<(u32, String)>,
+}
+
+impl FixMessage {
+    pub fn new(msg_type: &str) -> Self {
+        FixMessage::default().with(35, msg_type)
+    }
+
+    /// add the field to the message
+    pub fn with(mut self, tag: u32, value: impl ToString) -> Self {
+        self.fields.push((tag, value.to_string()));
+        self
+    }
+
+    /// value of the first field with the tag
+    pub fn get(&self, tag: u32) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn msg_type(&self) -> Option<&str> {
+        self.get(35)
+    }
+
+    /// parse tag=value fields separated by SOH, `|` is accepted as a separator as well
+    pub fn parse(message: &str) -> Result<Self, FixError> {
+        let fields = message
+            .split([SOH, '|'])
+            .filter(|field| !field.is_empty())
+            .map(|field| {
+                let (tag, value) = field
+                    .split_once('=')
+                    .ok_or_else(|| FixError::MalformedField(field.to_string()))?;
+                let tag = tag
+                    .parse()
+                    .map_err(|_| FixError::MalformedField(field.to_string()))?;
+                Ok((tag, value.to_string()))
+            })
+            .collect::<Result<Vec<_>, FixError>>()?;
+        Ok(FixMessage { fields })
+    }
+
+    /// encode with BeginString, BodyLength and CheckSum
+    pub fn encode(&self) -> String {
+        let mut body = String::new();
+        for (tag, value) in self
+            .fields
+            .iter()
+            .filter(|(tag, _)| ![8, 9, 10].contains(tag))
+        {
+            let _ = write!(body, "{tag}={value}{SOH}");
+        }
+        let mut message = format!("8={BEGIN_STRING}{SOH}9={}{SOH}{body}", body.len());
+        let checksum = message.bytes().map(|b| b as u32).sum::<u32>() % 256;
+        let _ = write!(message, "10={checksum:03}{SOH}");
+        message
+    }
+
+    fn required(&self, tag: u32) -> Result<&str, FixError> {
+        self.get(tag).ok_or(FixError::MissingTag(tag))
+    }
+
+    fn required_parse<T: FromStr>(&self, tag: u32) -> Result<T, FixError> {
+        let value = self.required(tag)?;
+        value
+            .parse()
+            .map_err(|_| FixError::InvalidValue(tag, value.to_string()))
+    }
+}
+
+/// Request of the client converted from the FIX message
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixRequest {
+    /// NewOrderSingle (D)
+    NewOrder(Order),
+    /// OrderCancelRequest (F), contains the id of the order to cancel
+    Cancel(Oid),
+}
+
+impl FixRequest {
+    /// convert the message, timestamp is taken from TransactTime (60) if present
+    pub fn from_message(message: &FixMessage, now: Timestamp) -> Result<Self, FixError> {
+        match message.required(35)? {
+            "D" => {
+                let id = Oid::new(message.required_parse(11)?);
+                let side = match message.required(54)? {
+                    "1" => OrderSide::Buy,
+                    "2" => OrderSide::Sell,
+                    side => return Err(FixError::InvalidValue(54, side.to_string())),
+                };
+                let volume: Volume = message.required_parse(38)?;
+                let timestamp = match message.get(60) {
+                    Some(time) => parse_transact_time(time)?,
+                    None => now,
+                };
+                let order = match message.required(40)? {
+                    "1" => Order::new_market(id, side, timestamp, volume),
+                    "K" => Order::new_market_to_limit(id, side, timestamp, volume),
+                    "2" => {
+                        let price: Price = message.required(44)?.parse().map_err(|_| {
+                            FixError::InvalidValue(44, message.get(44).unwrap_or("").to_string())
+                        })?;
+                        Order::new_limit(id, side, timestamp, price, volume)
+                    }
+                    kind => return Err(FixError::InvalidValue(40, kind.to_string())),
+                };
+                let time_in_force = match message.get(59).unwrap_or("0") {
+                    // day orders rest on the book until cancelled or the book is closed
+                    "0" | "1" => TimeInForce::GoodTillCancel,
+                    "3" => TimeInForce::ImmediateOrCancel,
+                    tif => return Err(FixError::InvalidValue(59, tif.to_string())),
+                };
+                Ok(FixRequest::NewOrder(
+                    order.with_time_in_force(time_in_force),
+                ))
+            }
+            "F" => Ok(FixRequest::Cancel(Oid::new(message.required_parse(41)?))),
+            msg_type => Err(FixError::UnsupportedMessage(msg_type.to_string())),
+        }
+    }
+}
+
+/// UTCTimestamp `YYYYMMDD-HH:MM:SS[.sss]`
+fn parse_transact_time(time: &str) -> Result<Timestamp, FixError> {
+    ["%Y%m%d-%H:%M:%S%.f", "%Y%m%d-%H:%M:%S"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(time, format).ok())
+        .map(|time| time.and_utc().into())
+        .ok_or_else(|| FixError::InvalidValue(60, time.to_string()))
+}
+
+/// OrdRejReason (103) for the error
+pub fn reject_reason(error: &OrderBookError) -> u32 {
+    match error.reject_code() {
+        // exchange closed
+        RejectCode::BookHalted | RejectCode::InvalidState | RejectCode::InvalidStateTransition => 2,
+        // order exceeds limit
+        RejectCode::PriceOutOfBand => 3,
+        // too late to enter
+        RejectCode::OrderExpired => 4,
+        // unknown order
+        RejectCode::UnknownOrder | RejectCode::VolumeNotAboveFilled => 5,
+        // duplicate order
+        RejectCode::DuplicateOrderId => 6,
+        // incorrect quantity
+        RejectCode::InvalidLotSize | RejectCode::VolumeBelowMinimum | RejectCode::ZeroVolume => 13,
+        // unsupported order characteristic
+        RejectCode::InvalidTickSize | RejectCode::PostOnlyWouldCross => 11,
+        // other
+        _ => 99,
+    }
+}
+
+fn side_code(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "1",
+        OrderSide::Sell => "2",
+    }
+}
+
+/// ExecutionReport of the fill for the buy and the sell order
+/// fill of two resting orders has no single price, the sell order price is reported as LastPx
+pub fn fill_reports(fill: &Fill, exec_id: &str) -> [FixMessage; 2] {
+    [
+        (fill.buy_order_id, OrderSide::Buy),
+        (fill.sell_order_id, OrderSide::Sell),
+    ]
+    .map(|(order_id, side)| {
+        FixMessage::new("8")
+            .with(37, order_id)
+            .with(11, order_id)
+            .with(17, exec_id)
+            .with(150, "F")
+            .with(54, side_code(side))
+            .with(31, fill.trade_price)
+            .with(32, fill.volume)
+    })
+}
+
+/// ExecutionReport of the cancelled or expired order
+pub fn cancel_report(report: &CancellationReport, exec_id: &str) -> FixMessage {
+    let message = FixMessage::new("8")
+        .with(37, report.order_id())
+        .with(11, report.order_id())
+        .with(17, exec_id);
+    match report.status() {
+        CancellationStatus::Cancelled => message.with(150, "4").with(39, "4"),
+        CancellationStatus::Expired => message.with(150, "C").with(39, "C"),
+        CancellationStatus::NotCancelled(reason) => {
+            message.with(150, "8").with(39, "8").with(58, reason)
+        }
+    }
+}
+
+/// ExecutionReport rejecting the order
+pub fn reject_report(order_id: Oid, error: &OrderBookError, exec_id: &str) -> FixMessage {
+    FixMessage::new("8")
+        .with(37, order_id)
+        .with(11, order_id)
+        .with(17, exec_id)
+        .with(150, "8")
+        .with(39, "8")
+        .with(103, reject_reason(error))
+        .with(58, error)
+}
+
+#[cfg(test)]
+mod tests_fix {
+    use super::*;
+    use crate::OrderType;
+
+    #[test]
+    fn test_new_order_single() {
+        let message = FixMessage::parse(
+            "35=D|11=42|54=2|38=100|40=2|44=10.25|59=3|60=20240102-10:00:00.500|",
+        )
+        .unwrap();
+        let FixRequest::NewOrder(order) =
+            FixRequest::from_message(&message, Timestamp::new(0)).unwrap()
+        else {
+            panic!("expected new order");
+        };
+        assert_eq!(order.id, Oid::new(42));
+        assert_eq!(order.side, OrderSide::Sell);
+        assert_eq!(order.kind, OrderType::Limit);
+        assert_eq!(order.price, Some(Price::new(10.25)));
+        assert_eq!(order.time_in_force, TimeInForce::ImmediateOrCancel);
+        assert_eq!(order.timestamp, Timestamp::new(1_704_189_600_500));
+
+        let cancel = FixMessage::parse("35=F|41=42|11=43").unwrap();
+        assert_eq!(
+            FixRequest::from_message(&cancel, Timestamp::new(0)),
+            Ok(FixRequest::Cancel(Oid::new(42)))
+        );
+        let missing = FixMessage::parse("35=D|11=42|54=1|40=1").unwrap();
+        assert_eq!(
+            FixRequest::from_message(&missing, Timestamp::new(0)),
+            Err(FixError::MissingTag(38))
+        );
+    }
+
+    #[test]
+    fn test_execution_reports() {
+        let report = reject_report(
+            Oid::new(7),
+            &OrderBookError::InvalidLotSize(Volume::new(3)),
+            "1",
+        );
+        assert_eq!(report.get(103), Some("13"));
+
+        let encoded = report.encode();
+        assert!(encoded.starts_with("8=FIX.4.4\x019="));
+        let parsed = FixMessage::parse(&encoded).unwrap();
+        assert_eq!(parsed.msg_type(), Some("8"));
+        assert_eq!(parsed.get(39), Some("8"));
+        let checksum = encoded[..encoded.len() - 7]
+            .bytes()
+            .map(|b| b as u32)
+            .sum::<u32>()
+            % 256;
+        assert_eq!(parsed.get(10), Some(format!("{checksum:03}").as_str()));
+
+        let fill = Fill {
+            buy_order_id: Oid::new(1),
+            sell_order_id: Oid::new(2),
+            buy_order_price: Price::new(10.0),
+            sell_order_price: Price::new(10.0),
+            trade_price: Price::new(10.0),
+            volume: Volume::new(5),
+            sequence: 1,
+        };
+        let [buy, sell] = fill_reports(&fill, "2");
+        assert_eq!(buy.get(54), Some("1"));
+        assert_eq!(sell.get(37), Some("2"));
+        assert_eq!(sell.get(31), Some("10"));
+        assert_eq!(sell.get(32), Some("5"));
+    }
+}
@@ -0,0 +1,251 @@
+//!
+//! Garbage collection of the book for quiet periods of long running engines.
+//! Queue entries of orders that are no longer on the book are unlinked, levels left without
+//! orders are removed and the removed levels are compacted. The work can be bounded by the
+//! number of visited levels, the next call continues where the previous one stopped,
+//! so the collection can be spread over many short quiet periods. Each call is logged as one
+//! event, the compaction it runs is part of it.
+//!
+
+use crate::{BookEvent, LevelStore, Limits, OrderBook, OrderMap, OrderSide, Price, Volume};
+
+/// Work done by the garbage collection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GarbageCollection {
+    pub visited_levels: usize,
+    /// queue entries of orders that were no longer on the book
+    pub dead_entries: usize,
+    /// levels removed as they had no orders left
+    pub empty_levels: usize,
+    /// levels released by the compaction at the end of the collection
+    pub released_levels: usize,
+    /// every level of the book has been visited and the book compacted
+    pub complete: bool,
+}
+
+impl<S: LevelStore> Limits<Price, Volume, S> {
+    /// visit the levels from the cursor in ascending price order within the budget,
+    /// returns false if the budget ran out before every level was visited
+    fn collect_garbage(
+        &mut self,
+        side: OrderSide,
+        orders: &OrderMap,
+        budget: &mut usize,
+        collection: &mut GarbageCollection,
+    ) -> bool {
+        while !self.gc_done {
+            let next = match self.gc_cursor {
+                Some(price) => self.store.above(price),
+                None => self.store.first(),
+            };
+            let Some((price, index)) = next else {
+                self.gc_done = true;
+                self.gc_cursor = None;
+                break;
+            };
+            if *budget == 0 {
+                return false;
+            }
+            *budget -= 1;
+            self.gc_cursor = Some(price);
+            collection.visited_levels += 1;
+            let Some(level) = self.levels.get_mut(index) else {
+                continue;
+            };
+            collection.dead_entries += level.orders.retain(|slot, handle| {
+                // slot of a removed order could have been reused by an order of another level
+                orders.get_by_handle(handle).is_some_and(|order| {
+                    order.side == side && order.price == price && order.queue_slot == Some(slot)
+                })
+            });
+            if level.orders.len() == 0 {
+                level.total_volume = Volume::ZERO;
+                self.touched.push(price);
+                self.remove_level(price, index);
+                collection.empty_levels += 1;
+            }
+        }
+        true
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// visit every level of the book, remove the orders that are no longer on the book
+    /// from the level queues and the levels left empty, and compact the book
+    pub fn garbage_collect(&mut self) -> GarbageCollection {
+        self.garbage_collect_bounded(usize::MAX)
+    }
+
+    /// same as `garbage_collect` visiting at most the budget of levels,
+    /// the next call continues from the last visited level, bids are visited before asks
+    pub fn garbage_collect_bounded(&mut self, budget: usize) -> GarbageCollection {
+        self.logged(
+            || BookEvent::GarbageCollect { budget },
+            |book| book.collect_garbage(budget),
+        )
+    }
+
+    /// compaction at the end of the collection is part of its event
+    fn collect_garbage(&mut self, mut budget: usize) -> GarbageCollection {
+        let mut collection = GarbageCollection::default();
+        for (side, limits) in [
+            (OrderSide::Buy, &mut self.bids),
+            (OrderSide::Sell, &mut self.asks),
+        ] {
+            if !limits.collect_garbage(side, &self.orders, &mut budget, &mut collection) {
+                break;
+            }
+        }
+        if collection.empty_levels > 0 {
+            self.update_top_of_book();
+        }
+        if self.bids.gc_done && self.asks.gc_done {
+            self.bids.gc_done = false;
+            self.asks.gc_done = false;
+            collection.released_levels = self.compact();
+            collection.complete = true;
+        }
+        collection
+    }
+}
+
+#[cfg(test)]
+mod tests_gc {
+    use crate::*;
+
+    fn limit(id: u64, side: OrderSide, price: f64) -> Order {
+        Order::new_limit(
+            Oid::new(id),
+            side,
+            Timestamp::new(id),
+            price.into(),
+            5.into(),
+        )
+    }
+
+    #[test]
+    fn test_dead_entries_and_empty_levels_are_collected() {
+        let mut order_book = OrderBook::default();
+        for order in [
+            limit(1, OrderSide::Buy, 9.0),
+            limit(2, OrderSide::Buy, 9.0),
+            limit(3, OrderSide::Buy, 8.0),
+            limit(4, OrderSide::Sell, 11.0),
+            limit(5, OrderSide::Sell, 12.0),
+        ] {
+            order_book.execute(&order).unwrap();
+        }
+        order_book.cancel_order(Oid::new(5)).unwrap();
+        // orders dropped from the book without leaving their levels
+        for id in [2, 4] {
+            order_book.orders.remove(&Oid::new(id));
+        }
+        order_book
+            .asks
+            .level_at_mut(&Price::new(11.0))
+            .unwrap()
+            .total_volume = Volume::ZERO;
+        order_book
+            .bids
+            .level_at_mut(&Price::new(9.0))
+            .unwrap()
+            .total_volume = 5.into();
+
+        // budget runs out on the bids
+        let collection = order_book.garbage_collect_bounded(1);
+        assert_eq!(collection.visited_levels, 1);
+        assert!(!collection.complete);
+        let collection = order_book.garbage_collect_bounded(2);
+        assert_eq!(
+            collection,
+            GarbageCollection {
+                visited_levels: 2,
+                dead_entries: 2,
+                empty_levels: 1,
+                released_levels: 2,
+                complete: true,
+            }
+        );
+        assert_eq!(order_book.get_best_sell(), None);
+        assert_eq!(order_book.get_best_buy_volume(), Some(5.into()));
+        assert!(order_book.check_integrity().is_ok());
+
+        // next collection starts over
+        let collection = order_book.garbage_collect();
+        assert_eq!(collection.visited_levels, 2);
+        assert_eq!(collection.dead_entries, 0);
+        assert!(collection.complete);
+    }
+
+    #[test]
+    fn test_cancelled_levels_are_reclaimed_over_several_calls() {
+        let mut order_book = OrderBook::default();
+        for id in 1..=6 {
+            let order = limit(id, OrderSide::Buy, 10.0 - id as f64);
+            order_book.execute(&order).unwrap();
+            let order = limit(id + 10, OrderSide::Sell, 10.0 + id as f64);
+            order_book.execute(&order).unwrap();
+        }
+        order_book.execute(&limit(7, OrderSide::Buy, 9.0)).unwrap();
+        for id in [1, 2, 3, 12, 14] {
+            order_book.cancel_order(Oid::new(id)).unwrap();
+        }
+        order_book.cancel_order(Oid::new(7)).unwrap();
+
+        let mut total = GarbageCollection::default();
+        let mut calls = 0;
+        while !total.complete {
+            let collection = order_book.garbage_collect_bounded(2);
+            assert!(collection.visited_levels <= 2);
+            total.visited_levels += collection.visited_levels;
+            total.dead_entries += collection.dead_entries;
+            total.empty_levels += collection.empty_levels;
+            total.released_levels += collection.released_levels;
+            total.complete = collection.complete;
+            calls += 1;
+        }
+        assert_eq!(calls, 4);
+        // cancels unlinked the queue entries and removed the levels, the collection compacts them
+        assert_eq!(
+            total,
+            GarbageCollection {
+                visited_levels: 7,
+                dead_entries: 0,
+                empty_levels: 0,
+                released_levels: 5,
+                complete: true,
+            }
+        );
+        assert_eq!(order_book.level_count(OrderSide::Buy), 3);
+        assert_eq!(order_book.level_count(OrderSide::Sell), 4);
+        assert_eq!(order_book.order_count(), 7);
+        assert_eq!(order_book.get_best_buy(), Some(6.0.into()));
+        assert_eq!(order_book.get_best_sell(), Some(11.0.into()));
+        assert!(order_book.check_integrity().is_ok());
+
+        // released levels are reused by new orders
+        order_book.execute(&limit(20, OrderSide::Buy, 9.0)).unwrap();
+        assert_eq!(order_book.get_best_buy_volume(), Some(5.into()));
+        assert_eq!(order_book.garbage_collect().released_levels, 0);
+    }
+
+    #[test]
+    fn test_garbage_collection_is_logged_once() {
+        let mut order_book = OrderBook::default().with_event_log();
+        order_book.execute(&limit(1, OrderSide::Buy, 9.0)).unwrap();
+        order_book.cancel_order(Oid::new(1)).unwrap();
+        order_book.garbage_collect_bounded(1);
+        order_book.garbage_collect();
+
+        let events = &order_book.book_events()[2..];
+        assert_eq!(
+            events,
+            [
+                BookEvent::GarbageCollect { budget: 1 },
+                BookEvent::GarbageCollect { budget: usize::MAX },
+            ]
+        );
+        let replayed = OrderBook::from_events(order_book.book_events().to_vec());
+        assert_eq!(replayed.l3_snapshot(), order_book.l3_snapshot());
+    }
+}
@@ -0,0 +1,212 @@
+//!
+//! Point-in-time view of the book. Events of the log are recorded together with the event
+//! sequence and the time of the book after each of them, and a copy of the book is kept
+//! every given number of events. State of the book as of any sequence number or time is
+//! materialized by replaying the events recorded after the nearest checkpoint.
+//!
+
+use alloc::vec::Vec;
+
+use crate::{BookEvent, OrderBook, Timestamp};
+
+/// number of events between two checkpoints of the history
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 1024;
+
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    event: BookEvent,
+    // event sequence of the book after the event
+    sequence: u64,
+    // time of the book after the event, it never goes back
+    timestamp: Timestamp,
+}
+
+/// Events of the book with periodic checkpoints
+#[derive(Debug, Clone)]
+pub struct BookHistory {
+    // book after the last recorded event
+    book: OrderBook,
+    entries: Vec<HistoryEntry>,
+    // book after the given number of events, ordered by the number of events
+    checkpoints: Vec<(usize, OrderBook)>,
+    checkpoint_interval: usize,
+}
+
+impl Default for BookHistory {
+    fn default() -> Self {
+        BookHistory::new(OrderBook::default())
+    }
+}
+
+impl BookHistory {
+    /// history starting with the book, the book has the configuration of the logged book
+    pub fn new(mut book: OrderBook) -> Self {
+        book.event_log = None;
+        BookHistory {
+            checkpoints: Vec::from([(0, book.clone())]),
+            book,
+            entries: Vec::new(),
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+        }
+    }
+
+    /// history of the logged events starting with an empty book
+    pub fn from_events(events: impl IntoIterator<Item = BookEvent>) -> Self {
+        let mut history = BookHistory::default();
+        history.extend(events);
+        history
+    }
+
+    /// number of events between checkpoints, fewer events are replayed by queries
+    /// at the cost of keeping more copies of the book
+    pub fn with_checkpoint_interval(mut self, events: usize) -> Self {
+        self.checkpoint_interval = events.max(1);
+        self
+    }
+
+    /// apply the next event of the log and record it
+    pub fn record(&mut self, event: BookEvent) {
+        let timestamp = match (event.timestamp(), self.entries.last()) {
+            (Some(time), Some(last)) => Timestamp::new(u64::from(time).max(last.timestamp.into())),
+            (Some(time), None) => time,
+            (None, Some(last)) => last.timestamp,
+            (None, None) => Timestamp::new(0),
+        };
+        self.book.apply_event(event.clone());
+        self.entries.push(HistoryEntry {
+            event,
+            sequence: self.book.event_sequence(),
+            timestamp,
+        });
+        if self.entries.len().is_multiple_of(self.checkpoint_interval) {
+            self.checkpoints
+                .push((self.entries.len(), self.book.clone()));
+        }
+    }
+
+    pub fn extend(&mut self, events: impl IntoIterator<Item = BookEvent>) {
+        for event in events {
+            self.record(event);
+        }
+    }
+
+    /// book after the last recorded event
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// number of recorded events
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// recorded events in order
+    pub fn events(&self) -> impl Iterator<Item = &BookEvent> {
+        self.entries.iter().map(|entry| &entry.event)
+    }
+
+    /// book once the event with the sequence number was applied,
+    /// the starting book if the sequence is before the first event
+    pub fn at_sequence(&self, sequence: u64) -> OrderBook {
+        let count = self
+            .entries
+            .partition_point(|entry| entry.sequence <= sequence);
+        self.after_events(count)
+    }
+
+    /// book after all events up to and including the time
+    pub fn at_time(&self, timestamp: Timestamp) -> OrderBook {
+        let count = self
+            .entries
+            .partition_point(|entry| u64::from(entry.timestamp) <= u64::from(timestamp));
+        self.after_events(count)
+    }
+
+    /// book after the given number of recorded events
+    pub fn after_events(&self, count: usize) -> OrderBook {
+        let count = count.min(self.entries.len());
+        if count == self.entries.len() {
+            return self.book.clone();
+        }
+        let index = self
+            .checkpoints
+            .partition_point(|(events, _)| *events <= count)
+            - 1;
+        let (replayed, checkpoint) = &self.checkpoints[index];
+        let mut book = checkpoint.clone();
+        book.replay_events(
+            self.entries[*replayed..count]
+                .iter()
+                .map(|entry| entry.event.clone()),
+        );
+        book
+    }
+}
+
+#[cfg(test)]
+mod tests_history {
+    use crate::*;
+
+    fn session() -> Vec<BookEvent> {
+        let mut order_book = OrderBook::default().with_event_log();
+        for id in 1..=10 {
+            let side = if id % 2 == 0 {
+                OrderSide::Buy
+            } else {
+                OrderSide::Sell
+            };
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id * 10),
+                Price::new(10.0 + (id % 3) as f64),
+                5.into(),
+            );
+            let _ = order_book.execute(&order);
+        }
+        order_book.drain_book_events()
+    }
+
+    #[test]
+    fn test_book_as_of_sequence_and_time() {
+        let events = session();
+        let history = BookHistory::from_events(events.clone()).with_checkpoint_interval(3);
+        let mut checkpointed = BookHistory::default().with_checkpoint_interval(3);
+        checkpointed.extend(events.clone());
+        assert_eq!(checkpointed.len(), 10);
+
+        for count in 0..=events.len() {
+            let expected = OrderBook::from_events(events[..count].iter().cloned());
+            let book = checkpointed.after_events(count);
+            assert_eq!(book.depth(usize::MAX), expected.depth(usize::MAX));
+            assert_eq!(book.event_sequence(), expected.event_sequence());
+            assert_eq!(
+                checkpointed
+                    .at_sequence(expected.event_sequence())
+                    .depth(usize::MAX),
+                expected.depth(usize::MAX)
+            );
+        }
+
+        // book when the fifth order was sent, before and after it was executed
+        let before = history.at_time(Timestamp::new(49));
+        let after = history.at_time(Timestamp::new(50));
+        assert_eq!(
+            before.depth(usize::MAX),
+            OrderBook::from_events(events[..4].iter().cloned()).depth(usize::MAX)
+        );
+        assert_eq!(
+            after.depth(usize::MAX),
+            OrderBook::from_events(events[..5].iter().cloned()).depth(usize::MAX)
+        );
+        assert!(history.at_time(Timestamp::new(0)).is_empty());
+        assert_eq!(
+            history.at_sequence(u64::MAX).depth(usize::MAX),
+            history.book().depth(usize::MAX)
+        );
+    }
+}
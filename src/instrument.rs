@@ -0,0 +1,210 @@
+//!
+//! Instrument trading rules enforced by the book, and the metadata of the instruments
+//! kept in a registry keyed by symbol.
+//!
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+use crate::notional;
+use crate::utils::PRICE_DECIMALS;
+use crate::{OrderBookError, Price, Symbol, Volume};
+
+/// Instrument specification
+/// prices must be a multiple of the tick size, volumes a multiple of the lot size
+/// and not smaller than the minimum volume
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstrumentSpec {
+    pub tick_size: Price,
+    pub lot_size: Volume,
+    pub min_volume: Volume,
+}
+
+impl InstrumentSpec {
+    pub fn new(tick_size: Price, lot_size: Volume, min_volume: Volume) -> Self {
+        InstrumentSpec {
+            tick_size,
+            lot_size,
+            min_volume,
+        }
+    }
+
+    /// check that the price is on a tick
+    pub fn validate_price(&self, price: Price) -> Result<(), OrderBookError> {
+        let tick = self.tick_size.mantissa();
+        if tick > 0 && price.mantissa() % tick != 0 {
+            return Err(OrderBookError::InvalidTickSize(price));
+        }
+        Ok(())
+    }
+
+    /// price in ticks of the tick size, for books keyed by integer ticks
+    /// none if the price is not on a tick or the spec has no tick size
+    pub fn to_ticks(&self, price: Price) -> Option<i64> {
+        let tick = self.tick_size.mantissa();
+        (tick > 0 && price.mantissa() % tick == 0).then(|| price.mantissa() / tick)
+    }
+
+    /// price of the number of ticks, none if the spec has no tick size or the price overflows
+    pub fn from_ticks(&self, ticks: i64) -> Option<Price> {
+        let tick = self.tick_size.mantissa();
+        if tick <= 0 {
+            return None;
+        }
+        ticks.checked_mul(tick).map(Price::from_mantissa)
+    }
+
+    /// check that the volume is on a lot and not below the minimum
+    pub fn validate_volume(&self, volume: Volume) -> Result<(), OrderBookError> {
+        if volume < self.min_volume {
+            return Err(OrderBookError::VolumeBelowMinimum(volume));
+        }
+        let lot = self.lot_size.mantissa();
+        if lot > 0 && !volume.mantissa().is_multiple_of(lot) {
+            return Err(OrderBookError::InvalidLotSize(volume));
+        }
+        Ok(())
+    }
+}
+
+impl Default for InstrumentSpec {
+    /// any price and volume is accepted
+    fn default() -> Self {
+        InstrumentSpec {
+            tick_size: Price::ZERO,
+            lot_size: Volume::ZERO,
+            min_volume: Volume::ZERO,
+        }
+    }
+}
+
+/// Instrument metadata
+/// the book of the instrument validates orders by its spec
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instrument {
+    pub symbol: Symbol,
+    pub spec: InstrumentSpec,
+    /// number of decimal places prices are shown with
+    pub price_precision: u32,
+    /// currency of the prices, empty if not known
+    pub currency: String,
+    /// contract multiplier, value of one unit of volume in price units
+    pub multiplier: u64,
+}
+
+impl Instrument {
+    /// instrument with full price precision, no currency and multiplier of one
+    pub fn new(symbol: Symbol, spec: InstrumentSpec) -> Self {
+        Instrument {
+            symbol,
+            spec,
+            price_precision: PRICE_DECIMALS,
+            currency: String::new(),
+            multiplier: 1,
+        }
+    }
+
+    /// precision above the precision of the price is capped
+    pub fn with_price_precision(mut self, price_precision: u32) -> Self {
+        self.price_precision = price_precision.min(PRICE_DECIMALS);
+        self
+    }
+
+    pub fn with_currency(mut self, currency: impl Into<String>) -> Self {
+        self.currency = currency.into();
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: u64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// price times volume times multiplier, saturating on overflow
+    pub fn notional(&self, price: Price, volume: Volume) -> Price {
+        notional::notional(price, volume, self.multiplier)
+    }
+
+    /// price rounded to the price precision of the instrument
+    pub fn format_price(&self, price: Price) -> String {
+        alloc::format!(
+            "{price:.precision$}",
+            precision = self.price_precision as usize
+        )
+    }
+}
+
+/// Instruments keyed by symbol
+#[derive(Debug, Clone, Default)]
+pub struct InstrumentRegistry {
+    instruments: BTreeMap<Symbol, Instrument>,
+}
+
+impl InstrumentRegistry {
+    pub fn new() -> Self {
+        InstrumentRegistry::default()
+    }
+
+    /// add the instrument, returns the previous instrument of the symbol
+    pub fn register(&mut self, instrument: Instrument) -> Option<Instrument> {
+        self.instruments
+            .insert(instrument.symbol.clone(), instrument)
+    }
+
+    pub fn remove(&mut self, symbol: &Symbol) -> Option<Instrument> {
+        self.instruments.remove(symbol)
+    }
+
+    pub fn get(&self, symbol: &Symbol) -> Option<&Instrument> {
+        self.instruments.get(symbol)
+    }
+
+    pub fn contains(&self, symbol: &Symbol) -> bool {
+        self.instruments.contains_key(symbol)
+    }
+
+    /// instruments in alphabetical order of their symbols
+    pub fn iter(&self) -> impl Iterator<Item = &Instrument> {
+        self.instruments.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.instruments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instruments.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests_instrument {
+    use crate::*;
+
+    #[test]
+    fn test_instrument_metadata() {
+        let future = Instrument::new(
+            Symbol::from("ES"),
+            InstrumentSpec::new(0.25.into(), 1.into(), 1.into()),
+        )
+        .with_price_precision(2)
+        .with_currency("USD")
+        .with_multiplier(50);
+        assert_eq!(
+            future.notional(Price::new(4_500.25), 2.into()),
+            Price::new(450_025.0)
+        );
+        assert_eq!(future.format_price(Price::new(4_500.25)), "4500.25");
+        assert_eq!(future.format_price(Price::new(4_500.0)), "4500.00");
+        assert_eq!(future.spec.to_ticks(Price::new(4_500.25)), Some(18_001));
+        assert_eq!(future.spec.to_ticks(Price::new(4_500.1)), None);
+        assert_eq!(future.spec.from_ticks(18_001), Some(Price::new(4_500.25)));
+        assert_eq!(InstrumentSpec::default().to_ticks(Price::new(1.0)), None);
+
+        let mut registry = InstrumentRegistry::new();
+        assert!(registry.register(future.clone()).is_none());
+        assert_eq!(registry.get(&Symbol::from("ES")), Some(&future));
+        assert_eq!(registry.register(future).map(|i| i.multiplier), Some(50));
+        assert_eq!(registry.len(), 1);
+    }
+}
@@ -0,0 +1,287 @@
+//!
+//! Invariant checks of the book. Every public operation leaves the book consistent,
+//! the checker lets users verify it in tests or periodically in production.
+//!
+
+use alloc::vec::Vec;
+
+use hashbrown::HashSet;
+
+use crate::{LevelStore, Oid, OrderBook, OrderSide, Price, TradingState, Volume};
+
+/// Violated invariant of the book
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntegrityViolation {
+    /// level volume is not the sum of the visible volume of its orders
+    LevelVolumeMismatch {
+        side: OrderSide,
+        price: Price,
+        level_volume: Volume,
+        orders_volume: Volume,
+    },
+    /// level with no volume left is still active
+    EmptyLevel { side: OrderSide, price: Price },
+    /// level is queued at a price different from its own
+    LevelPriceMismatch { side: OrderSide, price: Price },
+    /// price is indexed inconsistently by the level store, i.e. in the level map but not in
+    /// the price ordered levels
+    SortedLevelsMismatch { side: OrderSide, price: Price },
+    /// price is both active and removed
+    RemovedLevelActive { side: OrderSide, price: Price },
+    /// level queue refers to an order that is not on the book
+    QueuedOrderMissing { side: OrderSide, price: Price },
+    /// order is queued at a level of the wrong side or price
+    OrderAtWrongLevel { id: Oid, price: Price },
+    /// order is on the book but not queued at any level
+    OrderNotQueued(Oid),
+    /// best level is not the highest bid or lowest ask
+    StaleBest {
+        side: OrderSide,
+        best: Option<Price>,
+        expected: Option<Price>,
+    },
+    /// cached volume of the best level is not the volume of the level
+    StaleBestVolume {
+        side: OrderSide,
+        best_volume: Option<Volume>,
+        expected: Option<Volume>,
+    },
+    /// best bid is at or above the best ask while the book is open and orders of the two
+    /// levels can fill each other,
+    /// orders added with `add_order` leave the book crossed until they are matched,
+    /// orders with minimum quantity stay crossed as long as no fill satisfies them
+    CrossedBook { bid: Price, ask: Price },
+}
+
+/// Result of the integrity check
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntegrityReport {
+    pub violations: Vec<IntegrityViolation>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// validate the internal invariants of the book
+    pub fn check_integrity(&self) -> IntegrityReport {
+        let mut violations = Vec::new();
+        let mut queued = HashSet::new();
+        for side in [OrderSide::Buy, OrderSide::Sell] {
+            self.check_limits(side, &mut queued, &mut violations);
+        }
+        for order in self.orders.values() {
+            if !queued.contains(&order.id) {
+                violations.push(IntegrityViolation::OrderNotQueued(order.id));
+            }
+        }
+        if self.state == TradingState::Open {
+            if let (Some(bid), Some(ask)) = (self.get_best_buy(), self.get_best_sell()) {
+                if bid >= ask && self.best_orders_can_fill() {
+                    violations.push(IntegrityViolation::CrossedBook { bid, ask });
+                }
+            }
+        }
+        IntegrityReport { violations }
+    }
+
+    /// any order of the best bid level and any order of the best ask level accept a fill
+    /// of each other, as they are paired by the matching
+    fn best_orders_can_fill(&self) -> bool {
+        let best_orders = |side: OrderSide| {
+            self.limits(side)
+                .iter_levels(side)
+                .next()
+                .into_iter()
+                .flat_map(|level| level.orders.iter())
+                .filter_map(|handle| self.orders.get_by_handle(handle))
+        };
+        best_orders(OrderSide::Buy).any(|buy| {
+            best_orders(OrderSide::Sell).any(|sell| {
+                let volume = buy.visible_volume().min(sell.visible_volume());
+                buy.accepts_fill(volume) && sell.accepts_fill(volume)
+            })
+        })
+    }
+
+    fn check_limits(
+        &self,
+        side: OrderSide,
+        queued: &mut HashSet<Oid>,
+        violations: &mut Vec<IntegrityViolation>,
+    ) {
+        let limits = self.limits(side);
+        for price in limits.store.inconsistent_prices() {
+            violations.push(IntegrityViolation::SortedLevelsMismatch { side, price });
+        }
+        for (price, _) in limits.store.iter() {
+            if limits.removed_levels.contains_key(&price) {
+                violations.push(IntegrityViolation::RemovedLevelActive { side, price });
+            }
+        }
+
+        for level in limits.iter_levels(side) {
+            let price = level.price;
+            if limits.store.get(&price) != level.index {
+                violations.push(IntegrityViolation::LevelPriceMismatch { side, price });
+            }
+            if level.total_volume.is_zero() {
+                violations.push(IntegrityViolation::EmptyLevel { side, price });
+            }
+            let mut orders_volume = Volume::ZERO;
+            for handle in level.orders.iter() {
+                let Some(order) = self.orders.get_by_handle(handle) else {
+                    violations.push(IntegrityViolation::QueuedOrderMissing { side, price });
+                    continue;
+                };
+                if order.side != side || order.price != price {
+                    violations.push(IntegrityViolation::OrderAtWrongLevel {
+                        id: order.id,
+                        price,
+                    });
+                }
+                orders_volume += order.visible_volume();
+                queued.insert(order.id);
+            }
+            if orders_volume != level.total_volume {
+                violations.push(IntegrityViolation::LevelVolumeMismatch {
+                    side,
+                    price,
+                    level_volume: level.total_volume,
+                    orders_volume,
+                });
+            }
+        }
+
+        let expected = match side {
+            OrderSide::Buy => limits.store.last(),
+            OrderSide::Sell => limits.store.first(),
+        }
+        .map(|(price, _)| price);
+        let best = limits.get_best_limit();
+        if best != expected {
+            violations.push(IntegrityViolation::StaleBest {
+                side,
+                best,
+                expected,
+            });
+        }
+        let expected = limits
+            .get_best()
+            .and_then(|index| limits.levels.get(index))
+            .map(|level| level.total_volume);
+        let best_volume = limits.get_best_volume();
+        if best_volume != expected {
+            violations.push(IntegrityViolation::StaleBestVolume {
+                side,
+                best_volume,
+                expected,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_integrity {
+    use crate::*;
+
+    #[test]
+    fn test_check_integrity() {
+        let mut order_book = OrderBook::default();
+        for (id, side, price, volume) in [
+            (1, OrderSide::Buy, 10.0, 5),
+            (2, OrderSide::Buy, 10.0, 7),
+            (3, OrderSide::Buy, 9.0, 1),
+            (4, OrderSide::Sell, 11.0, 3),
+            (5, OrderSide::Sell, 12.0, 4),
+        ] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+        let order = Order::new_limit(
+            Oid::new(6),
+            OrderSide::Sell,
+            Timestamp::new(6),
+            10.0.into(),
+            6.into(),
+        )
+        .with_display_volume(2.into());
+        order_book.execute(&order).unwrap();
+        order_book.cancel_order(Oid::new(4)).unwrap();
+        order_book
+            .amend_order(Oid::new(2), 9.0.into(), 3.into())
+            .unwrap();
+        assert_eq!(order_book.check_integrity(), IntegrityReport::default());
+
+        let index = order_book.bids.store.get(&Price::new(9.0)).unwrap();
+        order_book.bids.levels.get_mut(index).unwrap().total_volume = 1.into();
+        assert_eq!(
+            order_book.check_integrity().violations,
+            vec![
+                IntegrityViolation::LevelVolumeMismatch {
+                    side: OrderSide::Buy,
+                    price: 9.0.into(),
+                    level_volume: 1.into(),
+                    orders_volume: 3.into(),
+                },
+                // cached volume of the best level no longer matches the corrupted level
+                IntegrityViolation::StaleBestVolume {
+                    side: OrderSide::Buy,
+                    best_volume: Some(3.into()),
+                    expected: Some(1.into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_crossed_book_with_min_qty_orders() {
+        let mut order_book = OrderBook::default();
+        let bid = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Buy,
+            Timestamp::new(1),
+            98.0.into(),
+            3.into(),
+        )
+        .with_min_qty(3.into());
+        let ask = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Sell,
+            Timestamp::new(2),
+            97.0.into(),
+            1.into(),
+        );
+        order_book.execute(&bid).unwrap();
+        order_book.execute(&ask).unwrap();
+        // crossed, but no fill satisfies the minimum quantity of the bid
+        assert_eq!(order_book.get_best_buy(), Some(98.0.into()));
+        assert_eq!(order_book.get_best_sell(), Some(97.0.into()));
+        assert!(order_book.check_integrity().is_ok());
+
+        let order = LimitOrder::new(
+            Oid::new(3),
+            OrderSide::Buy,
+            Timestamp::new(3),
+            99.0.into(),
+            1.into(),
+        );
+        order_book.add_order(order).unwrap();
+        assert_eq!(
+            order_book.check_integrity().violations,
+            vec![IntegrityViolation::CrossedBook {
+                bid: 99.0.into(),
+                ask: 97.0.into()
+            }]
+        );
+    }
+}
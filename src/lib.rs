@@ -10,209 +10,589 @@
 //! Limit at which to execute orders. The book is updated in real-time as orders are placed and
 //! executed.
 //!
+//! The core book builds without the standard library, only `alloc` is required.
+//! Disable the default `std` feature to build it with `no_std`.
+//!
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod analytics;
+mod auction;
+mod audit;
+mod bands;
+mod builder;
+mod candles;
+mod clock;
+mod command;
+mod conflation;
+mod crossing;
+mod delta;
+mod depth;
+mod depth_limit;
+mod engine;
+mod events;
+pub mod feed;
+#[cfg(feature = "fix")]
+pub mod fix;
+mod gc;
+mod history;
+mod instrument;
+mod integrity;
+mod manager;
+mod mass_cancel;
+mod metrics;
+mod mirror;
+mod notifier;
+mod notional;
+mod numeric;
+mod oco;
+mod oid;
+#[cfg(feature = "ouch")]
+pub mod ouch;
+mod owner;
+mod positions;
+mod pricing;
 mod primitives;
-use stable_vec::StableVec;
-use std::{
-    collections::VecDeque,
-    ops::{Deref, DerefMut},
+mod profile;
+mod protection;
+#[cfg(feature = "std")]
+mod publish;
+#[cfg(feature = "python")]
+mod python;
+mod queue;
+mod quote;
+mod reference;
+mod reject;
+mod repair;
+#[cfg(feature = "std")]
+pub mod replay;
+mod risk;
+#[cfg(feature = "sbe")]
+pub mod sbe;
+mod sequence;
+#[cfg(feature = "tokio")]
+mod service;
+#[cfg(feature = "std")]
+mod sharded;
+mod snapshot;
+#[cfg(feature = "spsc")]
+mod spsc;
+mod state;
+mod status;
+mod store;
+mod tape;
+#[cfg(feature = "proptest")]
+pub mod testing;
+mod throttle;
+pub mod utils;
+#[cfg(feature = "wasm")]
+mod wasm;
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
 };
+use core::fmt::{Display, Formatter};
+use core::ops::{Deref, DerefMut};
+use itertools::Either;
+use stable_vec::StableVec;
 use thiserror::Error;
 
 pub use primitives::{
-    LimitOrder, Oid, Order, OrderSide, OrderType, Price, Spread, Timestamp, Volume,
+    FractionalVolumeError, LevelIndex, LimitOrder, Oid, Order, OrderSide, OrderType, OwnerId,
+    ParseEnumError, ParsePriceError, ParseVolumeError, PostOnly, Price, Spread, TimeInForce,
+    Timestamp, Volume,
 };
 
-use primitives::{LevelIndex, LevelMap, OrderMap};
+use primitives::{LevelMap, OrderHandle, OrderMap};
+use queue::OrderQueue;
+
+pub use analytics::SweepEstimate;
+pub use auction::AuctionResult;
+pub use audit::{AuditEvent, AuditRecord};
+pub use bands::PriceBands;
+pub use builder::OrderBookBuilder;
+pub use candles::{Candle, CandleBuilder};
+#[cfg(feature = "std")]
+pub use clock::SystemClock;
+pub use clock::{Clock, ManualClock};
+pub use command::{Command, ExecutionReport};
+pub use conflation::{ConflatedBatch, Conflator};
+pub use crossing::CrossingEvent;
+pub use delta::{BookDelta, DeltaEvent};
+pub use depth::{Bbo, DepthLevel, DepthSnapshot, L3Snapshot, OrderEntry};
+pub use engine::{Match, MatchingEngine, MatchingEngineError};
+pub use events::BookEvent;
+pub use gc::GarbageCollection;
+pub use history::{BookHistory, DEFAULT_CHECKPOINT_INTERVAL};
+pub use instrument::{Instrument, InstrumentRegistry, InstrumentSpec};
+pub use integrity::{IntegrityReport, IntegrityViolation};
+pub use manager::{ManagerError, OrderBookManager, Participant, Symbol};
+pub use metrics::{LatencyHistogram, LatencyMetrics, LatencyOperation};
+pub use mirror::{MirrorBook, MirrorError, UncrossPolicy, Uncrossing};
+pub use notifier::BboNotifier;
+pub use numeric::{LevelOrder, PriceLike, QuantityLike};
+pub use oco::{GroupId, OcoEvent, OcoTrigger};
+pub use oid::OidGenerator;
+pub use positions::{Position, Positions};
+pub use pricing::TradePricePolicy;
+pub use protection::{ProtectionEvent, ProtectionLimit};
+#[cfg(feature = "std")]
+pub use publish::{SnapshotPublisher, SnapshotReader};
+pub use quote::{Quote, QuoteReport};
+pub use reject::RejectCode;
+pub use repair::BestValidity;
+pub use risk::{
+    MaxNotional, MaxOpenNotional, MaxOrderSize, PriceCollar, RejectReason, RiskValidator,
+};
+#[cfg(feature = "spsc")]
+pub use rtrb;
+#[cfg(feature = "tokio")]
+pub use service::BookService;
+#[cfg(feature = "std")]
+pub use sharded::ShardedBookManager;
+pub use snapshot::{BookSnapshot, SnapshotError, SNAPSHOT_VERSION};
+#[cfg(feature = "spsc")]
+pub use spsc::{spsc_driver, Response, SpscDriver};
+pub use state::{StateTransition, TradingState};
+pub use status::{OrderState, OrderView, DEFAULT_FINISHED_ORDERS_CAPACITY};
+pub use store::{DenseLevels, HashedLevels, LevelStore, SortedLevels};
+
+pub use tape::{TapeEntry, TradeTape};
+pub use throttle::{RateLimit, RateLimiter};
+#[cfg(feature = "wasm")]
+pub use wasm::{WasmBbo, WasmOrderBook, WasmTrade};
+
+use audit::AuditTrail;
+use crossing::Crossing;
+use delta::DeltaFeed;
+use depth_limit::DepthLimit;
+use oco::OcoGroups;
+use protection::Protection;
+use quote::Quotes;
+use status::FinishedOrders;
 
 /// Limit level
 /// represents Price level and list of orders in FIFO order
 #[derive(Debug, Clone)]
-pub struct Level {
+pub struct Level<P = Price, V = Volume> {
     index: Option<LevelIndex>,
-    price: Price,
-    total_volume: Volume,
-    orders: VecDeque<Oid>,
+    price: P,
+    total_volume: V,
+    orders: OrderQueue,
 }
 
-impl Eq for Level {}
-impl PartialEq for Level {
+impl<P: PriceLike, V: QuantityLike> Eq for Level<P, V> {}
+impl<P: PriceLike, V: QuantityLike> PartialEq for Level<P, V> {
     fn eq(&self, other: &Self) -> bool {
         self.price == other.price
     }
 }
 
-impl PartialOrd for Level {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+impl<P: PriceLike, V: QuantityLike> PartialOrd for Level<P, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Ord for Level {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+impl<P: PriceLike, V: QuantityLike> Ord for Level<P, V> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.price.cmp(&other.price)
     }
 }
 
-impl Level {
+impl<P: PriceLike, V: QuantityLike> Level<P, V> {
     /// Create a new Limit level
-    pub fn new(price: Price) -> Level {
+    pub fn new(price: P) -> Self {
         Level {
             index: None,
             price,
-            total_volume: Volume::ZERO,
-            orders: VecDeque::new(),
+            total_volume: V::ZERO,
+            orders: OrderQueue::default(),
         }
     }
 
+    pub fn price(&self) -> P {
+        self.price
+    }
+
+    /// volume visible at the level
+    pub fn total_volume(&self) -> V {
+        self.total_volume
+    }
+
+    /// number of orders queued at the level
+    pub fn order_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// level volume is left unchanged if it is smaller than the volume
+    pub fn reduce_volume(&mut self, volume: V) -> Result<(), OrderBookError> {
+        self.total_volume = self
+            .total_volume
+            .checked_sub(volume)
+            .ok_or(OrderBookError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
     /// Add an order to the Limit level
-    pub fn add_order(&mut self, order: &LimitOrder) {
-        {
-            self.total_volume += order.volume;
+    /// only the visible part of the order contributes to the level volume
+    pub fn add_order<O: LevelOrder<P, V>>(
+        &mut self,
+        order: &mut O,
+        handle: OrderHandle,
+    ) -> Result<(), OrderBookError> {
+        self.total_volume = self
+            .total_volume
+            .checked_add(order.visible_volume())
+            .ok_or(OrderBookError::ArithmeticOverflow)?;
+        *order.queue_slot_mut() = Some(self.orders.push_back(handle));
+        Ok(())
+    }
+
+    /// unlink the order from the level queue and remove its visible volume from the level,
+    /// the order is always removed, level volume cannot go below zero
+    fn remove_order<O: LevelOrder<P, V>>(&mut self, order: &mut O) {
+        if let Some(slot) = order.queue_slot_mut().take() {
+            self.orders.remove(slot);
         }
-        self.orders.push_back(order.id);
+        self.total_volume = self.total_volume.saturating_sub(order.visible_volume());
     }
 
-    pub fn reduce_volume(&mut self, volume: Volume) {
-        self.total_volume -= volume;
+    /// fill the order queued at the level
+    /// once the visible volume is filled the order is removed from the level,
+    /// iceberg order with hidden volume left shows the next tranche at the back of the level
+    /// returns true if the order has been completely filled
+    fn fill_queued_order<O: LevelOrder<P, V>>(
+        &mut self,
+        order: &mut O,
+        handle: OrderHandle,
+        volume: V,
+    ) -> Result<bool, OrderBookError> {
+        // both are checked before the order or the level is changed
+        let filled = order
+            .filled_volume()
+            .checked_add(volume)
+            .filter(|filled| *filled <= order.volume())
+            .ok_or(OrderBookError::ArithmeticOverflow)?;
+        self.reduce_volume(volume)?;
+        order.set_filled_volume(filled);
+        if order.visible_volume().is_zero() {
+            if let Some(slot) = order.queue_slot_mut().take() {
+                self.orders.remove(slot);
+            }
+            if !order.remaining_volume().is_zero() {
+                // next tranche loses time priority
+                order.refresh_display();
+                self.add_order(order, handle)?;
+            }
+        }
+        Ok(order.remaining_volume().is_zero())
     }
 }
 
 // stable vec of levels, once added level will not change its index
 // it will be removed only when the level is empty
 // so when looking up the index we will get None
-#[derive(Debug, Clone, Default)]
-struct Levels(StableVec<Level>);
+// slots of removed levels are reused for new levels
+// and order queues of released levels are pooled for the queues of new levels
+#[derive(Debug, Clone)]
+struct Levels<P = Price, V = Volume> {
+    levels: StableVec<Level<P, V>>,
+    free: Vec<LevelIndex>,
+    pool: Vec<OrderQueue>,
+    pool_size: usize,
+}
+
+impl<P, V> Default for Levels<P, V> {
+    fn default() -> Self {
+        Levels {
+            levels: StableVec::new(),
+            free: Vec::new(),
+            pool: Vec::new(),
+            pool_size: 0,
+        }
+    }
+}
+
+impl<P: PriceLike, V: QuantityLike> Levels<P, V> {
+    /// new level with an order queue from the pool, its buffers are reused
+    fn new_level(&mut self, price: P) -> Level<P, V> {
+        Level {
+            orders: self.pool.pop().unwrap_or_default(),
+            ..Level::new(price)
+        }
+    }
+}
 
-impl Levels {
-    fn push(&mut self, level: Level) -> LevelIndex {
-        LevelIndex(self.0.push(level))
+impl<P, V> Levels<P, V> {
+    fn push(&mut self, level: Level<P, V>) -> LevelIndex {
+        match self.free.pop() {
+            Some(index) => {
+                self.levels.insert(*index, level);
+                index
+            }
+            None => LevelIndex(self.levels.push(level)),
+        }
+    }
+
+    fn get(&self, index: LevelIndex) -> Option<&Level<P, V>> {
+        self.levels.get(*index)
+    }
+
+    fn get_mut(&mut self, index: LevelIndex) -> Option<&mut Level<P, V>> {
+        self.levels.get_mut(*index)
     }
 
-    fn get(&self, index: LevelIndex) -> Option<&Level> {
-        self.0.get(*index)
+    /// drop the level, its slot will be reused by the next pushed level
+    /// and its order queue by the next new level while the pool is not full
+    fn release(&mut self, index: LevelIndex) {
+        if let Some(level) = self.levels.remove(*index) {
+            self.free.push(index);
+            if self.pool.len() < self.pool_size {
+                let mut orders = level.orders;
+                orders.clear();
+                self.pool.push(orders);
+            }
+        }
     }
 
-    fn get_mut(&mut self, index: LevelIndex) -> Option<&mut Level> {
-        self.0.get_mut(*index)
+    /// keep up to the number of order queues of released levels
+    fn set_pool_size(&mut self, size: usize) {
+        self.pool_size = size;
+        self.pool.truncate(size);
     }
 }
 
-impl Deref for Levels {
-    type Target = StableVec<Level>;
+impl<P, V> Deref for Levels<P, V> {
+    type Target = StableVec<Level<P, V>>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.levels
     }
 }
 
-impl DerefMut for Levels {
+impl<P, V> DerefMut for Levels<P, V> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.levels
     }
 }
 
 /// Limits (i.e. Price): 21.0453 to orders at that price
-#[derive(Debug, Default)]
-pub struct Limits {
+#[derive(Debug, Clone, Default)]
+pub struct Limits<P = Price, V = Volume, S = HashedLevels<P>> {
     /// LimitIndex -> Level
     /// this will allow for O(1) lookup of Limit levels
     /// when inserting an order at a specific Limit level
-    levels: Levels,
-    /// Price Limit -> LimitIndex
-    /// this will allow for O(1) lookup of Limit levels
-    /// at a specific price
-    level_map: LevelMap,
+    levels: Levels<P, V>,
+    /// Price Limit -> LimitIndex of the levels with volume, ordered by price
+    /// this will allow for the lookup of Limit levels at a specific price,
+    /// of the next best level and traversal of levels in price order
+    store: S,
     /// contains the levels that have no volume left
-    /// so the level_map is smaller and we can quickly find the best limit
-    removed_levels: LevelMap,
+    /// so the store is smaller and we can quickly find the best limit,
+    /// removed levels are kept for reuse until the limits are compacted
+    removed_levels: LevelMap<P>,
     /// for bids is max for asks is min limit
     best: Option<LevelIndex>,
+    /// price of the best level, set together with the best level
+    best_price: Option<P>,
+    /// volume of the best level, refreshed after every change of the level
+    best_volume: Option<V>,
+    /// prices of the levels changed since the deltas were last published
+    touched: Vec<P>,
+    /// price of the last level visited by the garbage collection
+    gc_cursor: Option<P>,
+    /// every level has been visited by the current garbage collection
+    gc_done: bool,
 }
 
-impl Limits {
+impl<P: PriceLike, V: QuantityLike, S: LevelStore<P>> Limits<P, V, S> {
     /// depends on the side, i.e. for ask find smallest Limit, for bid find largest Limit
-    pub fn get_best_limit(&self) -> Option<Price> {
-        if let Some(index) = self.best {
-            self.levels.get(index).map(|l| l.price)
-        } else {
-            None
-        }
+    pub fn get_best_limit(&self) -> Option<P> {
+        self.best_price
+    }
+
+    /// volume of the best level
+    pub fn get_best_volume(&self) -> Option<V> {
+        self.best_volume
     }
 
     pub fn get_best(&self) -> Option<LevelIndex> {
         self.best
     }
 
-    /// add an order to the Limit map
-    pub fn add_order(&mut self, order: &LimitOrder) {
-        let price = &order.price;
+    /// set the best level and cache its price and volume
+    fn set_best(&mut self, best: Option<LevelIndex>) {
+        self.best = best;
+        let level = best.and_then(|index| self.levels.get(index));
+        self.best_price = level.map(|level| level.price);
+        self.best_volume = level.map(|level| level.total_volume);
+    }
+
+    /// cache the volume of the best level after its volume has changed
+    fn refresh_best_volume(&mut self) {
+        self.best_volume = self
+            .best
+            .and_then(|index| self.levels.get(index))
+            .map(|level| level.total_volume);
+    }
+
+    /// move the level that has no volume left to the removed levels
+    /// if it was the best level, best is flagged for update
+    fn remove_level(&mut self, price: P, index: LevelIndex) {
+        if let Some(level) = self.levels.get_mut(index) {
+            // level has no volume left, so there are no orders to keep
+            level.orders.clear();
+        }
+        self.store.remove(&price);
+        self.removed_levels.insert(price, index);
+        if self.best == Some(index) {
+            self.set_best(None); // this will flag that we need to update the best limit
+        }
+    }
 
-        if let Some(index) = self.removed_levels.remove(price) {
-            // add the order to the existing Limit level
-            self.level_map.insert(*price, index);
+    /// drop the levels that have no volume left, so their slots can be reused
+    /// returns the number of dropped levels
+    pub fn compact(&mut self) -> usize {
+        let removed = self.removed_levels.len();
+        for (_, index) in self.removed_levels.drain() {
+            self.levels.release(index);
         }
+        self.removed_levels.shrink_to_fit();
+        self.store.shrink_to_fit();
+        removed
+    }
+
+    /// make room for the number of additional levels without reallocating
+    fn reserve(&mut self, additional: usize) {
+        self.levels.reserve(additional);
+        self.store.reserve(additional);
+        self.removed_levels.reserve(additional);
+    }
+
+    /// number of order queues of compacted levels kept for new levels
+    fn set_level_pool(&mut self, size: usize) {
+        self.levels.set_pool_size(size);
+    }
+
+    /// levels that have volume, starting from the best price
+    /// for bids prices are descending, for asks ascending
+    fn iter_levels(&self, side: OrderSide) -> impl Iterator<Item = &Level<P, V>> {
+        let indices = match side {
+            OrderSide::Buy => Either::Left(self.store.iter().rev()),
+            OrderSide::Sell => Either::Right(self.store.iter()),
+        };
+        indices.filter_map(|(_, index)| self.levels.get(index))
+    }
 
-        match self.level_map.get(price) {
+    /// level with the next worse price than the given price
+    fn next_level(&self, side: OrderSide, price: P) -> Option<LevelIndex> {
+        let next = match side {
+            OrderSide::Buy => self.store.below(price),
+            OrderSide::Sell => self.store.above(price),
+        };
+        next.map(|(_, index)| index)
+    }
+
+    /// find the best level using the ordered index
+    /// for bids it is the highest price, for asks the lowest
+    fn update_best(&mut self, side: OrderSide) {
+        let best = match side {
+            OrderSide::Buy => self.store.last(),
+            OrderSide::Sell => self.store.first(),
+        };
+        self.set_best(best.map(|(_, index)| index));
+    }
+
+    /// number of levels with volume
+    fn level_count(&self) -> usize {
+        self.store.len()
+    }
+
+    /// level with volume at the price
+    fn level_at(&self, price: &P) -> Option<&Level<P, V>> {
+        self.store
+            .get(price)
+            .and_then(|index| self.levels.get(index))
+    }
+
+    fn level_at_mut(&mut self, price: &P) -> Option<&mut Level<P, V>> {
+        self.store
+            .get(price)
+            .and_then(|index| self.levels.get_mut(index))
+    }
+
+    /// add an order to the Limit map
+    /// order is not added if the level volume would overflow
+    pub fn add_order<O: LevelOrder<P, V>>(
+        &mut self,
+        order: &mut O,
+        handle: OrderHandle,
+    ) -> Result<(), OrderBookError> {
+        let price = order.price();
+        self.touched.push(price);
+
+        let active = self.store.get(&price);
+        let index = match active.or_else(|| self.removed_levels.get(&price).copied()) {
             None => {
                 // create a new limit level
-                let mut level = Level::new(*price);
-                level.add_order(order);
+                let mut level = self.levels.new_level(price);
+                level.add_order(order, handle)?;
                 let index = self.levels.push(level);
                 let level = self.levels.get_mut(index).unwrap();
                 level.index = Some(index);
-                self.level_map.insert(*price, index);
-
-                // update the best limit
-                if let Some(current_best_index) = self.best {
-                    if let Some(best_level) = self.levels.get(current_best_index) {
-                        match order.side {
-                            OrderSide::Buy => {
-                                if *price > best_level.price {
-                                    self.best = Some(index);
-                                }
-                            }
-                            OrderSide::Sell => {
-                                if *price < best_level.price {
-                                    self.best = Some(index);
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    self.best = Some(index);
-                }
+                index
             }
             Some(index) => {
                 // add the order to the existing Limit level
-                if let Some(level) = self.levels.get_mut(*index) {
-                    level.add_order(order);
+                if let Some(level) = self.levels.get_mut(index) {
+                    level.add_order(order, handle)?;
                 }
-                // no need to check for best limit since we are adding to existing level
+                index
             }
+        };
+        if active.is_none() {
+            // level is new or re-activated from the removed levels
+            self.removed_levels.remove(&price);
+            self.store.insert(price, index);
+        }
+
+        // update the best limit, level could have been re-activated from removed levels
+        // so we need to check it even if we have added the order to the existing level
+        let better = self.best_price.is_none_or(|best_price| match order.side() {
+            OrderSide::Buy => price > best_price,
+            OrderSide::Sell => price < best_price,
+        });
+        if better {
+            self.set_best(Some(index));
+        } else if self.best == Some(index) {
+            self.refresh_best_volume();
         }
+        Ok(())
     }
 
-    /// cancell order
-    /// since we postopne removal of cancelled orders when filling the new order
-    /// all we need to do is to update the total level volume so it is in sync
-    pub fn cancel_order(&mut self, order: &LimitOrder) {
+    /// cancel order
+    /// order is unlinked from its level queue, level with no volume left is removed,
+    /// removing the best level clears the best until the book finds the next best level
+    pub fn cancel_order<O: LevelOrder<P, V>>(&mut self, order: &mut O) {
+        let price = order.price();
+        self.touched.push(price);
         let mut index_to_remove = None;
-        if let Some(index) = self.level_map.get(&order.price) {
-            if let Some(level) = self.levels.get_mut(*index) {
-                let volume = order.volume - order.filled_volume.unwrap_or(Volume::ZERO);
-                level.reduce_volume(volume);
+        if let Some(index) = self.store.get(&price) {
+            if let Some(level) = self.levels.get_mut(index) {
+                level.remove_order(order);
                 if level.total_volume.is_zero() {
-                    index_to_remove = Some(*index);
-                    if self.best == Some(*index) {
-                        self.best = None; // this will flag that we need to update the best limit
-                    }
+                    index_to_remove = Some(index);
                 }
             }
         }
         if let Some(index_to_remove) = index_to_remove {
-            self.level_map.remove(&order.price);
-            self.removed_levels.insert(order.price, index_to_remove);
+            self.remove_level(price, index_to_remove);
+        } else if self.best_price == Some(price) {
+            self.refresh_best_volume();
         }
     }
 }
@@ -227,26 +607,121 @@ pub enum OrderBookError {
     NoOrderToMatch,
     #[error("Cancellation error")]
     CancelOrderError(#[from] CancelOrderError),
+    #[error("Amend error")]
+    AmendOrderError(#[from] AmendOrderError),
     // if this happens, best is to update the best limits
     #[error("Empty level")]
     LevelHasNoValidOrders,
+    /// Post only order would take liquidity
+    #[error("Post only order {0} would cross the spread")]
+    PostOnlyWouldCross(Oid),
+    /// Operation is not allowed in the current trading state
+    #[error("Operation not allowed when book is {0:?}")]
+    InvalidState(TradingState),
+    /// Order price is outside of the price bands
+    #[error("Price {0:?} is outside of the price bands")]
+    PriceOutOfBand(Price),
+    /// Price is not a multiple of the tick size
+    #[error("Price {0:?} is not on a tick")]
+    InvalidTickSize(Price),
+    /// Volume is not a multiple of the lot size
+    #[error("Volume {0:?} is not a multiple of the lot size")]
+    InvalidLotSize(Volume),
+    #[error("Volume {0:?} is below the minimum volume")]
+    VolumeBelowMinimum(Volume),
+    #[error("Book cannot transition from {from:?} to {to:?}")]
+    InvalidStateTransition {
+        from: TradingState,
+        to: TradingState,
+    },
+    /// Order was rejected by a pre-trade risk check
+    #[error("Order {0} rejected: {1}")]
+    RiskRejected(Oid, RejectReason),
+    /// Order with the same id is resting on the book
+    #[error("Order {0} is already on the book")]
+    DuplicateOrderId(Oid),
+    #[error("Order {0} volume is zero")]
+    ZeroVolume(Oid),
+    /// Limit order has no price
+    #[error("Limit order {0} price is required")]
+    MissingPrice(Oid),
+    /// Order expired before it was accepted
+    #[error("Order {0} has expired")]
+    OrderExpired(Oid),
+    /// Order referenced by the request is not on the book
+    #[error("Order {0} is not on the book")]
+    UnknownOrder(Oid),
+    /// Volume or price arithmetic would overflow, the operation was not applied
+    #[error("Arithmetic overflow")]
+    ArithmeticOverflow,
+    /// Internal state of the book is inconsistent, the book should not be used any more
+    #[error("Order book is corrupted: {0:?}")]
+    Corrupted(CorruptionKind),
+}
+
+/// Inconsistency found in the book while matching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum CorruptionKind {
+    /// best level of the side is not in the levels
+    BestLevelMissing(OrderSide),
+    /// level has volume but no orders in its queue
+    LevelWithoutOrders { side: OrderSide, price: Price },
+    /// level queue refers to an order that is not on the book
+    QueuedOrderMissing { side: OrderSide, price: Price },
+}
+
+impl From<CorruptionKind> for OrderBookError {
+    fn from(kind: CorruptionKind) -> Self {
+        OrderBookError::Corrupted(kind)
+    }
 }
 
 /// Cancellation status
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CancellationStatus {
     /// Order was cancelled
     Cancelled,
+    /// Order was removed from the book since it expired
+    Expired,
     /// Order was not cancelled
     NotCancelled(String),
 }
 
 /// Cancellation report
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CancellationReport {
     order_id: Oid,
     status: CancellationStatus,
+    sequence: u64,
+}
+
+impl CancellationReport {
+    pub fn order_id(&self) -> Oid {
+        self.order_id
+    }
+
+    /// event sequence of the cancellation
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn status(&self) -> &CancellationStatus {
+        &self.status
+    }
+}
+
+impl Display for CancellationReport {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match &self.status {
+            CancellationStatus::Cancelled => write!(f, "order {} cancelled", self.order_id),
+            CancellationStatus::Expired => write!(f, "order {} expired", self.order_id),
+            CancellationStatus::NotCancelled(reason) => {
+                write!(f, "order {} not cancelled: {reason}", self.order_id)
+            }
+        }
+    }
 }
 
 /// Cancel order error  
@@ -260,16 +735,63 @@ pub enum CancelOrderError {
     AlreadyCancelled(Oid),
 }
 
+/// Amend status
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmendStatus {
+    /// Volume was reduced in place, order kept its queue position
+    Reduced,
+    /// Order was cancelled and added again with the new price and volume, loosing its queue position
+    Replaced,
+}
+
+/// Amend report
+#[derive(Debug, Clone)]
+pub struct AmendReport {
+    pub order_id: Oid,
+    pub status: AmendStatus,
+    /// time priority of the order after the amend
+    pub timestamp: Timestamp,
+    /// event sequence of the amend
+    pub sequence: u64,
+}
+
+/// Amend order error
+#[derive(Error, Debug, PartialEq, PartialOrd, Clone)]
+pub enum AmendOrderError {
+    /// Order not found
+    #[error("Order {0} not found")]
+    NotFound(Oid),
+    /// New volume must be greater than the volume already filled
+    #[error("Order {0} new volume is not greater than filled volume")]
+    VolumeNotAboveFilled(Oid),
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Fill {
     pub buy_order_id: Oid,
     pub sell_order_id: Oid,
     pub buy_order_price: Price,
     pub sell_order_price: Price,
+    /// price the trade is executed at, given by the trade price policy of the book
+    pub trade_price: Price,
     pub volume: Volume,
+    /// event sequence of the fill
+    pub sequence: u64,
+}
+
+impl Display for Fill {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "{} @ {} buy {} sell {}",
+            self.volume, self.trade_price, self.buy_order_id, self.sell_order_id
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FillAtMarket {
     pub market_order_id: Oid,
     pub order_id: Oid,
@@ -277,796 +799,1564 @@ pub struct FillAtMarket {
     pub filled_volume: Volume,
 }
 
+/// Trade
+/// result of executing an order, contains all executions against the resting orders
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trade {
+    pub order_id: Oid,
+    pub volume: Volume,
+    pub filled_volume: Volume,
+    /// volume that was not filled and has not been added to the book
+    pub cancelled_volume: Volume,
+    pub executions: Vec<Execution>,
+    /// event sequence of the accepted order, its executions follow it
+    pub sequence: u64,
+}
+
+impl Trade {
+    /// Create a new trade
+    pub fn new(order_id: Oid, volume: Volume) -> Self {
+        Trade {
+            order_id,
+            volume,
+            filled_volume: Volume::ZERO,
+            cancelled_volume: Volume::ZERO,
+            executions: Vec::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Add an execution to the trade
+    pub fn add_execution(&mut self, execution: Execution) {
+        self.filled_volume += execution.volume;
+        self.executions.push(execution)
+    }
+
+    /// volume that has not been filled nor cancelled, zero if the fills exceed the volume
+    pub fn remaining_volume(&self) -> Volume {
+        self.volume
+            .saturating_sub(self.filled_volume)
+            .saturating_sub(self.cancelled_volume)
+    }
+
+    /// cancel the volume that has not been filled
+    pub fn cancel_remaining(&mut self) {
+        self.cancelled_volume += self.remaining_volume();
+    }
+}
+
+impl Display for Trade {
+    /// order, its filled and cancelled volume and the executions
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "order {} filled {}/{} cancelled {}",
+            self.order_id, self.filled_volume, self.volume, self.cancelled_volume
+        )?;
+        for execution in &self.executions {
+            write!(
+                f,
+                ", {} @ {} with {}",
+                execution.volume, execution.price, execution.order_id
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Execution
+/// single match against a resting order, at the price given by the trade price policy of the book
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Execution {
+    pub order_id: Oid,
+    pub price: Price,
+    pub volume: Volume,
+    /// execution against iceberg volume that was not visible when the order arrived
+    pub hidden: bool,
+    /// event sequence of the execution
+    pub sequence: u64,
+}
+
+impl Execution {
+    /// Create a new execution
+    pub fn new(order_id: Oid, price: Price, volume: Volume) -> Self {
+        Execution {
+            order_id,
+            price,
+            volume,
+            hidden: false,
+            sequence: 0,
+        }
+    }
+
+    /// Create a new execution against hidden volume of an iceberg order
+    pub fn new_hidden(order_id: Oid, price: Price, volume: Volume) -> Self {
+        Execution {
+            hidden: true,
+            ..Execution::new(order_id, price, volume)
+        }
+    }
+}
+
 /// Limit Order Book
 /// Trades are made when highest bid Limit is greater than or equal to the lowest ask Limit (spread is crossed)
 /// If order cannot be filled immediately, it is added to the book
-#[derive(Debug, Default)]
-pub struct OrderBook {
+/// Clone of the book shares the clock and the risk validators of the book
+/// Levels of both sides are kept in the level store, hashed levels by default
+#[derive(Debug, Clone)]
+pub struct OrderBook<S = HashedLevels> {
     // Bid side of the book, represents open offers to buy an asset
-    bids: Limits,
+    bids: Limits<Price, Volume, S>,
     // Ask side of the book, represents open offers to sell an asset
-    asks: Limits,
+    asks: Limits<Price, Volume, S>,
     // this will allow for O(1) lookup of orders for cancellation
     orders: OrderMap,
     // spread is the diff between min ask and max bid
     spread: Option<Spread>,
+    // orders are matched only when the book is open
+    state: TradingState,
+    // limit orders outside of the bands are rejected, matching is halted when fill would be outside
+    price_bands: Option<PriceBands>,
+    // tick and lot size validation
+    instrument_spec: Option<InstrumentSpec>,
+    // value of one unit of volume, one if not set
+    contract_multiplier: Option<u64>,
+    // best bid and ask, updated after every change of the book
+    bbo: Bbo,
+    // filled and cancelled orders kept for status queries
+    finished_orders: FinishedOrders,
+    // most recent trades, recorded only when enabled
+    trade_tape: Option<TradeTape>,
+    // OHLCV bars of the trades, built only when enabled
+    candles: Option<CandleBuilder>,
+    // incremental market data, published only when enabled
+    delta_feed: Option<DeltaFeed>,
+    // deltas coalesced into batches for slow consumers, only when enabled
+    conflator: Option<Conflator>,
+    // price of the trade between the passive and the aggressive order
+    trade_price_policy: TradePricePolicy,
+    // pre-trade risk checks run before orders are added or executed
+    risk_validators: Vec<Arc<dyn RiskValidator<S>>>,
+    // one-cancels-other groups of resting orders
+    oco_groups: OcoGroups,
+    // current two-sided quote of each owner
+    quotes: Quotes,
+    // volume executed by the owners, tracked only when enabled
+    protection: Option<Protection>,
+    // crossed state of the book and its changes
+    crossing: Crossing,
+    // number of price levels kept on each side, all levels are kept when none
+    depth_limit: Option<DepthLimit>,
+    // price and volume of the most recent fill
+    last_trade: Option<(Price, Volume)>,
+    // previous close or last auction price
+    reference_price: Option<Price>,
+    // sequence number of the last accepted event
+    event_sequence: u64,
+    // time source stamping accepted orders, caller timestamps are used when none
+    clock: Option<Arc<dyn Clock>>,
+    // latest time of the orders put on the book, replaced orders take it when there is no clock
+    accept_time: Option<Timestamp>,
+    // lifecycle events of the orders, recorded only when enabled
+    audit_trail: Option<AuditTrail>,
+    // reports of the order events, collected only when enabled
+    execution_reports: Option<Vec<ExecutionReport>>,
+    // mutating calls of the book, recorded only when enabled
+    event_log: Option<Vec<BookEvent>>,
+    // latencies of the operations, recorded only when enabled
+    #[cfg(feature = "metrics")]
+    latency_metrics: Option<LatencyMetrics>,
+    // counters and gauges exported through the metrics facade, only when enabled
+    #[cfg(feature = "metrics")]
+    exported_metrics: Option<metrics::ExportedMetrics>,
 }
 
-impl OrderBook {
-    pub fn add_order(&mut self, order: LimitOrder) {
-        match order.side {
-            OrderSide::Buy => self.bids.add_order(&order),
-            OrderSide::Sell => self.asks.add_order(&order),
-        }
-        self.orders.insert(order.id, order);
-        self.update_spreads();
+impl Default for OrderBook {
+    fn default() -> Self {
+        OrderBook::empty()
     }
+}
 
-    fn update_spreads(&mut self) {
-        let ask_best_limit = self.asks.get_best_limit();
-        let bid_best_limit = self.bids.get_best_limit();
-        match (ask_best_limit, bid_best_limit) {
-            (Some(ask_limit), Some(bid_limit)) => {
-                self.spread = Some(Spread((ask_limit - bid_limit).into()));
-            }
-            _ => {
-                self.spread = None;
-            }
+impl<S: LevelStore> OrderBook<S> {
+    /// empty book keeping its levels in the level store of the type,
+    /// i.e. `OrderBook::<SortedLevels>::empty()`
+    pub fn empty() -> Self {
+        OrderBook {
+            bids: Default::default(),
+            asks: Default::default(),
+            orders: Default::default(),
+            spread: Default::default(),
+            state: Default::default(),
+            price_bands: Default::default(),
+            instrument_spec: Default::default(),
+            contract_multiplier: Default::default(),
+            bbo: Default::default(),
+            finished_orders: Default::default(),
+            trade_tape: Default::default(),
+            candles: Default::default(),
+            delta_feed: Default::default(),
+            conflator: Default::default(),
+            trade_price_policy: Default::default(),
+            risk_validators: Default::default(),
+            oco_groups: Default::default(),
+            quotes: Default::default(),
+            protection: Default::default(),
+            crossing: Default::default(),
+            depth_limit: Default::default(),
+            last_trade: Default::default(),
+            reference_price: Default::default(),
+            event_sequence: Default::default(),
+            clock: Default::default(),
+            accept_time: Default::default(),
+            audit_trail: Default::default(),
+            execution_reports: Default::default(),
+            event_log: Default::default(),
+            #[cfg(feature = "metrics")]
+            latency_metrics: Default::default(),
+            #[cfg(feature = "metrics")]
+            exported_metrics: Default::default(),
         }
     }
 
-    fn update_best_buy(&mut self) {
-        if let Some(max) = self
-            .bids
-            .levels
-            .values()
-            .filter(|l| l.total_volume > 0.into())
-            .max()
-        {
-            self.bids.best = self.bids.level_map.get(&max.price).copied();
-        }
+    /// set the price bands enforced by the book
+    pub fn with_price_bands(mut self, price_bands: PriceBands) -> Self {
+        self.price_bands = Some(price_bands);
+        self
     }
 
-    fn update_best_sell(&mut self) {
-        if let Some(min) = self
-            .asks
-            .levels
-            .values()
-            .filter(|l| l.total_volume > 0.into())
-            .min()
-        {
-            self.asks.best = self.asks.level_map.get(&min.price).copied();
-        }
+    pub fn set_price_bands(&mut self, price_bands: Option<PriceBands>) {
+        self.logged(
+            || BookEvent::SetPriceBands(price_bands.clone()),
+            |book| book.price_bands = price_bands.clone(),
+        );
     }
 
-    pub fn get_best_sell(&self) -> Option<Price> {
-        self.asks.get_best_limit()
+    pub fn price_bands(&self) -> Option<&PriceBands> {
+        self.price_bands.as_ref()
     }
 
-    pub fn get_best_buy(&self) -> Option<Price> {
-        self.bids.get_best_limit()
+    /// set the tick size, lot size and minimum volume enforced by the book
+    pub fn with_instrument_spec(mut self, instrument_spec: InstrumentSpec) -> Self {
+        self.instrument_spec = Some(instrument_spec);
+        self
     }
 
-    pub fn get_best_sell_index(&self) -> Option<LevelIndex> {
-        self.asks.get_best()
+    pub fn instrument_spec(&self) -> Option<&InstrumentSpec> {
+        self.instrument_spec.as_ref()
     }
 
-    pub fn get_best_buy_index(&self) -> Option<LevelIndex> {
-        self.bids.get_best()
+    /// set how the price of the trade is determined when two orders match
+    pub fn with_trade_price_policy(mut self, trade_price_policy: TradePricePolicy) -> Self {
+        self.trade_price_policy = trade_price_policy;
+        self
     }
 
-    pub fn get_best_buy_volume(&self) -> Option<Volume> {
-        self.bids
-            .get_best()
-            .and_then(|index| self.bids.levels.get(index))
-            .map(|l| l.total_volume)
+    pub fn trade_price_policy(&self) -> TradePricePolicy {
+        self.trade_price_policy
     }
 
-    pub fn get_best_sell_volume(&self) -> Option<Volume> {
-        self.asks
-            .get_best()
-            .and_then(|index| self.asks.levels.get(index))
-            .map(|l| l.total_volume)
+    /// keep the order queues of up to the number of compacted levels on each side,
+    /// new levels reuse their buffers instead of allocating, books with churning prices
+    /// that are compacted periodically allocate less
+    pub fn with_level_pool(mut self, size: usize) -> Self {
+        self.bids.set_level_pool(size);
+        self.asks.set_level_pool(size);
+        self
     }
 
-    /// cancellation does not modify any of the underlying collections. Order is marked as cancelled and will be removed
-    /// at the time of order filling, when we iterate over the orders
-    pub fn cancel_order(&mut self, order_id: Oid) -> Result<CancellationReport, CancelOrderError> {
-        // immutable borrows of self, therefore the need for new scope
-        // so if we do not return err then the immutable borrow will go out of scope
-        // and will allow for mutable borrow to allow for removal of the order from hashmap
-        match self.orders.remove(&order_id) {
-            None => return Err(CancelOrderError::NotFound(order_id)),
-            Some(order) => {
-                // update the level so the level volume is updated
-                match order.side {
-                    OrderSide::Buy => self.bids.cancel_order(&order),
-                    OrderSide::Sell => self.asks.cancel_order(&order),
-                }
-            }
+    /// check the price and volume against the instrument spec
+    fn check_instrument_spec(
+        &self,
+        price: Option<Price>,
+        volume: Volume,
+    ) -> Result<(), OrderBookError> {
+        let Some(spec) = &self.instrument_spec else {
+            return Ok(());
+        };
+        if let Some(price) = price {
+            spec.validate_price(price)?;
         }
-        Ok(CancellationReport {
-            order_id,
-            status: CancellationStatus::Cancelled,
-        })
+        spec.validate_volume(volume)
     }
 
-    /// get volume of open orders for either buying or selling side of the book
-    pub fn get_volume_at_limit(&self, limit: Price, side: OrderSide) -> Option<Volume> {
-        let limit_map = match side {
-            OrderSide::Buy => &self.bids,
-            OrderSide::Sell => &self.asks,
-        };
-        limit_map
-            .level_map
-            .get(&limit)
-            .map(|index| limit_map.levels[**index].total_volume)
+    /// reject the id of an order resting on the book, instead of overwriting it
+    fn check_duplicate_id(&self, order_id: Oid) -> Result<(), OrderBookError> {
+        if self.orders.get(&order_id).is_some() {
+            return Err(OrderBookError::DuplicateOrderId(order_id));
+        }
+        Ok(())
     }
 
-    pub fn find_and_fill_best_orders(&mut self) -> Result<Fill, OrderBookError> {
-        let fill = self.find_and_fill()?;
-
-        self.remove_or_update_filled_orders(&fill);
-
-        if self.asks.best.is_none() {
-            self.update_best_sell();
+    /// check that the price is within the price bands
+    fn check_price_bands(&self, price: Price) -> Result<(), OrderBookError> {
+        match &self.price_bands {
+            Some(bands) if !bands.contains(price) => Err(OrderBookError::PriceOutOfBand(price)),
+            _ => Ok(()),
         }
+    }
 
-        if self.bids.best.is_none() {
-            self.update_best_buy();
-        }
+    /// add the limit order to the book without matching it
+    /// post only order that would cross the spread is rejected or re-priced
+    /// with a clock the order is stamped with the current time
+    pub fn add_order(&mut self, order: LimitOrder) -> Result<(), OrderBookError> {
+        let order = match self.accept_now() {
+            Some(now) => LimitOrder {
+                timestamp: now,
+                ..order
+            },
+            None => order,
+        };
+        self.logged(
+            || BookEvent::AddOrder(order.clone()),
+            |book| {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::debug_span!(
+                    "add_order",
+                    order_id = %order.id,
+                    side = %order.side,
+                    price = %order.price,
+                    volume = %order.volume
+                )
+                .entered();
+                book.timed(LatencyOperation::Add, |book| {
+                    let side = order.side;
+                    book.accept_order(order.clone())?;
+                    book.trim_depth(side);
+                    book.update_top_of_book();
+                    Ok(())
+                })
+            },
+        )
+    }
 
-        self.update_spreads();
+    /// add the remainder of the executed or amended order, it has already been validated
+    /// and priced, so it is only linked into its level
+    fn rest_order(&mut self, order: LimitOrder) -> Result<(), OrderBookError> {
+        let side = order.side;
+        self.link_order(order)?;
+        self.trim_depth(side);
+        self.update_top_of_book();
+        Ok(())
+    }
 
-        Ok(fill)
+    /// add the limit orders to the book without matching them, returns the result of each order
+    /// top of the book and the spread are updated once for the whole batch,
+    /// with a clock the orders are stamped with the current time
+    pub fn add_orders(
+        &mut self,
+        orders: impl IntoIterator<Item = Order>,
+    ) -> Vec<Result<(), OrderBookError>> {
+        let now = self.accept_now();
+        let orders = orders
+            .into_iter()
+            .map(|order| match now {
+                Some(now) => Order {
+                    timestamp: now,
+                    ..order
+                },
+                None => order,
+            })
+            .collect::<Vec<_>>();
+        self.logged(
+            || BookEvent::AddOrders(orders.clone()),
+            |book| book.add_order_batch(&orders),
+        )
     }
 
-    fn remove_or_update_filled_orders(&mut self, fill: &Fill) {
-        // check if the orders should be removed
-        // otherwise we need to update the order volume
+    fn add_order_batch(&mut self, orders: &[Order]) -> Vec<Result<(), OrderBookError>> {
+        let results = orders
+            .iter()
+            .map(|order| {
+                let limit_order = LimitOrder::try_from(order).map_err(|_| {
+                    let error = OrderBookError::OrderCannotBePlaced(
+                        "only limit orders can be added to the book".to_string(),
+                    );
+                    self.record_rejected(order.id, &error);
+                    error
+                })?;
+                self.accept_order(limit_order)
+            })
+            .collect();
+        self.trim_depth(OrderSide::Buy);
+        self.trim_depth(OrderSide::Sell);
+        self.update_top_of_book();
+        results
+    }
 
-        let mut buy_order_to_cancel = None;
-        let mut sell_order_to_cancel = None;
+    /// insert the new order and sequence its acceptance
+    fn accept_order(&mut self, order: LimitOrder) -> Result<(), OrderBookError> {
+        let order_id = order.id;
+        let accepted = AuditEvent::Accepted {
+            timestamp: order.timestamp,
+            price: Some(order.price),
+            volume: order.volume,
+        };
+        self.insert_order(order)
+            .inspect_err(|error| self.record_rejected(order_id, error))?;
+        let sequence = self.next_event_sequence();
+        self.record_event(order_id, sequence, accepted);
+        Ok(())
+    }
 
-        if let Some(buy_order) = self.orders.get_mut(&fill.buy_order_id) {
-            let buy_volume = buy_order.volume - buy_order.filled_volume.unwrap_or(Volume::ZERO);
+    /// validate the order and link it into its level, top of the book is not updated
+    fn insert_order(&mut self, mut order: LimitOrder) -> Result<(), OrderBookError> {
+        if matches!(self.state, TradingState::Closed | TradingState::Suspended) {
+            return Err(OrderBookError::InvalidState(self.state));
+        }
+        if order.is_expired(order.timestamp) {
+            return Err(OrderBookError::OrderExpired(order.id));
+        }
+        self.check_duplicate_id(order.id)?;
+        self.check_instrument_spec(Some(order.price), order.volume)?;
+        check_min_qty(order.display_volume, order.min_qty)?;
+        self.check_price_bands(order.price)?;
+        if !self.risk_validators.is_empty() {
+            self.check_risk(&Order::from(&order))
+                .map_err(|reason| OrderBookError::RiskRejected(order.id, reason))?;
+        }
+        if let Some(post_only) = order.post_only {
+            order.price = self.post_only_price(&order, post_only)?;
+        }
+        self.link_order(order)
+    }
 
-            if buy_volume == fill.volume {
-                buy_order_to_cancel = self.orders.remove(&fill.buy_order_id);
-            } else {
-                buy_order.filled_volume =
-                    Some(buy_order.filled_volume.unwrap_or(Volume::ZERO) + fill.volume);
+    /// link the validated order into its level,
+    /// order is not added if the level volume would overflow
+    fn link_order(&mut self, mut order: LimitOrder) -> Result<(), OrderBookError> {
+        order.refresh_display();
+        let (id, side, timestamp) = (order.id, order.side, order.timestamp);
+        let handle = self.orders.insert(order);
+        if let Some(order) = self.orders.get_by_handle_mut(handle) {
+            let added = match side {
+                OrderSide::Buy => self.bids.add_order(order, handle),
+                OrderSide::Sell => self.asks.add_order(order, handle),
+            };
+            if let Err(error) = added {
+                self.orders.remove(&id);
+                return Err(error);
             }
         }
+        self.note_accept_time(timestamp);
+        Ok(())
+    }
 
-        if let Some(order) = buy_order_to_cancel {
-            self.bids.cancel_order(&order);
+    /// keep the latest time of the orders put on the book
+    pub(crate) fn note_accept_time(&mut self, timestamp: Timestamp) {
+        if self.accept_time.is_none_or(|latest| latest < timestamp) {
+            self.accept_time = Some(timestamp);
         }
+    }
 
-        if let Some(sell_order) = self.orders.get_mut(&fill.sell_order_id) {
-            let sell_volume = sell_order.volume - sell_order.filled_volume.unwrap_or(Volume::ZERO);
-
-            if sell_volume == fill.volume {
-                sell_order_to_cancel = self.orders.remove(&fill.sell_order_id);
-            } else {
-                sell_order.filled_volume =
-                    Some(sell_order.filled_volume.unwrap_or(Volume::ZERO) + fill.volume);
-            }
+    /// price at which the post only order can be added without crossing the spread
+    fn post_only_price(
+        &mut self,
+        order: &LimitOrder,
+        post_only: PostOnly,
+    ) -> Result<Price, OrderBookError> {
+        match (self.crossing_price(order.side, order.price), post_only) {
+            (None, _) => Ok(order.price),
+            (Some(best), PostOnly::Slide { tick_size }) => Ok(match order.side {
+                OrderSide::Buy => best - tick_size,
+                OrderSide::Sell => best + tick_size,
+            }),
+            (Some(_), PostOnly::Reject) => Err(OrderBookError::PostOnlyWouldCross(order.id)),
         }
+    }
 
-        if let Some(order) = sell_order_to_cancel {
-            self.asks.cancel_order(&order);
+    /// best opposite price if order at the given price would cross the spread
+    fn crossing_price(&mut self, side: OrderSide, price: Price) -> Option<Price> {
+        // make sure we compare against the actual best price
+        if self.bids.best.is_none() {
+            self.update_best_buy();
+        }
+        if self.asks.best.is_none() {
+            self.update_best_sell();
+        }
+        match side {
+            OrderSide::Buy => self.get_best_sell().filter(|best| price >= *best),
+            OrderSide::Sell => self.get_best_buy().filter(|best| price <= *best),
         }
     }
 
-    fn find_and_fill(&mut self) -> Result<Fill, OrderBookError> {
-        let Some(best_buy_level_index) = self.bids.get_best() else {
-            return Err(OrderBookError::NoOrderToMatch);
+    /// Execute the order against the opposite side of the book.
+    /// Order is matched against resting orders in price-time priority until it is filled
+    /// or the spread is no longer crossed. Remainder of a good till cancel limit order is added
+    /// to the book, remainder of a market or immediate or cancel order is cancelled.
+    pub fn execute(&mut self, order: &Order) -> Result<Trade, OrderBookError> {
+        let now = self.now();
+        self.execute_at(order, now)
+    }
+
+    /// execute the order stamped with the time of the clock
+    pub(crate) fn execute_at(
+        &mut self,
+        order: &Order,
+        now: Option<Timestamp>,
+    ) -> Result<Trade, OrderBookError> {
+        self.logged(
+            || BookEvent::Execute {
+                order: order.clone(),
+                now,
+            },
+            |book| {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::debug_span!(
+                    "execute",
+                    order_id = %order.id,
+                    side = %order.side,
+                    price = ?order.price,
+                    volume = %order.volume
+                )
+                .entered();
+                book.timed(LatencyOperation::Match, |book| {
+                    book.execute_order(order, now)
+                })
+                .inspect_err(|error| book.record_rejected(order.id, error))
+            },
+        )
+    }
+
+    fn execute_order(
+        &mut self,
+        order: &Order,
+        now: Option<Timestamp>,
+    ) -> Result<Trade, OrderBookError> {
+        if order.volume.is_zero() {
+            return Err(OrderBookError::ZeroVolume(order.id));
+        }
+        // checked before matching, so the remainder can always be added to the book
+        self.check_duplicate_id(order.id)?;
+        let stamped;
+        let order = match self.stamp_at(order, now)? {
+            Some(stamped_order) => {
+                stamped = stamped_order;
+                &stamped
+            }
+            None => order,
         };
-        let Some(best_sell_level_index) = self.asks.get_best() else {
-            return Err(OrderBookError::NoOrderToMatch);
+        let converted;
+        let order = match order.kind {
+            OrderType::MarketToLimit => match self.market_to_limit(order) {
+                Some(limit_order) => {
+                    converted = limit_order;
+                    &converted
+                }
+                // checked as a market order, since there is no price to rest at
+                None => order,
+            },
+            _ => order,
         };
-
-        let Some(best_buy_level) = self.bids.levels.get_mut(best_buy_level_index) else {
-            return Err(OrderBookError::NoOrderToMatch);
+        let limit_price = match order.kind {
+            OrderType::Market | OrderType::MarketToLimit => None,
+            OrderType::Limit => Some(order.price.ok_or(OrderBookError::MissingPrice(order.id))?),
         };
-        let Some(best_sell_level) = self.asks.levels.get_mut(best_sell_level_index) else {
-            return Err(OrderBookError::NoOrderToMatch);
-        };
-
-        // 1. check if the level is not empty. One reason why it could be empty is because cancel_order could be called and make the level no longer best
-        // although matching engine should call update_best_limits after cancellation, as this would require publishing new best
-        // 1. check prices if we can do a match
-        // 2. if we can match, pop the orders from the levels
-        // 3. make a match
-        // 4. update the levels
+        self.check_instrument_spec(limit_price, order.volume)?;
+        check_min_qty(order.display_volume, order.min_qty)?;
+        if let Some(price) = limit_price {
+            self.check_price_bands(price)?;
+        }
+        self.check_risk(order)
+            .map_err(|reason| OrderBookError::RiskRejected(order.id, reason))?;
+
+        match self.state {
+            TradingState::Open => {}
+            TradingState::PreOpen => {
+                if !rests_on_book(order) {
+                    return Err(OrderBookError::OrderCannotBePlaced(
+                        "only good till cancel limit orders are accepted during auction"
+                            .to_string(),
+                    ));
+                }
+            }
+            TradingState::Halted => {
+                // aggressive orders are rejected, passive orders can still rest on the book
+                let aggressive = !rests_on_book(order)
+                    || limit_price.is_some_and(|p| self.crossing_price(order.side, p).is_some());
+                if aggressive && order.post_only.is_none() {
+                    return Err(OrderBookError::InvalidState(self.state));
+                }
+            }
+            TradingState::Closed | TradingState::Suspended => {
+                return Err(OrderBookError::InvalidState(self.state))
+            }
+        }
 
-        if best_buy_level.total_volume.is_zero() || best_sell_level.total_volume.is_zero() {
-            // todo: split this error into two for bid and ask for clarity
-            return Err(OrderBookError::LevelHasNoValidOrders);
+        let mut trade = Trade::new(order.id, order.volume);
+        trade.sequence = self.next_event_sequence();
+        self.record_event(order.id, trade.sequence, AuditEvent::accepted(order));
+        if order.kind == OrderType::MarketToLimit {
+            // nothing to execute against, so there is no price to rest at
+            trade.cancel_remaining();
+            self.record_event(order.id, trade.sequence, AuditEvent::Cancelled);
+            return Ok(trade);
+        }
+        // outside of continuous trading orders are not matched on arrival
+        if order.post_only.is_none() && self.state == TradingState::Open {
+            let filled = self.fill_order(&mut trade, order.side, limit_price, usize::MAX);
+            self.record_executions(order, &mut trade.executions);
+            self.trigger_oco(trade.executions.iter().map(|e| e.order_id));
+            let band_breached = filled.inspect_err(|_| self.update_top_of_book())?;
+            if band_breached {
+                // next fill would be outside of the price bands, so matching is halted
+                // and the remainder is cancelled since it would cross the spread
+                self.state = TradingState::Halted;
+                trade.cancel_remaining();
+            }
         }
 
-        if best_buy_level.price < best_sell_level.price {
-            // cannot match buy order that lower price than a sell order
-            return Err(OrderBookError::NoOrderToMatch);
+        if trade.remaining_volume().is_zero() {
+            self.update_top_of_book();
+        } else if rests_on_book(order) {
+            // rest the remaining volume on the book
+            let mut limit_order =
+                LimitOrder::try_from(order).map_err(|_| OrderBookError::MissingPrice(order.id))?;
+            if !trade.filled_volume.is_zero() {
+                limit_order.filled_volume = Some(trade.filled_volume);
+            }
+            if let Some(post_only) = limit_order.post_only {
+                limit_order.price = self.post_only_price(&limit_order, post_only)?;
+            }
+            if let Err(error) = self.rest_order(limit_order) {
+                if trade.executions.is_empty() {
+                    return Err(error);
+                }
+                // executions are kept, the remainder that cannot rest is cancelled
+                trade.cancel_remaining();
+                self.update_top_of_book();
+            }
+        } else {
+            trade.cancel_remaining();
+            self.update_top_of_book();
+        }
+        if !trade.cancelled_volume.is_zero() {
+            self.record_event(order.id, self.event_sequence, AuditEvent::Cancelled);
         }
+        self.protect_after_executions(order, &trade.executions);
+
+        Ok(trade)
+    }
 
-        while let Some(buy_order_id) = best_buy_level.orders.front() {
-            let Some(buy_order) = self.orders.get(buy_order_id) else {
-                // no order, so it has been cancelled
-                // remove it from level orders
-                best_buy_level.orders.pop_front();
+    /// limit order at the best opposite price that the market to limit order turns into,
+    /// none if the opposite side of the book is empty
+    pub(crate) fn market_to_limit(&self, order: &Order) -> Option<Order> {
+        let best = match order.side {
+            OrderSide::Buy => self.bbo.ask_price,
+            OrderSide::Sell => self.bbo.bid_price,
+        }?;
+        Some(Order {
+            kind: OrderType::Limit,
+            price: Some(best),
+            ..order.clone()
+        })
+    }
+
+    /// fill the trade against the opposite side of the book, making at most max_executions
+    /// if price is none, we are filling market order, so we take any price
+    /// resting orders whose minimum quantity is not satisfied are skipped and keep their priority,
+    /// when no order of a level can be filled, the next level is tried
+    /// returns true if filling stopped since the next fill would be outside of the price bands,
+    /// inconsistent book stops filling with the corrupted error, executions made so far are kept
+    fn fill_order(
+        &mut self,
+        trade: &mut Trade,
+        side: OrderSide,
+        price: Option<Price>,
+        max_executions: usize,
+    ) -> Result<bool, OrderBookError> {
+        let price_bands = self.price_bands.as_ref();
+        let trade_price_policy = self.trade_price_policy;
+        let finished_orders = &mut self.finished_orders;
+        let (limits, orders) = match side {
+            OrderSide::Buy => (&mut self.asks, &mut self.orders),
+            OrderSide::Sell => (&mut self.bids, &mut self.orders),
+        };
+        // iceberg orders that have shown next tranche while filling this trade
+        let mut refreshed = Vec::new();
+        // price of the last level where no order could be filled
+        let mut skipped_level = None;
+
+        while !trade.remaining_volume().is_zero() && trade.executions.len() < max_executions {
+            let next = match skipped_level {
+                Some(skipped_price) => limits.next_level(side.opposite(), skipped_price),
+                None => {
+                    if limits.best.is_none() {
+                        limits.update_best(side.opposite());
+                    }
+                    limits.best
+                }
+            };
+            let Some(index) = next else {
+                // no more orders on the opposite side
+                break;
+            };
+            let Some(level) = limits.levels.get_mut(index) else {
+                return Err(CorruptionKind::BestLevelMissing(side.opposite()).into());
+            };
+            if level.total_volume.is_zero() {
+                // best is stale, level will be moved to removed levels and best updated
+                let level_price = level.price;
+                limits.remove_level(level_price, index);
+                continue;
+            }
+            let crossed = price.is_none_or(|p| match side {
+                OrderSide::Buy => level.price <= p,
+                OrderSide::Sell => level.price >= p,
+            });
+            if !crossed {
+                break;
+            }
+
+            // first order of the level in time priority that accepts the fill
+            let (resting_side, level_price) = (side.opposite(), level.price);
+            if level.orders.front().is_none() {
+                return Err(CorruptionKind::LevelWithoutOrders {
+                    side: resting_side,
+                    price: level_price,
+                }
+                .into());
+            }
+            let mut eligible = None;
+            for handle in level.orders.iter() {
+                let Some(queued) = orders.get_by_handle(handle) else {
+                    return Err(CorruptionKind::QueuedOrderMissing {
+                        side: resting_side,
+                        price: level_price,
+                    }
+                    .into());
+                };
+                if queued.accepts_fill(queued.visible_volume().min(trade.remaining_volume())) {
+                    eligible = Some(handle);
+                    break;
+                }
+            }
+            let Some(handle) = eligible else {
+                skipped_level = Some(level_price);
                 continue;
             };
+            if price_bands.is_some_and(|bands| !bands.contains(level_price)) {
+                return Ok(true);
+            }
+            let Some(resting_order) = orders.get_by_handle_mut(handle) else {
+                return Err(CorruptionKind::QueuedOrderMissing {
+                    side: resting_side,
+                    price: level_price,
+                }
+                .into());
+            };
+            let resting_oid = resting_order.id;
+
+            let volume = resting_order.visible_volume().min(trade.remaining_volume());
+            let trade_price = trade_price_policy.trade_price(resting_order.price, price);
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                resting_order_id = %resting_oid,
+                price = %trade_price,
+                volume = %volume,
+                "match"
+            );
+
+            if refreshed.contains(&resting_oid) {
+                trade.add_execution(Execution::new_hidden(resting_oid, trade_price, volume));
+            } else {
+                trade.add_execution(Execution::new(resting_oid, trade_price, volume));
+            }
 
-            // so we have a buy order to fill
-            // no we need to find a sell order to match them
+            let hidden_before = resting_order.hidden_volume;
+            limits.touched.push(level.price);
+            if level.fill_queued_order(resting_order, handle, volume)? {
+                // resting order is fully filled, remove it from the book
+                if let Some(order) = orders.remove(&resting_oid) {
+                    finished_orders.record(OrderView::finished(&order, OrderState::Filled));
+                }
+            } else if resting_order.hidden_volume != hidden_before {
+                refreshed.push(resting_oid);
+            }
 
-            while let Some(sell_order_id) = best_sell_level.orders.front() {
-                let Some(sell_order) = self.orders.get(sell_order_id) else {
-                    // no order, so it has been cancelled
-                    best_sell_level.orders.pop_front();
-                    continue;
-                };
+            if level.total_volume.is_zero() {
+                let level_price = level.price;
+                limits.remove_level(level_price, index);
+            }
+        }
+        Ok(false)
+    }
+
+    /// refresh stale best levels, the spread and the top of book
+    /// called after every change of the book
+    fn update_top_of_book(&mut self) {
+        // best cleared by a removed level or left without volume is found again
+        self.bids.heal_best(OrderSide::Buy, &self.orders);
+        self.asks.heal_best(OrderSide::Sell, &self.orders);
+        // volume of the best levels could have been changed by fills and amends
+        self.bids.refresh_best_volume();
+        self.asks.refresh_best_volume();
+        self.bbo = Bbo {
+            bid_price: self.get_best_buy(),
+            bid_volume: self.get_best_buy_volume().unwrap_or(Volume::ZERO),
+            ask_price: self.get_best_sell(),
+            ask_volume: self.get_best_sell_volume().unwrap_or(Volume::ZERO),
+        };
+        self.publish_level_deltas();
+        let ask_best_limit = self.asks.get_best_limit();
+        let bid_best_limit = self.bids.get_best_limit();
+        match (ask_best_limit, bid_best_limit) {
+            (Some(ask_limit), Some(bid_limit)) => {
+                self.spread = Some(Spread(ask_limit - bid_limit));
+            }
+            _ => {
+                self.spread = None;
+            }
+        }
+        self.track_crossing();
+        #[cfg(feature = "metrics")]
+        self.export_gauges();
+    }
 
-                // now we match the orders
-                // we need to find the volume to fill, by getting the smaller volume of the two orders
+    fn update_best_buy(&mut self) {
+        self.bids.update_best(OrderSide::Buy);
+    }
 
-                let buy_volume = buy_order.volume - buy_order.filled_volume.unwrap_or(Volume::ZERO);
+    fn update_best_sell(&mut self) {
+        self.asks.update_best(OrderSide::Sell);
+    }
 
-                let sell_volume =
-                    sell_order.volume - sell_order.filled_volume.unwrap_or(Volume::ZERO);
+    /// release memory held by price levels that have no volume left
+    /// long running books with churning prices should call this periodically,
+    /// returns the number of released levels
+    pub fn compact(&mut self) -> usize {
+        self.logged(
+            || BookEvent::Compact,
+            |book| book.bids.compact() + book.asks.compact(),
+        )
+    }
 
-                let volume = buy_volume.min(sell_volume);
+    /// number of orders resting on the book
+    pub fn order_count(&self) -> usize {
+        self.orders.len()
+    }
 
-                let fill = Fill {
-                    buy_order_id: buy_order.id,
-                    sell_order_id: sell_order.id,
-                    buy_order_price: buy_order.price,
-                    sell_order_price: sell_order.price,
-                    volume,
-                };
+    /// true if there are no orders on the book
+    pub fn is_empty(&self) -> bool {
+        self.orders.len() == 0
+    }
 
-                // check if the orders should be removed
-                // if the volume is equal to the order volume, we can remove the order from the level
+    /// number of price levels with volume on the side of the book
+    pub fn level_count(&self, side: OrderSide) -> usize {
+        self.limits(side).level_count()
+    }
 
-                // have we completely filled the buy order?
-                if buy_volume == volume {
-                    // if so we can remove the order from the level
-                    best_buy_level.orders.pop_front();
-                } else {
-                    best_buy_level.reduce_volume(volume);
-                }
+    /// visible volume of all levels on the side of the book
+    pub fn total_volume(&self, side: OrderSide) -> Volume {
+        self.iter_levels(side).map(|level| level.total_volume).sum()
+    }
 
-                if sell_volume == volume {
-                    best_sell_level.orders.pop_front();
-                } else {
-                    best_sell_level.reduce_volume(volume);
-                }
+    /// price of the level furthest from the best price, lowest bid or highest ask
+    pub fn worst_price(&self, side: OrderSide) -> Option<Price> {
+        self.iter_levels(side).last().map(|level| level.price)
+    }
 
-                return Ok(fill);
-            }
-            break;
+    fn limits(&self, side: OrderSide) -> &Limits<Price, Volume, S> {
+        match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
         }
+    }
 
-        Err(OrderBookError::NoOrderToMatch)
+    pub fn get_best_sell(&self) -> Option<Price> {
+        self.asks.get_best_limit()
     }
 
-    pub fn fill_market_order(&mut self, order: &Order) -> Result<FillAtMarket, OrderBookError> {
-        match order.side {
-            OrderSide::Buy => self.fill_buy_market_order(order),
-            OrderSide::Sell => self.fill_sell_market_order(order),
-        }
+    pub fn get_best_buy(&self) -> Option<Price> {
+        self.bids.get_best_limit()
     }
 
-    fn fill_buy_market_order(&mut self, order: &Order) -> Result<FillAtMarket, OrderBookError> {
-        let Some(best_level_index) = self.asks.get_best() else {
-            return Err(OrderBookError::NoOrderToMatch);
-        };
-        let Ok(fill) = self.fill_buy_market_order_from_sell_level(order, best_level_index) else {
-            // this means that there was no order to match at the current level
-            // this should never happen therefore, and this means that OrderBook is corrupted
-            panic!("OrderBook is corrupted");
-        };
+    pub fn get_best_sell_index(&self) -> Option<LevelIndex> {
+        self.asks.get_best()
+    }
 
-        // update levels
-        let Some(filled_order) = self.orders.get_mut(&fill.order_id) else {
-            // this should never happen, as we have just filled the order
-            panic!("OrderBook is corrupted");
-        };
+    pub fn get_best_buy_index(&self) -> Option<LevelIndex> {
+        self.bids.get_best()
+    }
+
+    pub fn get_best_buy_volume(&self) -> Option<Volume> {
+        self.bids.get_best_volume()
+    }
 
-        if filled_order.volume == filled_order.filled_volume.unwrap_or(Volume::ZERO) {
-            self.asks.cancel_order(filled_order);
-            // check if we need to update best sell
+    pub fn get_best_sell_volume(&self) -> Option<Volume> {
+        self.asks.get_best_volume()
+    }
+
+    /// cancel the order, order is removed from the book and unlinked from its level in O(1)
+    pub fn cancel_order(&mut self, order_id: Oid) -> Result<CancellationReport, CancelOrderError> {
+        self.logged(
+            || BookEvent::Cancel(order_id),
+            |book| {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::debug_span!("cancel_order", order_id = %order_id).entered();
+                book.timed(LatencyOperation::Cancel, |book| {
+                    book.cancel_resting_order(order_id)
+                })
+            },
+        )
+    }
 
-            if self.asks.best.is_none() {
-                self.update_best_sell();
+    fn cancel_resting_order(
+        &mut self,
+        order_id: Oid,
+    ) -> Result<CancellationReport, CancelOrderError> {
+        // immutable borrows of self, therefore the need for new scope
+        // so if we do not return err then the immutable borrow will go out of scope
+        // and will allow for mutable borrow to allow for removal of the order from hashmap
+        match self.orders.remove(&order_id) {
+            None => {
+                let error = CancelOrderError::NotFound(order_id);
+                self.record_rejected(order_id, &error.clone().into());
+                return Err(error);
+            }
+            Some(mut order) => {
+                // update the level so the level volume is updated
+                match order.side {
+                    OrderSide::Buy => self.bids.cancel_order(&mut order),
+                    OrderSide::Sell => self.asks.cancel_order(&mut order),
+                }
+                self.finished_orders
+                    .record(OrderView::finished(&order, OrderState::Cancelled));
+                self.oco_groups.forget(order_id);
             }
-        } else {
-            // update the level volume
-            // but this was already done when we filled the order and order has not been fully filled
-            // this is since we already had mut ref to level
         }
+        let sequence = self.next_event_sequence();
+        self.record_event(order_id, sequence, AuditEvent::Cancelled);
+        self.update_top_of_book();
+        Ok(CancellationReport {
+            order_id,
+            status: CancellationStatus::Cancelled,
+            sequence,
+        })
+    }
 
-        Ok(fill)
+    /// amend price and volume of the resting order, volume is the new total volume of the order
+    /// reducing volume at the same price keeps the order queue position, price change or volume
+    /// increase is treated as cancel and replace, so the order is moved to the back of the level.
+    /// Replaced order is added to the book without matching, it takes the time of the clock,
+    /// or the latest time of the orders on the book when there is no clock.
+    pub fn amend_order(
+        &mut self,
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+    ) -> Result<AmendReport, OrderBookError> {
+        self.amend(order_id, price, volume, None)
     }
 
-    fn fill_sell_market_order(&mut self, order: &Order) -> Result<FillAtMarket, OrderBookError> {
-        let Some(best_level_index) = self.bids.get_best() else {
-            return Err(OrderBookError::NoOrderToMatch);
-        };
-        let Ok(fill) = self.fill_sell_market_order_from_buy_level(order, best_level_index) else {
-            // this means that there was no order to match at the current level
-            // this should never happen therefore, and this means that OrderBook is corrupted
-            panic!("OrderBook is corrupted");
-        };
+    /// amend the order like `amend_order`, replaced order gets the given time as its new time
+    pub fn amend_order_at(
+        &mut self,
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+        now: Timestamp,
+    ) -> Result<AmendReport, OrderBookError> {
+        self.amend(order_id, price, volume, Some(now))
+    }
+
+    fn amend(
+        &mut self,
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+        timestamp: Option<Timestamp>,
+    ) -> Result<AmendReport, OrderBookError> {
+        let timestamp = timestamp.or_else(|| self.accept_now());
+        self.logged(
+            || BookEvent::Amend {
+                order_id,
+                price,
+                volume,
+                timestamp,
+            },
+            |book| {
+                book.modify_order(order_id, price, volume, timestamp)
+                    .inspect_err(|error| book.record_rejected(order_id, error))
+            },
+        )
+    }
+
+    /// replaced order takes the new time if given, otherwise the latest time of the orders,
+    /// it is checked before the resting order is changed, so a rejected amend leaves it as it was
+    fn modify_order(
+        &mut self,
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+        now: Option<Timestamp>,
+    ) -> Result<AmendReport, OrderBookError> {
+        if matches!(self.state, TradingState::Closed | TradingState::Suspended) {
+            return Err(OrderBookError::InvalidState(self.state));
+        }
+        self.check_instrument_spec(Some(price), volume)?;
+        self.check_price_bands(price)?;
 
-        // update levels
-        let Some(filled_order) = self.orders.get_mut(&fill.order_id) else {
-            // this should never happen, as we have just filled the order
-            panic!("OrderBook is corrupted");
+        let Some(order) = self.orders.get_mut(&order_id) else {
+            return Err(AmendOrderError::NotFound(order_id).into());
         };
+        if volume <= order.filled_volume.unwrap_or(Volume::ZERO) {
+            return Err(AmendOrderError::VolumeNotAboveFilled(order_id).into());
+        }
 
-        if filled_order.volume == filled_order.filled_volume.unwrap_or(Volume::ZERO) {
-            self.bids.cancel_order(filled_order);
-            // check if we need to update best sell
+        if price == order.price && volume <= order.volume {
+            // reduce in place, hidden volume of iceberg order is reduced first
+            let reduce_by = order.volume - volume;
+            let hidden_reduce_by = reduce_by.min(order.hidden_volume);
 
-            if self.bids.best.is_none() {
-                self.update_best_buy();
+            let limits = match order.side {
+                OrderSide::Buy => &mut self.bids,
+                OrderSide::Sell => &mut self.asks,
+            };
+            if let Some(level) = limits.level_at_mut(&price) {
+                level.reduce_volume(reduce_by - hidden_reduce_by)?;
             }
-        } else {
-            // update the level volume
-            // but this was already done when we filled the order and order has not been fully filled
-            // this is since we already had mut ref to level
+            order.volume = volume;
+            order.hidden_volume -= hidden_reduce_by;
+            limits.touched.push(price);
+            let timestamp = order.timestamp;
+            let sequence = self.next_event_sequence();
+            self.record_event(order_id, sequence, AuditEvent::Amended { price, volume });
+            self.update_top_of_book();
+
+            return Ok(AmendReport {
+                order_id,
+                status: AmendStatus::Reduced,
+                timestamp,
+                sequence,
+            });
         }
 
-        Ok(fill)
-    }
+        let mut replaced = LimitOrder {
+            price,
+            volume,
+            hidden_volume: Volume::ZERO,
+            queue_slot: None,
+            ..order.clone()
+        };
+        let (side, resting_price, resting_visible) =
+            (order.side, order.price, order.visible_volume());
+        replaced.timestamp = match now {
+            Some(now) => now,
+            None => self
+                .accept_time
+                .filter(|latest| *latest > order.timestamp)
+                .unwrap_or(order.timestamp),
+        };
 
-    fn fill_sell_market_order_from_buy_level(
-        &mut self,
-        market_order: &Order,
-        level_index: LevelIndex,
-    ) -> Result<FillAtMarket, OrderBookError> {
-        let Some(level) = self.bids.levels.get_mut(level_index) else {
-            return Err(OrderBookError::NoOrderToMatch);
+        // validate the replaced order before we cancel the order, so it is not lost
+        if !self.risk_validators.is_empty() {
+            self.check_risk(&Order::from(&replaced))
+                .map_err(|reason| OrderBookError::RiskRejected(order_id, reason))?;
+        }
+        if let Some(post_only) = replaced.post_only {
+            replaced.price = self.post_only_price(&replaced, post_only)?;
+        }
+        replaced.refresh_display();
+        let level_volume = self
+            .get_volume_at_limit(replaced.price, side)
+            .unwrap_or(Volume::ZERO);
+        let level_volume = match replaced.price == resting_price {
+            true => level_volume.saturating_sub(resting_visible),
+            false => level_volume,
         };
-        // peek order at front of the level
-        while let Some(limit_order_oid) = level.orders.front() {
-            let Some(limit_order) = self.orders.get_mut(limit_order_oid) else {
-                // if there is no order then it might have been cancelled
-                // and removed from the map, and since we pospone the removal of orders from the level
-                // till we encounter such order, we can safely remove the order from the level
-                level.orders.pop_front();
-                continue;
-            };
-            let remaining_limit_volume =
-                limit_order.volume - limit_order.filled_volume.unwrap_or(Volume::ZERO);
-            let market_order_volume = market_order.volume;
-            if remaining_limit_volume <= market_order_volume {
-                // fully fill the buy limit order from order book
-                let fill = FillAtMarket {
-                    market_order_id: market_order.id,
-                    order_id: limit_order.id,
-                    order_price: limit_order.price,
-                    filled_volume: remaining_limit_volume,
-                };
-                // remove buy limit order from the level
-                level.orders.pop_front();
-                limit_order.filled_volume = Some(
-                    limit_order.filled_volume.unwrap_or(Volume::ZERO) + remaining_limit_volume,
-                );
-                // sanity check
-                if limit_order.volume != limit_order.filled_volume.unwrap_or(Volume::ZERO) {
-                    panic!("OrderBook is corrupted");
-                }
-                return Ok(fill);
-            } else {
-                // buy limit order not fully filled
-                let fill = FillAtMarket {
-                    market_order_id: market_order.id,
-                    order_id: limit_order.id,
-                    order_price: limit_order.price,
-                    filled_volume: remaining_limit_volume,
-                };
-                limit_order.filled_volume = Some(
-                    limit_order.filled_volume.unwrap_or(Volume::ZERO) + remaining_limit_volume,
-                );
-                // sanity check
-                if limit_order.volume < limit_order.filled_volume.unwrap_or(Volume::ZERO) {
-                    panic!("OrderBook is corrupted");
-                }
-                level.reduce_volume(remaining_limit_volume);
-                return Ok(fill);
-            }
+        if level_volume
+            .checked_add(replaced.visible_volume())
+            .is_none()
+        {
+            return Err(OrderBookError::ArithmeticOverflow);
+        }
+
+        let Some(mut order) = self.orders.remove(&order_id) else {
+            return Err(AmendOrderError::NotFound(order_id).into());
+        };
+        match order.side {
+            OrderSide::Buy => self.bids.cancel_order(&mut order),
+            OrderSide::Sell => self.asks.cancel_order(&mut order),
+        }
+        if self.bids.best.is_none() {
+            self.update_best_buy();
         }
+        if self.asks.best.is_none() {
+            self.update_best_sell();
+        }
+
+        let timestamp = replaced.timestamp;
+        let sequence = self.next_event_sequence();
+        self.record_event(order_id, sequence, AuditEvent::Amended { price, volume });
+        self.rest_order(replaced)?;
+
+        Ok(AmendReport {
+            order_id,
+            status: AmendStatus::Replaced,
+            timestamp,
+            sequence,
+        })
+    }
 
-        Err(OrderBookError::NoOrderToMatch)
+    /// remove all orders that have expired at the given time
+    /// level volumes, best limits and the spread are updated once all expired orders are removed
+    pub fn purge_expired(&mut self, now: Timestamp) -> Vec<CancellationReport> {
+        self.logged(
+            || BookEvent::PurgeExpired(now),
+            |book| {
+                let expired = book
+                    .orders
+                    .values()
+                    .filter(|o| o.is_expired(now))
+                    .map(|o| o.id)
+                    .collect::<Vec<_>>();
+                book.remove_orders(expired, CancellationStatus::Expired)
+            },
+        )
     }
 
-    fn fill_buy_market_order_from_sell_level(
+    /// remove the resting orders from the book, top of the book is updated once at the end
+    pub(crate) fn remove_orders(
         &mut self,
-        market_order: &Order,
-        level_index: LevelIndex,
-    ) -> Result<FillAtMarket, OrderBookError> {
-        let Some(level) = self.bids.levels.get_mut(level_index) else {
-            return Err(OrderBookError::NoOrderToMatch);
-        };
-        // peek order at front of the level
-        while let Some(limit_order_oid) = level.orders.front() {
-            let Some(limit_order) = self.orders.get_mut(limit_order_oid) else {
-                // if there is no order then it might have been cancelled
-                // and removed from the map, and since we pospone the removal of orders from the level
-                // till we encounter such order, we can safely remove the order from the level
-                level.orders.pop_front();
-                continue;
-            };
-            let remaining_limit_volume =
-                limit_order.volume - limit_order.filled_volume.unwrap_or(Volume::ZERO);
-            let market_order_volume = market_order.volume;
-            if remaining_limit_volume <= market_order_volume {
-                // fully fill the buy limit order from order book
-                let fill = FillAtMarket {
-                    market_order_id: market_order.id,
-                    order_id: limit_order.id,
-                    order_price: limit_order.price,
-                    filled_volume: remaining_limit_volume,
-                };
-                // remove buy limit order from the level
-                level.orders.pop_front();
-                limit_order.filled_volume = Some(
-                    limit_order.filled_volume.unwrap_or(Volume::ZERO) + remaining_limit_volume,
-                );
-                // sanity check
-                if limit_order.volume != limit_order.filled_volume.unwrap_or(Volume::ZERO) {
-                    panic!("OrderBook is corrupted");
+        order_ids: impl IntoIterator<Item = Oid>,
+        status: CancellationStatus,
+    ) -> Vec<CancellationReport> {
+        let mut reports = Vec::new();
+        for order_id in order_ids {
+            if let Some(mut order) = self.orders.remove(&order_id) {
+                match order.side {
+                    OrderSide::Buy => self.bids.cancel_order(&mut order),
+                    OrderSide::Sell => self.asks.cancel_order(&mut order),
                 }
-                return Ok(fill);
-            } else {
-                // buy limit order not fully filled
-                let fill = FillAtMarket {
-                    market_order_id: market_order.id,
-                    order_id: limit_order.id,
-                    order_price: limit_order.price,
-                    filled_volume: remaining_limit_volume,
+                self.finished_orders
+                    .record(OrderView::finished(&order, OrderState::Cancelled));
+                self.oco_groups.forget(order_id);
+                let sequence = self.next_event_sequence();
+                let event = match status {
+                    CancellationStatus::Expired => AuditEvent::Expired,
+                    _ => AuditEvent::Cancelled,
                 };
-                limit_order.filled_volume = Some(
-                    limit_order.filled_volume.unwrap_or(Volume::ZERO) + remaining_limit_volume,
-                );
-                // sanity check
-                if limit_order.volume < limit_order.filled_volume.unwrap_or(Volume::ZERO) {
-                    panic!("OrderBook is corrupted");
-                }
-                level.reduce_volume(remaining_limit_volume);
-                return Ok(fill);
+                self.record_event(order_id, sequence, event);
+                reports.push(CancellationReport {
+                    order_id,
+                    status: status.clone(),
+                    sequence,
+                });
             }
         }
 
-        Err(OrderBookError::NoOrderToMatch)
-    }
-
-    // pub fn fill_buy_order(
-    //     &mut self,
-    //     mut trade: Trade,
-    //     buy_price: Option<Price>,
-    // ) -> Result<Trade, OrderBookError> {
-    //     // find the lowest sell Limit
-    //     // if the lowest sell Limit is less than or equal to the buy Limit, we can fill the order, substracting the volume
-    //     // if the lowest sell Limit is greater than the buy Limit, we add the order to the book, with the volume
-    //     // equal to the order quantity
-
-    //     // before we do sorting we fill against best sell
-    //     if let Some(best_sell_level_index) = self.asks.best {
-    //         self.fill_buy_order_from_level(&mut trade, best_sell_level_index);
-
-    //         if trade.filled_volume == trade.volume {
-    //             let best_sell_level = self.asks.levels.get_mut(best_sell_level_index).unwrap();
-    //             if best_sell_level.orders.is_empty() {
-    //                 self.update_best_sell();
-    //             }
-    //             return Ok(trade);
-    //         }
-    //     }
-
-    //     // if we still have something to fill, we do not need to update best sell now, we will do it later
-    //     // when we finish filling the order
-
-    //     let sorted = self
-    //         .asks
-    //         .levels
-    //         .values_mut()
-    //         .filter(|l| filter_limit_for_buy(l, &buy_price))
-    //         .sorted();
-
-    //     let mut remaining_buy_volume = trade.volume - trade.filled_volume;
-
-    //     'top: for l in sorted {
-    //         // update best sell
-    //         // this will keep updating best index with each iteration
-    //         if self.bids.best != l.index {
-    //             self.bids.best = l.index;
-    //         }
-    //         // peek order at front of the level
-    //         while let Some(oid) = l.orders.front() {
-    //             // todo: remove might trigger memcpy
-    //             // although we need to get the owned value otherwise we will be borrowing self hence problem with borrow checker
-    //             let Some(mut sell_order) = self.orders.remove(oid) else {
-    //                 // if there is no order then it might have been cancelled
-    //                 // and removed from the map, and since we pospone the removal of orders from the level
-    //                 // till we encounter such order, we can safely remove the order from the level
-    //                 l.orders.pop_front();
-    //                 continue;
-    //             };
-    //             let sell_volume = sell_order.volume;
-    //             if sell_volume <= remaining_buy_volume {
-    //                 // fill the sell order
-    //                 trade.add_execution(Execution::new(
-    //                     sell_order.id,
-    //                     sell_order.price,
-    //                     sell_volume,
-    //                 ));
-    //                 // remove order from the level
-    //                 l.orders.pop_front();
-    //                 l.cancell_order(&sell_order);
-    //                 sell_order.volume = Volume::ZERO;
-    //                 remaining_buy_volume -= sell_volume;
-    //             } else {
-    //                 // fill the buy order, put the order back to the book
-    //                 let execution =
-    //                     Execution::new(sell_order.id, sell_order.price, remaining_buy_volume);
-    //                 trade.add_execution(execution);
-    //                 sell_order.volume -= remaining_buy_volume;
-    //                 remaining_buy_volume = Volume::ZERO;
-    //             }
-    //             // we should put back the sell order if it was not completely filled
-    //             if !sell_order.volume.is_zero() {
-    //                 self.orders.insert(sell_order.id, sell_order);
-    //             }
-    //             // if buy order was filled completely, we can break the loop
-    //             if remaining_buy_volume.is_zero() {
-    //                 break 'top;
-    //             }
-    //             // otherwise we still have volume to fill
-    //         } // no more orders at the level, we can move to the next level
-    //     }
-    //     Ok(trade)
-    // }
-
-    // fn fill_buy_order_from_level(&mut self, trade: &mut Trade, sell_level_index: LevelIndex) {
-    //     let sell_level = self.asks.levels.get_mut(sell_level_index).unwrap();
-
-    //     let mut remaining_buy_volume = trade.volume;
-    //     // peek order at front of the level
-    //     while let Some(sell_order_oid) = sell_level.orders.front() {
-    //         let Some(mut sell_order) = self.orders.remove(sell_order_oid) else {
-    //             // if there is no order then it might have been cancelled
-    //             // and removed from the map, and since we pospone the removal of orders from the level
-    //             // till we encounter such order, we can safely remove the order from the level
-    //             sell_level.orders.pop_front();
-    //             continue;
-    //         };
-    //         let sell_volume = sell_order.volume;
-    //         if sell_volume <= remaining_buy_volume {
-    //             // fill the sell order
-    //             trade.add_execution(Execution::new(sell_order.id, sell_order.price, sell_volume));
-    //             // remove order from the level
-    //             sell_level.orders.pop_front();
-    //             sell_level.cancell_order(&sell_order);
-    //             sell_order.volume = Volume::ZERO;
-    //             remaining_buy_volume -= sell_volume;
-    //         } else {
-    //             // sell_volume > remaining_buy_volume
-    //             // fill the sell order, put the order back to the book
-    //             let execution =
-    //                 Execution::new(sell_order.id, sell_order.price, remaining_buy_volume);
-    //             trade.add_execution(execution);
-    //             sell_order.volume -= remaining_buy_volume;
-    //             remaining_buy_volume = Volume::ZERO;
-    //         }
-    //         // we should put back the sell order if it was not completely filled
-    //         if !sell_order.volume.is_zero() {
-    //             self.orders.insert(sell_order.id, sell_order);
-    //         }
-    //         // if buy order was filled completely, we can break the loop
-    //         if remaining_buy_volume.is_zero() {
-    //             break;
-    //         }
-    //     }
-    // }
-
-    // pub fn fill_sell_order(
-    //     &mut self,
-    //     mut trade: Trade,
-    //     sell_price: Option<Price>,
-    // ) -> Result<Trade, OrderBookError> {
-    //     // find the highest bid Limit
-    //     // if the highest bid Limit is greater than or equal to the ask Limit, we can fill the order, substracting the volume
-    //     // if the highest bid Limit is less than the ask Limit, we add the order to the book, with the volume
-    //     // equal to the order quantity
-
-    //     // before we do sorting we fill against best sell
-    //     if let Some(best_buy_level_index) = self.bids.best {
-    //         self.fill_sell_order_from_level(&mut trade, best_buy_level_index);
-
-    //         if trade.filled_volume == trade.volume {
-    //             let best_buy_level = self.bids.levels.get_mut(best_buy_level_index).unwrap();
-    //             if best_buy_level.orders.is_empty() {
-    //                 self.update_best_sell();
-    //             }
-    //             return Ok(trade);
-    //         }
-    //     }
-
-    //     let mut remaining_sell_volume = trade.volume;
-
-    //     let sorted = self
-    //         .bids
-    //         .levels
-    //         .values_mut()
-    //         .filter(|l| filter_limit_for_sell(l, &sell_price))
-    //         .sorted_by(sort_limit_descending);
-
-    //     'top: for l in sorted {
-    //         // update best sell
-    //         // this will keep updating best index with each iteration
-    //         if self.asks.best != l.index {
-    //             self.asks.best = l.index;
-    //         }
-    //         // peek order at front of the level
-    //         while let Some(oid) = l.orders.front() {
-    //             // todo: remove might trigger memcpy
-    //             // although we need to get the owned value otherwise we will be borrowing self hence problem with borrow checker
-    //             let Some(mut buy_order) = self.orders.remove(oid) else {
-    //                 // if there is no order then it might have been cancelled
-    //                 // and removed from the map, and since we pospone the removal of orders from the level
-    //                 // till we encounter such order, we can safely remove the order from the level
-    //                 l.orders.pop_front();
-    //                 continue;
-    //             };
-    //             let buy_volume = buy_order.volume;
-    //             if buy_volume <= remaining_sell_volume {
-    //                 // fill the sell order
-    //                 trade.add_execution(Execution::new(buy_order.id, buy_order.price, buy_volume));
-    //                 // remove order from the level
-    //                 l.orders.pop_front();
-    //                 l.cancell_order(&buy_order);
-    //                 buy_order.volume = Volume::ZERO;
-    //                 remaining_sell_volume -= buy_volume;
-    //             } else {
-    //                 // fill the buy order, put the order back to the book
-    //                 let execution =
-    //                     Execution::new(buy_order.id, buy_order.price, remaining_sell_volume);
-    //                 trade.add_execution(execution);
-    //                 buy_order.volume -= remaining_sell_volume;
-    //                 remaining_sell_volume = Volume::ZERO;
-    //             }
-    //             // we should put back the sell order if it was not completely filled
-    //             if !buy_order.volume.is_zero() {
-    //                 self.orders.insert(buy_order.id, buy_order);
-    //             }
-    //             // if sell order was filled completely, we can break the loop
-    //             if remaining_sell_volume.is_zero() {
-    //                 break 'top;
-    //             }
-    //             // otherwise we still have volume to fill
-    //         }
-    //     }
-    //     Ok(trade)
-    // }
-
-    // fn fill_sell_order_from_level(&mut self, trade: &mut Trade, buy_level_index: LevelIndex) {
-    //     let buy_level = self.bids.levels.get_mut(buy_level_index).unwrap();
-
-    //     let mut remaining_sell_volume = trade.volume;
-    //     // peek order at front of the level
-    //     while let Some(buy_order_oid) = buy_level.orders.front() {
-    //         let Some(mut buy_order) = self.orders.remove(buy_order_oid) else {
-    //             // if there is no order then it might have been cancelled
-    //             // and removed from the map, and since we pospone the removal of orders from the level
-    //             // till we encounter such order, we can safely remove the order from the level
-    //             buy_level.orders.pop_front();
-    //             continue;
-    //         };
-    //         let buy_volume = buy_order.volume;
-    //         if buy_volume <= remaining_sell_volume {
-    //             // fill the sell order
-    //             trade.add_execution(Execution::new(buy_order.id, buy_order.price, buy_volume));
-    //             // remove order from the level
-    //             buy_level.orders.pop_front();
-    //             buy_level.cancell_order(&buy_order);
-    //             buy_order.volume = Volume::ZERO;
-    //             remaining_sell_volume -= buy_volume;
-    //         } else {
-    //             // sell_volume > remaining_buy_volume
-    //             // fill the sell order, put the order back to the book
-    //             let execution =
-    //                 Execution::new(buy_order.id, buy_order.price, remaining_sell_volume);
-    //             trade.add_execution(execution);
-    //             buy_order.volume -= remaining_sell_volume;
-    //             remaining_sell_volume = Volume::ZERO;
-    //         }
-    //         // we should put back the sell order if it was not completely filled
-    //         if !buy_order.volume.is_zero() {
-    //             self.orders.insert(buy_order.id, buy_order);
-    //         }
-    //         // if buy order was filled completely, we can break the loop
-    //         if remaining_sell_volume.is_zero() {
-    //             break;
-    //         }
-    //     }
-    // }
-}
+        if !reports.is_empty() {
+            self.update_top_of_book();
+        }
 
-// we want to inline since this is a small function and we want to avoid the overhead of a function call
-#[inline]
-#[allow(clippy::needless_lifetimes, dead_code)]
-fn sort_limit_descending<'a, 'b>(l: &'a &mut Level, r: &'b &mut Level) -> std::cmp::Ordering {
-    l.price.cmp(&r.price).reverse()
-}
-#[inline]
-#[allow(clippy::needless_lifetimes, dead_code)]
-fn filter_limit_for_buy<'a>(l: &'a &mut Level, price: &Option<Price>) -> bool {
-    if l.total_volume > 0.into() {
-        // in case price is none, we want to return true since we are in market order which has no price
-        return price.map(|p| l.price <= p).unwrap_or(true);
+        reports
     }
-    false
-}
-#[inline]
-#[allow(clippy::needless_lifetimes, dead_code)]
-fn filter_limit_for_sell<'a>(l: &'a &mut Level, price: &Option<Price>) -> bool {
-    if l.total_volume > 0.into() {
-        // in case price is none, we want to return true since we are in market order which has no price
-        return price.map(|p| l.price >= p).unwrap_or(true);
+
+    /// get volume of open orders for either buying or selling side of the book
+    pub fn get_volume_at_limit(&self, limit: Price, side: OrderSide) -> Option<Volume> {
+        let limit_map = match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        };
+        limit_map.level_at(&limit).map(|level| level.total_volume)
     }
-    false
-}
 
-mod tests_limit_map {
+    pub fn find_and_fill_best_orders(&mut self) -> Result<Fill, OrderBookError> {
+        self.logged(
+            || BookEvent::FillBestOrders,
+            |book| book.fill_best_in_bands(),
+        )
+    }
 
-    #[test]
-    fn test_limit_map() {
-        let mut limit_map = crate::Limits::default();
-        let order = crate::LimitOrder::new(
-            crate::primitives::Oid::new(1),
-            crate::OrderSide::Buy,
-            crate::primitives::Timestamp::new(1),
-            21.0453.into(),
-            100.into(),
-        );
-        limit_map.add_order(&order);
+    fn fill_best_in_bands(&mut self) -> Result<Fill, OrderBookError> {
+        if self.state != TradingState::Open {
+            return Err(OrderBookError::InvalidState(self.state));
+        }
+        // bands could have changed since the orders were added, so we check them again
+        for price in [self.get_best_buy(), self.get_best_sell()]
+            .into_iter()
+            .flatten()
+        {
+            if let Err(e) = self.check_price_bands(price) {
+                self.state = TradingState::Halted;
+                return Err(e);
+            }
+        }
+        self.fill_best_orders(None)
     }
-}
 
-#[allow(unused_imports)]
-mod tests_order_book {
+    /// fill the best orders, at the trade price if given, otherwise by the trade price policy
+    pub(crate) fn fill_best_orders(
+        &mut self,
+        trade_price: Option<Price>,
+    ) -> Result<Fill, OrderBookError> {
+        let (fill, timestamp) = self.find_and_fill(trade_price)?;
 
-    use crate::primitives::*;
-    use crate::*;
+        self.remove_filled_levels();
+        self.update_top_of_book();
+        self.trigger_oco([fill.buy_order_id, fill.sell_order_id]);
+        self.protect_after_fill(&fill, timestamp);
 
-    #[test]
-    fn test_order_book_new() {
-        let order_book = crate::OrderBook::default();
-        assert_eq!(order_book.bids.best, None);
-        assert_eq!(order_book.asks.best, None);
-        assert_eq!(order_book.orders.len(), 0);
-        assert_eq!(order_book.spread, None);
+        Ok(fill)
     }
 
-    #[test]
-    fn test_cancel_order() {
-        let mut order_book = OrderBook::default();
-        let order = &Order::new_limit(
+    fn remove_filled_levels(&mut self) {
+        // check if the best levels have any volume left
+        // otherwise they should be removed so the best limit can be updated
+
+        for limits in [&mut self.bids, &mut self.asks] {
+            let Some(index) = limits.best else {
+                continue;
+            };
+            let Some(level) = limits.levels.get(index) else {
+                continue;
+            };
+            if level.total_volume.is_zero() {
+                let price = level.price;
+                limits.remove_level(price, index);
+            }
+        }
+    }
+
+    /// fill the first matching orders of the crossed best levels,
+    /// returns the fill and the time of the later order
+    fn find_and_fill(
+        &mut self,
+        trade_price: Option<Price>,
+    ) -> Result<(Fill, Timestamp), OrderBookError> {
+        let Some(best_buy_level_index) = self.bids.get_best() else {
+            return Err(OrderBookError::NoOrderToMatch);
+        };
+        let Some(best_sell_level_index) = self.asks.get_best() else {
+            return Err(OrderBookError::NoOrderToMatch);
+        };
+
+        let Some(best_buy_level) = self.bids.levels.get_mut(best_buy_level_index) else {
+            return Err(CorruptionKind::BestLevelMissing(OrderSide::Buy).into());
+        };
+        let Some(best_sell_level) = self.asks.levels.get_mut(best_sell_level_index) else {
+            return Err(CorruptionKind::BestLevelMissing(OrderSide::Sell).into());
+        };
+
+        // 1. check if the level is not empty. One reason why it could be empty is because cancel_order could be called and make the level no longer best
+        // although matching engine should call update_best_limits after cancellation, as this would require publishing new best
+        // 1. check prices if we can do a match
+        // 2. if we can match, pop the orders from the levels
+        // 3. make a match
+        // 4. update the levels
+
+        if best_buy_level.total_volume.is_zero() || best_sell_level.total_volume.is_zero() {
+            // todo: split this error into two for bid and ask for clarity
+            return Err(OrderBookError::LevelHasNoValidOrders);
+        }
+
+        if best_buy_level.price < best_sell_level.price {
+            // cannot match buy order that lower price than a sell order
+            return Err(OrderBookError::NoOrderToMatch);
+        }
+
+        // levels have volume, so their queues must have orders that are on the book
+        for (side, level) in [
+            (OrderSide::Buy, &*best_buy_level),
+            (OrderSide::Sell, &*best_sell_level),
+        ] {
+            if level.orders.front().is_none() {
+                let price = level.price;
+                return Err(CorruptionKind::LevelWithoutOrders { side, price }.into());
+            }
+        }
+        let queued = |side: OrderSide, level: &Level, handle: OrderHandle| {
+            let price = level.price;
+            self.orders
+                .get_by_handle(handle)
+                .ok_or(CorruptionKind::QueuedOrderMissing { side, price })
+        };
+        // first pair of orders in time priority whose minimum quantities accept the fill,
+        // without minimum quantities these are the orders at the front of the levels
+        let mut matched = None;
+        'search: for buy_handle in best_buy_level.orders.iter() {
+            let buy = queued(OrderSide::Buy, best_buy_level, buy_handle)?;
+            for sell_handle in best_sell_level.orders.iter() {
+                let sell = queued(OrderSide::Sell, best_sell_level, sell_handle)?;
+                let volume = buy.visible_volume().min(sell.visible_volume());
+                if buy.accepts_fill(volume) && sell.accepts_fill(volume) {
+                    matched = Some((
+                        (buy_handle, buy.id, buy.visible_volume(), buy.timestamp),
+                        (sell_handle, sell.id, sell.visible_volume(), sell.timestamp),
+                    ));
+                    break 'search;
+                }
+            }
+        }
+        let Some((
+            (buy_handle, buy_order_id, buy_volume, buy_timestamp),
+            (sell_handle, sell_order_id, sell_volume, sell_timestamp),
+        )) = matched
+        else {
+            return Err(OrderBookError::NoOrderToMatch);
+        };
+
+        // now we match the orders
+        // we need to find the volume to fill, by getting the smaller volume of the two orders
+        let volume = buy_volume.min(sell_volume);
+
+        // the later of the two orders took the liquidity
+        let (aggressor_side, timestamp, passive_price, aggressor_price) =
+            if buy_timestamp > sell_timestamp {
+                (
+                    OrderSide::Buy,
+                    buy_timestamp,
+                    best_sell_level.price,
+                    best_buy_level.price,
+                )
+            } else {
+                (
+                    OrderSide::Sell,
+                    sell_timestamp,
+                    best_buy_level.price,
+                    best_sell_level.price,
+                )
+            };
+        // levels are borrowed, so the counter is advanced in place
+        self.event_sequence += 1;
+        let fill = Fill {
+            buy_order_id,
+            sell_order_id,
+            buy_order_price: best_buy_level.price,
+            sell_order_price: best_sell_level.price,
+            trade_price: trade_price.unwrap_or_else(|| {
+                self.trade_price_policy
+                    .trade_price(passive_price, Some(aggressor_price))
+            }),
+            volume,
+            sequence: self.event_sequence,
+        };
+
+        // update the orders and levels, completely filled orders are removed from the book
+        self.bids.touched.push(best_buy_level.price);
+        self.asks.touched.push(best_sell_level.price);
+        for (level, order_id, handle) in [
+            (&mut *best_buy_level, buy_order_id, buy_handle),
+            (&mut *best_sell_level, sell_order_id, sell_handle),
+        ] {
+            if let Some(order) = self.orders.get_by_handle_mut(handle) {
+                if level.fill_queued_order(order, handle, volume)? {
+                    if let Some(order) = self.orders.remove(&order_id) {
+                        self.finished_orders
+                            .record(OrderView::finished(&order, OrderState::Filled));
+                    }
+                }
+            }
+        }
+
+        self.record_trade(TapeEntry {
+            timestamp,
+            aggressor_side,
+            price: fill.trade_price,
+            volume,
+            buy_order_id,
+            sell_order_id,
+            sequence: fill.sequence,
+        });
+        for order_id in [buy_order_id, sell_order_id] {
+            let leaves = self
+                .orders
+                .get(&order_id)
+                .map_or(Volume::ZERO, |order| order.remaining_volume());
+            self.record_fill(order_id, fill.trade_price, volume, leaves, fill.sequence);
+        }
+
+        Ok((fill, timestamp))
+    }
+
+    /// match crossed orders until the spread is no longer crossed, stale best levels are
+    /// refreshed in between the fills. Matching stops early when the book is not open, a fill
+    /// would be outside of the price bands or the book is inconsistent.
+    pub fn match_all(&mut self) -> Vec<Fill> {
+        let mut fills = Vec::new();
+        self.match_all_into(|fill| fills.push(fill));
+        fills
+    }
+
+    /// same as `match_all`, the fills are passed to the sink as they are made instead of
+    /// being collected, so hot loops can match without allocating
+    /// returns the number of fills
+    pub fn match_all_into(&mut self, sink: impl FnMut(Fill)) -> usize {
+        self.logged(|| BookEvent::MatchAll, |book| book.match_crossed(sink))
+    }
+
+    fn match_crossed(&mut self, mut sink: impl FnMut(Fill)) -> usize {
+        let mut fills = 0;
+        loop {
+            match self.find_and_fill_best_orders() {
+                Ok(fill) => {
+                    fills += 1;
+                    sink(fill);
+                }
+                Err(OrderBookError::LevelHasNoValidOrders) => {
+                    // best level is stale, remove it so the next best level is used
+                    self.remove_filled_levels();
+                    self.update_top_of_book();
+                }
+                Err(_) => break,
+            }
+        }
+        fills
+    }
+
+    /// fill market order against the order at the front of the best level on the opposite side
+    pub fn fill_market_order(&mut self, order: &Order) -> Result<FillAtMarket, OrderBookError> {
+        self.logged(
+            || BookEvent::FillMarketOrder(order.clone()),
+            |book| book.fill_at_market(order),
+        )
+    }
+
+    fn fill_at_market(&mut self, order: &Order) -> Result<FillAtMarket, OrderBookError> {
+        if self.state != TradingState::Open {
+            return Err(OrderBookError::InvalidState(self.state));
+        }
+        let mut trade = Trade::new(order.id, order.volume);
+        let filled = self.fill_order(&mut trade, order.side, None, 1);
+        self.record_executions(order, &mut trade.executions);
+        self.trigger_oco(trade.executions.iter().map(|e| e.order_id));
+        self.update_top_of_book();
+        self.protect_after_executions(order, &trade.executions);
+        if filled? {
+            self.state = TradingState::Halted;
+        }
+
+        let Some(execution) = trade.executions.pop() else {
+            return Err(OrderBookError::NoOrderToMatch);
+        };
+        Ok(FillAtMarket {
+            market_order_id: order.id,
+            order_id: execution.order_id,
+            order_price: execution.price,
+            filled_volume: execution.volume,
+        })
+    }
+}
+
+/// minimum quantity cannot be combined with the display volume of iceberg orders
+fn check_min_qty(
+    display_volume: Option<Volume>,
+    min_qty: Option<Volume>,
+) -> Result<(), OrderBookError> {
+    if display_volume.is_some() && min_qty.is_some() {
+        return Err(OrderBookError::OrderCannotBePlaced(
+            "iceberg order cannot have a minimum quantity".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// only good till cancel limit orders can rest on the book
+fn rests_on_book(order: &Order) -> bool {
+    order.kind == OrderType::Limit && order.time_in_force == TimeInForce::GoodTillCancel
+}
+
+// we want to inline since this is a small function and we want to avoid the overhead of a function call
+#[inline]
+#[allow(clippy::needless_lifetimes, dead_code)]
+fn sort_limit_descending<'a, 'b>(l: &'a &mut Level, r: &'b &mut Level) -> core::cmp::Ordering {
+    l.price.cmp(&r.price).reverse()
+}
+#[inline]
+#[allow(clippy::needless_lifetimes, dead_code)]
+fn filter_limit_for_buy<'a>(l: &'a &mut Level, price: &Option<Price>) -> bool {
+    if l.total_volume > 0.into() {
+        // in case price is none, we want to return true since we are in market order which has no price
+        return price.map(|p| l.price <= p).unwrap_or(true);
+    }
+    false
+}
+#[inline]
+#[allow(clippy::needless_lifetimes, dead_code)]
+fn filter_limit_for_sell<'a>(l: &'a &mut Level, price: &Option<Price>) -> bool {
+    if l.total_volume > 0.into() {
+        // in case price is none, we want to return true since we are in market order which has no price
+        return price.map(|p| l.price >= p).unwrap_or(true);
+    }
+    false
+}
+
+mod tests_limit_map {
+    #[test]
+    fn test_limit_map() {
+        let id = 1;
+        let mut limit_map: crate::Limits = crate::Limits::default();
+        let mut order = crate::LimitOrder::new(
+            crate::primitives::Oid::new(1),
+            crate::OrderSide::Buy,
+            crate::primitives::Timestamp::new(1),
+            21.0453.into(),
+            100.into(),
+        );
+        limit_map
+            .add_order(&mut order, crate::primitives::OrderHandle(id as usize))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_levels_are_sorted_from_best() {
+        let mut limit_map: crate::Limits = crate::Limits::default();
+        for (id, price) in [(1, 21.0), (2, 23.0), (3, 22.0)] {
+            let mut order = crate::LimitOrder::new(
+                crate::primitives::Oid::new(id),
+                crate::OrderSide::Buy,
+                crate::primitives::Timestamp::new(id),
+                price.into(),
+                100.into(),
+            );
+            limit_map
+                .add_order(&mut order, crate::primitives::OrderHandle(id as usize))
+                .unwrap();
+        }
+        let prices = limit_map
+            .iter_levels(crate::OrderSide::Buy)
+            .map(|l| l.price)
+            .collect::<Vec<_>>();
+        assert_eq!(prices, vec![23.0.into(), 22.0.into(), 21.0.into()]);
+
+        let best = limit_map.get_best().unwrap();
+        limit_map.remove_level(23.0.into(), best);
+        assert_eq!(limit_map.get_best_limit(), None);
+        limit_map.update_best(crate::OrderSide::Buy);
+        assert_eq!(limit_map.get_best_limit(), Some(22.0.into()));
+    }
+}
+
+#[allow(unused_imports)]
+mod tests_order_book {
+
+    use crate::primitives::*;
+    use crate::*;
+
+    #[test]
+    fn test_order_book_new() {
+        let order_book = crate::OrderBook::default();
+        assert_eq!(order_book.bids.best, None);
+        assert_eq!(order_book.asks.best, None);
+        assert_eq!(order_book.order_count(), 0);
+        assert_eq!(order_book.spread, None);
+    }
+
+    #[test]
+    fn test_cancel_order() {
+        let mut order_book = OrderBook::default();
+        let order = &Order::new_limit(
             Oid::new(1),
             OrderSide::Buy,
             chrono::Utc::now().into(),
             21.0453.into(),
             100.into(),
         );
-        order_book.add_order(order.try_into().unwrap());
-        assert_eq!(order_book.orders.len(), 1);
+        order_book.add_order(order.try_into().unwrap()).unwrap();
+        assert_eq!(order_book.order_count(), 1);
         let order = order_book.cancel_order(Oid::new(1)).unwrap();
-        assert_eq!(order_book.orders.len(), 0);
+        assert_eq!(order_book.order_count(), 0);
         assert_eq!(order.order_id, Oid::new(1));
         assert_eq!(order.status, CancellationStatus::Cancelled);
 
@@ -1077,12 +2367,85 @@ mod tests_order_book {
             21.0453.into(),
             50.into(),
         );
-        order_book.add_order(order.try_into().unwrap());
-        assert_eq!(order_book.orders.len(), 1);
+        order_book.add_order(order.try_into().unwrap()).unwrap();
+        assert_eq!(order_book.order_count(), 1);
         let order = order_book.cancel_order(Oid::new(2)).unwrap();
-        assert_eq!(order_book.orders.len(), 0);
+        assert_eq!(order_book.order_count(), 0);
         assert_eq!(order.order_id, Oid::new(2));
         assert_eq!(order.status, CancellationStatus::Cancelled);
+        assert_eq!(order.to_string(), "order 2 cancelled");
+    }
+
+    #[test]
+    fn test_cancel_unlinks_order_from_level() {
+        let mut order_book = OrderBook::default();
+        for id in 1..=3 {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                10.0.into(),
+                10.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        order_book.cancel_order(Oid::new(2)).unwrap();
+        assert_eq!(order_book.get_best_sell_volume(), Some(20.into()));
+
+        let trade = order_book
+            .execute(&Order::new_market(
+                Oid::new(4),
+                OrderSide::Buy,
+                Timestamp::new(4),
+                20.into(),
+            ))
+            .unwrap();
+        let filled = trade
+            .executions
+            .iter()
+            .map(|e| e.order_id)
+            .collect::<Vec<_>>();
+        assert_eq!(filled, vec![Oid::new(1), Oid::new(3)]);
+        assert_eq!(
+            trade.to_string(),
+            "order 4 filled 20/20 cancelled 0, 10 @ 10 with 1, 10 @ 10 with 3"
+        );
+        assert_eq!(order_book.get_best_sell(), None);
+    }
+
+    #[test]
+    fn test_best_price_and_volume_are_cached() {
+        let mut order_book = OrderBook::default();
+        for (id, price) in [(1, 10.0), (2, 11.0), (3, 11.0)] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                Price::new(price),
+                10.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        let best = |book: &OrderBook| (book.get_best_buy(), book.get_best_buy_volume());
+        assert_eq!(best(&order_book), (Some(11.0.into()), Some(20.into())));
+
+        order_book.cancel_order(Oid::new(2)).unwrap();
+        assert_eq!(best(&order_book), (Some(11.0.into()), Some(10.into())));
+        let sell = Order::new_market(Oid::new(4), OrderSide::Sell, Timestamp::new(4), 4.into());
+        order_book.execute(&sell).unwrap();
+        assert_eq!(best(&order_book), (Some(11.0.into()), Some(6.into())));
+        order_book
+            .amend_order(Oid::new(3), 11.0.into(), 5.into())
+            .unwrap();
+        assert_eq!(best(&order_book), (Some(11.0.into()), Some(1.into())));
+        assert!(order_book.check_integrity().is_ok());
+
+        // emptied best level falls back to the next level
+        order_book.cancel_order(Oid::new(3)).unwrap();
+        assert_eq!(best(&order_book), (Some(10.0.into()), Some(10.into())));
+        order_book.cancel_order(Oid::new(1)).unwrap();
+        assert_eq!(best(&order_book), (None, None));
+        assert!(order_book.check_integrity().is_ok());
     }
 
     #[test]
@@ -1095,7 +2458,7 @@ mod tests_order_book {
             21.0.into(),
             100.into(),
         );
-        order_book.add_order(order.try_into().unwrap());
+        order_book.add_order(order.try_into().unwrap()).unwrap();
         let fill_result = order_book.find_and_fill_best_orders();
         assert!(fill_result.is_err());
         assert_eq!(fill_result.unwrap_err(), OrderBookError::NoOrderToMatch);
@@ -1108,7 +2471,7 @@ mod tests_order_book {
             22.0.into(),
             50.into(),
         );
-        order_book.add_order(order.try_into().unwrap());
+        order_book.add_order(order.try_into().unwrap()).unwrap();
         assert_eq!(order_book.get_best_buy(), Some(22.0.into()));
 
         let fill = order_book.find_and_fill_best_orders().unwrap();
@@ -1117,6 +2480,11 @@ mod tests_order_book {
         assert_eq!(fill.volume, 50.into());
         assert_eq!(fill.buy_order_price, 22.0.into());
         assert_eq!(fill.sell_order_price, 21.0.into());
+        // both orders can have the same timestamp, so the trade price is not asserted
+        assert_eq!(
+            fill.to_string(),
+            format!("50 @ {} buy 3 sell 1", fill.trade_price)
+        );
 
         assert!(order_book.get_best_buy().is_none());
         assert!(order_book.get_best_buy_volume().is_none());
@@ -1130,7 +2498,7 @@ mod tests_order_book {
             25.0.into(),
             125.into(),
         );
-        order_book.add_order(order.try_into().unwrap());
+        order_book.add_order(order.try_into().unwrap()).unwrap();
 
         let fill = order_book.find_and_fill_best_orders().unwrap();
         assert_eq!(fill.buy_order_id, Oid::new(2));
@@ -1151,7 +2519,7 @@ mod tests_order_book {
             20.0.into(),
             75.into(),
         );
-        order_book.add_order(order.try_into().unwrap());
+        order_book.add_order(order.try_into().unwrap()).unwrap();
 
         let fill = order_book.find_and_fill_best_orders().unwrap();
         assert_eq!(fill.buy_order_id, Oid::new(2));
@@ -1166,92 +2534,970 @@ mod tests_order_book {
         assert!(order_book.get_best_sell_volume().is_none());
     }
 
-    // #[test]
-    // fn test_market_order_should_result_in_empty_order_book() {
-    //     let mut order_book = crate::OrderBook::default();
-    //     let order = &crate::Order::new_limit(
-    //         crate::primitives::Oid::new(1),
-    //         crate::OrderSide::Sell,
-    //         chrono::Utc::now().into(),
-    //         21.0453.into(),
-    //         100.into(),
-    //     );
-    //     let _ = order_book.execute(order);
-
-    //     let order = &crate::Order::new_limit(
-    //         crate::primitives::Oid::new(2),
-    //         crate::OrderSide::Sell,
-    //         chrono::Utc::now().into(),
-    //         21.0454.into(),
-    //         50.into(),
-    //     );
-    //     let _ = order_book.execute(order);
-
-    //     let order = &crate::Order::new_market(
-    //         crate::primitives::Oid::new(3),
-    //         crate::OrderSide::Buy,
-    //         chrono::Utc::now().into(),
-    //         150.into(),
-    //     );
-    //     let trade = order_book.execute(order).unwrap();
-    //     assert_eq!(trade.order_id, crate::primitives::Oid::new(3));
-    //     assert_eq!(trade.volume, 150.into());
-    //     assert_eq!(trade.filled_volume, 150.into());
-    //     assert_eq!(trade.executions.len(), 2);
-    //     let execution = &trade.executions[0];
-    //     assert_eq!(execution.order_id, crate::primitives::Oid::new(1));
-    //     assert_eq!(execution.price, 21.0453.into());
-    //     assert_eq!(execution.volume, 100.into());
-    //     let execution = &trade.executions[1];
-    //     assert_eq!(execution.order_id, crate::primitives::Oid::new(2));
-    //     assert_eq!(execution.price, 21.0454.into());
-    //     assert_eq!(execution.volume, 50.into());
-
-    //     assert_eq!(order_book.orders.len(), 0);
-    // }
-
-    // #[test]
-    // fn test_sell_market_order_should_result_in_empty_order_book() {
-    //     let mut order_book = crate::OrderBook::default();
-    //     let order = &crate::Order::new_limit(
-    //         crate::primitives::Oid::new(1),
-    //         crate::OrderSide::Buy,
-    //         chrono::Utc::now().into(),
-    //         21.0453.into(),
-    //         100.into(),
-    //     );
-    //     let _ = order_book.execute(order);
-
-    //     let order = &crate::Order::new_limit(
-    //         crate::primitives::Oid::new(2),
-    //         crate::OrderSide::Buy,
-    //         chrono::Utc::now().into(),
-    //         21.0454.into(),
-    //         50.into(),
-    //     );
-    //     let _ = order_book.execute(order);
-
-    //     let order = &crate::Order::new_market(
-    //         crate::primitives::Oid::new(3),
-    //         crate::OrderSide::Sell,
-    //         chrono::Utc::now().into(),
-    //         150.into(),
-    //     );
-    //     let trade = order_book.execute(order).unwrap();
-
-    //     assert_eq!(trade.order_id, crate::primitives::Oid::new(3));
-    //     assert_eq!(trade.volume, 150.into());
-    //     assert_eq!(trade.filled_volume, 150.into());
-    //     assert_eq!(trade.executions.len(), 2);
-    //     let execution = &trade.executions[0];
-    //     assert_eq!(execution.order_id, crate::primitives::Oid::new(2));
-    //     assert_eq!(execution.price, 21.0454.into());
-    //     assert_eq!(execution.volume, 50.into());
-    //     let execution = &trade.executions[1];
-    //     assert_eq!(execution.order_id, crate::primitives::Oid::new(1));
-    //     assert_eq!(execution.price, 21.0453.into());
-    //     assert_eq!(execution.volume, 100.into());
-
-    //     assert_eq!(order_book.orders.len(), 0);
-    // }
+    #[test]
+    fn test_market_order_should_result_in_empty_order_book() {
+        let mut order_book = crate::OrderBook::default();
+        let order = &crate::Order::new_limit(
+            crate::primitives::Oid::new(1),
+            crate::OrderSide::Sell,
+            chrono::Utc::now().into(),
+            21.0453.into(),
+            100.into(),
+        );
+        let _ = order_book.execute(order);
+
+        let order = &crate::Order::new_limit(
+            crate::primitives::Oid::new(2),
+            crate::OrderSide::Sell,
+            chrono::Utc::now().into(),
+            21.0454.into(),
+            50.into(),
+        );
+        let _ = order_book.execute(order);
+
+        let order = &crate::Order::new_market(
+            crate::primitives::Oid::new(3),
+            crate::OrderSide::Buy,
+            chrono::Utc::now().into(),
+            150.into(),
+        );
+        let trade = order_book.execute(order).unwrap();
+        assert_eq!(trade.order_id, crate::primitives::Oid::new(3));
+        assert_eq!(trade.volume, 150.into());
+        assert_eq!(trade.filled_volume, 150.into());
+        assert_eq!(trade.executions.len(), 2);
+        let execution = &trade.executions[0];
+        assert_eq!(execution.order_id, crate::primitives::Oid::new(1));
+        assert_eq!(execution.price, 21.0453.into());
+        assert_eq!(execution.volume, 100.into());
+        let execution = &trade.executions[1];
+        assert_eq!(execution.order_id, crate::primitives::Oid::new(2));
+        assert_eq!(execution.price, 21.0454.into());
+        assert_eq!(execution.volume, 50.into());
+
+        assert_eq!(order_book.order_count(), 0);
+    }
+
+    #[test]
+    fn test_sell_market_order_should_result_in_empty_order_book() {
+        let mut order_book = crate::OrderBook::default();
+        let order = &crate::Order::new_limit(
+            crate::primitives::Oid::new(1),
+            crate::OrderSide::Buy,
+            chrono::Utc::now().into(),
+            21.0453.into(),
+            100.into(),
+        );
+        let _ = order_book.execute(order);
+
+        let order = &crate::Order::new_limit(
+            crate::primitives::Oid::new(2),
+            crate::OrderSide::Buy,
+            chrono::Utc::now().into(),
+            21.0454.into(),
+            50.into(),
+        );
+        let _ = order_book.execute(order);
+
+        let order = &crate::Order::new_market(
+            crate::primitives::Oid::new(3),
+            crate::OrderSide::Sell,
+            chrono::Utc::now().into(),
+            150.into(),
+        );
+        let trade = order_book.execute(order).unwrap();
+
+        assert_eq!(trade.order_id, crate::primitives::Oid::new(3));
+        assert_eq!(trade.volume, 150.into());
+        assert_eq!(trade.filled_volume, 150.into());
+        assert_eq!(trade.executions.len(), 2);
+        let execution = &trade.executions[0];
+        assert_eq!(execution.order_id, crate::primitives::Oid::new(2));
+        assert_eq!(execution.price, 21.0454.into());
+        assert_eq!(execution.volume, 50.into());
+        let execution = &trade.executions[1];
+        assert_eq!(execution.order_id, crate::primitives::Oid::new(1));
+        assert_eq!(execution.price, 21.0453.into());
+        assert_eq!(execution.volume, 100.into());
+
+        assert_eq!(order_book.order_count(), 0);
+    }
+
+    #[test]
+    fn test_limit_order_remainder_rests_on_book() {
+        let mut order_book = OrderBook::default();
+        let order = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            chrono::Utc::now().into(),
+            21.0.into(),
+            100.into(),
+        );
+        order_book.execute(&order).unwrap();
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Sell,
+            chrono::Utc::now().into(),
+            23.0.into(),
+            100.into(),
+        );
+        order_book.execute(&order).unwrap();
+
+        let order = Order::new_limit(
+            Oid::new(3),
+            OrderSide::Buy,
+            chrono::Utc::now().into(),
+            22.0.into(),
+            150.into(),
+        );
+        let trade = order_book.execute(&order).unwrap();
+        assert_eq!(trade.filled_volume, 100.into());
+        assert_eq!(trade.executions.len(), 1);
+        assert_eq!(trade.executions[0].order_id, Oid::new(1));
+        assert_eq!(trade.executions[0].price, 21.0.into());
+
+        assert_eq!(order_book.get_best_buy(), Some(22.0.into()));
+        assert_eq!(order_book.get_best_buy_volume(), Some(50.into()));
+        assert_eq!(order_book.get_best_sell(), Some(23.0.into()));
+        assert_eq!(order_book.get_best_sell_volume(), Some(100.into()));
+        assert_eq!(order_book.spread, Some(Spread(1.0.into())));
+    }
+
+    #[test]
+    fn test_ioc_order_remainder_is_cancelled() {
+        let mut order_book = OrderBook::default();
+        let order = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            chrono::Utc::now().into(),
+            21.0.into(),
+            100.into(),
+        );
+        order_book.execute(&order).unwrap();
+
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Buy,
+            chrono::Utc::now().into(),
+            22.0.into(),
+            150.into(),
+        )
+        .with_time_in_force(TimeInForce::ImmediateOrCancel);
+        let trade = order_book.execute(&order).unwrap();
+        assert_eq!(trade.filled_volume, 100.into());
+        assert_eq!(trade.cancelled_volume, 50.into());
+        assert_eq!(trade.executions.len(), 1);
+
+        assert!(order_book.get_best_buy().is_none());
+        assert!(order_book.get_best_sell().is_none());
+        assert_eq!(order_book.order_count(), 0);
+    }
+
+    #[test]
+    fn test_purge_expired_orders() {
+        let mut order_book = OrderBook::default();
+        let order = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Buy,
+            Timestamp::new(1),
+            21.0.into(),
+            100.into(),
+        )
+        .with_expiry(Timestamp::new(10));
+        order_book.execute(&order).unwrap();
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Buy,
+            Timestamp::new(2),
+            20.0.into(),
+            100.into(),
+        )
+        .with_expiry(Timestamp::new(20));
+        order_book.execute(&order).unwrap();
+        let order = Order::new_limit(
+            Oid::new(3),
+            OrderSide::Sell,
+            Timestamp::new(3),
+            25.0.into(),
+            100.into(),
+        );
+        order_book.execute(&order).unwrap();
+
+        assert!(order_book.purge_expired(Timestamp::new(5)).is_empty());
+
+        let reports = order_book.purge_expired(Timestamp::new(10));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].order_id, Oid::new(1));
+        assert_eq!(reports[0].status, CancellationStatus::Expired);
+        assert_eq!(order_book.get_best_buy(), Some(20.0.into()));
+        assert_eq!(order_book.spread, Some(Spread(5.0.into())));
+
+        let reports = order_book.purge_expired(Timestamp::new(30));
+        assert_eq!(reports.len(), 1);
+        assert!(order_book.get_best_buy().is_none());
+        assert_eq!(order_book.get_best_sell(), Some(25.0.into()));
+        assert_eq!(order_book.spread, None);
+    }
+
+    #[test]
+    fn test_iceberg_order_shows_display_volume() {
+        let mut order_book = OrderBook::default();
+        let order = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            21.0.into(),
+            250.into(),
+        )
+        .with_display_volume(100.into());
+        order_book.execute(&order).unwrap();
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Sell,
+            Timestamp::new(2),
+            21.0.into(),
+            50.into(),
+        );
+        order_book.execute(&order).unwrap();
+        assert_eq!(order_book.get_best_sell_volume(), Some(150.into()));
+
+        let order = Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(3), 200.into());
+        let trade = order_book.execute(&order).unwrap();
+        assert_eq!(trade.filled_volume, 200.into());
+        assert_eq!(trade.executions.len(), 3);
+        // visible tranche of the iceberg order
+        assert_eq!(trade.executions[0].order_id, Oid::new(1));
+        assert_eq!(trade.executions[0].volume, 100.into());
+        assert!(!trade.executions[0].hidden);
+        // refreshed tranche lost priority to the order behind it
+        assert_eq!(trade.executions[1].order_id, Oid::new(2));
+        assert_eq!(trade.executions[1].volume, 50.into());
+        assert!(!trade.executions[1].hidden);
+        assert_eq!(trade.executions[2].order_id, Oid::new(1));
+        assert_eq!(trade.executions[2].volume, 50.into());
+        assert!(trade.executions[2].hidden);
+
+        // remaining 50 of the second tranche is visible, last 50 is hidden
+        assert_eq!(order_book.get_best_sell_volume(), Some(50.into()));
+        let resting = order_book.orders.get_mut(&Oid::new(1)).unwrap();
+        assert_eq!(resting.remaining_volume(), 100.into());
+        assert_eq!(resting.hidden_volume, 50.into());
+    }
+
+    #[test]
+    fn test_post_only_order_crossing_the_spread() {
+        let mut order_book = OrderBook::default();
+        let order = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            21.0.into(),
+            100.into(),
+        );
+        order_book.execute(&order).unwrap();
+
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Buy,
+            Timestamp::new(2),
+            21.0.into(),
+            100.into(),
+        )
+        .with_post_only(PostOnly::Reject);
+        assert_eq!(
+            order_book.execute(&order).unwrap_err(),
+            OrderBookError::PostOnlyWouldCross(Oid::new(2))
+        );
+        assert_eq!(order_book.get_best_sell_volume(), Some(100.into()));
+        assert!(order_book.get_best_buy().is_none());
+
+        let order = order.with_post_only(PostOnly::Slide {
+            tick_size: 0.5.into(),
+        });
+        let trade = order_book.execute(&order).unwrap();
+        assert!(trade.executions.is_empty());
+        assert_eq!(order_book.get_best_buy(), Some(20.5.into()));
+        assert_eq!(order_book.get_best_sell_volume(), Some(100.into()));
+    }
+
+    #[test]
+    fn test_amend_order_priority() {
+        let mut order_book = OrderBook::default();
+        for id in 1..=2 {
+            let order = Order::new_limit(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                21.0.into(),
+                100.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+
+        // reduce keeps priority
+        let report = order_book
+            .amend_order(Oid::new(1), 21.0.into(), 60.into())
+            .unwrap();
+        assert_eq!(report.status, AmendStatus::Reduced);
+        assert_eq!(order_book.get_best_sell_volume(), Some(160.into()));
+        let order = Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(3), 10.into());
+        let trade = order_book.execute(&order).unwrap();
+        assert_eq!(trade.executions[0].order_id, Oid::new(1));
+
+        // increase looses priority
+        let report = order_book
+            .amend_order(Oid::new(1), 21.0.into(), 200.into())
+            .unwrap();
+        assert_eq!(report.status, AmendStatus::Replaced);
+        assert_eq!(order_book.get_best_sell_volume(), Some(290.into()));
+        let order = Order::new_market(Oid::new(4), OrderSide::Buy, Timestamp::new(4), 10.into());
+        let trade = order_book.execute(&order).unwrap();
+        assert_eq!(trade.executions[0].order_id, Oid::new(2));
+
+        // price change moves the order to the new level
+        let report = order_book
+            .amend_order(Oid::new(2), 20.0.into(), 90.into())
+            .unwrap();
+        assert_eq!(report.status, AmendStatus::Replaced);
+        assert_eq!(order_book.get_best_sell(), Some(20.0.into()));
+        assert_eq!(order_book.get_best_sell_volume(), Some(80.into()));
+        assert_eq!(
+            order_book.get_volume_at_limit(21.0.into(), OrderSide::Sell),
+            Some(190.into())
+        );
+
+        assert_eq!(
+            order_book
+                .amend_order(Oid::new(2), 20.0.into(), 10.into())
+                .unwrap_err(),
+            AmendOrderError::VolumeNotAboveFilled(Oid::new(2)).into()
+        );
+
+        // replaced order takes the fresh time, reduced order keeps its time
+        let report = order_book
+            .amend_order_at(Oid::new(2), 20.0.into(), 95.into(), Timestamp::new(10))
+            .unwrap();
+        assert_eq!(report.status, AmendStatus::Replaced);
+        assert_eq!(report.timestamp, Timestamp::new(10));
+        let report = order_book
+            .amend_order_at(Oid::new(2), 20.0.into(), 50.into(), Timestamp::new(11))
+            .unwrap();
+        assert_eq!(report.status, AmendStatus::Reduced);
+        assert_eq!(report.timestamp, Timestamp::new(10));
+        assert_eq!(
+            order_book
+                .iter_orders(OrderSide::Sell)
+                .map(|order| order.timestamp)
+                .next(),
+            Some(Timestamp::new(10))
+        );
+    }
+
+    #[test]
+    fn test_replaced_order_takes_fresh_time() {
+        let mut order_book = OrderBook::default();
+        for (id, price) in [(1, 21.0), (2, 21.0), (3, 22.0)] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id * 10),
+                price.into(),
+                100.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+
+        // replaced order ranks behind the order that arrived later at the same price
+        let report = order_book
+            .amend_order(Oid::new(1), 21.0.into(), 150.into())
+            .unwrap();
+        assert_eq!(report.status, AmendStatus::Replaced);
+        assert_eq!(report.timestamp, Timestamp::new(30));
+        let queue = order_book
+            .iter_orders(OrderSide::Sell)
+            .take(2)
+            .map(|order| (order.id, order.timestamp))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            queue,
+            [
+                (Oid::new(2), Timestamp::new(20)),
+                (Oid::new(1), Timestamp::new(30))
+            ]
+        );
+
+        // with a clock the replaced order takes the time of the clock
+        order_book.set_clock(ManualClock::new(Timestamp::new(100)));
+        let report = order_book
+            .amend_order(Oid::new(2), 21.5.into(), 100.into())
+            .unwrap();
+        assert_eq!(report.timestamp, Timestamp::new(100));
+        let report = order_book
+            .amend_order(Oid::new(3), 21.5.into(), 100.into())
+            .unwrap();
+        assert_eq!(report.timestamp, Timestamp::new(100));
+    }
+
+    #[test]
+    fn test_rejected_amend_keeps_the_order() {
+        let mut order_book = OrderBook::default();
+        for id in 1..=2 {
+            let order = Order::new_limit(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                20.0.into(),
+                100.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+
+        // passive orders can still be amended while matching is halted
+        order_book.halt(Timestamp::new(3)).unwrap();
+        let report = order_book
+            .amend_order(Oid::new(1), 20.0.into(), 150.into())
+            .unwrap();
+        assert_eq!(report.status, AmendStatus::Replaced);
+        assert_eq!(order_book.get_best_buy_volume(), Some(250.into()));
+
+        order_book.close(Timestamp::new(4)).unwrap();
+        for (price, volume) in [(20.0, 200), (19.0, 150), (20.0, 50)] {
+            assert_eq!(
+                order_book
+                    .amend_order(Oid::new(1), price.into(), volume.into())
+                    .unwrap_err(),
+                OrderBookError::InvalidState(TradingState::Closed)
+            );
+        }
+        assert_eq!(order_book.order_count(), 2);
+        assert_eq!(
+            order_book.get_order(Oid::new(1)).unwrap().volume,
+            150.into()
+        );
+        assert_eq!(order_book.get_best_buy_volume(), Some(250.into()));
+    }
+
+    #[test]
+    fn test_price_bands() {
+        let mut order_book =
+            OrderBook::default().with_price_bands(PriceBands::fixed(20.0.into(), 30.0.into()));
+        let order = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            31.0.into(),
+            100.into(),
+        );
+        assert_eq!(
+            order_book.execute(&order).unwrap_err(),
+            OrderBookError::PriceOutOfBand(31.0.into())
+        );
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Sell,
+            Timestamp::new(2),
+            25.0.into(),
+            100.into(),
+        );
+        order_book.execute(&order).unwrap();
+
+        // reference price moved, so the resting order is now outside of the bands
+        order_book.set_price_bands(Some(PriceBands::percentage(20.0.into(), 10.0)));
+        let order = Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(3), 10.into());
+        let trade = order_book.execute(&order).unwrap();
+        assert!(trade.executions.is_empty());
+        assert_eq!(trade.cancelled_volume, 10.into());
+        assert_eq!(order_book.state(), TradingState::Halted);
+        assert_eq!(order_book.get_best_sell_volume(), Some(100.into()));
+    }
+
+    #[test]
+    fn test_instrument_spec_validation() {
+        let mut order_book = OrderBook::default().with_instrument_spec(InstrumentSpec::new(
+            0.05.into(),
+            10.into(),
+            20.into(),
+        ));
+        let order = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Buy,
+            Timestamp::new(1),
+            21.03.into(),
+            100.into(),
+        );
+        assert_eq!(
+            order_book.execute(&order).unwrap_err(),
+            OrderBookError::InvalidTickSize(21.03.into())
+        );
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Buy,
+            Timestamp::new(2),
+            21.05.into(),
+            105.into(),
+        );
+        assert_eq!(
+            order_book.execute(&order).unwrap_err(),
+            OrderBookError::InvalidLotSize(105.into())
+        );
+        let order = Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(3), 10.into());
+        assert_eq!(
+            order_book.execute(&order).unwrap_err(),
+            OrderBookError::VolumeBelowMinimum(10.into())
+        );
+        let order = Order::new_limit(
+            Oid::new(4),
+            OrderSide::Buy,
+            Timestamp::new(4),
+            21.05.into(),
+            110.into(),
+        );
+        order_book.execute(&order).unwrap();
+        assert_eq!(order_book.get_best_buy(), Some(21.05.into()));
+    }
+
+    #[test]
+    fn test_level_pool_recycles_order_queues() {
+        let mut order_book = OrderBook::default().with_level_pool(2);
+        for id in 1..=3 {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                Price::new(10.0 + id as f64),
+                10.into(),
+            );
+            order_book.add_order(order).unwrap();
+            order_book.cancel_order(Oid::new(id)).unwrap();
+        }
+        assert_eq!(order_book.compact(), 3);
+        // pool is bounded
+        assert_eq!(order_book.bids.levels.pool.len(), 2);
+
+        let order = LimitOrder::new(
+            Oid::new(4),
+            OrderSide::Buy,
+            Timestamp::new(4),
+            Price::new(20.0),
+            10.into(),
+        );
+        order_book.add_order(order.clone()).unwrap();
+        assert_eq!(order_book.bids.levels.pool.len(), 1);
+        assert_eq!(order_book.get_best_buy_volume(), Some(10.into()));
+        assert!(order_book.check_integrity().is_ok());
+
+        // without the pool compacted queues are dropped
+        let mut order_book = OrderBook::default();
+        order_book.add_order(order).unwrap();
+        order_book.cancel_order(Oid::new(4)).unwrap();
+        order_book.compact();
+        assert!(order_book.bids.levels.pool.is_empty());
+    }
+
+    #[test]
+    fn test_compact_reuses_level_slots() {
+        let mut order_book = OrderBook::default();
+        for id in 1..=3 {
+            let price = Price::new(10.0 + id as f64);
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                price,
+                10.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        for id in 1..=2 {
+            order_book.cancel_order(Oid::new(id)).unwrap();
+        }
+        assert_eq!(order_book.compact(), 2);
+        assert_eq!(order_book.bids.levels.num_elements(), 1);
+
+        // new levels take the released slots
+        for id in 4..=5 {
+            let price = Price::new(20.0 + id as f64);
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                price,
+                10.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        assert_eq!(order_book.bids.levels.next_push_index(), 3);
+        assert_eq!(order_book.get_best_buy(), Some(Price::new(25.0)));
+        assert_eq!(
+            order_book.get_volume_at_limit(Price::new(11.0), OrderSide::Buy),
+            None
+        );
+        assert_eq!(
+            order_book.get_volume_at_limit(Price::new(13.0), OrderSide::Buy),
+            Some(10.into())
+        );
+    }
+
+    #[test]
+    fn test_book_statistics() {
+        let mut order_book = OrderBook::default();
+        assert!(order_book.is_empty());
+        assert_eq!(order_book.worst_price(OrderSide::Buy), None);
+        let orders = [
+            (1, OrderSide::Buy, 10.0, 5),
+            (2, OrderSide::Buy, 10.0, 5),
+            (3, OrderSide::Buy, 9.0, 5),
+            (4, OrderSide::Sell, 11.0, 7),
+        ];
+        for (id, side, price, volume) in orders {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        assert!(!order_book.is_empty());
+        assert_eq!(order_book.order_count(), 4);
+        assert_eq!(order_book.level_count(OrderSide::Buy), 2);
+        assert_eq!(order_book.level_count(OrderSide::Sell), 1);
+        assert_eq!(order_book.total_volume(OrderSide::Buy), 15.into());
+        assert_eq!(
+            order_book.worst_price(OrderSide::Buy),
+            Some(Price::new(9.0))
+        );
+        assert_eq!(
+            order_book.worst_price(OrderSide::Sell),
+            Some(Price::new(11.0))
+        );
+    }
+
+    #[test]
+    fn test_add_orders_batch() {
+        let mut order_book = OrderBook::default();
+        let orders = vec![
+            Order::new_limit(
+                Oid::new(1),
+                OrderSide::Buy,
+                Timestamp::new(1),
+                10.0.into(),
+                5.into(),
+            ),
+            Order::new_market(Oid::new(2), OrderSide::Sell, Timestamp::new(2), 5.into()),
+            Order::new_limit(
+                Oid::new(3),
+                OrderSide::Buy,
+                Timestamp::new(3),
+                11.0.into(),
+                2.into(),
+            ),
+            Order::new_limit(
+                Oid::new(4),
+                OrderSide::Sell,
+                Timestamp::new(4),
+                12.0.into(),
+                3.into(),
+            ),
+        ];
+        let results = order_book.add_orders(orders);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert_eq!(order_book.order_count(), 3);
+        assert_eq!(order_book.get_best_buy(), Some(Price::new(11.0)));
+        assert_eq!(order_book.best_bid_ask().ask_volume, 3.into());
+        assert_eq!(order_book.spread, Some(Spread(1.0.into())));
+    }
+
+    #[test]
+    fn test_corrupted_book_returns_error() {
+        let mut order_book = OrderBook::default();
+        let order = LimitOrder::new(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            10.0.into(),
+            5.into(),
+        );
+        order_book.add_order(order).unwrap();
+        // order is removed from the slab but stays queued at its level
+        order_book.orders.remove(&Oid::new(1));
+
+        let order = Order::new_market(Oid::new(2), OrderSide::Buy, Timestamp::new(2), 1.into());
+        assert_eq!(
+            order_book.execute(&order).unwrap_err(),
+            OrderBookError::Corrupted(CorruptionKind::QueuedOrderMissing {
+                side: OrderSide::Sell,
+                price: 10.0.into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_level_volume_overflow_is_rejected() {
+        let mut order_book = OrderBook::default();
+        let order = |id: u64, volume: Volume| {
+            LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                10.0.into(),
+                volume,
+            )
+        };
+        let big = Volume::from_mantissa(u64::MAX - 1);
+        order_book.add_order(order(1, big)).unwrap();
+        assert_eq!(
+            order_book.add_order(order(2, Volume::from_mantissa(2))),
+            Err(OrderBookError::ArithmeticOverflow)
+        );
+        assert_eq!(order_book.order_count(), 1);
+        assert_eq!(order_book.get_best_buy_volume(), Some(big));
+
+        assert_eq!(big.checked_add(Volume::from_mantissa(2)), None);
+        assert_eq!(Volume::ZERO.checked_sub(big), None);
+        assert_eq!(Volume::ZERO.saturating_sub(big), Volume::ZERO);
+        assert_eq!(
+            Price::new(1.0).checked_sub(Price::new(2.0)),
+            Some(Price::new(-1.0))
+        );
+    }
+
+    #[test]
+    fn test_match_all_uncrosses_the_book() {
+        let mut order_book = OrderBook::default();
+        let orders = [
+            (1, OrderSide::Buy, 12.0, 3),
+            (2, OrderSide::Buy, 11.0, 4),
+            (3, OrderSide::Buy, 9.0, 5),
+            (4, OrderSide::Sell, 10.0, 2),
+            (5, OrderSide::Sell, 10.0, 4),
+            (6, OrderSide::Sell, 13.0, 1),
+        ];
+        for (id, side, price, volume) in orders {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+
+        let fills = order_book.match_all();
+        assert_eq!(
+            fills
+                .iter()
+                .map(|f| (
+                    u64::from(f.buy_order_id),
+                    u64::from(f.sell_order_id),
+                    f.volume
+                ))
+                .collect::<Vec<_>>(),
+            vec![(1, 4, 2.into()), (1, 5, 1.into()), (2, 5, 3.into())]
+        );
+        assert_eq!(order_book.get_best_buy(), Some(Price::new(11.0)));
+        assert_eq!(order_book.get_best_buy_volume(), Some(1.into()));
+        assert_eq!(order_book.get_best_sell(), Some(Price::new(13.0)));
+        assert!(order_book.match_all().is_empty());
+    }
+
+    #[test]
+    fn test_match_all_into_sink() {
+        let mut order_book = OrderBook::default();
+        let mut fills = Vec::with_capacity(4);
+        let buffer = fills.as_ptr();
+        for round in 0..2 {
+            for (id, side, price) in [(1, OrderSide::Buy, 11.0), (2, OrderSide::Sell, 10.0)] {
+                let order = LimitOrder::new(
+                    Oid::new(round * 10 + id),
+                    side,
+                    Timestamp::new(id),
+                    price.into(),
+                    5.into(),
+                );
+                order_book.add_order(order).unwrap();
+            }
+            fills.clear();
+            assert_eq!(order_book.match_all_into(|fill| fills.push(fill)), 1);
+            assert_eq!(fills[0].buy_order_id, Oid::new(round * 10 + 1));
+            assert_eq!(fills[0].volume, 5.into());
+        }
+        // buffer is reused across the calls
+        assert_eq!(fills.as_ptr(), buffer);
+        assert_eq!(order_book.match_all_into(|_| unreachable!()), 0);
+    }
+
+    #[test]
+    fn test_market_to_limit_order() {
+        let mut order_book = OrderBook::default();
+        for (id, price) in [(1, 10.0), (2, 11.0)] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+
+        let order =
+            Order::new_market_to_limit(Oid::new(3), OrderSide::Buy, Timestamp::new(3), 8.into());
+        let trade = order_book.execute(&order).unwrap();
+        // only the best level is taken, the remainder rests at its price
+        assert_eq!(trade.filled_volume, 5.into());
+        assert_eq!(trade.cancelled_volume, Volume::ZERO);
+        assert_eq!(order_book.get_best_buy(), Some(Price::new(10.0)));
+        assert_eq!(order_book.get_best_buy_volume(), Some(3.into()));
+        assert_eq!(order_book.get_best_sell(), Some(Price::new(11.0)));
+
+        // nothing to execute against on the opposite side
+        let order =
+            Order::new_market_to_limit(Oid::new(4), OrderSide::Sell, Timestamp::new(4), 1.into());
+        order_book.cancel_order(Oid::new(3)).unwrap();
+        let trade = order_book.execute(&order).unwrap();
+        assert_eq!(trade.cancelled_volume, 1.into());
+        assert_eq!(order_book.order_count(), 1);
+
+        // checked like a market order even without liquidity
+        let order =
+            Order::new_market_to_limit(Oid::new(5), OrderSide::Sell, Timestamp::new(5), 5.into());
+        let spec = InstrumentSpec::new(Price::new(0.01), 10.into(), 10.into());
+        let mut lot_book = OrderBook::default().with_instrument_spec(spec);
+        assert!(lot_book.execute(&order).is_err());
+        order_book.kill_switch();
+        assert_eq!(
+            order_book.execute(&order).unwrap_err(),
+            OrderBookError::InvalidState(TradingState::Suspended)
+        );
+    }
+
+    #[test]
+    fn test_min_qty_orders_are_skipped() {
+        let mut order_book = OrderBook::default();
+        let sell = |id: u64, price: f64, volume: u64| {
+            Order::new_limit(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            )
+        };
+        for order in [
+            sell(1, 10.0, 10).with_all_or_none(),
+            sell(2, 10.0, 5),
+            sell(3, 11.0, 5),
+            sell(4, 12.0, 8).with_min_qty(3.into()),
+        ] {
+            order_book.execute(&order).unwrap();
+        }
+        let buy = |id: u64, price: f64, volume: u64| {
+            Order::new_limit(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            )
+            .with_time_in_force(TimeInForce::ImmediateOrCancel)
+        };
+
+        // all or none order is skipped, the orders behind it and at the next level are filled
+        let trade = order_book.execute(&buy(5, 11.0, 6)).unwrap();
+        assert_eq!(
+            trade
+                .executions
+                .iter()
+                .map(|e| (e.order_id, e.volume))
+                .collect::<Vec<_>>(),
+            vec![(Oid::new(2), 5.into()), (Oid::new(3), 1.into())]
+        );
+        // skipped order keeps its priority and is filled once the volume is enough
+        let trade = order_book.execute(&buy(6, 10.0, 12)).unwrap();
+        assert_eq!(trade.executions.len(), 1);
+        assert_eq!(trade.executions[0].order_id, Oid::new(1));
+        assert_eq!(trade.executions[0].volume, 10.into());
+
+        order_book.execute(&buy(7, 12.0, 4)).unwrap();
+        let trade = order_book.execute(&buy(8, 12.0, 2)).unwrap();
+        assert_eq!(trade.filled_volume, Volume::ZERO);
+        order_book.execute(&buy(9, 12.0, 6)).unwrap();
+        assert_eq!(
+            order_book
+                .get_order(Oid::new(4))
+                .map(|o| o.remaining_volume),
+            Some(2.into())
+        );
+        let trade = order_book.execute(&buy(10, 12.0, 1)).unwrap();
+        assert_eq!(trade.filled_volume, Volume::ZERO);
+        // fill of the whole remaining volume is accepted below the minimum quantity
+        let trade = order_book.execute(&buy(11, 12.0, 2)).unwrap();
+        assert_eq!(trade.filled_volume, 2.into());
+        assert!(order_book.is_empty());
+
+        let iceberg = sell(12, 10.0, 10)
+            .with_display_volume(2.into())
+            .with_min_qty(2.into());
+        assert!(order_book.execute(&iceberg).is_err());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans_and_events() {
+        use std::io::Write;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Output(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Output {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let mut order_book = OrderBook::default();
+            let sell = LimitOrder::new(
+                Oid::new(1),
+                OrderSide::Sell,
+                Timestamp::new(1),
+                10.0.into(),
+                5.into(),
+            );
+            order_book.add_order(sell).unwrap();
+            let buy = Order::new_market(Oid::new(2), OrderSide::Buy, Timestamp::new(2), 2.into());
+            order_book.execute(&buy).unwrap();
+            order_book.cancel_order(Oid::new(1)).unwrap();
+            assert!(order_book.cancel_order(Oid::new(1)).is_err());
+        });
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        let logged = |span: &str, message: &str| {
+            lines
+                .iter()
+                .any(|line| line.contains(span) && line.contains(message))
+        };
+        assert!(logged("add_order{order_id=1", "order event"));
+        assert!(logged("execute{order_id=2", "match resting_order_id=1"));
+        assert!(logged("cancel_order{order_id=1}", "order event"));
+        assert!(logged("cancel_order{order_id=1}", "order rejected"));
+    }
 }
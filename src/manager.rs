@@ -0,0 +1,513 @@
+//!
+//! Order books of many instruments.
+//! Orders are routed to the book of their symbol, order ids are unique across all books,
+//! so cancels and amends are routed by the order id alone. The manager remembers which
+//! participant submitted each resting order. Quotes of a participant are owned on the books
+//! by the owner with the same id. Metadata of the instrument of each book is kept in
+//! the instrument registry, the book validates orders by the spec of the instrument.
+//!
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use hashbrown::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    AmendReport, Bbo, CancelOrderError, CancellationReport, Instrument, InstrumentRegistry,
+    InstrumentSpec, Oid, Order, OrderBook, OrderBookError, OrderView, OwnerId, Price, Quote,
+    QuoteReport, Timestamp, Trade, Volume,
+};
+
+/// Instrument symbol
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(String);
+
+impl Symbol {
+    pub fn new(value: impl Into<String>) -> Self {
+        Symbol(value.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(value: &str) -> Self {
+        Symbol::new(value)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Participant that submitted the order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Participant(pub u64);
+
+/// Order book manager error
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum ManagerError {
+    #[error("Unknown symbol {0}")]
+    UnknownSymbol(Symbol),
+    #[error("Book for symbol {0} already exists")]
+    SymbolExists(Symbol),
+    #[error("Order {0} is already resting on a book")]
+    DuplicateOrderId(Oid),
+    /// Thread of the shard has stopped, i.e. after a panic
+    #[error("Shard {0} has stopped")]
+    ShardStopped(usize),
+    #[error("Order book error: {0}")]
+    OrderBookError(#[from] OrderBookError),
+}
+
+impl From<CancelOrderError> for ManagerError {
+    fn from(value: CancelOrderError) -> Self {
+        ManagerError::OrderBookError(value.into())
+    }
+}
+
+/// Owns the order books keyed by symbol
+#[derive(Debug, Default)]
+pub struct OrderBookManager {
+    books: BTreeMap<Symbol, OrderBook>,
+    instruments: InstrumentRegistry,
+    // symbol and participant of the orders resting on the books
+    owners: HashMap<Oid, (Symbol, Participant)>,
+}
+
+impl OrderBookManager {
+    pub fn new() -> Self {
+        OrderBookManager::default()
+    }
+
+    /// add the book of the symbol
+    pub fn add_book(&mut self, symbol: Symbol, book: OrderBook) -> Result<(), ManagerError> {
+        if self.books.contains_key(&symbol) {
+            return Err(ManagerError::SymbolExists(symbol));
+        }
+        self.books.insert(symbol, book);
+        Ok(())
+    }
+
+    /// add an empty book of the symbol trading by the instrument spec
+    pub fn add_symbol(&mut self, symbol: Symbol, spec: InstrumentSpec) -> Result<(), ManagerError> {
+        self.add_instrument(Instrument::new(symbol, spec))
+    }
+
+    /// register the instrument and add its empty book trading by the instrument spec
+    pub fn add_instrument(&mut self, instrument: Instrument) -> Result<(), ManagerError> {
+        let book = OrderBook::default()
+            .with_instrument_spec(instrument.spec)
+            .with_contract_multiplier(instrument.multiplier);
+        self.add_book(instrument.symbol.clone(), book)?;
+        self.instruments.register(instrument);
+        Ok(())
+    }
+
+    /// remove the book of the symbol together with its resting orders and instrument
+    pub fn remove_book(&mut self, symbol: &Symbol) -> Option<OrderBook> {
+        let book = self.books.remove(symbol)?;
+        self.instruments.remove(symbol);
+        self.owners.retain(|_, (s, _)| s != symbol);
+        Some(book)
+    }
+
+    /// instrument of the book, books added with `add_book` have none
+    pub fn instrument(&self, symbol: &Symbol) -> Option<&Instrument> {
+        self.instruments.get(symbol)
+    }
+
+    pub fn instruments(&self) -> &InstrumentRegistry {
+        &self.instruments
+    }
+
+    /// notional of the price and volume given by the instrument of the symbol
+    pub fn notional(
+        &self,
+        symbol: &Symbol,
+        price: Price,
+        volume: Volume,
+    ) -> Result<Price, ManagerError> {
+        self.instruments
+            .get(symbol)
+            .map(|instrument| instrument.notional(price, volume))
+            .ok_or_else(|| ManagerError::UnknownSymbol(symbol.clone()))
+    }
+
+    /// price formatted with the precision of the instrument of the symbol
+    pub fn format_price(&self, symbol: &Symbol, price: Price) -> Result<String, ManagerError> {
+        self.instruments
+            .get(symbol)
+            .map(|instrument| instrument.format_price(price))
+            .ok_or_else(|| ManagerError::UnknownSymbol(symbol.clone()))
+    }
+
+    pub fn book(&self, symbol: &Symbol) -> Option<&OrderBook> {
+        self.books.get(symbol)
+    }
+
+    pub fn book_mut(&mut self, symbol: &Symbol) -> Option<&mut OrderBook> {
+        self.books.get_mut(symbol)
+    }
+
+    /// symbols in alphabetical order
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.books.keys()
+    }
+
+    pub fn instrument_spec(&self, symbol: &Symbol) -> Option<&InstrumentSpec> {
+        self.books.get(symbol)?.instrument_spec()
+    }
+
+    /// symbol of the resting order
+    pub fn symbol_of(&self, order_id: Oid) -> Option<&Symbol> {
+        self.owners.get(&order_id).map(|(symbol, _)| symbol)
+    }
+
+    /// execute the order on the book of the symbol
+    pub fn execute(
+        &mut self,
+        symbol: &Symbol,
+        participant: Participant,
+        order: &Order,
+    ) -> Result<Trade, ManagerError> {
+        if self.owners.contains_key(&order.id) {
+            return Err(ManagerError::DuplicateOrderId(order.id));
+        }
+        let book = self
+            .books
+            .get_mut(symbol)
+            .ok_or_else(|| ManagerError::UnknownSymbol(symbol.clone()))?;
+        let trade = book.execute(order)?;
+
+        // forget resting orders that have been filled
+        for execution in &trade.executions {
+            if book.orders.get(&execution.order_id).is_none() {
+                self.owners.remove(&execution.order_id);
+            }
+        }
+        if book.orders.get(&order.id).is_some() {
+            self.owners.insert(order.id, (symbol.clone(), participant));
+        }
+        Ok(trade)
+    }
+
+    /// replace the quotes of the participant on many books, returns the result of each quote
+    /// quotes are independent, rejected quote does not affect the others
+    pub fn mass_quote(
+        &mut self,
+        participant: Participant,
+        quotes: impl IntoIterator<Item = (Symbol, Quote, (Oid, Oid))>,
+        timestamp: Timestamp,
+    ) -> Vec<Result<QuoteReport, ManagerError>> {
+        quotes
+            .into_iter()
+            .map(|(symbol, quote, order_ids)| {
+                self.submit_quote(&symbol, participant, quote, order_ids, timestamp)
+            })
+            .collect()
+    }
+
+    /// replace the quote of the participant on the book of the symbol
+    pub fn submit_quote(
+        &mut self,
+        symbol: &Symbol,
+        participant: Participant,
+        quote: Quote,
+        order_ids: (Oid, Oid),
+        timestamp: Timestamp,
+    ) -> Result<QuoteReport, ManagerError> {
+        let book = self
+            .books
+            .get_mut(symbol)
+            .ok_or_else(|| ManagerError::UnknownSymbol(symbol.clone()))?;
+        let owner = OwnerId::new(participant.0);
+        // ids of the replaced quote can be reused
+        let replaced = book.quote_orders(owner);
+        for order_id in [order_ids.0, order_ids.1] {
+            if self.owners.contains_key(&order_id) && !replaced.contains(&order_id) {
+                return Err(ManagerError::DuplicateOrderId(order_id));
+            }
+        }
+        let report = book.submit_quote(owner, quote, order_ids, timestamp)?;
+
+        for cancelled in &report.cancelled {
+            self.owners.remove(&cancelled.order_id());
+        }
+        for trade in &report.trades {
+            for execution in &trade.executions {
+                if book.orders.get(&execution.order_id).is_none() {
+                    self.owners.remove(&execution.order_id);
+                }
+            }
+            if book.orders.get(&trade.order_id).is_some() {
+                self.owners
+                    .insert(trade.order_id, (symbol.clone(), participant));
+            }
+        }
+        Ok(report)
+    }
+
+    /// cancel the resting order on whichever book it rests
+    pub fn cancel_order(&mut self, order_id: Oid) -> Result<CancellationReport, ManagerError> {
+        let (symbol, _) = self
+            .owners
+            .get(&order_id)
+            .ok_or(CancelOrderError::NotFound(order_id))?;
+        let book = self
+            .books
+            .get_mut(symbol)
+            .ok_or_else(|| ManagerError::UnknownSymbol(symbol.clone()))?;
+        let report = book.cancel_order(order_id)?;
+        self.owners.remove(&order_id);
+        Ok(report)
+    }
+
+    /// amend the resting order on whichever book it rests
+    pub fn amend_order(
+        &mut self,
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+    ) -> Result<AmendReport, ManagerError> {
+        let (symbol, _) = self
+            .owners
+            .get(&order_id)
+            .ok_or(CancelOrderError::NotFound(order_id))?;
+        let book = self
+            .books
+            .get_mut(symbol)
+            .ok_or_else(|| ManagerError::UnknownSymbol(symbol.clone()))?;
+        Ok(book.amend_order(order_id, price, volume)?)
+    }
+
+    /// remove expired orders from all books
+    pub fn purge_expired(&mut self, now: Timestamp) -> Vec<(Symbol, CancellationReport)> {
+        let mut reports = Vec::new();
+        for (symbol, book) in self.books.iter_mut() {
+            for report in book.purge_expired(now) {
+                self.owners.remove(&report.order_id());
+                reports.push((symbol.clone(), report));
+            }
+        }
+        reports
+    }
+
+    /// best bid and ask of every book
+    pub fn best_bid_asks(&self) -> Vec<(&Symbol, Bbo)> {
+        self.books
+            .iter()
+            .map(|(symbol, book)| (symbol, book.best_bid_ask()))
+            .collect()
+    }
+
+    /// status of every resting order of the participant
+    pub fn open_orders(&self, participant: Participant) -> Vec<(&Symbol, OrderView)> {
+        let mut orders = self
+            .owners
+            .iter()
+            .filter(|(_, (_, p))| *p == participant)
+            .filter_map(|(id, (symbol, _))| {
+                let view = self.books.get(symbol)?.get_order(*id)?;
+                Some((symbol, view))
+            })
+            .collect::<Vec<_>>();
+        orders.sort_by_key(|(symbol, view)| (*symbol, u64::from(view.id)));
+        orders
+    }
+}
+
+#[cfg(test)]
+mod tests_manager {
+    use crate::*;
+
+    fn limit(id: u64, side: OrderSide, price: f64, volume: u64) -> Order {
+        Order::new_limit(
+            Oid::new(id),
+            side,
+            Timestamp::new(id),
+            price.into(),
+            volume.into(),
+        )
+    }
+
+    #[test]
+    fn test_route_orders_by_symbol() {
+        let (aapl, msft) = (Symbol::from("AAPL"), Symbol::from("MSFT"));
+        let (alice, bob) = (Participant(1), Participant(2));
+        let mut manager = OrderBookManager::new();
+        manager
+            .add_symbol(
+                aapl.clone(),
+                InstrumentSpec::new(0.01.into(), 1.into(), 1.into()),
+            )
+            .unwrap();
+        manager
+            .add_book(msft.clone(), OrderBook::default())
+            .unwrap();
+        assert_eq!(
+            manager.add_book(msft.clone(), OrderBook::default()),
+            Err(ManagerError::SymbolExists(msft.clone()))
+        );
+
+        manager
+            .execute(&aapl, alice, &limit(1, OrderSide::Buy, 100.0, 10))
+            .unwrap();
+        manager
+            .execute(&msft, alice, &limit(2, OrderSide::Sell, 200.0, 5))
+            .unwrap();
+        manager
+            .execute(&msft, bob, &limit(3, OrderSide::Buy, 199.0, 5))
+            .unwrap();
+        assert_eq!(
+            manager
+                .execute(&aapl, bob, &limit(2, OrderSide::Buy, 100.0, 1))
+                .unwrap_err(),
+            ManagerError::DuplicateOrderId(Oid::new(2))
+        );
+        assert!(matches!(
+            manager.execute(&aapl, bob, &limit(4, OrderSide::Buy, 100.001, 1)),
+            Err(ManagerError::OrderBookError(
+                OrderBookError::InvalidTickSize(_)
+            ))
+        ));
+
+        let bbos = manager.best_bid_asks();
+        assert_eq!(bbos[0].0, &aapl);
+        assert_eq!(bbos[0].1.bid_price, Some(Price::new(100.0)));
+        assert_eq!(bbos[1].1.ask_price, Some(Price::new(200.0)));
+
+        let open = manager.open_orders(alice);
+        assert_eq!(
+            open.iter()
+                .map(|(s, v)| (s.as_str(), v.id))
+                .collect::<Vec<_>>(),
+            vec![("AAPL", Oid::new(1)), ("MSFT", Oid::new(2))]
+        );
+
+        // bob fills alice's sell, cancel and amend are routed by order id
+        manager
+            .execute(&msft, bob, &limit(5, OrderSide::Buy, 200.0, 5))
+            .unwrap();
+        manager
+            .amend_order(Oid::new(1), 100.0.into(), 4.into())
+            .unwrap();
+        assert_eq!(manager.open_orders(alice)[0].1.volume, 4.into());
+        assert_eq!(manager.open_orders(alice).len(), 1);
+        manager.cancel_order(Oid::new(3)).unwrap();
+        assert!(manager.open_orders(bob).is_empty());
+        assert_eq!(manager.symbol_of(Oid::new(1)), Some(&aapl));
+    }
+
+    #[test]
+    fn test_instruments_drive_validation_and_formatting() {
+        let es = Symbol::from("ES");
+        let mut manager = OrderBookManager::new();
+        let instrument = Instrument::new(
+            es.clone(),
+            InstrumentSpec::new(0.25.into(), 1.into(), 1.into()),
+        )
+        .with_price_precision(2)
+        .with_currency("USD")
+        .with_multiplier(50);
+        manager.add_instrument(instrument.clone()).unwrap();
+        assert_eq!(
+            manager.add_instrument(instrument),
+            Err(ManagerError::SymbolExists(es.clone()))
+        );
+        assert_eq!(
+            manager.instrument(&es).map(|i| i.currency.as_str()),
+            Some("USD")
+        );
+
+        assert!(matches!(
+            manager.execute(&es, Participant(1), &limit(1, OrderSide::Buy, 4_500.1, 1)),
+            Err(ManagerError::OrderBookError(
+                OrderBookError::InvalidTickSize(_)
+            ))
+        ));
+        assert_eq!(
+            manager.notional(&es, 4_500.0.into(), 2.into()),
+            Ok(Price::new(450_000.0))
+        );
+        assert_eq!(
+            manager.format_price(&es, 4_500.0.into()).unwrap(),
+            "4500.00"
+        );
+
+        manager.remove_book(&es).unwrap();
+        assert!(manager.instruments().is_empty());
+        assert_eq!(
+            manager.notional(&es, 1.0.into(), 1.into()),
+            Err(ManagerError::UnknownSymbol(es))
+        );
+    }
+
+    #[test]
+    fn test_mass_quote() {
+        let (aapl, msft) = (Symbol::from("AAPL"), Symbol::from("MSFT"));
+        let maker = Participant(1);
+        let mut manager = OrderBookManager::new();
+        for symbol in [&aapl, &msft] {
+            manager
+                .add_book(symbol.clone(), OrderBook::default())
+                .unwrap();
+        }
+        let quote = |bid: f64, ask: f64| Quote::new(bid.into(), 10.into(), ask.into(), 10.into());
+
+        let results = manager.mass_quote(
+            maker,
+            [
+                (aapl.clone(), quote(99.0, 101.0), (Oid::new(1), Oid::new(2))),
+                (
+                    msft.clone(),
+                    quote(199.0, 201.0),
+                    (Oid::new(3), Oid::new(4)),
+                ),
+                (
+                    Symbol::from("IBM"),
+                    quote(1.0, 2.0),
+                    (Oid::new(5), Oid::new(6)),
+                ),
+            ],
+            Timestamp::new(1),
+        );
+        assert!(results[0].is_ok() && results[1].is_ok());
+        assert_eq!(
+            results[2].as_ref().unwrap_err(),
+            &ManagerError::UnknownSymbol(Symbol::from("IBM"))
+        );
+        assert_eq!(manager.open_orders(maker).len(), 4);
+
+        // ids of the replaced quote can be reused, ids of other orders cannot
+        let results = manager.mass_quote(
+            maker,
+            [
+                (aapl.clone(), quote(99.5, 100.5), (Oid::new(2), Oid::new(1))),
+                (
+                    msft.clone(),
+                    quote(198.0, 202.0),
+                    (Oid::new(1), Oid::new(7)),
+                ),
+            ],
+            Timestamp::new(2),
+        );
+        assert_eq!(results[0].as_ref().unwrap().cancelled.len(), 2);
+        assert_eq!(
+            results[1].as_ref().unwrap_err(),
+            &ManagerError::DuplicateOrderId(Oid::new(1))
+        );
+        let aapl_bbo = manager.book(&aapl).unwrap().best_bid_ask();
+        assert_eq!(aapl_bbo.bid_price, Some(Price::new(99.5)));
+        assert_eq!(manager.symbol_of(Oid::new(3)), Some(&msft));
+        assert_eq!(manager.open_orders(maker).len(), 4);
+    }
+}
@@ -0,0 +1,131 @@
+//!
+//! Mass cancellation. Orders of an owner, of a whole side or of a price range are removed
+//! in one pass, level volumes and best limits are updated once for all of them.
+//!
+
+use alloc::vec::Vec;
+
+use crate::{
+    BookEvent, CancellationReport, CancellationStatus, LevelStore, OrderBook, OrderSide, OwnerId,
+    Price,
+};
+
+impl<S: LevelStore> OrderBook<S> {
+    /// cancel all resting orders of the owner, reports are ordered by order id
+    pub fn cancel_all(&mut self, owner: OwnerId) -> Vec<CancellationReport> {
+        self.logged(
+            || BookEvent::CancelAll(owner),
+            |book| {
+                let mut order_ids = book
+                    .orders
+                    .owned_by(owner)
+                    .map(|order| order.id)
+                    .collect::<Vec<_>>();
+                order_ids.sort_by_key(|id| u64::from(*id));
+                book.remove_orders(order_ids, CancellationStatus::Cancelled)
+            },
+        )
+    }
+
+    /// cancel all resting orders of the side, reports are in price-time priority
+    pub fn cancel_side(&mut self, side: OrderSide) -> Vec<CancellationReport> {
+        self.logged(
+            || BookEvent::CancelSide(side),
+            |book| {
+                let order_ids = book
+                    .iter_orders(side)
+                    .map(|order| order.id)
+                    .collect::<Vec<_>>();
+                book.remove_orders(order_ids, CancellationStatus::Cancelled)
+            },
+        )
+    }
+
+    /// cancel resting orders of the side priced between the two prices, both inclusive,
+    /// reports are in price-time priority
+    pub fn cancel_range(
+        &mut self,
+        side: OrderSide,
+        from_price: Price,
+        to_price: Price,
+    ) -> Vec<CancellationReport> {
+        self.logged(
+            || BookEvent::CancelRange {
+                side,
+                from_price,
+                to_price,
+            },
+            |book| {
+                let (low, high) = (from_price.min(to_price), from_price.max(to_price));
+                let order_ids = book
+                    .iter_levels(side)
+                    .filter(|level| (low..=high).contains(&level.price))
+                    .flat_map(|level| level.orders.iter())
+                    .filter_map(|handle| book.orders.get_by_handle(handle))
+                    .map(|order| order.id)
+                    .collect::<Vec<_>>();
+                book.remove_orders(order_ids, CancellationStatus::Cancelled)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_mass_cancel {
+    use crate::*;
+
+    fn order_book() -> OrderBook {
+        let mut order_book = OrderBook::default();
+        for (id, owner, side, price) in [
+            (1, 1, OrderSide::Buy, 10.0),
+            (2, 2, OrderSide::Buy, 9.0),
+            (3, 1, OrderSide::Buy, 8.0),
+            (4, 1, OrderSide::Sell, 11.0),
+            (5, 2, OrderSide::Sell, 12.0),
+            (6, 2, OrderSide::Buy, 10.0),
+        ] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            )
+            .with_owner(OwnerId::new(owner));
+            order_book.execute(&order).unwrap();
+        }
+        order_book
+    }
+
+    fn ids(reports: &[CancellationReport]) -> Vec<u64> {
+        reports.iter().map(|r| u64::from(r.order_id())).collect()
+    }
+
+    #[test]
+    fn test_mass_cancel() {
+        let mut book = order_book();
+        assert_eq!(ids(&book.cancel_all(OwnerId::new(1))), vec![1, 3, 4]);
+        assert_eq!(book.order_count(), 3);
+        assert_eq!(book.get_best_buy_volume(), Some(5.into()));
+        assert_eq!(book.get_best_sell(), Some(Price::new(12.0)));
+        assert_eq!(
+            book.get_order(Oid::new(1)).map(|o| o.state),
+            Some(OrderState::Cancelled)
+        );
+
+        let mut book = order_book();
+        assert_eq!(ids(&book.cancel_side(OrderSide::Buy)), vec![1, 6, 2, 3]);
+        assert_eq!(book.get_best_buy(), None);
+        assert_eq!(book.level_count(OrderSide::Buy), 0);
+        assert_eq!(book.order_count(), 2);
+
+        let mut book = order_book();
+        let reports = book.cancel_range(OrderSide::Buy, 9.0.into(), 10.0.into());
+        assert_eq!(ids(&reports), vec![1, 6, 2]);
+        assert_eq!(book.best_bid_ask().bid_price, Some(Price::new(8.0)));
+        assert!(book
+            .cancel_range(OrderSide::Sell, 13.0.into(), 20.0.into())
+            .is_empty());
+        assert!(book.check_integrity().is_ok());
+    }
+}
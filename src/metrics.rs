@@ -0,0 +1,474 @@
+//!
+//! Latency histograms of the book operations.
+//! Values are counted in log-linear buckets like an HDR histogram, each power of two range
+//! is split into 64 buckets, so quantiles are reported within 1.6% of the recorded value
+//! over the whole u64 range with a fixed amount of memory.
+//! With the `metrics` feature the book records the time spent in add, cancel and match
+//! operations once enabled with `with_latency_metrics`.
+//! Counters of the order events and gauges of the book can also be exported through the
+//! `metrics` facade with `with_exported_metrics`, to be scraped by any installed recorder,
+//! i.e. the Prometheus exporter. Nothing is emitted until a recorder is installed.
+//!
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "metrics")]
+use alloc::string::String;
+
+#[cfg(feature = "metrics")]
+use ::metrics::{counter, gauge, Counter, Gauge};
+
+#[cfg(feature = "metrics")]
+use crate::{AuditEvent, OrderBookError, OrderSide, Symbol};
+use crate::{LevelStore, OrderBook};
+
+// values below 2^SUB_BUCKET_BITS have a bucket each
+const SUB_BUCKET_BITS: u32 = 7;
+const SUB_BUCKET_HALF: usize = 1 << (SUB_BUCKET_BITS - 1);
+const BUCKETS: usize = (64 - SUB_BUCKET_BITS as usize + 1) * SUB_BUCKET_HALF + SUB_BUCKET_HALF;
+
+/// Histogram of latencies in nanoseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    min: u64,
+    max: u64,
+    sum: u128,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram {
+            counts: vec![0; BUCKETS],
+            count: 0,
+            min: u64::MAX,
+            max: 0,
+            sum: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        LatencyHistogram::default()
+    }
+
+    pub fn record(&mut self, value: u64) {
+        self.counts[bucket(value)] += 1;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += u128::from(value);
+    }
+
+    /// number of recorded values
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+
+    /// value at or below which the quantile of the recorded values are, quantile is from 0 to 1
+    /// highest value of the bucket is reported, capped by the maximum recorded value
+    pub fn value_at_quantile(&self, quantile: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        // rank of the value, rounded up
+        let target = quantile.clamp(0.0, 1.0) * self.count as f64;
+        let rank = (target as u64 + u64::from((target as u64 as f64) < target)).max(1);
+        let mut seen = 0;
+        let index = self.counts.iter().position(|count| {
+            seen += count;
+            seen >= rank
+        })?;
+        Some(bucket_highest(index).min(self.max))
+    }
+
+    pub fn p50(&self) -> Option<u64> {
+        self.value_at_quantile(0.5)
+    }
+
+    pub fn p99(&self) -> Option<u64> {
+        self.value_at_quantile(0.99)
+    }
+
+    pub fn p999(&self) -> Option<u64> {
+        self.value_at_quantile(0.999)
+    }
+
+    /// add the values recorded by the other histogram
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+    }
+
+    pub fn reset(&mut self) {
+        *self = LatencyHistogram::default();
+    }
+}
+
+/// bucket of the value
+fn bucket(value: u64) -> usize {
+    if value < 1 << SUB_BUCKET_BITS {
+        return value as usize;
+    }
+    let shift = 63 - value.leading_zeros() - (SUB_BUCKET_BITS - 1);
+    ((shift as usize) << (SUB_BUCKET_BITS - 1)) + (value >> shift) as usize
+}
+
+/// highest value counted in the bucket
+fn bucket_highest(index: usize) -> u64 {
+    if index < 1 << SUB_BUCKET_BITS {
+        return index as u64;
+    }
+    let shift = index / SUB_BUCKET_HALF - 1;
+    let lowest = ((index % SUB_BUCKET_HALF + SUB_BUCKET_HALF) as u64) << shift;
+    lowest + ((1u64 << shift) - 1)
+}
+
+/// Operation of the book whose latency is recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyOperation {
+    /// limit order added without matching
+    Add,
+    Cancel,
+    /// order executed against the book
+    Match,
+}
+
+/// Latency histograms of the book operations
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyMetrics {
+    pub add: LatencyHistogram,
+    pub cancel: LatencyHistogram,
+    pub matching: LatencyHistogram,
+}
+
+impl LatencyMetrics {
+    pub fn histogram(&self, operation: LatencyOperation) -> &LatencyHistogram {
+        match operation {
+            LatencyOperation::Add => &self.add,
+            LatencyOperation::Cancel => &self.cancel,
+            LatencyOperation::Match => &self.matching,
+        }
+    }
+
+    pub fn record(&mut self, operation: LatencyOperation, nanos: u64) {
+        let histogram = match operation {
+            LatencyOperation::Add => &mut self.add,
+            LatencyOperation::Cancel => &mut self.cancel,
+            LatencyOperation::Match => &mut self.matching,
+        };
+        histogram.record(nanos);
+    }
+}
+
+/// Handles of the counters and gauges of one book, labelled with its symbol
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone)]
+pub(crate) struct ExportedMetrics {
+    symbol: String,
+    accepted: Counter,
+    cancelled: Counter,
+    expired: Counter,
+    amended: Counter,
+    fills: Counter,
+    open_orders: Gauge,
+    bid_levels: Gauge,
+    ask_levels: Gauge,
+    spread: Gauge,
+}
+
+#[cfg(feature = "metrics")]
+impl ExportedMetrics {
+    fn new(symbol: &Symbol) -> Self {
+        let symbol = String::from(symbol.as_str());
+        let levels = |side: &'static str| gauge!("lob_price_levels", "symbol" => symbol.clone(), "side" => side);
+        ExportedMetrics {
+            accepted: counter!("lob_orders_accepted_total", "symbol" => symbol.clone()),
+            cancelled: counter!("lob_orders_cancelled_total", "symbol" => symbol.clone()),
+            expired: counter!("lob_orders_expired_total", "symbol" => symbol.clone()),
+            amended: counter!("lob_orders_amended_total", "symbol" => symbol.clone()),
+            fills: counter!("lob_order_fills_total", "symbol" => symbol.clone()),
+            open_orders: gauge!("lob_open_orders", "symbol" => symbol.clone()),
+            bid_levels: levels("bid"),
+            ask_levels: levels("ask"),
+            spread: gauge!("lob_spread", "symbol" => symbol.clone()),
+            symbol,
+        }
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// record the latency of add, cancel and match operations
+    #[cfg(feature = "metrics")]
+    pub fn with_latency_metrics(mut self) -> Self {
+        self.latency_metrics = Some(LatencyMetrics::default());
+        self
+    }
+
+    /// latencies recorded since the metrics were enabled or reset
+    #[cfg(feature = "metrics")]
+    pub fn latency_metrics(&self) -> Option<&LatencyMetrics> {
+        self.latency_metrics.as_ref()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn reset_latency_metrics(&mut self) {
+        if let Some(metrics) = &mut self.latency_metrics {
+            *metrics = LatencyMetrics::default();
+        }
+    }
+
+    /// export counters of the order events and gauges of the book through the `metrics` facade,
+    /// labelled with the symbol, the handles are registered with the recorder installed now
+    #[cfg(feature = "metrics")]
+    pub fn with_exported_metrics(mut self, symbol: &Symbol) -> Self {
+        self.exported_metrics = Some(ExportedMetrics::new(symbol));
+        self.export_gauges();
+        self
+    }
+
+    /// count the event of the order
+    #[cfg(feature = "metrics")]
+    pub(crate) fn export_event(&self, event: &AuditEvent) {
+        let Some(metrics) = &self.exported_metrics else {
+            return;
+        };
+        let counter = match event {
+            AuditEvent::Accepted { .. } => &metrics.accepted,
+            AuditEvent::Filled { .. } => &metrics.fills,
+            AuditEvent::Amended { .. } => &metrics.amended,
+            AuditEvent::Cancelled => &metrics.cancelled,
+            AuditEvent::Expired => &metrics.expired,
+        };
+        counter.increment(1);
+    }
+
+    /// count the rejected order by its reject code, rejects are not on the hot path
+    /// so the counter is looked up on each reject
+    #[cfg(feature = "metrics")]
+    pub(crate) fn export_rejected(&self, error: &OrderBookError) {
+        if let Some(metrics) = &self.exported_metrics {
+            counter!(
+                "lob_orders_rejected_total",
+                "symbol" => metrics.symbol.clone(),
+                "reason" => error.reject_code().message()
+            )
+            .increment(1);
+        }
+    }
+
+    /// set the gauges of the book after a change
+    #[cfg(feature = "metrics")]
+    pub(crate) fn export_gauges(&self) {
+        let Some(metrics) = &self.exported_metrics else {
+            return;
+        };
+        metrics.open_orders.set(self.order_count() as f64);
+        metrics
+            .bid_levels
+            .set(self.level_count(OrderSide::Buy) as f64);
+        metrics
+            .ask_levels
+            .set(self.level_count(OrderSide::Sell) as f64);
+        // spread of a one sided book is not defined
+        metrics
+            .spread
+            .set(self.spread.map_or(f64::NAN, |spread| spread.0.to_f64()));
+    }
+
+    /// run the operation and record its latency
+    #[cfg(feature = "metrics")]
+    pub(crate) fn timed<T>(
+        &mut self,
+        operation: LatencyOperation,
+        apply: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        if self.latency_metrics.is_none() {
+            return apply(self);
+        }
+        let started = std::time::Instant::now();
+        let result = apply(self);
+        let elapsed = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        if let Some(metrics) = &mut self.latency_metrics {
+            metrics.record(operation, elapsed);
+        }
+        result
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    #[inline(always)]
+    pub(crate) fn timed<T>(
+        &mut self,
+        _operation: LatencyOperation,
+        apply: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        apply(self)
+    }
+}
+
+#[cfg(test)]
+mod tests_metrics {
+    use super::{bucket, bucket_highest, BUCKETS};
+    use crate::*;
+
+    #[test]
+    fn test_histogram_quantiles() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.p50(), None);
+        for value in 1..=10_000 {
+            histogram.record(value);
+        }
+        assert_eq!(histogram.count(), 10_000);
+        assert_eq!(histogram.min(), Some(1));
+        assert_eq!(histogram.max(), Some(10_000));
+        for (quantile, expected) in [(0.5, 5_000.0), (0.99, 9_900.0), (0.999, 9_990.0)] {
+            let value = histogram.value_at_quantile(quantile).unwrap() as f64;
+            assert!(
+                (value - expected).abs() / expected < 0.016,
+                "{quantile} {value}"
+            );
+        }
+        assert_eq!(histogram.value_at_quantile(1.0), Some(10_000));
+
+        // buckets cover the whole range without gaps
+        for index in 1..BUCKETS {
+            assert_eq!(bucket(bucket_highest(index - 1) + 1), index);
+        }
+        assert_eq!(bucket(u64::MAX), BUCKETS - 1);
+
+        let mut other = LatencyHistogram::new();
+        other.record(1_000_000);
+        histogram.merge(&other);
+        assert_eq!(histogram.max(), Some(1_000_000));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_book_records_latencies() {
+        let mut order_book = OrderBook::default().with_latency_metrics();
+        let order = LimitOrder::new(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            10.0.into(),
+            5.into(),
+        );
+        order_book.add_order(order).unwrap();
+        let buy = Order::new_market(Oid::new(2), OrderSide::Buy, Timestamp::new(2), 2.into());
+        order_book.execute(&buy).unwrap();
+        order_book.cancel_order(Oid::new(1)).unwrap();
+
+        let metrics = order_book.latency_metrics().unwrap();
+        for operation in [
+            LatencyOperation::Add,
+            LatencyOperation::Cancel,
+            LatencyOperation::Match,
+        ] {
+            assert_eq!(metrics.histogram(operation).count(), 1);
+        }
+        assert!(metrics.matching.p999().is_some());
+        order_book.reset_latency_metrics();
+        assert_eq!(order_book.latency_metrics().unwrap().add.count(), 0);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_book_exports_counters_and_gauges() {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        ::metrics::with_local_recorder(&recorder, || {
+            let mut order_book = OrderBook::default().with_exported_metrics(&Symbol::from("AAPL"));
+            for (id, side, price) in [
+                (1, OrderSide::Sell, 11.0),
+                (2, OrderSide::Sell, 12.0),
+                (3, OrderSide::Buy, 10.0),
+            ] {
+                let order = LimitOrder::new(
+                    Oid::new(id),
+                    side,
+                    Timestamp::new(id),
+                    price.into(),
+                    5.into(),
+                );
+                order_book.add_order(order).unwrap();
+            }
+            let buy = Order::new_market(Oid::new(4), OrderSide::Buy, Timestamp::new(4), 5.into());
+            order_book.execute(&buy).unwrap();
+            order_book.cancel_order(Oid::new(3)).unwrap();
+            let duplicate = LimitOrder::new(
+                Oid::new(2),
+                OrderSide::Sell,
+                Timestamp::new(5),
+                12.0.into(),
+                5.into(),
+            );
+            assert!(order_book.add_order(duplicate).is_err());
+        });
+
+        let values = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let key = key.key();
+                let labels = key
+                    .labels()
+                    .filter(|label| label.key() != "symbol")
+                    .map(|label| label.value().to_string())
+                    .collect::<Vec<_>>();
+                ((key.name().to_string(), labels), value)
+            })
+            .collect::<std::collections::HashMap<_, _>>();
+        let value = |name: &str, labels: &[&str]| {
+            let labels = labels.iter().map(|label| label.to_string()).collect();
+            &values[&(name.to_string(), labels)]
+        };
+        assert_eq!(
+            *value("lob_orders_accepted_total", &[]),
+            DebugValue::Counter(4)
+        );
+        assert_eq!(*value("lob_order_fills_total", &[]), DebugValue::Counter(2));
+        assert_eq!(
+            *value("lob_orders_cancelled_total", &[]),
+            DebugValue::Counter(1)
+        );
+        assert_eq!(
+            *value("lob_orders_rejected_total", &["duplicate order id"]),
+            DebugValue::Counter(1)
+        );
+        assert_eq!(
+            *value("lob_open_orders", &[]),
+            DebugValue::Gauge(1.0.into())
+        );
+        assert_eq!(
+            *value("lob_price_levels", &["bid"]),
+            DebugValue::Gauge(0.0.into())
+        );
+        assert_eq!(
+            *value("lob_price_levels", &["ask"]),
+            DebugValue::Gauge(1.0.into())
+        );
+    }
+}
@@ -0,0 +1,398 @@
+//!
+//! Mirror of an external L2 book built from sequence numbered deltas.
+//! Each price level is kept on the mirrored book as a single order with the level volume,
+//! so all query APIs of the order book can be used on the mirror.
+//! Deltas of the two sides can arrive out of order, so the mirror can become crossed,
+//! it is reported by the crossing events and resolved with `uncross_top`.
+//!
+
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    BookDelta, CrossingEvent, DeltaEvent, DepthSnapshot, Fill, LimitOrder, Oid, OrderBook,
+    OrderBookError, OrderSide, Price, Timestamp, Volume,
+};
+
+/// Mirror book error
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum MirrorError {
+    /// Deltas were lost, mirror has to be rebuilt from a new snapshot
+    #[error("Sequence gap, expected {expected} received {received}")]
+    SequenceGap { expected: u64, received: u64 },
+    /// Delta has already been applied
+    #[error("Delta {0} has already been applied")]
+    StaleDelta(u64),
+    #[error("Order book error: {0}")]
+    OrderBookError(#[from] OrderBookError),
+}
+
+/// How the crossed top of the mirror is resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UncrossPolicy {
+    /// match the crossed levels as if they had traded on the venue
+    SynthesizeTrades,
+    /// drop the crossed level updated least recently, it is assumed to be stale,
+    /// both levels are dropped when they were updated by the same delta
+    DropStaleSide,
+}
+
+/// Changes made to uncross the mirror
+#[derive(Debug, Clone, Default)]
+pub struct Uncrossing {
+    /// synthesized trades of the crossed levels
+    pub fills: Vec<Fill>,
+    /// dropped stale levels
+    pub dropped: Vec<(OrderSide, Price)>,
+}
+
+/// L2 book maintained from deltas of an external feed
+#[derive(Debug, Default)]
+pub struct MirrorBook {
+    book: OrderBook,
+    sequence: u64,
+    // synthetic order that holds the volume of each level and sequence of its last update
+    levels: HashMap<(OrderSide, Price), (Oid, u64)>,
+    next_id: u64,
+}
+
+impl MirrorBook {
+    /// empty mirror, the first delta applied must have sequence 1
+    pub fn new() -> Self {
+        MirrorBook::default()
+    }
+
+    /// mirror starting from the depth snapshot taken at the sequence
+    pub fn from_snapshot(snapshot: &DepthSnapshot, sequence: u64) -> Result<Self, MirrorError> {
+        let mut mirror = MirrorBook {
+            sequence,
+            ..MirrorBook::default()
+        };
+        for (side, levels) in [
+            (OrderSide::Buy, &snapshot.bids),
+            (OrderSide::Sell, &snapshot.asks),
+        ] {
+            for level in levels {
+                mirror.set_level(side, level.price, level.volume)?;
+            }
+        }
+        Ok(mirror)
+    }
+
+    /// keep only the given number of best levels of each side on the mirrored book
+    pub fn with_max_depth(mut self, levels: usize) -> Self {
+        self.book = core::mem::take(&mut self.book).with_max_depth(levels);
+        self
+    }
+
+    /// mirrored book, use it to query the levels
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// take the crossing events of the mirrored book since the last call,
+    /// external feed may leave the mirror crossed since its orders are not matched
+    pub fn drain_crossing_events(&mut self) -> Vec<CrossingEvent> {
+        self.book.drain_crossing_events()
+    }
+
+    /// sequence number of the last applied delta
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// apply the next delta of the feed
+    /// deltas that were already applied are rejected, a gap in sequence numbers means
+    /// the mirror is out of date and has to be rebuilt from a snapshot
+    pub fn apply_delta(&mut self, delta: &BookDelta) -> Result<(), MirrorError> {
+        if delta.sequence <= self.sequence {
+            return Err(MirrorError::StaleDelta(delta.sequence));
+        }
+        if delta.sequence != self.sequence + 1 {
+            return Err(MirrorError::SequenceGap {
+                expected: self.sequence + 1,
+                received: delta.sequence,
+            });
+        }
+        match delta.event {
+            DeltaEvent::LevelAdded {
+                side,
+                price,
+                volume,
+                ..
+            }
+            | DeltaEvent::LevelUpdated {
+                side,
+                price,
+                volume,
+                ..
+            } => self.set_level(side, price, volume)?,
+            DeltaEvent::LevelRemoved { side, price } => self.remove_level(side, price)?,
+            DeltaEvent::Trade { .. } => {}
+        }
+        self.sequence = delta.sequence;
+        Ok(())
+    }
+
+    /// resolve the crossed top of the mirror by the policy,
+    /// nothing is changed when the best bid is not above the best ask
+    pub fn uncross_top(&mut self, policy: UncrossPolicy) -> Result<Uncrossing, MirrorError> {
+        let mut uncrossing = Uncrossing::default();
+        while self.book.is_crossed() {
+            let (Some(bid), Some(ask)) = (self.book.get_best_buy(), self.book.get_best_sell())
+            else {
+                break;
+            };
+            match policy {
+                UncrossPolicy::SynthesizeTrades => {
+                    let fill = self.book.find_and_fill_best_orders()?;
+                    uncrossing.fills.push(fill);
+                }
+                UncrossPolicy::DropStaleSide => {
+                    let bid_updated = self.updated(OrderSide::Buy, bid);
+                    let ask_updated = self.updated(OrderSide::Sell, ask);
+                    if bid_updated.is_none() || ask_updated.is_none() {
+                        // levels are not mirrored from the feed, there is nothing to drop
+                        break;
+                    }
+                    if bid_updated <= ask_updated {
+                        self.remove_level(OrderSide::Buy, bid)?;
+                        uncrossing.dropped.push((OrderSide::Buy, bid));
+                    }
+                    if ask_updated <= bid_updated {
+                        self.remove_level(OrderSide::Sell, ask)?;
+                        uncrossing.dropped.push((OrderSide::Sell, ask));
+                    }
+                }
+            }
+        }
+        Ok(uncrossing)
+    }
+
+    /// sequence of the delta that last updated the level
+    fn updated(&self, side: OrderSide, price: Price) -> Option<u64> {
+        self.levels
+            .get(&(side, price))
+            .map(|(_, sequence)| *sequence)
+    }
+
+    fn set_level(
+        &mut self,
+        side: OrderSide,
+        price: Price,
+        volume: Volume,
+    ) -> Result<(), MirrorError> {
+        if volume.is_zero() {
+            return self.remove_level(side, price);
+        }
+        // level beyond the depth limit has been dropped from the book
+        let resting = self
+            .levels
+            .get(&(side, price))
+            .map(|(id, _)| *id)
+            .filter(|id| self.book.orders.get(id).is_some());
+        let id = match resting {
+            Some(id) => {
+                self.book.amend_order(id, price, volume)?;
+                id
+            }
+            None => {
+                self.next_id += 1;
+                let id = Oid::new(self.next_id);
+                let order = LimitOrder::new(id, side, Timestamp::new(self.next_id), price, volume);
+                self.book.add_order(order)?;
+                id
+            }
+        };
+        // delta being applied is the next one after the sequence
+        self.levels.insert((side, price), (id, self.sequence + 1));
+        Ok(())
+    }
+
+    fn remove_level(&mut self, side: OrderSide, price: Price) -> Result<(), MirrorError> {
+        if let Some((id, _)) = self.levels.remove(&(side, price)) {
+            if self.book.orders.get(&id).is_some() {
+                self.book.cancel_order(id).map_err(OrderBookError::from)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_mirror {
+    use crate::*;
+
+    #[test]
+    fn test_mirror_follows_the_book() {
+        let mut order_book = OrderBook::default();
+        let order = LimitOrder::new(
+            Oid::new(1),
+            OrderSide::Buy,
+            Timestamp::new(1),
+            9.0.into(),
+            5.into(),
+        );
+        order_book.add_order(order).unwrap();
+        let mut order_book = order_book.with_delta_feed();
+        let mut mirror =
+            MirrorBook::from_snapshot(&order_book.depth(usize::MAX), order_book.sequence())
+                .unwrap();
+
+        let orders = [
+            Order::new_limit(
+                Oid::new(2),
+                OrderSide::Sell,
+                Timestamp::new(2),
+                10.0.into(),
+                5.into(),
+            ),
+            Order::new_limit(
+                Oid::new(3),
+                OrderSide::Buy,
+                Timestamp::new(3),
+                9.0.into(),
+                3.into(),
+            ),
+            Order::new_limit(
+                Oid::new(4),
+                OrderSide::Buy,
+                Timestamp::new(4),
+                10.0.into(),
+                2.into(),
+            ),
+        ];
+        for order in &orders {
+            order_book.execute(order).unwrap();
+        }
+        order_book.cancel_order(Oid::new(1)).unwrap();
+
+        let deltas = order_book.drain_deltas();
+        for delta in &deltas {
+            mirror.apply_delta(delta).unwrap();
+        }
+        assert_eq!(mirror.sequence(), order_book.sequence());
+        assert_eq!(mirror.book().best_bid_ask(), order_book.best_bid_ask());
+        assert_eq!(mirror.book().total_volume(OrderSide::Buy), 3.into());
+
+        assert_eq!(
+            mirror.apply_delta(&deltas[0]),
+            Err(MirrorError::StaleDelta(deltas[0].sequence))
+        );
+        let mut skipped = deltas[0].clone();
+        skipped.sequence = order_book.sequence() + 2;
+        assert_eq!(
+            mirror.apply_delta(&skipped),
+            Err(MirrorError::SequenceGap {
+                expected: order_book.sequence() + 1,
+                received: skipped.sequence
+            })
+        );
+    }
+
+    #[test]
+    fn test_crossed_mirror_is_uncrossed() {
+        let level = |sequence: u64, side: OrderSide, price: f64, volume: u64| BookDelta {
+            sequence,
+            event_sequence: 0,
+            event: DeltaEvent::LevelUpdated {
+                side,
+                price: price.into(),
+                volume: volume.into(),
+                order_count: 1,
+            },
+        };
+        // ask at 9.5 arrives before the removal of the bid at 10.0
+        let deltas = [
+            level(1, OrderSide::Buy, 9.0, 5),
+            level(2, OrderSide::Buy, 10.0, 5),
+            level(3, OrderSide::Sell, 11.0, 5),
+            level(4, OrderSide::Sell, 9.5, 3),
+        ];
+        let mirror = || {
+            let mut mirror = MirrorBook::new();
+            for delta in &deltas {
+                mirror.apply_delta(delta).unwrap();
+            }
+            mirror
+        };
+
+        let mut stale = mirror();
+        assert!(stale.book().is_crossed());
+        let uncrossing = stale.uncross_top(UncrossPolicy::DropStaleSide).unwrap();
+        assert_eq!(uncrossing.dropped, vec![(OrderSide::Buy, 10.0.into())]);
+        assert!(uncrossing.fills.is_empty());
+        assert_eq!(stale.book().get_best_buy(), Some(9.0.into()));
+        assert_eq!(stale.book().get_best_sell(), Some(9.5.into()));
+        assert_eq!(
+            stale.drain_crossing_events(),
+            vec![
+                CrossingEvent::Crossed {
+                    bid: 10.0.into(),
+                    ask: 9.5.into()
+                },
+                CrossingEvent::Uncrossed
+            ]
+        );
+
+        let mut traded = mirror();
+        let uncrossing = traded.uncross_top(UncrossPolicy::SynthesizeTrades).unwrap();
+        assert_eq!(uncrossing.fills.len(), 1);
+        assert_eq!(uncrossing.fills[0].volume, 3.into());
+        assert_eq!(traded.book().get_best_buy_volume(), Some(2.into()));
+        assert_eq!(traded.book().get_best_sell(), Some(11.0.into()));
+
+        // feed keeps updating the traded levels
+        traded
+            .apply_delta(&level(5, OrderSide::Sell, 9.5, 4))
+            .unwrap();
+        assert_eq!(traded.book().get_best_sell(), Some(9.5.into()));
+        assert!(traded
+            .uncross_top(UncrossPolicy::DropStaleSide)
+            .unwrap()
+            .dropped
+            .contains(&(OrderSide::Buy, 10.0.into())));
+    }
+
+    #[test]
+    fn test_depth_limited_mirror() {
+        let mut mirror = MirrorBook::new().with_max_depth(1);
+        let level = |price: f64, volume: u64| DeltaEvent::LevelUpdated {
+            side: OrderSide::Buy,
+            price: price.into(),
+            volume: volume.into(),
+            order_count: 1,
+        };
+        let events = [
+            level(9.0, 5),
+            level(10.0, 5),
+            level(9.0, 7),
+            DeltaEvent::LevelRemoved {
+                side: OrderSide::Buy,
+                price: 10.0.into(),
+            },
+        ];
+        for (sequence, event) in events.into_iter().enumerate() {
+            let delta = BookDelta {
+                sequence: sequence as u64 + 1,
+                event_sequence: 0,
+                event,
+            };
+            mirror.apply_delta(&delta).unwrap();
+            assert!(mirror.book().level_count(OrderSide::Buy) <= 1);
+        }
+        // dropped level is added again once the feed updates it
+        assert!(mirror.book().is_empty());
+        mirror
+            .apply_delta(&BookDelta {
+                sequence: 5,
+                event_sequence: 0,
+                event: level(9.0, 3),
+            })
+            .unwrap();
+        assert_eq!(mirror.book().get_best_buy_volume(), Some(3.into()));
+    }
+}
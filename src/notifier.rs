@@ -0,0 +1,161 @@
+//!
+//! Throttled notifications of the top of the book. Strategy code is notified when the best bid
+//! or ask moves by at least the given number of ticks, updates within the minimum interval
+//! after the previous notification are held back and the latest of them is notified once
+//! the interval has elapsed. The notifier is fed with the top of the book after each change,
+//! i.e. from the execution reports or the delta feed, and polled on a timer.
+//!
+
+use crate::{Bbo, Price, Timestamp};
+
+/// Top of the book notifier with rate and change filtering
+#[derive(Debug, Clone)]
+pub struct BboNotifier {
+    tick_size: Price,
+    min_ticks: u64,
+    min_interval: u64,
+    last: Option<(Timestamp, Bbo)>,
+    pending: Option<Bbo>,
+}
+
+impl BboNotifier {
+    /// notify every price change, prices are compared in multiples of the tick size
+    pub fn new(tick_size: Price) -> Self {
+        BboNotifier {
+            tick_size,
+            min_ticks: 1,
+            min_interval: 0,
+            last: None,
+            pending: None,
+        }
+    }
+
+    /// notify only when the best bid or ask moves by at least the number of ticks,
+    /// with zero every change is notified including volume changes within the level
+    pub fn with_min_ticks(mut self, ticks: u64) -> Self {
+        self.min_ticks = ticks;
+        self
+    }
+
+    /// minimum time in milliseconds between two notifications
+    pub fn with_min_interval(mut self, millis: u64) -> Self {
+        self.min_interval = millis;
+        self
+    }
+
+    /// last notified top of the book
+    pub fn last(&self) -> Option<Bbo> {
+        self.last.map(|(_, bbo)| bbo)
+    }
+
+    /// top of the book to notify, if it moved enough since the last notification
+    /// and the interval has elapsed
+    pub fn update(&mut self, now: Timestamp, bbo: Bbo) -> Option<Bbo> {
+        self.pending = match self.last {
+            Some((_, last)) if !self.is_significant(&last, &bbo) => None,
+            _ => Some(bbo),
+        };
+        self.poll(now)
+    }
+
+    /// held back top of the book once the interval has elapsed
+    pub fn poll(&mut self, now: Timestamp) -> Option<Bbo> {
+        let due = self.last.is_none_or(|(time, _)| {
+            u64::from(now).saturating_sub(time.into()) >= self.min_interval
+        });
+        if !due {
+            return None;
+        }
+        let bbo = self.pending.take()?;
+        self.last = Some((now, bbo));
+        Some(bbo)
+    }
+
+    fn is_significant(&self, last: &Bbo, bbo: &Bbo) -> bool {
+        if self.min_ticks == 0 {
+            return last != bbo;
+        }
+        [
+            (last.bid_price, bbo.bid_price),
+            (last.ask_price, bbo.ask_price),
+        ]
+        .into_iter()
+        .any(|prices| match prices {
+            (Some(last), Some(price)) => self.ticks(last, price) >= self.min_ticks,
+            // side appeared or disappeared
+            (last, price) => last != price,
+        })
+    }
+
+    fn ticks(&self, from: Price, to: Price) -> u64 {
+        let tick = self.tick_size.mantissa().unsigned_abs().max(1);
+        from.mantissa().abs_diff(to.mantissa()) / tick
+    }
+}
+
+#[cfg(test)]
+mod tests_notifier {
+    use crate::*;
+
+    fn bbo(bid: f64, bid_volume: u64, ask: f64) -> Bbo {
+        Bbo {
+            bid_price: Some(bid.into()),
+            bid_volume: bid_volume.into(),
+            ask_price: Some(ask.into()),
+            ask_volume: 5.into(),
+        }
+    }
+
+    #[test]
+    fn test_small_and_frequent_changes_are_filtered() {
+        let mut notifier = BboNotifier::new(0.5.into())
+            .with_min_ticks(2)
+            .with_min_interval(100);
+        assert_eq!(
+            notifier.update(Timestamp::new(0), bbo(10.0, 5, 11.0)),
+            Some(bbo(10.0, 5, 11.0))
+        );
+        // volume change and one tick move are not notified
+        assert_eq!(
+            notifier.update(Timestamp::new(200), bbo(10.0, 9, 11.0)),
+            None
+        );
+        assert_eq!(
+            notifier.update(Timestamp::new(300), bbo(10.0, 5, 11.5)),
+            None
+        );
+
+        // two ticks move is notified, the next one within the interval is held back
+        assert_eq!(
+            notifier.update(Timestamp::new(350), bbo(10.0, 5, 12.0)),
+            Some(bbo(10.0, 5, 12.0))
+        );
+        assert_eq!(
+            notifier.update(Timestamp::new(400), bbo(9.0, 5, 12.0)),
+            None
+        );
+        assert_eq!(notifier.poll(Timestamp::new(420)), None);
+        assert_eq!(notifier.poll(Timestamp::new(450)), Some(bbo(9.0, 5, 12.0)));
+        assert_eq!(notifier.poll(Timestamp::new(600)), None);
+
+        // move back within the threshold cancels the held back update
+        assert_eq!(
+            notifier.update(Timestamp::new(500), bbo(8.0, 5, 12.0)),
+            None
+        );
+        assert_eq!(
+            notifier.update(Timestamp::new(520), bbo(9.0, 5, 12.0)),
+            None
+        );
+        assert_eq!(notifier.poll(Timestamp::new(700)), None);
+
+        // empty side is always notified
+        let empty = Bbo {
+            ask_price: None,
+            ask_volume: Volume::ZERO,
+            ..bbo(9.0, 5, 12.0)
+        };
+        assert_eq!(notifier.update(Timestamp::new(800), empty), Some(empty));
+        assert_eq!(notifier.last(), Some(empty));
+    }
+}
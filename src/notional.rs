@@ -0,0 +1,104 @@
+//!
+//! Notional value of orders and fills, price times volume times the contract multiplier.
+//! The multiplier is one unless it is set on the book, books added to the manager with
+//! an instrument take the multiplier of the instrument.
+//!
+
+use crate::utils::VOLUME_SCALE;
+use crate::{Fill, LevelStore, Oid, OrderBook, Price, Trade, Volume};
+
+/// price times volume times multiplier, still scaled by the volume scale
+pub(crate) fn scaled_notional(price: Price, volume: Volume, multiplier: u64) -> i128 {
+    price.mantissa() as i128 * *volume as i128 * multiplier as i128
+}
+
+/// price of the scaled notional, saturating on overflow
+pub(crate) fn notional_price(scaled: i128) -> Price {
+    let notional = scaled / VOLUME_SCALE as i128;
+    Price::from_mantissa(notional.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+}
+
+/// price times volume times multiplier, saturating on overflow
+pub(crate) fn notional(price: Price, volume: Volume, multiplier: u64) -> Price {
+    notional_price(scaled_notional(price, volume, multiplier))
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// value of one unit of volume in price units
+    pub fn with_contract_multiplier(mut self, multiplier: u64) -> Self {
+        self.contract_multiplier = Some(multiplier);
+        self
+    }
+
+    pub fn contract_multiplier(&self) -> u64 {
+        self.contract_multiplier.unwrap_or(1)
+    }
+
+    /// price times volume times the contract multiplier
+    pub fn notional(&self, price: Price, volume: Volume) -> Price {
+        notional(price, volume, self.contract_multiplier())
+    }
+
+    /// notional of the open volume of the resting order at its price
+    pub fn order_notional(&self, order_id: Oid) -> Option<Price> {
+        let order = self.orders.get(&order_id)?;
+        Some(self.notional(order.price, order.remaining_volume()))
+    }
+
+    /// notional of the fill at its trade price
+    pub fn fill_notional(&self, fill: &Fill) -> Price {
+        self.notional(fill.trade_price, fill.volume)
+    }
+
+    /// notional of all executions of the trade
+    pub fn trade_notional(&self, trade: &Trade) -> Price {
+        notional_price(
+            trade
+                .executions
+                .iter()
+                .map(|execution| {
+                    scaled_notional(
+                        execution.price,
+                        execution.volume,
+                        self.contract_multiplier(),
+                    )
+                })
+                .sum(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_notional {
+    use crate::*;
+
+    #[test]
+    fn test_notional_with_multiplier() {
+        let mut order_book = OrderBook::default().with_contract_multiplier(10);
+        let sell = |id: u64, price: f64| {
+            Order::new_limit(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            )
+        };
+        order_book.execute(&sell(1, 10.0)).unwrap();
+        order_book.execute(&sell(2, 11.0)).unwrap();
+        assert_eq!(
+            order_book.order_notional(Oid::new(1)),
+            Some(Price::new(500.0))
+        );
+
+        let buy = Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(3), 7.into());
+        let trade = order_book.execute(&buy).unwrap();
+        // 5 at 10 and 2 at 11
+        assert_eq!(order_book.trade_notional(&trade), Price::new(720.0));
+        assert_eq!(
+            order_book.order_notional(Oid::new(2)),
+            Some(Price::new(330.0))
+        );
+        assert_eq!(order_book.order_notional(Oid::new(1)), None);
+    }
+}
@@ -0,0 +1,239 @@
+//!
+//! Numeric traits of the price and the volume.
+//! Price levels are generic over them, so integer tick or decimal types of the user
+//! can be used for the levels, `Price` and `Volume` are the default types.
+//! Orders queued at the levels implement `LevelOrder` of the same types.
+//!
+
+use core::fmt::Debug;
+use core::hash::Hash;
+use core::iter::Sum;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+use crate::{LimitOrder, OrderSide, Price, Volume};
+
+/// Price of a level, levels are ordered and looked up by the price
+pub trait PriceLike: Copy + Ord + Hash + Debug + Default {}
+
+/// Volume of a level, it is the sum of the volumes of the queued orders
+pub trait QuantityLike:
+    Copy + Ord + Debug + Default + Add<Output = Self> + Sub<Output = Self> + AddAssign + SubAssign + Sum
+{
+    const ZERO: Self;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+
+    /// None if the sum overflows
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// None if the difference overflows
+    fn checked_sub(self, other: Self) -> Option<Self>;
+
+    fn saturating_sub(self, other: Self) -> Self;
+}
+
+impl PriceLike for Price {}
+
+impl QuantityLike for Volume {
+    const ZERO: Self = Volume::ZERO;
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Volume::checked_add(self, other)
+    }
+
+    fn checked_sub(self, other: Self) -> Option<Self> {
+        Volume::checked_sub(self, other)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        Volume::saturating_sub(self, other)
+    }
+}
+
+/// Order queued at a level of the price and volume types
+pub trait LevelOrder<P, V> {
+    fn side(&self) -> OrderSide;
+
+    fn price(&self) -> P;
+
+    /// volume of the order, including the filled volume
+    fn volume(&self) -> V;
+
+    fn filled_volume(&self) -> V;
+
+    fn set_filled_volume(&mut self, volume: V);
+
+    /// volume that has not been filled yet
+    fn remaining_volume(&self) -> V;
+
+    /// volume shown on the book, it contributes to the level volume
+    fn visible_volume(&self) -> V;
+
+    /// show the next part of the remaining volume, returns the visible volume
+    fn refresh_display(&mut self) -> V;
+
+    /// slot of the order in the level queue, set while the order is queued
+    fn queue_slot_mut(&mut self) -> &mut Option<usize>;
+}
+
+impl LevelOrder<Price, Volume> for LimitOrder {
+    fn side(&self) -> OrderSide {
+        self.side
+    }
+
+    fn price(&self) -> Price {
+        self.price
+    }
+
+    fn volume(&self) -> Volume {
+        self.volume
+    }
+
+    fn filled_volume(&self) -> Volume {
+        self.filled_volume.unwrap_or(Volume::ZERO)
+    }
+
+    fn set_filled_volume(&mut self, volume: Volume) {
+        self.filled_volume = Some(volume);
+    }
+
+    fn remaining_volume(&self) -> Volume {
+        LimitOrder::remaining_volume(self)
+    }
+
+    fn visible_volume(&self) -> Volume {
+        LimitOrder::visible_volume(self)
+    }
+
+    fn refresh_display(&mut self) -> Volume {
+        LimitOrder::refresh_display(self)
+    }
+
+    fn queue_slot_mut(&mut self) -> &mut Option<usize> {
+        &mut self.queue_slot
+    }
+}
+
+/// price in ticks
+impl PriceLike for i64 {}
+
+/// price in ticks, for instruments that cannot have negative prices
+impl PriceLike for u64 {}
+
+/// volume in lots
+impl QuantityLike for u64 {
+    const ZERO: Self = 0;
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        u64::checked_add(self, other)
+    }
+
+    fn checked_sub(self, other: Self) -> Option<Self> {
+        u64::checked_sub(self, other)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        u64::saturating_sub(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests_numeric {
+    use crate::primitives::OrderHandle;
+    use crate::*;
+
+    struct TickOrder {
+        side: OrderSide,
+        price: i64,
+        lots: u64,
+        filled: u64,
+        slot: Option<usize>,
+    }
+
+    impl LevelOrder<i64, u64> for TickOrder {
+        fn side(&self) -> OrderSide {
+            self.side
+        }
+
+        fn price(&self) -> i64 {
+            self.price
+        }
+
+        fn volume(&self) -> u64 {
+            self.lots
+        }
+
+        fn filled_volume(&self) -> u64 {
+            self.filled
+        }
+
+        fn set_filled_volume(&mut self, volume: u64) {
+            self.filled = volume;
+        }
+
+        fn remaining_volume(&self) -> u64 {
+            self.lots - self.filled
+        }
+
+        fn visible_volume(&self) -> u64 {
+            self.remaining_volume()
+        }
+
+        fn refresh_display(&mut self) -> u64 {
+            self.remaining_volume()
+        }
+
+        fn queue_slot_mut(&mut self) -> &mut Option<usize> {
+            &mut self.slot
+        }
+    }
+
+    #[test]
+    fn test_levels_with_integer_ticks() {
+        let mut level: Level<i64, u64> = Level::new(1_050);
+        assert_eq!(level.price(), 1_050);
+        assert!(level.total_volume().is_zero());
+        assert!(level < Level::new(1_100));
+
+        let limits = Limits::<i64, u64>::default();
+        assert_eq!(limits.get_best_limit(), None);
+        assert_eq!(
+            level.reduce_volume(1),
+            Err(OrderBookError::ArithmeticOverflow)
+        );
+        assert_eq!(level.order_count(), 0);
+    }
+
+    #[test]
+    fn test_limits_with_user_orders() {
+        let order = |price: i64, lots: u64| TickOrder {
+            side: OrderSide::Buy,
+            price,
+            lots,
+            filled: 0,
+            slot: None,
+        };
+        let mut limits = Limits::<i64, u64>::default();
+        let mut first = order(1_050, 3);
+        let mut second = order(1_100, 5);
+        limits.add_order(&mut first, OrderHandle(0)).unwrap();
+        limits.add_order(&mut second, OrderHandle(1)).unwrap();
+        assert_eq!(limits.get_best_limit(), Some(1_100));
+        assert_eq!(limits.get_best_volume(), Some(5));
+
+        let level = limits.level_at_mut(&1_100).unwrap();
+        assert_eq!(
+            level.fill_queued_order(&mut second, OrderHandle(1), 2),
+            Ok(false)
+        );
+        assert_eq!(level.total_volume(), 3);
+        assert_eq!(second.filled, 2);
+
+        limits.cancel_order(&mut second);
+        limits.update_best(OrderSide::Buy);
+        assert_eq!(limits.get_best_limit(), Some(1_050));
+        assert_eq!(limits.get_best_volume(), Some(3));
+    }
+}
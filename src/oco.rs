@@ -0,0 +1,309 @@
+//!
+//! One-cancels-other order groups. Two resting orders are linked into a group, when one of them
+//! is filled, fully or above the volume threshold, the other one is cancelled.
+//! Cancelling a member dissolves the group, the other order stays on the book.
+//! Events of the groups are collected with `drain_oco_events`.
+//!
+
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::{BookEvent, CancellationStatus, LevelStore, Oid, OrderBook, OrderBookError, Volume};
+
+/// OCO group id, assigned by the book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GroupId(u64);
+
+impl From<GroupId> for u64 {
+    fn from(value: GroupId) -> Self {
+        value.0
+    }
+}
+
+/// When the group is triggered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OcoTrigger {
+    /// one of the orders is completely filled
+    #[default]
+    Filled,
+    /// filled volume of one of the orders reaches the threshold
+    FilledAtLeast(Volume),
+}
+
+/// Event of the OCO group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcoEvent {
+    /// order of the group was filled, the other order was cancelled,
+    /// cancelled is none if the other order has already left the book
+    Triggered {
+        group: GroupId,
+        filled: Oid,
+        cancelled: Option<Oid>,
+    },
+    /// order of the group was cancelled, the other order stays on the book
+    Dissolved { group: GroupId, cancelled: Oid },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OcoGroup {
+    orders: [Oid; 2],
+    trigger: OcoTrigger,
+}
+
+impl OcoGroup {
+    fn other(&self, order_id: Oid) -> Oid {
+        if self.orders[0] == order_id {
+            self.orders[1]
+        } else {
+            self.orders[0]
+        }
+    }
+}
+
+/// Linked orders of the book
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OcoGroups {
+    next_group: u64,
+    groups: HashMap<GroupId, OcoGroup>,
+    members: HashMap<Oid, GroupId>,
+    events: Vec<OcoEvent>,
+}
+
+impl OcoGroups {
+    fn remove(&mut self, group: GroupId) -> Option<OcoGroup> {
+        let removed = self.groups.remove(&group)?;
+        for order_id in removed.orders {
+            self.members.remove(&order_id);
+        }
+        Some(removed)
+    }
+
+    /// dissolve the group of the cancelled order
+    pub(crate) fn forget(&mut self, order_id: Oid) {
+        if let Some(group) = self.members.get(&order_id).copied() {
+            self.remove(group);
+            self.events.push(OcoEvent::Dissolved {
+                group,
+                cancelled: order_id,
+            });
+        }
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// link two resting orders into an OCO group
+    pub fn link_oco(
+        &mut self,
+        first: Oid,
+        second: Oid,
+        trigger: OcoTrigger,
+    ) -> Result<GroupId, OrderBookError> {
+        self.logged(
+            || BookEvent::LinkOco {
+                first,
+                second,
+                trigger,
+            },
+            |book| book.link_orders(first, second, trigger),
+        )
+    }
+
+    fn link_orders(
+        &mut self,
+        first: Oid,
+        second: Oid,
+        trigger: OcoTrigger,
+    ) -> Result<GroupId, OrderBookError> {
+        for order_id in [first, second] {
+            if self.orders.get(&order_id).is_none() {
+                return Err(OrderBookError::UnknownOrder(order_id));
+            }
+            if self.oco_groups.members.contains_key(&order_id) {
+                return Err(OrderBookError::OrderCannotBePlaced(alloc::format!(
+                    "order {order_id} is already in an OCO group"
+                )));
+            }
+        }
+        if first == second {
+            return Err(OrderBookError::OrderCannotBePlaced(
+                "OCO group needs two different orders".into(),
+            ));
+        }
+
+        let groups = &mut self.oco_groups;
+        groups.next_group += 1;
+        let group = GroupId(groups.next_group);
+        groups.groups.insert(
+            group,
+            OcoGroup {
+                orders: [first, second],
+                trigger,
+            },
+        );
+        groups.members.insert(first, group);
+        groups.members.insert(second, group);
+        Ok(group)
+    }
+
+    /// OCO group of the resting order
+    pub fn oco_group(&self, order_id: Oid) -> Option<GroupId> {
+        self.oco_groups.members.get(&order_id).copied()
+    }
+
+    /// take the OCO events since the last call
+    pub fn drain_oco_events(&mut self) -> Vec<OcoEvent> {
+        core::mem::take(&mut self.oco_groups.events)
+    }
+
+    /// cancel the other order of the groups whose member was filled above the trigger
+    pub(crate) fn trigger_oco(&mut self, filled: impl IntoIterator<Item = Oid>) {
+        if self.oco_groups.groups.is_empty() {
+            return;
+        }
+        for order_id in filled {
+            let Some(group_id) = self.oco_groups.members.get(&order_id).copied() else {
+                continue;
+            };
+            let Some(group) = self.oco_groups.groups.get(&group_id).copied() else {
+                continue;
+            };
+            // filled order has left the book
+            let triggered = self
+                .orders
+                .get(&order_id)
+                .is_none_or(|order| match group.trigger {
+                    OcoTrigger::Filled => false,
+                    OcoTrigger::FilledAtLeast(threshold) => {
+                        order.filled_volume.unwrap_or(Volume::ZERO) >= threshold
+                    }
+                });
+            if !triggered {
+                continue;
+            }
+
+            self.oco_groups.remove(group_id);
+            let other = group.other(order_id);
+            let cancelled = self
+                .remove_orders([other], CancellationStatus::Cancelled)
+                .first()
+                .map(|report| report.order_id());
+            self.oco_groups.events.push(OcoEvent::Triggered {
+                group: group_id,
+                filled: order_id,
+                cancelled,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_oco {
+    use crate::*;
+
+    fn book() -> OrderBook {
+        let mut order_book = OrderBook::default();
+        for (id, side, price) in [
+            (1, OrderSide::Sell, 11.0),
+            (2, OrderSide::Sell, 13.0),
+            (3, OrderSide::Sell, 12.0),
+        ] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                10.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        order_book
+    }
+
+    fn buy(id: u64, volume: u64) -> Order {
+        Order::new_market(
+            Oid::new(id),
+            OrderSide::Buy,
+            Timestamp::new(id),
+            volume.into(),
+        )
+    }
+
+    #[test]
+    fn test_filled_order_cancels_the_other() {
+        let mut order_book = book();
+        let group = order_book
+            .link_oco(Oid::new(1), Oid::new(2), OcoTrigger::Filled)
+            .unwrap();
+        assert_eq!(order_book.oco_group(Oid::new(2)), Some(group));
+        assert!(order_book
+            .link_oco(Oid::new(2), Oid::new(3), OcoTrigger::Filled)
+            .is_err());
+
+        order_book.execute(&buy(4, 5)).unwrap();
+        assert!(order_book.drain_oco_events().is_empty());
+
+        order_book.execute(&buy(5, 7)).unwrap();
+        assert_eq!(
+            order_book.drain_oco_events(),
+            vec![OcoEvent::Triggered {
+                group,
+                filled: Oid::new(1),
+                cancelled: Some(Oid::new(2)),
+            }]
+        );
+        assert_eq!(
+            order_book.get_order(Oid::new(2)).map(|o| o.state),
+            Some(OrderState::Cancelled)
+        );
+        assert_eq!(order_book.get_best_sell(), Some(Price::new(12.0)));
+        assert_eq!(order_book.oco_group(Oid::new(1)), None);
+    }
+
+    #[test]
+    fn test_threshold_and_dissolve() {
+        let mut order_book = book();
+        let group = order_book
+            .link_oco(
+                Oid::new(1),
+                Oid::new(3),
+                OcoTrigger::FilledAtLeast(4.into()),
+            )
+            .unwrap();
+        let bid = LimitOrder::new(
+            Oid::new(4),
+            OrderSide::Buy,
+            Timestamp::new(4),
+            11.0.into(),
+            4.into(),
+        );
+        order_book.add_order(bid).unwrap();
+        order_book.find_and_fill_best_orders().unwrap();
+        assert_eq!(
+            order_book.drain_oco_events(),
+            vec![OcoEvent::Triggered {
+                group,
+                filled: Oid::new(1),
+                cancelled: Some(Oid::new(3)),
+            }]
+        );
+
+        let group = order_book
+            .link_oco(Oid::new(1), Oid::new(2), OcoTrigger::Filled)
+            .unwrap();
+        order_book.cancel_order(Oid::new(2)).unwrap();
+        assert_eq!(
+            order_book.drain_oco_events(),
+            vec![OcoEvent::Dissolved {
+                group,
+                cancelled: Oid::new(2),
+            }]
+        );
+        assert_eq!(
+            order_book
+                .get_order(Oid::new(1))
+                .map(|o| o.remaining_volume),
+            Some(6.into())
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//!
+//! Order id generation. Ids are taken from an atomic sequence, so one generator can be shared
+//! by several threads. Sharded generators keep the shard, i.e. the gateway, in the high bits
+//! of the id, so gateways generate unique ids without coordination.
+//!
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Oid;
+
+/// number of low bits holding the sequence of a sharded generator
+const SHARD_SHIFT: u32 = 48;
+
+/// Generator of unique order ids, the first id is 1
+#[derive(Debug)]
+pub struct OidGenerator {
+    shard: Option<u16>,
+    next: AtomicU64,
+}
+
+impl Default for OidGenerator {
+    fn default() -> Self {
+        OidGenerator::new()
+    }
+}
+
+impl OidGenerator {
+    pub fn new() -> Self {
+        OidGenerator {
+            shard: None,
+            next: AtomicU64::new(1),
+        }
+    }
+
+    /// ids of the shard have the shard number in the high 16 bits
+    pub fn sharded(shard: u16) -> Self {
+        OidGenerator {
+            shard: Some(shard),
+            ..OidGenerator::new()
+        }
+    }
+
+    /// continue the sequence after ids that were already used, i.e. after a restart
+    pub fn starting_at(self, next: u64) -> Self {
+        self.next.store(next, Ordering::Relaxed);
+        self
+    }
+
+    pub fn shard(&self) -> Option<u16> {
+        self.shard
+    }
+
+    pub fn next_id(&self) -> Oid {
+        let sequence = self.next.fetch_add(1, Ordering::Relaxed);
+        match self.shard {
+            Some(shard) => Oid::new(((shard as u64) << SHARD_SHIFT) | sequence),
+            None => Oid::new(sequence),
+        }
+    }
+
+    /// shard of the id made by a sharded generator
+    pub fn shard_of(id: Oid) -> u16 {
+        (u64::from(id) >> SHARD_SHIFT) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests_oid {
+    use crate::*;
+
+    #[test]
+    fn test_generated_ids_are_unique() {
+        let ids = OidGenerator::new();
+        assert_eq!(ids.next_id(), Oid::new(1));
+        assert_eq!(ids.next_id(), Oid::new(2));
+
+        let gateway = OidGenerator::sharded(3).starting_at(10);
+        let id = gateway.next_id();
+        assert_eq!(OidGenerator::shard_of(id), 3);
+        assert_eq!(u64::from(id) & 0xffff_ffff_ffff, 10);
+        assert_ne!(
+            OidGenerator::sharded(4).next_id(),
+            OidGenerator::sharded(3).next_id()
+        );
+
+        let mut order_book = OrderBook::default();
+        let order =
+            |id: Oid| LimitOrder::new(id, OrderSide::Buy, Timestamp::new(1), 10.0.into(), 5.into());
+        order_book.add_order(order(Oid::new(1))).unwrap();
+        assert_eq!(
+            order_book.add_order(order(Oid::new(1))),
+            Err(OrderBookError::DuplicateOrderId(Oid::new(1)))
+        );
+        let crossing = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(2),
+            9.0.into(),
+            1.into(),
+        );
+        assert_eq!(
+            order_book.execute(&crossing).map(|_| ()),
+            Err(OrderBookError::DuplicateOrderId(Oid::new(1)))
+        );
+        assert_eq!(order_book.get_best_buy_volume(), Some(5.into()));
+    }
+}
@@ -0,0 +1,552 @@
+//!
+//! Binary order entry in the style of Nasdaq OUCH.
+//! Enter, Cancel and Replace requests of the client are decoded into orders, cancels and
+//! amends of the book, Accepted, Executed and Canceled responses are encoded for the client.
+//! Fields are big endian, orders are identified by a numeric token used as the order id on
+//! the book, prices have 4 decimal places and shares are whole units. Messages are passed
+//! without the length prefix of the SoupBinTCP framing.
+//!
+//! | message | layout |
+//! |---|---|
+//! | Enter Order `O` | token u64, side `B`/`S`, shares u32, price u32, time in force u32 |
+//! | Cancel Order `X` | token u64 |
+//! | Replace Order `U` | token u64, shares u32, price u32 |
+//! | Accepted `A` | timestamp u64, token u64, side `B`/`S`, shares u32, price u32 |
+//! | Executed `E` | timestamp u64, token u64, shares u32, price u32, match number u64 |
+//! | Canceled `C` | timestamp u64, token u64, shares u32, reason `U`/`I`/`T` |
+//!
+
+use alloc::vec::Vec;
+
+use thiserror::Error;
+
+use crate::utils::{PRICE_SCALE, VOLUME_SCALE};
+use crate::{
+    CancellationStatus, Oid, Order, OrderSide, Price, TimeInForce, Timestamp, Trade, Volume,
+};
+
+/// OUCH prices have 4 decimal places
+const OUCH_PRICE_SCALE: i64 = 10_000;
+/// price of the Enter Order message of a market order
+pub const MARKET_PRICE: u32 = 0x7FFF_FFFF;
+
+/// OUCH decoding or encoding error
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum OuchError {
+    #[error("Message {0:?} is shorter than expected")]
+    Truncated(char),
+    #[error("Message type {0:?} is not supported")]
+    UnsupportedMessage(char),
+    #[error("Buffer of {available} bytes is too short, {required} bytes required")]
+    BufferTooShort { required: usize, available: usize },
+    #[error("Invalid value of field {0}")]
+    InvalidValue(&'static str),
+}
+
+/// Request of the client
+#[derive(Debug, Clone, PartialEq)]
+pub enum OuchRequest {
+    /// Enter Order (O), time in force 0 is immediate or cancel, any other rests on the book
+    Enter(Order),
+    /// Cancel Order (X), contains the id of the order to cancel
+    Cancel(Oid),
+    /// Replace Order (U), order keeps its token, see `OrderBook::amend_order`
+    Replace {
+        order_id: Oid,
+        price: Price,
+        volume: Volume,
+    },
+}
+
+impl OuchRequest {
+    /// decode single message, first byte is the message type, now is the time of the order
+    pub fn decode(bytes: &[u8], now: Timestamp) -> Result<Self, OuchError> {
+        let Some(&kind) = bytes.first() else {
+            return Err(OuchError::Truncated(' '));
+        };
+        let expected_len = match kind {
+            b'O' => 22,
+            b'X' => 9,
+            b'U' => 17,
+            _ => return Err(OuchError::UnsupportedMessage(kind as char)),
+        };
+        if bytes.len() < expected_len {
+            return Err(OuchError::Truncated(kind as char));
+        }
+        let mut reader = Reader { bytes: &bytes[1..] };
+        let order_id = Oid::new(reader.u64());
+        let request = match kind {
+            b'O' => {
+                let side = side_from_code(reader.u8())?;
+                let volume = reader.shares();
+                let order = match reader.u32() {
+                    MARKET_PRICE => Order::new_market(order_id, side, now, volume),
+                    price => Order::new_limit(order_id, side, now, ouch_price(price), volume),
+                };
+                let time_in_force = match reader.u32() {
+                    0 => TimeInForce::ImmediateOrCancel,
+                    _ => TimeInForce::GoodTillCancel,
+                };
+                OuchRequest::Enter(order.with_time_in_force(time_in_force))
+            }
+            b'X' => OuchRequest::Cancel(order_id),
+            _ => OuchRequest::Replace {
+                order_id,
+                volume: reader.shares(),
+                price: ouch_price(reader.u32()),
+            },
+        };
+        Ok(request)
+    }
+}
+
+/// Reason of the Canceled message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    /// cancelled on request of the client (U)
+    UserRequested,
+    /// remaining shares of the immediate or cancel order (I)
+    ImmediateOrCancel,
+    /// order expired (T)
+    Timeout,
+}
+
+impl CancelReason {
+    /// reason of the cancelled order, none if the order was not cancelled
+    pub fn from_status(status: &CancellationStatus) -> Option<Self> {
+        match status {
+            CancellationStatus::Cancelled => Some(CancelReason::UserRequested),
+            CancellationStatus::Expired => Some(CancelReason::Timeout),
+            CancellationStatus::NotCancelled(_) => None,
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            CancelReason::UserRequested => b'U',
+            CancelReason::ImmediateOrCancel => b'I',
+            CancelReason::Timeout => b'T',
+        }
+    }
+}
+
+/// Response to the client
+#[derive(Debug, Clone, PartialEq)]
+pub enum OuchResponse {
+    /// Accepted (A), price of a market order is `MARKET_PRICE`
+    Accepted {
+        timestamp: Timestamp,
+        order_id: Oid,
+        side: OrderSide,
+        volume: Volume,
+        price: Option<Price>,
+    },
+    /// Executed (E), match number is the event sequence of the execution
+    Executed {
+        timestamp: Timestamp,
+        order_id: Oid,
+        volume: Volume,
+        price: Price,
+        match_number: u64,
+    },
+    /// Canceled (C), volume is the number of cancelled shares
+    Canceled {
+        timestamp: Timestamp,
+        order_id: Oid,
+        volume: Volume,
+        reason: CancelReason,
+    },
+}
+
+impl OuchResponse {
+    /// Accepted, Executed of both orders of each execution and Canceled remainder
+    /// of the order executed on the book
+    pub fn for_trade(order: &Order, trade: &Trade, timestamp: Timestamp) -> Vec<Self> {
+        let mut responses = Vec::with_capacity(2 + 2 * trade.executions.len());
+        responses.push(OuchResponse::Accepted {
+            timestamp,
+            order_id: order.id,
+            side: order.side,
+            volume: order.volume,
+            price: order.price,
+        });
+        for execution in &trade.executions {
+            for order_id in [execution.order_id, trade.order_id] {
+                responses.push(OuchResponse::Executed {
+                    timestamp,
+                    order_id,
+                    volume: execution.volume,
+                    price: execution.price,
+                    match_number: execution.sequence,
+                });
+            }
+        }
+        if !trade.cancelled_volume.is_zero() {
+            responses.push(OuchResponse::Canceled {
+                timestamp,
+                order_id: trade.order_id,
+                volume: trade.cancelled_volume,
+                reason: CancelReason::ImmediateOrCancel,
+            });
+        }
+        responses
+    }
+
+    /// number of bytes of the encoded message
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            OuchResponse::Accepted { .. } => 26,
+            OuchResponse::Executed { .. } => 33,
+            OuchResponse::Canceled { .. } => 22,
+        }
+    }
+
+    /// encode the message, returns the number of bytes written
+    pub fn encode(&self, buffer: &mut [u8]) -> Result<usize, OuchError> {
+        let len = self.encoded_len();
+        if buffer.len() < len {
+            return Err(OuchError::BufferTooShort {
+                required: len,
+                available: buffer.len(),
+            });
+        }
+        let mut writer = Writer { buffer, offset: 0 };
+        match *self {
+            OuchResponse::Accepted {
+                timestamp,
+                order_id,
+                side,
+                volume,
+                price,
+            } => {
+                writer.header(b'A', timestamp, order_id);
+                writer.u8(side_code(side));
+                writer.u32(shares(volume)?);
+                writer.u32(price.map_or(Ok(MARKET_PRICE), price_code)?);
+            }
+            OuchResponse::Executed {
+                timestamp,
+                order_id,
+                volume,
+                price,
+                match_number,
+            } => {
+                writer.header(b'E', timestamp, order_id);
+                writer.u32(shares(volume)?);
+                writer.u32(price_code(price)?);
+                writer.u64(match_number);
+            }
+            OuchResponse::Canceled {
+                timestamp,
+                order_id,
+                volume,
+                reason,
+            } => {
+                writer.header(b'C', timestamp, order_id);
+                writer.u32(shares(volume)?);
+                writer.u8(reason.code());
+            }
+        }
+        Ok(len)
+    }
+
+    /// decode the encoded response, i.e. by the client
+    pub fn decode(bytes: &[u8]) -> Result<Self, OuchError> {
+        let Some(&kind) = bytes.first() else {
+            return Err(OuchError::Truncated(' '));
+        };
+        let expected_len = match kind {
+            b'A' => 26,
+            b'E' => 33,
+            b'C' => 22,
+            _ => return Err(OuchError::UnsupportedMessage(kind as char)),
+        };
+        if bytes.len() < expected_len {
+            return Err(OuchError::Truncated(kind as char));
+        }
+        let mut reader = Reader { bytes: &bytes[1..] };
+        let timestamp = Timestamp::new(reader.u64());
+        let order_id = Oid::new(reader.u64());
+        let response = match kind {
+            b'A' => OuchResponse::Accepted {
+                timestamp,
+                order_id,
+                side: side_from_code(reader.u8())?,
+                volume: reader.shares(),
+                price: match reader.u32() {
+                    MARKET_PRICE => None,
+                    price => Some(ouch_price(price)),
+                },
+            },
+            b'E' => OuchResponse::Executed {
+                timestamp,
+                order_id,
+                volume: reader.shares(),
+                price: ouch_price(reader.u32()),
+                match_number: reader.u64(),
+            },
+            _ => OuchResponse::Canceled {
+                timestamp,
+                order_id,
+                volume: reader.shares(),
+                reason: match reader.u8() {
+                    b'U' => CancelReason::UserRequested,
+                    b'I' => CancelReason::ImmediateOrCancel,
+                    b'T' => CancelReason::Timeout,
+                    _ => return Err(OuchError::InvalidValue("reason")),
+                },
+            },
+        };
+        Ok(response)
+    }
+}
+
+fn side_code(side: OrderSide) -> u8 {
+    match side {
+        OrderSide::Buy => b'B',
+        OrderSide::Sell => b'S',
+    }
+}
+
+fn side_from_code(code: u8) -> Result<OrderSide, OuchError> {
+    match code {
+        b'B' => Ok(OrderSide::Buy),
+        b'S' => Ok(OrderSide::Sell),
+        _ => Err(OuchError::InvalidValue("side")),
+    }
+}
+
+fn ouch_price(price: u32) -> Price {
+    Price::from_mantissa(price as i64 * (PRICE_SCALE / OUCH_PRICE_SCALE))
+}
+
+/// price with more than 4 decimal places or out of the range of the field is not encoded
+fn price_code(price: Price) -> Result<u32, OuchError> {
+    let scale = PRICE_SCALE / OUCH_PRICE_SCALE;
+    if price.mantissa() % scale != 0 {
+        return Err(OuchError::InvalidValue("price"));
+    }
+    u32::try_from(price.mantissa() / scale)
+        .ok()
+        .filter(|price| *price != MARKET_PRICE)
+        .ok_or(OuchError::InvalidValue("price"))
+}
+
+/// fractional volume or volume out of the range of the field is not encoded
+fn shares(volume: Volume) -> Result<u32, OuchError> {
+    if !volume.mantissa().is_multiple_of(VOLUME_SCALE) {
+        return Err(OuchError::InvalidValue("shares"));
+    }
+    u32::try_from(volume.mantissa() / VOLUME_SCALE).map_err(|_| OuchError::InvalidValue("shares"))
+}
+
+// reads big endian fields, length is checked before reading
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn uint(&mut self, len: usize) -> u64 {
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        head.iter()
+            .fold(0, |value, byte| (value << 8) | *byte as u64)
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.uint(1) as u8
+    }
+
+    fn u32(&mut self) -> u32 {
+        self.uint(4) as u32
+    }
+
+    fn u64(&mut self) -> u64 {
+        self.uint(8)
+    }
+
+    fn shares(&mut self) -> Volume {
+        Volume::new(self.u32() as u64)
+    }
+}
+
+// writes big endian fields, length is checked before writing
+struct Writer<'a> {
+    buffer: &'a mut [u8],
+    offset: usize,
+}
+
+impl Writer<'_> {
+    fn put(&mut self, bytes: &[u8]) {
+        self.buffer[self.offset..self.offset + bytes.len()].copy_from_slice(bytes);
+        self.offset += bytes.len();
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.put(&[value]);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.put(&value.to_be_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.put(&value.to_be_bytes());
+    }
+
+    fn header(&mut self, kind: u8, timestamp: Timestamp, order_id: Oid) {
+        self.u8(kind);
+        self.u64(timestamp.into());
+        self.u64(order_id.into());
+    }
+}
+
+#[cfg(test)]
+mod tests_ouch {
+    use crate::ouch::{CancelReason, OuchError, OuchRequest, OuchResponse, MARKET_PRICE};
+    use crate::*;
+
+    fn enter(token: u64, side: u8, shares: u32, price: u32, time_in_force: u32) -> Vec<u8> {
+        let mut bytes = vec![b'O'];
+        bytes.extend(token.to_be_bytes());
+        bytes.push(side);
+        bytes.extend(shares.to_be_bytes());
+        bytes.extend(price.to_be_bytes());
+        bytes.extend(time_in_force.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_ouch_requests() {
+        let now = Timestamp::new(7);
+        let OuchRequest::Enter(order) =
+            OuchRequest::decode(&enter(42, b'S', 100, 102_500, 99_999), now).unwrap()
+        else {
+            panic!("expected enter order");
+        };
+        assert_eq!(order.id, Oid::new(42));
+        assert_eq!(order.side, OrderSide::Sell);
+        assert_eq!(order.price, Some(Price::new(10.25)));
+        assert_eq!(order.volume, Volume::new(100));
+        assert_eq!(order.time_in_force, TimeInForce::GoodTillCancel);
+        assert_eq!(order.timestamp, now);
+
+        let OuchRequest::Enter(order) =
+            OuchRequest::decode(&enter(43, b'B', 5, MARKET_PRICE, 0), now).unwrap()
+        else {
+            panic!("expected enter order");
+        };
+        assert_eq!(order.kind, OrderType::Market);
+        assert_eq!(order.time_in_force, TimeInForce::ImmediateOrCancel);
+
+        let mut cancel = vec![b'X'];
+        cancel.extend(42u64.to_be_bytes());
+        assert_eq!(
+            OuchRequest::decode(&cancel, now),
+            Ok(OuchRequest::Cancel(Oid::new(42)))
+        );
+        let mut replace = vec![b'U'];
+        replace.extend(42u64.to_be_bytes());
+        replace.extend(50u32.to_be_bytes());
+        replace.extend(103_000u32.to_be_bytes());
+        assert_eq!(
+            OuchRequest::decode(&replace, now),
+            Ok(OuchRequest::Replace {
+                order_id: Oid::new(42),
+                price: Price::new(10.3),
+                volume: Volume::new(50),
+            })
+        );
+
+        assert_eq!(
+            OuchRequest::decode(&cancel[..5], now),
+            Err(OuchError::Truncated('X'))
+        );
+        assert_eq!(
+            OuchRequest::decode(&enter(1, b'Z', 1, 1, 0), now),
+            Err(OuchError::InvalidValue("side"))
+        );
+        assert_eq!(
+            OuchRequest::decode(b"Q", now),
+            Err(OuchError::UnsupportedMessage('Q'))
+        );
+    }
+
+    #[test]
+    fn test_ouch_gateway_responses() {
+        let mut order_book = OrderBook::default();
+        let now = Timestamp::new(1_000);
+        let mut buffer = [0u8; 64];
+        for bytes in [
+            enter(1, b'S', 30, 100_000, 99_999),
+            enter(2, b'B', 50, 100_000, 0),
+        ] {
+            let Ok(OuchRequest::Enter(order)) = OuchRequest::decode(&bytes, now) else {
+                panic!("expected enter order");
+            };
+            let trade = order_book.execute(&order).unwrap();
+            for response in OuchResponse::for_trade(&order, &trade, now) {
+                let len = response.encode(&mut buffer).unwrap();
+                assert_eq!(len, response.encoded_len());
+                assert_eq!(OuchResponse::decode(&buffer[..len]), Ok(response));
+            }
+        }
+
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Buy,
+            now,
+            Price::new(10.0),
+            Volume::new(50),
+        )
+        .with_time_in_force(TimeInForce::ImmediateOrCancel);
+        let mut order_book = OrderBook::default();
+        order_book
+            .execute(&Order::new_limit(
+                Oid::new(1),
+                OrderSide::Sell,
+                now,
+                Price::new(10.0),
+                Volume::new(30),
+            ))
+            .unwrap();
+        let trade = order_book.execute(&order).unwrap();
+        let responses = OuchResponse::for_trade(&order, &trade, now);
+        assert_eq!(responses.len(), 4);
+        assert!(matches!(
+            responses[1],
+            OuchResponse::Executed { order_id, .. } if order_id == Oid::new(1)
+        ));
+        assert_eq!(
+            responses[3],
+            OuchResponse::Canceled {
+                timestamp: now,
+                order_id: Oid::new(2),
+                volume: Volume::new(20),
+                reason: CancelReason::ImmediateOrCancel,
+            }
+        );
+        let len = responses[3].encode(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len][17..], &[0, 0, 0, 20, b'I']);
+
+        assert_eq!(
+            CancelReason::from_status(&CancellationStatus::Expired),
+            Some(CancelReason::Timeout)
+        );
+        let fractional = OuchResponse::Executed {
+            timestamp: now,
+            order_id: Oid::new(1),
+            volume: Volume::new(1),
+            price: Price::new(10.00001),
+            match_number: 1,
+        };
+        assert_eq!(
+            fractional.encode(&mut buffer),
+            Err(OuchError::InvalidValue("price"))
+        );
+        assert!(matches!(
+            fractional.encode(&mut buffer[..10]),
+            Err(OuchError::BufferTooShort { required: 33, .. })
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+//!
+//! Ownership of orders. Orders can carry the id of the participant or account that submitted
+//! them, the book keeps an index of resting orders by owner, so per owner queries do not need
+//! to scan the whole book.
+//!
+
+use alloc::vec::Vec;
+
+use crate::notional::{notional_price, scaled_notional};
+use crate::{LevelStore, Oid, OrderBook, OrderSide, OrderView, OwnerId, Price, Volume};
+
+impl<S: LevelStore> OrderBook<S> {
+    /// owner of the resting order
+    pub fn owner_of(&self, id: Oid) -> Option<OwnerId> {
+        self.orders.get(&id).and_then(|order| order.owner)
+    }
+
+    /// resting orders of the owner, ordered by order id
+    pub fn open_orders(&self, owner: OwnerId) -> Vec<OrderView> {
+        let mut orders = self
+            .orders
+            .owned_by(owner)
+            .map(OrderView::resting)
+            .collect::<Vec<_>>();
+        orders.sort_by_key(|order| u64::from(order.id));
+        orders
+    }
+
+    /// volume of the owner's orders on the side of the book that has not been filled yet,
+    /// hidden volume of iceberg orders included
+    pub fn open_volume(&self, owner: OwnerId, side: OrderSide) -> Volume {
+        self.orders
+            .owned_by(owner)
+            .filter(|order| order.side == side)
+            .map(|order| order.remaining_volume())
+            .sum()
+    }
+
+    /// price times open volume of the owner's orders on the side of the book,
+    /// times the contract multiplier
+    pub fn open_notional(&self, owner: OwnerId, side: OrderSide) -> Price {
+        let multiplier = self.contract_multiplier();
+        notional_price(
+            self.orders
+                .owned_by(owner)
+                .filter(|order| order.side == side)
+                .map(|order| scaled_notional(order.price, order.remaining_volume(), multiplier))
+                .sum(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_owner {
+    use crate::*;
+
+    #[test]
+    fn test_open_orders_by_owner() {
+        let (alice, bob) = (OwnerId::new(1), OwnerId::new(2));
+        let mut order_book = OrderBook::default();
+        for (id, owner, side, price, volume) in [
+            (3, alice, OrderSide::Buy, 10.0, 5),
+            (1, alice, OrderSide::Buy, 9.5, 2),
+            (2, bob, OrderSide::Sell, 11.0, 4),
+            (4, alice, OrderSide::Sell, 12.0, 1),
+        ] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            )
+            .with_owner(owner);
+            order_book.execute(&order).unwrap();
+        }
+        let order = Order::new_market(Oid::new(5), OrderSide::Sell, Timestamp::new(5), 3.into())
+            .with_owner(bob);
+        order_book.execute(&order).unwrap();
+
+        let open = order_book.open_orders(alice);
+        assert_eq!(
+            open.iter().map(|o| (o.id, o.state)).collect::<Vec<_>>(),
+            vec![
+                (Oid::new(1), OrderState::New),
+                (Oid::new(3), OrderState::PartiallyFilled),
+                (Oid::new(4), OrderState::New),
+            ]
+        );
+        assert_eq!(order_book.open_volume(alice, OrderSide::Buy), 4.into());
+        assert_eq!(
+            order_book.open_notional(alice, OrderSide::Buy),
+            Price::new(39.0)
+        );
+        assert_eq!(order_book.owner_of(Oid::new(2)), Some(bob));
+
+        order_book.cancel_order(Oid::new(2)).unwrap();
+        assert!(order_book.open_orders(bob).is_empty());
+        assert_eq!(order_book.open_volume(bob, OrderSide::Sell), Volume::ZERO);
+    }
+}
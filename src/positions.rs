@@ -0,0 +1,169 @@
+//!
+//! Positions of participants built from fills. Each owner has a signed position per symbol,
+//! positive is long and negative is short, with the average price of the open position
+//! and the profit or loss realized by reducing it.
+//!
+
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::utils::VOLUME_SCALE;
+use crate::{OrderSide, OwnerId, Price, Symbol, Volume};
+
+/// Position of the owner in one symbol
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Position {
+    /// signed volume in the smallest volume increments, positive is long and negative is short
+    pub quantity: i64,
+    /// average price of the open position, zero when the position is flat
+    pub average_price: Price,
+    /// profit or loss realized by reducing the position, price times volume
+    pub realized_pnl: Price,
+}
+
+impl Position {
+    pub fn is_flat(&self) -> bool {
+        self.quantity == 0
+    }
+
+    /// apply the fill of the side at the price to the position
+    pub fn apply(&mut self, side: OrderSide, price: Price, volume: Volume) {
+        let volume = *volume as i64;
+        let signed = match side {
+            OrderSide::Buy => volume,
+            OrderSide::Sell => -volume,
+        };
+        let quantity = self.quantity;
+
+        if quantity == 0 || quantity.signum() == signed.signum() {
+            // increase the position, average price is weighted by volume
+            let total = quantity.abs() as i128 + volume as i128;
+            let weighted = self.average_price.mantissa() as i128 * quantity.abs() as i128
+                + price.mantissa() as i128 * volume as i128;
+            self.average_price = Price::from_mantissa((weighted / total) as i64);
+            self.quantity += signed;
+            return;
+        }
+
+        // reduce the position, the part above the open position opens a new one at the price
+        let closed = volume.min(quantity.abs());
+        let pnl = (price.mantissa() as i128 - self.average_price.mantissa() as i128)
+            * closed as i128
+            * quantity.signum() as i128
+            / VOLUME_SCALE as i128;
+        self.realized_pnl = Price::from_mantissa(self.realized_pnl.mantissa() + pnl as i64);
+        self.quantity += signed;
+        if self.quantity == 0 {
+            self.average_price = Price::ZERO;
+        } else if self.quantity.signum() != quantity.signum() {
+            self.average_price = price;
+        }
+    }
+}
+
+/// Positions of all owners in all symbols
+#[derive(Debug, Clone, Default)]
+pub struct Positions {
+    positions: HashMap<(Symbol, OwnerId), Position>,
+}
+
+impl Positions {
+    pub fn new() -> Self {
+        Positions::default()
+    }
+
+    /// apply the fill of the owner's order to the position
+    pub fn apply(
+        &mut self,
+        symbol: &Symbol,
+        owner: OwnerId,
+        side: OrderSide,
+        price: Price,
+        volume: Volume,
+    ) {
+        self.positions
+            .entry((symbol.clone(), owner))
+            .or_default()
+            .apply(side, price, volume);
+    }
+
+    /// position of the owner in the symbol, flat position if the owner has not traded it
+    pub fn position(&self, symbol: &Symbol, owner: OwnerId) -> Position {
+        self.positions
+            .get(&(symbol.clone(), owner))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// positions of the owner in all symbols that it has traded
+    pub fn positions_of(&self, owner: OwnerId) -> Vec<(&Symbol, Position)> {
+        let mut positions = self
+            .positions
+            .iter()
+            .filter(|((_, o), _)| *o == owner)
+            .map(|((symbol, _), position)| (symbol, *position))
+            .collect::<Vec<_>>();
+        positions.sort_by(|l, r| l.0.cmp(r.0));
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests_positions {
+    use crate::*;
+
+    #[test]
+    fn test_position_average_price_and_pnl() {
+        let units = |units: i64| units * utils::VOLUME_SCALE as i64;
+        let mut position = Position::default();
+        position.apply(OrderSide::Buy, 10.0.into(), 10.into());
+        position.apply(OrderSide::Buy, 13.0.into(), 5.into());
+        assert_eq!(position.quantity, units(15));
+        assert_eq!(position.average_price, Price::new(11.0));
+
+        position.apply(OrderSide::Sell, 12.0.into(), 5.into());
+        assert_eq!(position.quantity, units(10));
+        assert_eq!(position.average_price, Price::new(11.0));
+        assert_eq!(position.realized_pnl, Price::new(5.0));
+
+        // position flips to short at the fill price
+        position.apply(OrderSide::Sell, 10.0.into(), 12.into());
+        assert_eq!(position.quantity, units(-2));
+        assert_eq!(position.average_price, Price::new(10.0));
+        assert_eq!(position.realized_pnl, Price::new(-5.0));
+
+        position.apply(OrderSide::Buy, 9.0.into(), 2.into());
+        assert!(position.is_flat());
+        assert_eq!(position.average_price, Price::ZERO);
+        assert_eq!(position.realized_pnl, Price::new(-3.0));
+    }
+
+    #[test]
+    fn test_positions_per_symbol() {
+        let (aapl, msft) = (Symbol::new("AAPL"), Symbol::new("MSFT"));
+        let owner = OwnerId::new(1);
+        let units = |units: i64| units * utils::VOLUME_SCALE as i64;
+        let mut positions = Positions::new();
+        positions.apply(&msft, owner, OrderSide::Sell, 300.0.into(), 2.into());
+        positions.apply(&aapl, owner, OrderSide::Buy, 100.0.into(), 1.into());
+        positions.apply(
+            &aapl,
+            OwnerId::new(2),
+            OrderSide::Sell,
+            100.0.into(),
+            1.into(),
+        );
+
+        assert_eq!(positions.position(&msft, owner).quantity, units(-2));
+        assert_eq!(
+            positions
+                .positions_of(owner)
+                .iter()
+                .map(|(symbol, position)| (symbol.as_str(), position.quantity))
+                .collect::<Vec<_>>(),
+            vec![("AAPL", units(1)), ("MSFT", units(-2))]
+        );
+        assert!(positions.position(&msft, OwnerId::new(2)).is_flat());
+    }
+}
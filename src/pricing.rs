@@ -0,0 +1,92 @@
+//!
+//! Trade price determination. When two orders match, the policy configured on the book decides
+//! the price the trade is executed at, so fills, executions and the trade tape all report
+//! the same price.
+//!
+
+use crate::Price;
+
+/// Trade price policy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TradePricePolicy {
+    /// trade at the price of the order that was resting on the book
+    #[default]
+    PassiveOrder,
+    /// trade at the price of the order that took the liquidity
+    AggressorOrder,
+    /// trade halfway between both order prices, rounded towards zero
+    Midpoint,
+}
+
+impl TradePricePolicy {
+    /// price of the trade between the passive order and the aggressor,
+    /// market order has no price, so it always trades at the passive order price
+    pub fn trade_price(&self, passive: Price, aggressor: Option<Price>) -> Price {
+        let Some(aggressor) = aggressor else {
+            return passive;
+        };
+        match self {
+            TradePricePolicy::PassiveOrder => passive,
+            TradePricePolicy::AggressorOrder => aggressor,
+            TradePricePolicy::Midpoint => {
+                let sum = passive.mantissa() as i128 + aggressor.mantissa() as i128;
+                Price::from_mantissa((sum / 2) as i64)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_pricing {
+    use crate::*;
+
+    #[test]
+    fn test_trade_price_policy() {
+        let order_book = OrderBook::default();
+        assert_eq!(
+            order_book.trade_price_policy(),
+            TradePricePolicy::PassiveOrder
+        );
+
+        let mut order_book =
+            OrderBook::default().with_trade_price_policy(TradePricePolicy::Midpoint);
+        for (id, side, price) in [(1, OrderSide::Buy, 10.5), (2, OrderSide::Sell, 10.0)] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        let fill = order_book.find_and_fill_best_orders().unwrap();
+        assert_eq!(fill.trade_price, Price::new(10.25));
+
+        let mut order_book =
+            OrderBook::default().with_trade_price_policy(TradePricePolicy::AggressorOrder);
+        let order = LimitOrder::new(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            10.0.into(),
+            5.into(),
+        );
+        order_book.add_order(order).unwrap();
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Buy,
+            Timestamp::new(2),
+            10.5.into(),
+            2.into(),
+        );
+        let trade = order_book.execute(&order).unwrap();
+        assert_eq!(trade.executions[0].price, Price::new(10.5));
+
+        // market order has no price of its own
+        let order = Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(3), 2.into());
+        let trade = order_book.execute(&order).unwrap();
+        assert_eq!(trade.executions[0].price, Price::new(10.0));
+    }
+}
@@ -1,30 +1,45 @@
 //!
 //! This module contains all the basic primitives that makes up the core of the order book
 
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
-use std::hash::Hash;
-use std::iter::Sum;
-use std::ops::{Add, AddAssign, Deref, DerefMut, Sub, SubAssign};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::hash::{BuildHasherDefault, Hash, Hasher};
+use core::iter::Sum;
+use core::ops::{Add, AddAssign, Deref, DerefMut, Sub, SubAssign};
+use core::str::FromStr;
+
+use hashbrown::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::utils::{self, PRICE_DECIMALS, PRICE_SCALE, VOLUME_DECIMALS, VOLUME_SCALE};
 
 /// Spread
-#[derive(Debug, PartialEq, PartialOrd, Clone)]
-pub struct Spread(pub f64);
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+pub struct Spread(pub Price);
+
+impl From<Price> for Spread {
+    fn from(value: Price) -> Self {
+        Spread(value)
+    }
+}
 
 impl From<f64> for Spread {
     fn from(value: f64) -> Self {
-        Spread(value)
+        Spread(value.into())
     }
 }
 
 impl From<Spread> for f64 {
     fn from(value: Spread) -> Self {
-        value.0
+        value.0.into()
     }
 }
 
 /// Order side
-#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OrderSide {
     /// Buy side
     Buy,
@@ -32,15 +47,107 @@ pub enum OrderSide {
     Sell,
 }
 
+impl OrderSide {
+    /// side of the book the order is matched against
+    pub fn opposite(&self) -> Self {
+        match self {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        }
+    }
+}
+
+impl Display for OrderSide {
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), core::fmt::Error> {
+        f.write_str(match self {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        })
+    }
+}
+
+impl FromStr for OrderSide {
+    type Err = ParseEnumError;
+
+    /// case insensitive `buy` or `sell`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "buy" => Ok(OrderSide::Buy),
+            "sell" => Ok(OrderSide::Sell),
+            _ => Err(ParseEnumError::OrderSide(s.to_string())),
+        }
+    }
+}
+
 /// Order type
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OrderType {
     Market,
     Limit,
+    /// Executed at the best opposite price, remainder rests as a limit order at that price
+    MarketToLimit,
+}
+
+impl Display for OrderType {
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), core::fmt::Error> {
+        f.write_str(match self {
+            OrderType::Market => "market",
+            OrderType::Limit => "limit",
+            OrderType::MarketToLimit => "market_to_limit",
+        })
+    }
+}
+
+impl FromStr for OrderType {
+    type Err = ParseEnumError;
+
+    /// case insensitive `market`, `limit` or `market_to_limit`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "market" => Ok(OrderType::Market),
+            "limit" => Ok(OrderType::Limit),
+            "market_to_limit" => Ok(OrderType::MarketToLimit),
+            _ => Err(ParseEnumError::OrderType(s.to_string())),
+        }
+    }
+}
+
+/// Parse order side or order type error
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum ParseEnumError {
+    #[error("Invalid order side: {0}")]
+    OrderSide(String),
+    #[error("Invalid order type: {0}")]
+    OrderType(String),
+}
+
+/// Time in force
+/// how long the order remains active on the book
+#[derive(Debug, Default, PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeInForce {
+    /// Order rests on the book until it is filled or cancelled
+    #[default]
+    GoodTillCancel,
+    /// Order is matched immediately, remainder that cannot be filled is cancelled
+    ImmediateOrCancel,
+}
+
+/// Post only instruction
+/// post only order must add liquidity to the book, it is never matched on arrival
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PostOnly {
+    /// Order that would cross the spread is rejected
+    Reject,
+    /// Order that would cross the spread is re-priced one tick away from the best opposite price
+    Slide { tick_size: Price },
 }
 
 /// Order Id
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Oid(u64);
 
 impl Oid {
@@ -50,7 +157,7 @@ impl Oid {
 }
 
 impl Display for Oid {
-    fn fmt(&self, f: &mut Formatter) -> std::result::Result<(), std::fmt::Error> {
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), core::fmt::Error> {
         write!(f, "{}", self.0)
     }
 }
@@ -60,8 +167,46 @@ impl From<u64> for Oid {
         Oid(value)
     }
 }
+
+impl From<Oid> for u64 {
+    fn from(value: Oid) -> Self {
+        value.0
+    }
+}
+
+/// Owner Id
+/// participant or account that submitted the order
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnerId(u64);
+
+impl OwnerId {
+    pub fn new(value: u64) -> Self {
+        OwnerId(value)
+    }
+}
+
+impl Display for OwnerId {
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<u64> for OwnerId {
+    fn from(value: u64) -> Self {
+        OwnerId(value)
+    }
+}
+
+impl From<OwnerId> for u64 {
+    fn from(value: OwnerId) -> Self {
+        value.0
+    }
+}
+
 /// Timestamp
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Timestamp(u64);
 
 impl Timestamp {
@@ -70,6 +215,27 @@ impl Timestamp {
     }
 }
 
+impl From<Timestamp> for u64 {
+    fn from(value: Timestamp) -> Self {
+        value.0
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = core::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().parse().map(Timestamp)
+    }
+}
+
+#[cfg(feature = "chrono")]
 impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
     fn from(value: chrono::DateTime<chrono::Utc>) -> Self {
         Timestamp(value.timestamp_millis() as u64)
@@ -77,49 +243,52 @@ impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
 }
 
 /// Price
-#[derive(Debug, Clone, Copy)]
-pub struct Price(f64);
+/// fixed point decimal with `PRICE_DECIMALS` decimal places stored as a scaled integer,
+/// so the same price always maps to the same level regardless of how it was computed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Price(i64);
 
 impl Price {
-    pub const ZERO: Self = Price(0.0);
-    pub const MAX: Self = Price(f64::MAX);
-    pub const MIN: Self = Price(f64::MIN);
+    pub const ZERO: Self = Price(0);
+    pub const MAX: Self = Price(i64::MAX);
+    pub const MIN: Self = Price(i64::MIN);
 
+    /// Create price from f64, rounded to the nearest representable price
     pub fn new(value: f64) -> Self {
-        Self(value)
+        Price(utils::scale_f64(value, PRICE_SCALE))
     }
-}
 
-impl Default for Price {
-    fn default() -> Self {
-        Price::ZERO
+    /// Create price from the scaled integer, i.e. 2_104_530_000 is 21.0453
+    pub const fn from_mantissa(mantissa: i64) -> Self {
+        Price(mantissa)
     }
-}
 
-impl Eq for Price {}
+    /// scaled integer representation of the price
+    pub fn mantissa(&self) -> i64 {
+        self.0
+    }
 
-impl PartialEq for Price {
-    fn eq(&self, other: &Self) -> bool {
-        self.0.to_bits() == other.0.to_bits()
+    pub fn to_f64(&self) -> f64 {
+        self.0 as f64 / PRICE_SCALE as f64
     }
-}
 
-impl Hash for Price {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.0.to_bits().hash(state);
+    /// None if the sum overflows
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Price)
     }
-}
 
-impl PartialOrd for Price {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    /// None if the difference overflows
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Price)
     }
-}
 
-impl Ord for Price {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // Compare bit patterns to handle NaN values consistently
-        self.0.to_bits().cmp(&other.0.to_bits())
+    pub fn saturating_add(self, other: Self) -> Self {
+        Price(self.0.saturating_add(other.0))
+    }
+
+    pub fn saturating_sub(self, other: Self) -> Self {
+        Price(self.0.saturating_sub(other.0))
     }
 }
 
@@ -151,85 +320,221 @@ impl Add for Price {
     }
 }
 
+impl Display for Price {
+    /// decimal without trailing zeros, precision of the formatter rounds the price
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), core::fmt::Error> {
+        utils::fmt_scaled(f, self.0 as i128, PRICE_DECIMALS)
+    }
+}
+
 impl From<Price> for f64 {
     fn from(value: Price) -> Self {
-        value.0
+        value.to_f64()
     }
 }
 
 impl From<f64> for Price {
     fn from(value: f64) -> Self {
-        Price(value)
+        Price::new(value)
     }
 }
 
-impl Deref for Price {
-    type Target = f64;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
+/// Parse price error
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum ParsePriceError {
+    #[error("Invalid price: {0}")]
+    Invalid(String),
+    #[error("Price {0} has more than {PRICE_DECIMALS} decimal places")]
+    TooManyDecimals(String),
+    #[error("Price {0} is out of range")]
+    OutOfRange(String),
 }
 
-impl DerefMut for Price {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl FromStr for Price {
+    type Err = ParsePriceError;
+
+    /// parse decimal string exactly, without going through f64
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        utils::parse_scaled(s, PRICE_DECIMALS)
+            .map(Price)
+            .map_err(|e| match e {
+                utils::ParseScaledError::Invalid => ParsePriceError::Invalid(s.to_string()),
+                utils::ParseScaledError::TooManyDecimals => {
+                    ParsePriceError::TooManyDecimals(s.to_string())
+                }
+                utils::ParseScaledError::OutOfRange => ParsePriceError::OutOfRange(s.to_string()),
+            })
     }
 }
 
 /// Volume
-#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Eq, Ord)]
+/// fixed point decimal with `VOLUME_DECIMALS` decimal places stored as a scaled integer,
+/// dereferences to the scaled integer, the number of the smallest volume increments.
+/// Scale depends on the `fractional-volume` feature, so volume is converted from and to
+/// whole units and serialized as a decimal string, never as the scaled integer.
+#[derive(Debug, Default, PartialEq, PartialOrd, Clone, Copy, Eq, Ord, Hash)]
 pub struct Volume(u64);
 
 impl Volume {
     pub const ZERO: Self = Volume(0);
 
-    pub fn new(value: u64) -> Self {
-        Volume(value)
+    /// Create volume of whole units, saturates at the largest volume
+    pub fn new(units: u64) -> Self {
+        Volume(units.saturating_mul(VOLUME_SCALE))
+    }
+
+    /// Create volume of whole units, None if it is not representable
+    pub fn try_new(units: u64) -> Option<Self> {
+        units.checked_mul(VOLUME_SCALE).map(Volume)
+    }
+
+    /// Create volume from the scaled integer
+    pub const fn from_mantissa(mantissa: u64) -> Self {
+        Volume(mantissa)
+    }
+
+    /// Create volume from f64, rounded to the nearest representable volume,
+    /// negative values are zero
+    pub fn from_f64(value: f64) -> Self {
+        Volume(utils::scale_f64(value, VOLUME_SCALE as i64).max(0) as u64)
+    }
+
+    pub fn mantissa(&self) -> u64 {
+        self.0
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.0 as f64 / VOLUME_SCALE as f64
     }
 
     pub fn is_zero(&self) -> bool {
         self.0 == 0
     }
+
+    /// None if the sum overflows
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Volume)
+    }
+
+    /// None if the other volume is bigger
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Volume)
+    }
+
+    pub fn saturating_add(self, other: Self) -> Self {
+        Volume(self.0.saturating_add(other.0))
+    }
+
+    /// zero if the other volume is bigger
+    pub fn saturating_sub(self, other: Self) -> Self {
+        Volume(self.0.saturating_sub(other.0))
+    }
 }
 
 impl From<u64> for Volume {
     fn from(value: u64) -> Self {
-        Volume(value)
+        Volume::new(value)
     }
 }
 
-impl From<Volume> for u64 {
-    fn from(value: Volume) -> Self {
-        value.0
+impl Display for Volume {
+    /// whole units, followed by the fraction without trailing zeros
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), core::fmt::Error> {
+        utils::fmt_scaled(f, self.0 as i128, VOLUME_DECIMALS)
+    }
+}
+
+/// Parse volume error
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum ParseVolumeError {
+    #[error("Invalid volume: {0}")]
+    Invalid(String),
+    #[error("Volume {0} has more than {VOLUME_DECIMALS} decimal places")]
+    TooManyDecimals(String),
+    #[error("Volume {0} is out of range")]
+    OutOfRange(String),
+}
+
+impl FromStr for Volume {
+    type Err = ParseVolumeError;
+
+    /// parse decimal string exactly, without going through f64
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match utils::parse_scaled(s, VOLUME_DECIMALS) {
+            Ok(value) if value >= 0 => Ok(Volume(value as u64)),
+            Ok(_) | Err(utils::ParseScaledError::Invalid) => {
+                Err(ParseVolumeError::Invalid(s.to_string()))
+            }
+            Err(utils::ParseScaledError::TooManyDecimals) => {
+                Err(ParseVolumeError::TooManyDecimals(s.to_string()))
+            }
+            Err(utils::ParseScaledError::OutOfRange) => {
+                Err(ParseVolumeError::OutOfRange(s.to_string()))
+            }
+        }
+    }
+}
+
+/// Volume is not a whole number of units
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+#[error("Volume {0} is not a whole number of units")]
+pub struct FractionalVolumeError(pub Volume);
+
+impl TryFrom<Volume> for u64 {
+    type Error = FractionalVolumeError;
+
+    /// whole units of the volume
+    fn try_from(value: Volume) -> Result<Self, Self::Error> {
+        match value.0.is_multiple_of(VOLUME_SCALE) {
+            true => Ok(value.0 / VOLUME_SCALE),
+            false => Err(FractionalVolumeError(value)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Volume {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Volume {
+    /// volume with more decimal places than the build supports is an error
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
     }
 }
 
-impl std::ops::AddAssign for Volume {
+// operators saturate, use checked_add and checked_sub where the overflow has to be detected
+
+impl core::ops::AddAssign for Volume {
     fn add_assign(&mut self, other: Self) {
-        self.0 += other.0;
+        *self = self.saturating_add(other);
     }
 }
 
-impl std::ops::SubAssign for Volume {
+impl core::ops::SubAssign for Volume {
     fn sub_assign(&mut self, other: Self) {
-        self.0 -= other.0;
+        *self = self.saturating_sub(other);
     }
 }
 
-impl std::ops::Add for Volume {
+impl core::ops::Add for Volume {
     type Output = Self;
 
     fn add(self, other: Self) -> Self::Output {
-        Volume(self.0 + other.0)
+        self.saturating_add(other)
     }
 }
 
-impl std::ops::Sub for Volume {
+impl core::ops::Sub for Volume {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self::Output {
-        Volume(self.0 - other.0)
+        self.saturating_sub(other)
     }
 }
 
@@ -289,45 +594,185 @@ impl DerefMut for LevelIndex {
     }
 }
 
+/// Hasher of the integer price keys of the level map
+/// prices are scaled integers or ticks, so the key is hashed with a multiply
+/// instead of a general purpose hasher, the hash is the same across runs and platforms
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LevelHasher(u64);
+
+impl LevelHasher {
+    const MULTIPLIER: u64 = 0x9e37_79b9_7f4a_7c15;
+
+    fn mix(&mut self, word: u64) {
+        self.0 = (self.0 ^ word).wrapping_mul(Self::MULTIPLIER);
+    }
+}
+
+impl Hasher for LevelHasher {
+    fn finish(&self) -> u64 {
+        // well mixed high bits of the product are moved to the low bits used for the buckets,
+        // prices on a tick are multiples of a power of two so their low bits would collide
+        self.0.rotate_left(26)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.mix(u64::from_le_bytes(word));
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.mix(value);
+    }
+
+    fn write_i64(&mut self, value: i64) {
+        self.mix(value as u64);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.mix(value as u64);
+    }
+}
+
 // map of Limit -> LevelIndex
 // this will allow for O(1) lookup of Limit levels
-// this will only grow, since each limit need to point to a stable index in the stable level vec
+// each limit points to a stable index in the stable level vec, until the level is compacted
+// keys are integers, prices never go through floating point on the hot path
 #[derive(Debug, Clone, Default)]
-pub struct LevelMap(pub HashMap<Price, LevelIndex>);
+pub struct LevelMap<P = Price>(pub HashMap<P, LevelIndex, BuildHasherDefault<LevelHasher>>);
 
-impl Deref for LevelMap {
-    type Target = HashMap<Price, LevelIndex>;
+impl<P> Deref for LevelMap<P> {
+    type Target = HashMap<P, LevelIndex, BuildHasherDefault<LevelHasher>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl DerefMut for LevelMap {
+impl<P> DerefMut for LevelMap<P> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
-// map of Order ID -> LimitOrder that contains full order data
-#[derive(Debug, Default)]
-pub struct OrderMap(pub HashMap<Oid, LimitOrder>);
-impl Deref for OrderMap {
-    type Target = HashMap<Oid, LimitOrder>;
+/// OrderHandle is an index to an order in the order slab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderHandle(pub usize);
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
+// slab of orders that contains full order data, with Order ID -> OrderHandle map
+// matching works with handles so the hot path does not need to hash order ids,
+// slots of removed orders are reused
+#[derive(Debug, Clone, Default)]
+pub struct OrderMap {
+    slab: Vec<Option<LimitOrder>>,
+    free: Vec<usize>,
+    handles: HashMap<Oid, OrderHandle>,
+    // ids of the orders of each owner
+    owners: HashMap<OwnerId, HashSet<Oid>>,
 }
 
-impl DerefMut for OrderMap {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl OrderMap {
+    /// insert the order, order with the same id is replaced
+    pub fn insert(&mut self, order: LimitOrder) -> OrderHandle {
+        if let Some(owner) = order.owner {
+            self.owners.entry(owner).or_default().insert(order.id);
+        }
+        if let Some(handle) = self.handles.get(&order.id).copied() {
+            let replaced = self.slab[handle.0].replace(order);
+            if let Some(replaced) = replaced {
+                if replaced.owner != self.slab[handle.0].as_ref().and_then(|o| o.owner) {
+                    self.forget_owner(&replaced);
+                }
+            }
+            return handle;
+        }
+        let id = order.id;
+        let handle = match self.free.pop() {
+            Some(slot) => {
+                self.slab[slot] = Some(order);
+                OrderHandle(slot)
+            }
+            None => {
+                self.slab.push(Some(order));
+                OrderHandle(self.slab.len() - 1)
+            }
+        };
+        self.handles.insert(id, handle);
+        handle
+    }
+
+    pub fn get(&self, id: &Oid) -> Option<&LimitOrder> {
+        let handle = *self.handles.get(id)?;
+        self.get_by_handle(handle)
+    }
+
+    pub fn get_mut(&mut self, id: &Oid) -> Option<&mut LimitOrder> {
+        let handle = *self.handles.get(id)?;
+        self.get_by_handle_mut(handle)
+    }
+
+    pub fn get_by_handle(&self, handle: OrderHandle) -> Option<&LimitOrder> {
+        self.slab.get(handle.0).and_then(Option::as_ref)
+    }
+
+    pub fn get_by_handle_mut(&mut self, handle: OrderHandle) -> Option<&mut LimitOrder> {
+        self.slab.get_mut(handle.0).and_then(Option::as_mut)
+    }
+
+    pub fn remove(&mut self, id: &Oid) -> Option<LimitOrder> {
+        let handle = self.handles.remove(id)?;
+        self.free.push(handle.0);
+        let order = self.slab[handle.0].take()?;
+        self.forget_owner(&order);
+        Some(order)
+    }
+
+    fn forget_owner(&mut self, order: &LimitOrder) {
+        let Some(owner) = order.owner else {
+            return;
+        };
+        if let Some(ids) = self.owners.get_mut(&owner) {
+            ids.remove(&order.id);
+            if ids.is_empty() {
+                self.owners.remove(&owner);
+            }
+        }
+    }
+
+    /// orders of the owner, in no particular order
+    pub fn owned_by(&self, owner: OwnerId) -> impl Iterator<Item = &LimitOrder> {
+        self.owners
+            .get(&owner)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.get(id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// make room for the number of additional orders without reallocating
+    pub fn reserve(&mut self, additional: usize) {
+        self.slab.reserve(additional);
+        self.handles.reserve(additional);
+    }
+
+    /// number of orders held without reallocating
+    pub fn capacity(&self) -> usize {
+        self.slab.capacity().min(self.handles.capacity())
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &LimitOrder> {
+        self.slab.iter().flatten()
     }
 }
 
 /// Order
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Order {
     pub id: Oid,
     pub side: OrderSide,
@@ -335,6 +780,15 @@ pub struct Order {
     pub price: Option<Price>,
     pub volume: Volume,
     pub timestamp: Timestamp,
+    pub time_in_force: TimeInForce,
+    /// good till date orders are removed from the book once expired
+    pub expiry: Option<Timestamp>,
+    /// iceberg orders show only the display volume on the book
+    pub display_volume: Option<Volume>,
+    pub post_only: Option<PostOnly>,
+    pub owner: Option<OwnerId>,
+    /// resting order is only matched by fills of at least this volume
+    pub min_qty: Option<Volume>,
 }
 
 impl Order {
@@ -353,6 +807,12 @@ impl Order {
             timestamp,
             price: Some(price),
             volume,
+            time_in_force: TimeInForce::default(),
+            expiry: None,
+            display_volume: None,
+            post_only: None,
+            owner: None,
+            min_qty: None,
         }
     }
     pub fn new_market(id: Oid, side: OrderSide, timestamp: Timestamp, volume: Volume) -> Self {
@@ -363,30 +823,81 @@ impl Order {
             timestamp,
             price: None,
             volume,
+            time_in_force: TimeInForce::default(),
+            expiry: None,
+            display_volume: None,
+            post_only: None,
+            owner: None,
+            min_qty: None,
+        }
+    }
+
+    pub fn new_market_to_limit(
+        id: Oid,
+        side: OrderSide,
+        timestamp: Timestamp,
+        volume: Volume,
+    ) -> Self {
+        Order {
+            kind: OrderType::MarketToLimit,
+            ..Order::new_market(id, side, timestamp, volume)
         }
     }
+
+    /// Set the time in force of the order
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    /// Set the time at which the order expires
+    pub fn with_expiry(mut self, expiry: Timestamp) -> Self {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    /// Make the order an iceberg order that shows at most display volume on the book
+    pub fn with_display_volume(mut self, display_volume: Volume) -> Self {
+        self.display_volume = Some(display_volume);
+        self
+    }
+
+    /// Make the order a post only order
+    pub fn with_post_only(mut self, post_only: PostOnly) -> Self {
+        self.post_only = Some(post_only);
+        self
+    }
+
+    /// Set the participant or account that submitted the order
+    pub fn with_owner(mut self, owner: OwnerId) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// Set the minimum volume of each fill of the resting order
+    pub fn with_min_qty(mut self, min_qty: Volume) -> Self {
+        self.min_qty = Some(min_qty);
+        self
+    }
+
+    /// Make the order an all or none order, resting order is only filled at once
+    pub fn with_all_or_none(self) -> Self {
+        let volume = self.volume;
+        self.with_min_qty(volume)
+    }
 }
 
 impl TryInto<LimitOrder> for Order {
     type Error = TryFromOrderError;
 
     fn try_into(self) -> Result<LimitOrder, Self::Error> {
-        match self.kind {
-            OrderType::Limit => Ok(LimitOrder {
-                id: self.id,
-                side: self.side,
-                timestamp: self.timestamp,
-                price: self.price.unwrap(), // we can unwrap since we know it is a limit order
-                volume: self.volume,
-                filled_volume: None,
-            }),
-            _ => Err(TryFromOrderError::OrderTypeNotLimit),
-        }
+        LimitOrder::try_from(&self)
     }
 }
 
 /// Limit Order
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LimitOrder {
     pub id: Oid,
     pub side: OrderSide,
@@ -394,6 +905,19 @@ pub struct LimitOrder {
     pub price: Price,
     pub volume: Volume,
     pub filled_volume: Option<Volume>,
+    pub time_in_force: TimeInForce,
+    pub expiry: Option<Timestamp>,
+    /// size of the tranche shown on the book for iceberg orders
+    pub display_volume: Option<Volume>,
+    /// volume of iceberg order that is not yet shown on the book
+    pub hidden_volume: Volume,
+    pub post_only: Option<PostOnly>,
+    pub owner: Option<OwnerId>,
+    /// minimum volume of each fill, fill of the whole remaining volume is always accepted
+    pub min_qty: Option<Volume>,
+    /// slot of the order in the level queue, set while the order rests on the book
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) queue_slot: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -413,12 +937,40 @@ impl TryFrom<&Order> for LimitOrder {
                 price: order.price.unwrap(), // we can unwrap since we know it is a limit order
                 volume: order.volume,
                 filled_volume: None,
+                time_in_force: order.time_in_force,
+                expiry: order.expiry,
+                display_volume: order.display_volume,
+                hidden_volume: Volume::ZERO,
+                post_only: order.post_only,
+                owner: order.owner,
+                min_qty: order.min_qty,
+                queue_slot: None,
             }),
             _ => Err(TryFromOrderError::OrderTypeNotLimit),
         }
     }
 }
 
+impl From<&LimitOrder> for Order {
+    fn from(order: &LimitOrder) -> Self {
+        Order {
+            time_in_force: order.time_in_force,
+            expiry: order.expiry,
+            display_volume: order.display_volume,
+            post_only: order.post_only,
+            owner: order.owner,
+            min_qty: order.min_qty,
+            ..Order::new_limit(
+                order.id,
+                order.side,
+                order.timestamp,
+                order.price,
+                order.volume,
+            )
+        }
+    }
+}
+
 impl LimitOrder {
     /// Create a new order
     pub fn new(
@@ -435,6 +987,218 @@ impl LimitOrder {
             price,
             volume,
             filled_volume: None,
+            time_in_force: TimeInForce::default(),
+            expiry: None,
+            display_volume: None,
+            hidden_volume: Volume::ZERO,
+            post_only: None,
+            owner: None,
+            min_qty: None,
+            queue_slot: None,
+        }
+    }
+
+    /// volume that has not been filled yet
+    pub fn remaining_volume(&self) -> Volume {
+        self.volume
+            .saturating_sub(self.filled_volume.unwrap_or(Volume::ZERO))
+    }
+
+    /// volume shown on the book, for iceberg orders this is the current tranche
+    pub fn visible_volume(&self) -> Volume {
+        self.remaining_volume().saturating_sub(self.hidden_volume)
+    }
+
+    /// show the next tranche of an iceberg order, for other orders whole remaining volume is shown
+    /// returns the volume that is visible after the refresh
+    pub fn refresh_display(&mut self) -> Volume {
+        let remaining = self.remaining_volume();
+        let visible = self
+            .display_volume
+            .map_or(remaining, |display| display.min(remaining));
+        self.hidden_volume = remaining - visible;
+        visible
+    }
+
+    /// check if a fill of the volume satisfies the minimum quantity of the order
+    pub fn accepts_fill(&self, volume: Volume) -> bool {
+        self.min_qty
+            .is_none_or(|min_qty| volume >= min_qty.min(self.remaining_volume()))
+    }
+
+    /// check if the order has expired at the given time
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.expiry.is_some_and(|expiry| expiry <= now)
+    }
+}
+
+#[cfg(test)]
+mod tests_primitives {
+    use super::*;
+
+    #[test]
+    fn test_price_is_exact() {
+        let computed = Price::new(0.1 + 0.2);
+        assert_eq!(computed, "0.3".parse().unwrap());
+        assert_eq!(Price::new(21.0453), Price::from_mantissa(2_104_530_000));
+        assert!(Price::new(-1.0) < Price::ZERO);
+        assert_eq!(
+            "1.123456789".parse::<Price>(),
+            Err(ParsePriceError::TooManyDecimals("1.123456789".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_level_map_keys_are_hashed_as_integers() {
+        let hash = |price: Price| {
+            let mut hasher = LevelHasher::default();
+            price.hash(&mut hasher);
+            hasher.finish()
+        };
+        // same price computed differently hashes the same
+        assert_eq!(hash(Price::new(0.1 + 0.2)), hash(Price::new(0.3)));
+        // prices on a tick of 0.01 spread over the low bits used for the buckets
+        let buckets = (1..=64)
+            .map(|ticks| hash(Price::from_mantissa(ticks * 1_000_000)) & 63)
+            .collect::<HashSet<_>>();
+        assert!(buckets.len() > 48);
+
+        let mut levels = LevelMap::<i64>::default();
+        levels.insert(1_050, LevelIndex(0));
+        assert_eq!(levels.get(&1_050), Some(&LevelIndex(0)));
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        let price = Price::new(21.0453);
+        assert_eq!(price.to_string(), "21.0453");
+        assert_eq!(format!("{price:.2}"), "21.05");
+        assert_eq!(format!("{:.1}", Price::new(-1.25)), "-1.3");
+        assert_eq!(format!("{:.3}", Price::new(10.0)), "10.000");
+        assert_eq!(Price::new(-0.5).to_string(), "-0.5");
+        assert_eq!(price.to_string().parse::<Price>(), Ok(price));
+
+        assert_eq!(Timestamp::new(42).to_string(), "42");
+        assert_eq!("42".parse::<Timestamp>(), Ok(Timestamp::new(42)));
+        for side in [OrderSide::Buy, OrderSide::Sell] {
+            assert_eq!(side.to_string().parse::<OrderSide>(), Ok(side));
         }
+        assert_eq!("SELL".parse::<OrderSide>(), Ok(OrderSide::Sell));
+        assert_eq!(
+            "short".parse::<OrderSide>(),
+            Err(ParseEnumError::OrderSide("short".to_string()))
+        );
+        for kind in [
+            OrderType::Market,
+            OrderType::Limit,
+            OrderType::MarketToLimit,
+        ] {
+            assert_eq!(kind.to_string().parse::<OrderType>(), Ok(kind));
+        }
+    }
+
+    #[test]
+    fn test_volume_is_scaled() {
+        let volume: Volume = "12".parse().unwrap();
+        assert_eq!(volume, Volume::new(12));
+        assert_eq!(volume.mantissa(), 12 * VOLUME_SCALE);
+        assert_eq!(volume.to_string(), "12");
+        assert_eq!(Volume::from_f64(12.0), volume);
+        assert!("-1".parse::<Volume>().is_err());
+
+        let max = Volume::from_mantissa(u64::MAX);
+        assert_eq!(Volume::new(u64::MAX), max);
+        assert_eq!(Volume::try_new(12), Some(volume));
+        assert_eq!(max + volume, max);
+        assert_eq!(volume - max, Volume::ZERO);
+        let mut sum = max;
+        sum += volume;
+        assert_eq!(sum, max);
+
+        assert_eq!(u64::try_from(volume), Ok(12));
+        assert_eq!(Volume::from(u64::try_from(volume).unwrap()), volume);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_volume_is_serialized_as_decimal() {
+        let volume = Volume::new(12);
+        assert_eq!(serde_json::to_string(&volume).unwrap(), "\"12\"");
+        assert_eq!(serde_json::from_str::<Volume>("\"12\"").unwrap(), volume);
+        assert!(serde_json::from_str::<Volume>("\"-1\"").is_err());
+    }
+
+    #[cfg(feature = "fractional-volume")]
+    #[test]
+    fn test_fractional_volume() {
+        let volume: Volume = "0.25".parse().unwrap();
+        assert_eq!(volume + volume, "0.5".parse().unwrap());
+        assert_eq!(Volume::new(1) - volume, Volume::from_f64(0.75));
+        assert_eq!((volume + Volume::new(2)).to_string(), "2.25");
+        assert_eq!(Volume::from_mantissa(1).to_string(), "0.00000001");
+        assert_eq!(Volume::try_new(u64::MAX / VOLUME_SCALE + 1), None);
+        assert_eq!(u64::try_from(volume), Err(FractionalVolumeError(volume)));
+        assert_eq!(
+            "0.123456789".parse::<Volume>(),
+            Err(ParseVolumeError::TooManyDecimals("0.123456789".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_order_map_reuses_slots() {
+        let order = |id| {
+            LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                Price::new(1.0),
+                Volume::new(1),
+            )
+        };
+        let mut orders = OrderMap::default();
+        let first = orders.insert(order(1));
+        orders.insert(order(2));
+        assert_eq!(orders.remove(&Oid::new(1)).map(|o| o.id), Some(Oid::new(1)));
+        assert!(orders.get_by_handle(first).is_none());
+
+        assert_eq!(orders.insert(order(3)), first);
+        assert_eq!(
+            orders.get_mut(&Oid::new(3)).map(|o| o.id),
+            Some(Oid::new(3))
+        );
+        assert_eq!(orders.len(), 2);
+    }
+
+    #[test]
+    fn test_order_map_owner_index() {
+        let order = |id, owner| LimitOrder {
+            owner: Some(OwnerId::new(owner)),
+            ..LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                Price::new(1.0),
+                Volume::new(1),
+            )
+        };
+        let mut orders = OrderMap::default();
+        orders.insert(order(1, 7));
+        orders.insert(order(2, 7));
+        orders.insert(order(3, 8));
+        // replaced order moves to the new owner
+        orders.insert(order(2, 8));
+        orders.remove(&Oid::new(3));
+
+        let owned = |orders: &OrderMap, owner| {
+            let mut ids = orders
+                .owned_by(OwnerId::new(owner))
+                .map(|o| u64::from(o.id))
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        assert_eq!(owned(&orders, 7), vec![1]);
+        assert_eq!(owned(&orders, 8), vec![2]);
+        assert!(owned(&orders, 9).is_empty());
     }
 }
@@ -0,0 +1,90 @@
+//!
+//! Volume profile. Histogram of volume by price bucket, either of the volume resting
+//! on one side of the book or of the volume traded on the tape.
+//! Prices are rounded down to the bucket, buckets are ordered by ascending price
+//! and a bucket that is not positive keeps every price as its own bucket.
+//!
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::depth::bucket_price;
+use crate::{LevelStore, OrderBook, OrderSide, Price, TradeTape, Volume};
+
+fn histogram(prices: impl Iterator<Item = (Price, Volume)>, bucket: Price) -> Vec<(Price, Volume)> {
+    let mut profile = BTreeMap::new();
+    for (price, volume) in prices {
+        let price = if bucket > Price::ZERO {
+            bucket_price(price, bucket, false)
+        } else {
+            price
+        };
+        *profile.entry(price).or_insert(Volume::ZERO) += volume;
+    }
+    profile.into_iter().collect()
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// visible volume resting on the side of the book by price bucket
+    pub fn volume_profile(&self, side: OrderSide, bucket: Price) -> Vec<(Price, Volume)> {
+        histogram(
+            self.iter_levels(side)
+                .map(|level| (level.price(), level.total_volume())),
+            bucket,
+        )
+    }
+
+    /// volume traded by price bucket, empty when the trade tape is not enabled
+    pub fn traded_volume_profile(&self, bucket: Price) -> Vec<(Price, Volume)> {
+        self.trade_tape
+            .as_ref()
+            .map(|tape| tape.volume_profile(bucket))
+            .unwrap_or_default()
+    }
+}
+
+impl TradeTape {
+    /// volume of the trades on the tape by price bucket
+    pub fn volume_profile(&self, bucket: Price) -> Vec<(Price, Volume)> {
+        histogram(self.iter().map(|entry| (entry.price, entry.volume)), bucket)
+    }
+}
+
+#[cfg(test)]
+mod tests_profile {
+    use crate::*;
+
+    #[test]
+    fn test_volume_profile() {
+        let mut order_book = OrderBook::default().with_trade_tape(10);
+        for (id, price, volume) in [(1, 10.02, 1), (2, 10.07, 2), (3, 10.09, 3), (4, 10.4, 4)] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        assert_eq!(
+            order_book.volume_profile(OrderSide::Sell, 0.05.into()),
+            vec![
+                (Price::new(10.0), 1.into()),
+                (Price::new(10.05), 5.into()),
+                (Price::new(10.4), 4.into())
+            ]
+        );
+        assert!(order_book
+            .volume_profile(OrderSide::Buy, 0.05.into())
+            .is_empty());
+
+        let order = Order::new_market(Oid::new(5), OrderSide::Buy, Timestamp::new(5), 4.into());
+        order_book.execute(&order).unwrap();
+        assert_eq!(
+            order_book.traded_volume_profile(0.1.into()),
+            vec![(Price::new(10.0), 4.into())]
+        );
+        assert_eq!(order_book.traded_volume_profile(Price::ZERO).len(), 3);
+    }
+}
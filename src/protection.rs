@@ -0,0 +1,215 @@
+//!
+//! Market maker protection. Volume executed by each owner is tracked over a sliding window,
+//! once it goes above the limit all remaining quotes and orders of the owner are pulled
+//! from the book and a protection event is emitted. The window is measured in the units
+//! of the order timestamps, which are expected to be in milliseconds.
+//! Events are collected with `drain_protection_events`.
+//!
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::{
+    CancellationReport, Execution, Fill, LevelStore, Oid, Order, OrderBook, OwnerId, Timestamp,
+    Volume,
+};
+
+/// Volume an owner can execute within the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtectionLimit {
+    /// length of the window in milliseconds
+    pub window: u64,
+    pub max_volume: Volume,
+}
+
+impl ProtectionLimit {
+    pub fn new(window: u64, max_volume: Volume) -> Self {
+        ProtectionLimit { window, max_volume }
+    }
+}
+
+/// Protection of the owner was triggered and its orders were cancelled
+#[derive(Debug, Clone)]
+pub struct ProtectionEvent {
+    pub owner: OwnerId,
+    /// volume executed within the window when the protection was triggered
+    pub executed: Volume,
+    pub timestamp: Timestamp,
+    pub cancelled: Vec<CancellationReport>,
+}
+
+/// Executions of the owners within the window
+#[derive(Debug, Clone)]
+pub(crate) struct Protection {
+    limit: ProtectionLimit,
+    executions: HashMap<OwnerId, VecDeque<(u64, Volume)>>,
+    events: Vec<ProtectionEvent>,
+}
+
+impl Protection {
+    fn new(limit: ProtectionLimit) -> Self {
+        Protection {
+            limit,
+            executions: HashMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// add the executed volume of the owner, returns the volume within the window
+    /// if it is above the limit
+    fn track(&mut self, owner: OwnerId, now: Timestamp, volume: Volume) -> Option<Volume> {
+        let now = u64::from(now);
+        let window = self.executions.entry(owner).or_default();
+        window.push_back((now, volume));
+        let start = now.saturating_sub(self.limit.window);
+        while window.front().is_some_and(|(time, _)| *time < start) {
+            window.pop_front();
+        }
+        let executed = window.iter().map(|(_, volume)| *volume).sum::<Volume>();
+        (executed > self.limit.max_volume).then_some(executed)
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// pull the orders of owners that execute more than the limit within the window
+    pub fn with_mm_protection(mut self, limit: ProtectionLimit) -> Self {
+        self.protection = Some(Protection::new(limit));
+        self
+    }
+
+    pub fn mm_protection(&self) -> Option<ProtectionLimit> {
+        self.protection.as_ref().map(|protection| protection.limit)
+    }
+
+    /// take the protection events since the last call
+    pub fn drain_protection_events(&mut self) -> Vec<ProtectionEvent> {
+        self.protection
+            .as_mut()
+            .map(|protection| core::mem::take(&mut protection.events))
+            .unwrap_or_default()
+    }
+
+    /// track executions of the order and of the resting orders it was filled against
+    pub(crate) fn protect_after_executions(&mut self, order: &Order, executions: &[Execution]) {
+        if self.protection.is_none() || executions.is_empty() {
+            return;
+        }
+        let mut executed = executions
+            .iter()
+            .map(|execution| (self.executed_owner(execution.order_id), execution.volume))
+            .collect::<Vec<_>>();
+        executed.push((
+            order.owner,
+            executions.iter().map(|execution| execution.volume).sum(),
+        ));
+        self.track_executed(order.timestamp, executed);
+    }
+
+    /// track executions of both orders of the fill
+    pub(crate) fn protect_after_fill(&mut self, fill: &Fill, timestamp: Timestamp) {
+        if self.protection.is_none() {
+            return;
+        }
+        let executed = [fill.buy_order_id, fill.sell_order_id]
+            .map(|order_id| (self.executed_owner(order_id), fill.volume));
+        self.track_executed(timestamp, executed);
+    }
+
+    // filled orders are no longer on the book, their owner is taken from the order status
+    fn executed_owner(&self, order_id: Oid) -> Option<OwnerId> {
+        self.get_order(order_id).and_then(|view| view.owner)
+    }
+
+    fn track_executed(
+        &mut self,
+        now: Timestamp,
+        executed: impl IntoIterator<Item = (Option<OwnerId>, Volume)>,
+    ) {
+        let Some(protection) = self.protection.as_mut() else {
+            return;
+        };
+        let mut triggered = Vec::new();
+        for (owner, volume) in executed {
+            let Some(owner) = owner else {
+                continue;
+            };
+            if let Some(executed) = protection.track(owner, now, volume) {
+                if !triggered.iter().any(|(o, _)| *o == owner) {
+                    triggered.push((owner, executed));
+                }
+            }
+        }
+
+        for (owner, executed) in triggered {
+            let mut cancelled = self.cancel_quote(owner);
+            cancelled.extend(self.cancel_all(owner));
+            if let Some(protection) = self.protection.as_mut() {
+                // window starts again once the owner is back
+                protection.executions.remove(&owner);
+                protection.events.push(ProtectionEvent {
+                    owner,
+                    executed,
+                    timestamp: now,
+                    cancelled,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_protection {
+    use crate::*;
+
+    #[test]
+    fn test_protection_pulls_owner_orders() {
+        let maker = OwnerId::new(1);
+        let mut order_book =
+            OrderBook::default().with_mm_protection(ProtectionLimit::new(1_000, 10.into()));
+        for (id, side, price) in [
+            (1, OrderSide::Sell, 11.0),
+            (2, OrderSide::Sell, 12.0),
+            (3, OrderSide::Buy, 9.0),
+        ] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                10.into(),
+            )
+            .with_owner(maker);
+            order_book.execute(&order).unwrap();
+        }
+        let buy = |id: u64, timestamp: u64, volume: u64| {
+            Order::new_market(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(timestamp),
+                volume.into(),
+            )
+        };
+
+        order_book.execute(&buy(4, 100, 6)).unwrap();
+        // executions older than the window are forgotten
+        order_book.execute(&buy(5, 1_200, 6)).unwrap();
+        assert!(order_book.drain_protection_events().is_empty());
+
+        order_book.execute(&buy(6, 1_300, 5)).unwrap();
+        let events = order_book.drain_protection_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].owner, maker);
+        assert_eq!(events[0].executed, 11.into());
+        assert_eq!(
+            events[0]
+                .cancelled
+                .iter()
+                .map(|report| report.order_id())
+                .collect::<Vec<_>>(),
+            vec![Oid::new(2), Oid::new(3)]
+        );
+        assert!(order_book.is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+//!
+//! Read snapshots of the book published for other threads. The matching thread publishes
+//! an immutable `BookSnapshot` after each batch of mutations, market data threads read
+//! the latest one without contending with the matching thread.
+//! The shared pointer is swapped atomically, neither side takes a lock.
+//! Readers keep the snapshot they loaded and only check an atomic version on each read,
+//! the shared pointer is loaded once per publish.
+//!
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::{BookSnapshot, OrderBook};
+
+#[derive(Debug, Default)]
+struct Published {
+    version: AtomicU64,
+    snapshot: ArcSwap<BookSnapshot>,
+}
+
+/// Writer side, publishes snapshots of the book
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotPublisher {
+    shared: Arc<Published>,
+}
+
+impl SnapshotPublisher {
+    pub fn new() -> Self {
+        SnapshotPublisher::default()
+    }
+
+    /// publish the snapshot of the book, returns its version
+    pub fn publish(&self, book: &OrderBook) -> u64 {
+        self.publish_snapshot(book.book_snapshot())
+    }
+
+    pub fn publish_snapshot(&self, snapshot: BookSnapshot) -> u64 {
+        self.shared.snapshot.store(Arc::new(snapshot));
+        // version is bumped after the swap, so a reader seeing it loads this snapshot or a newer one
+        self.shared.version.fetch_add(1, Ordering::Release) + 1
+    }
+
+    /// version of the latest snapshot, zero before the first publish
+    pub fn version(&self) -> u64 {
+        self.shared.version.load(Ordering::Acquire)
+    }
+
+    /// latest published snapshot
+    pub fn load(&self) -> Arc<BookSnapshot> {
+        self.shared.snapshot.load_full()
+    }
+
+    /// reader for another thread
+    pub fn reader(&self) -> SnapshotReader {
+        let version = self.version();
+        SnapshotReader {
+            shared: self.shared.clone(),
+            version,
+            snapshot: self.load(),
+        }
+    }
+}
+
+/// Reader side, caches the last loaded snapshot
+#[derive(Debug, Clone)]
+pub struct SnapshotReader {
+    shared: Arc<Published>,
+    version: u64,
+    snapshot: Arc<BookSnapshot>,
+}
+
+impl SnapshotReader {
+    /// latest published snapshot, the pointer is only loaded when a newer version was published
+    pub fn load(&mut self) -> &Arc<BookSnapshot> {
+        let version = self.shared.version.load(Ordering::Acquire);
+        if version != self.version {
+            self.snapshot = self.shared.snapshot.load_full();
+            self.version = version;
+        }
+        &self.snapshot
+    }
+
+    /// version seen by the last load, the loaded snapshot is at least as recent
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+#[cfg(test)]
+mod tests_publish {
+    use crate::*;
+
+    #[test]
+    fn test_readers_see_published_snapshots() {
+        let publisher = SnapshotPublisher::new();
+        let mut reader = publisher.reader();
+        assert!(reader.load().bids.is_empty());
+
+        let mut order_book = OrderBook::default();
+        let done = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let mut reader = publisher.reader();
+            let done = &done;
+            let handle = scope.spawn(move || {
+                // versions seen by the reader never go back
+                let mut last = 0;
+                while !done.load(std::sync::atomic::Ordering::Acquire) {
+                    let orders = reader.load().bids.len() as u64;
+                    assert!(reader.version() >= last);
+                    // snapshot may be newer than the version it was loaded for
+                    assert!(orders >= reader.version());
+                    last = reader.version();
+                }
+                reader.load().bids.len()
+            });
+            for id in 1..=100 {
+                let order = Order::new_limit(
+                    Oid::new(id),
+                    OrderSide::Buy,
+                    Timestamp::new(id),
+                    Price::new(id as f64),
+                    1.into(),
+                );
+                order_book.execute(&order).unwrap();
+                publisher.publish(&order_book);
+            }
+            done.store(true, std::sync::atomic::Ordering::Release);
+            assert_eq!(handle.join().unwrap(), 100);
+        });
+
+        assert_eq!(reader.version(), 0);
+        assert_eq!(reader.load().bids.len(), 100);
+        assert_eq!(reader.version(), publisher.version());
+    }
+}
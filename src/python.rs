@@ -0,0 +1,234 @@
+//!
+//! Python bindings of the book built with PyO3, the extension module is named `lob`.
+//! Prices and volumes are Python floats, sides are the strings `buy` and `sell`.
+//! Build the extension with `cargo rustc --release --features python --crate-type cdylib`.
+//!
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{
+    DepthLevel, Execution, Oid, Order, OrderBook, OrderSide, Price, Timestamp, Trade, Volume,
+};
+
+fn parse_side(side: &str) -> PyResult<OrderSide> {
+    side.parse()
+        .map_err(|_| PyValueError::new_err("side must be 'buy' or 'sell'"))
+}
+
+fn value_error(error: impl ToString) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// Order, limit order when the price is given, market order otherwise
+#[pyclass(name = "Order", module = "lob")]
+#[derive(Debug, Clone)]
+pub struct PyOrder {
+    order: Order,
+}
+
+#[pymethods]
+impl PyOrder {
+    #[new]
+    #[pyo3(signature = (id, side, volume, price=None, timestamp=0))]
+    fn new(id: u64, side: &str, volume: f64, price: Option<f64>, timestamp: u64) -> PyResult<Self> {
+        let side = parse_side(side)?;
+        let (id, timestamp, volume) = (
+            Oid::new(id),
+            Timestamp::new(timestamp),
+            Volume::from_f64(volume),
+        );
+        let order = match price {
+            Some(price) => Order::new_limit(id, side, timestamp, Price::new(price), volume),
+            None => Order::new_market(id, side, timestamp, volume),
+        };
+        Ok(PyOrder { order })
+    }
+
+    #[getter]
+    fn id(&self) -> u64 {
+        self.order.id.into()
+    }
+
+    #[getter]
+    fn side(&self) -> &'static str {
+        match self.order.side {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        }
+    }
+
+    #[getter]
+    fn price(&self) -> Option<f64> {
+        self.order.price.map(|price| price.to_f64())
+    }
+
+    #[getter]
+    fn volume(&self) -> f64 {
+        self.order.volume.to_f64()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Order(id={}, side='{}', volume={}, price={:?})",
+            self.id(),
+            self.side(),
+            self.volume(),
+            self.price()
+        )
+    }
+}
+
+/// Match against a resting order
+#[pyclass(name = "Execution", module = "lob", get_all)]
+#[derive(Debug, Clone)]
+pub struct PyExecution {
+    /// id of the resting order
+    order_id: u64,
+    price: f64,
+    volume: f64,
+}
+
+impl From<&Execution> for PyExecution {
+    fn from(execution: &Execution) -> Self {
+        PyExecution {
+            order_id: execution.order_id.into(),
+            price: execution.price.to_f64(),
+            volume: execution.volume.to_f64(),
+        }
+    }
+}
+
+/// Result of executing the order
+#[pyclass(name = "Trade", module = "lob", get_all)]
+#[derive(Debug, Clone)]
+pub struct PyTrade {
+    order_id: u64,
+    filled_volume: f64,
+    cancelled_volume: f64,
+    executions: Vec<PyExecution>,
+}
+
+impl From<Trade> for PyTrade {
+    fn from(trade: Trade) -> Self {
+        PyTrade {
+            order_id: trade.order_id.into(),
+            filled_volume: trade.filled_volume.to_f64(),
+            cancelled_volume: trade.cancelled_volume.to_f64(),
+            executions: trade.executions.iter().map(PyExecution::from).collect(),
+        }
+    }
+}
+
+/// Aggregated price level
+#[pyclass(name = "DepthLevel", module = "lob", get_all)]
+#[derive(Debug, Clone)]
+pub struct PyDepthLevel {
+    price: f64,
+    volume: f64,
+    order_count: usize,
+}
+
+impl From<DepthLevel> for PyDepthLevel {
+    fn from(level: DepthLevel) -> Self {
+        PyDepthLevel {
+            price: level.price.to_f64(),
+            volume: level.volume.to_f64(),
+            order_count: level.order_count,
+        }
+    }
+}
+
+/// Limit order book
+#[pyclass(name = "OrderBook", module = "lob")]
+#[derive(Debug, Default)]
+pub struct PyOrderBook {
+    book: OrderBook,
+}
+
+#[pymethods]
+impl PyOrderBook {
+    #[new]
+    fn new() -> Self {
+        PyOrderBook::default()
+    }
+
+    /// execute the order, remainder of a limit order rests on the book
+    fn execute(&mut self, order: &PyOrder) -> PyResult<PyTrade> {
+        self.book
+            .execute(&order.order)
+            .map(PyTrade::from)
+            .map_err(value_error)
+    }
+
+    fn cancel(&mut self, id: u64) -> PyResult<()> {
+        self.book
+            .cancel_order(Oid::new(id))
+            .map(|_| ())
+            .map_err(value_error)
+    }
+
+    fn best_bid(&self) -> Option<f64> {
+        self.book
+            .best_bid_ask()
+            .bid_price
+            .map(|price| price.to_f64())
+    }
+
+    fn best_ask(&self) -> Option<f64> {
+        self.book
+            .best_bid_ask()
+            .ask_price
+            .map(|price| price.to_f64())
+    }
+
+    /// top n bid and ask levels
+    fn depth(&self, n: usize) -> (Vec<PyDepthLevel>, Vec<PyDepthLevel>) {
+        let depth = self.book.depth(n);
+        (
+            depth.bids.into_iter().map(PyDepthLevel::from).collect(),
+            depth.asks.into_iter().map(PyDepthLevel::from).collect(),
+        )
+    }
+
+    fn __len__(&self) -> usize {
+        self.book.order_count()
+    }
+}
+
+#[pymodule]
+fn lob(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyOrderBook>()?;
+    m.add_class::<PyOrder>()?;
+    m.add_class::<PyTrade>()?;
+    m.add_class::<PyExecution>()?;
+    m.add_class::<PyDepthLevel>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_python {
+    use super::*;
+
+    #[test]
+    fn test_python_order_book() {
+        let mut book = PyOrderBook::new();
+        let sell = PyOrder::new(1, "sell", 5.0, Some(10.5), 1).unwrap();
+        book.execute(&sell).unwrap();
+        let buy = PyOrder::new(2, "buy", 3.0, None, 2).unwrap();
+        let trade = book.execute(&buy).unwrap();
+        assert_eq!(trade.filled_volume, 3.0);
+        assert_eq!(trade.executions[0].order_id, 1);
+        assert_eq!(trade.executions[0].price, 10.5);
+
+        let (bids, asks) = book.depth(5);
+        assert!(bids.is_empty());
+        assert_eq!(asks[0].volume, 2.0);
+        assert_eq!(book.best_ask(), Some(10.5));
+        book.cancel(1).unwrap();
+        assert_eq!(book.__len__(), 0);
+    }
+}
@@ -0,0 +1,138 @@
+//!
+//! FIFO queue of orders at a price level.
+//! Orders are kept in a doubly linked list stored in a vec, each order remembers its slot
+//! in the queue, so it can be unlinked in O(1) when cancelled.
+
+use alloc::vec::Vec;
+
+use crate::primitives::OrderHandle;
+
+#[derive(Debug, Clone)]
+struct Node {
+    handle: OrderHandle,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// Queue of orders in time priority
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OrderQueue {
+    nodes: Vec<Node>,
+    // slots of unlinked nodes that can be reused
+    free: Vec<usize>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    len: usize,
+}
+
+impl OrderQueue {
+    /// add the order at the back of the queue, returns the slot of the order
+    pub(crate) fn push_back(&mut self, handle: OrderHandle) -> usize {
+        let node = Node {
+            handle,
+            prev: self.tail,
+            next: None,
+        };
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        match self.tail {
+            Some(tail) => self.nodes[tail].next = Some(slot),
+            None => self.head = Some(slot),
+        }
+        self.tail = Some(slot);
+        self.len += 1;
+        slot
+    }
+
+    /// order at the front of the queue
+    pub(crate) fn front(&self) -> Option<OrderHandle> {
+        self.head.map(|slot| self.nodes[slot].handle)
+    }
+
+    /// unlink the order at the given slot, returns the order handle stored at the slot
+    pub(crate) fn remove(&mut self, slot: usize) -> OrderHandle {
+        let Node { handle, prev, next } = self.nodes[slot].clone();
+        match prev {
+            Some(prev) => self.nodes[prev].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => self.nodes[next].prev = prev,
+            None => self.tail = prev,
+        }
+        self.free.push(slot);
+        self.len -= 1;
+        handle
+    }
+
+    /// unlink the orders the predicate of their slot and handle is false for,
+    /// returns the number of unlinked orders
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(usize, OrderHandle) -> bool) -> usize {
+        let mut removed = 0;
+        let mut next = self.head;
+        while let Some(slot) = next {
+            next = self.nodes[slot].next;
+            if !keep(slot, self.nodes[slot].handle) {
+                self.remove(slot);
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// orders from the front to the back of the queue
+    pub(crate) fn iter(&self) -> impl Iterator<Item = OrderHandle> + '_ {
+        core::iter::successors(self.head, |slot| self.nodes[*slot].next)
+            .map(|slot| self.nodes[slot].handle)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// drop all orders, allocated memory is kept for reuse
+    pub(crate) fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.head = None;
+        self.tail = None;
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests_queue {
+    use super::*;
+
+    #[test]
+    fn test_remove_from_middle() {
+        let mut queue = OrderQueue::default();
+        let slots = (1..=4usize)
+            .map(|id| queue.push_back(OrderHandle(id)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(queue.remove(slots[1]), OrderHandle(2));
+        assert_eq!(queue.remove(slots[3]), OrderHandle(4));
+        assert_eq!(
+            queue.iter().collect::<Vec<_>>(),
+            [OrderHandle(1), OrderHandle(3)]
+        );
+
+        // freed slot is reused, order goes to the back
+        assert_eq!(queue.push_back(OrderHandle(5)), slots[3]);
+        queue.remove(slots[0]);
+        queue.remove(slots[2]);
+        assert_eq!(queue.front(), Some(OrderHandle(5)));
+        queue.remove(slots[3]);
+        assert_eq!(queue.front(), None);
+        assert_eq!(queue.len(), 0);
+    }
+}
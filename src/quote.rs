@@ -0,0 +1,294 @@
+//!
+//! Two-sided quotes of market makers. Each owner has at most one quote on the book,
+//! submitting a new quote cancels the orders of the previous one and places both sides again.
+//! The new quote is validated before the previous one is cancelled, so a rejected quote
+//! leaves the previous quote on the book. When a side fails to be placed after the other one,
+//! the placed side is pulled, so no order is left on the book without its quote.
+//!
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::{
+    BookEvent, CancellationReport, CancellationStatus, LevelStore, Oid, Order, OrderBook,
+    OrderBookError, OrderSide, OwnerId, Price, Timestamp, Trade, TradingState, Volume,
+};
+
+/// Bid and ask of the market maker, side with zero quantity is not quoted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    pub bid_px: Price,
+    pub bid_qty: Volume,
+    pub ask_px: Price,
+    pub ask_qty: Volume,
+}
+
+impl Quote {
+    pub fn new(bid_px: Price, bid_qty: Volume, ask_px: Price, ask_qty: Volume) -> Self {
+        Quote {
+            bid_px,
+            bid_qty,
+            ask_px,
+            ask_qty,
+        }
+    }
+
+    /// limit orders of the quoted sides, bid first
+    fn orders(&self, owner: OwnerId, order_ids: (Oid, Oid), timestamp: Timestamp) -> Vec<Order> {
+        [
+            (order_ids.0, OrderSide::Buy, self.bid_px, self.bid_qty),
+            (order_ids.1, OrderSide::Sell, self.ask_px, self.ask_qty),
+        ]
+        .into_iter()
+        .filter(|(_, _, _, volume)| !volume.is_zero())
+        .map(|(id, side, price, volume)| {
+            Order::new_limit(id, side, timestamp, price, volume).with_owner(owner)
+        })
+        .collect()
+    }
+}
+
+/// Result of the submitted quote
+#[derive(Debug, Clone)]
+pub struct QuoteReport {
+    /// orders of the previous quote that were still on the book
+    pub cancelled: Vec<CancellationReport>,
+    /// trades of the quoted sides, bid first
+    pub trades: Vec<Trade>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct QuoteOrders {
+    quote: Quote,
+    bid: Option<Oid>,
+    ask: Option<Oid>,
+}
+
+/// Current quote of each owner
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Quotes {
+    quotes: HashMap<OwnerId, QuoteOrders>,
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// replace the quote of the owner, order ids are used for the bid and the ask order
+    pub fn submit_quote(
+        &mut self,
+        owner: OwnerId,
+        quote: Quote,
+        order_ids: (Oid, Oid),
+        timestamp: Timestamp,
+    ) -> Result<QuoteReport, OrderBookError> {
+        self.logged(
+            || BookEvent::SubmitQuote {
+                owner,
+                quote,
+                order_ids,
+                timestamp,
+            },
+            |book| book.replace_quote(owner, quote, order_ids, timestamp),
+        )
+    }
+
+    fn replace_quote(
+        &mut self,
+        owner: OwnerId,
+        quote: Quote,
+        order_ids: (Oid, Oid),
+        timestamp: Timestamp,
+    ) -> Result<QuoteReport, OrderBookError> {
+        let orders = quote.orders(owner, order_ids, timestamp);
+        self.check_quote(&quote, &orders)?;
+
+        let cancelled = self.cancel_quote(owner);
+        let mut trades = Vec::with_capacity(orders.len());
+        for (placed, order) in orders.iter().enumerate() {
+            match self.execute(order) {
+                Ok(trade) => trades.push(trade),
+                Err(error) => {
+                    // i.e. the risk reference moved by the bid, the owner is left without quote
+                    let placed = orders[..placed].iter().map(|order| order.id);
+                    self.remove_orders(placed, CancellationStatus::Cancelled);
+                    return Err(error);
+                }
+            }
+        }
+        let quoted = |side: OrderSide| {
+            orders
+                .iter()
+                .find(|order| order.side == side)
+                .map(|order| order.id)
+        };
+        self.quotes.quotes.insert(
+            owner,
+            QuoteOrders {
+                quote,
+                bid: quoted(OrderSide::Buy),
+                ask: quoted(OrderSide::Sell),
+            },
+        );
+        Ok(QuoteReport { cancelled, trades })
+    }
+
+    /// cancel the orders of the owner's quote that are still on the book
+    pub fn cancel_quote(&mut self, owner: OwnerId) -> Vec<CancellationReport> {
+        self.logged(
+            || BookEvent::CancelQuote(owner),
+            |book| {
+                let Some(quote) = book.quotes.quotes.remove(&owner) else {
+                    return Vec::new();
+                };
+                book.remove_orders(
+                    [quote.bid, quote.ask].into_iter().flatten(),
+                    CancellationStatus::Cancelled,
+                )
+            },
+        )
+    }
+
+    /// last quote of the owner, while at least one of its orders is on the book
+    pub fn quote(&self, owner: OwnerId) -> Option<Quote> {
+        self.quotes
+            .quotes
+            .get(&owner)
+            .filter(|quote| self.quote_order_ids(quote).next().is_some())
+            .map(|quote| quote.quote)
+    }
+
+    /// orders of the owner's quote that are on the book
+    pub(crate) fn quote_orders(&self, owner: OwnerId) -> Vec<Oid> {
+        self.quotes
+            .quotes
+            .get(&owner)
+            .map(|quote| self.quote_order_ids(quote).collect())
+            .unwrap_or_default()
+    }
+
+    fn quote_order_ids<'a>(&'a self, quote: &QuoteOrders) -> impl Iterator<Item = Oid> + 'a {
+        [quote.bid, quote.ask]
+            .into_iter()
+            .flatten()
+            .filter(|id| self.orders.get(id).is_some())
+    }
+
+    /// validate both sides, so the previous quote is only cancelled when the new one can be placed
+    fn check_quote(&mut self, quote: &Quote, orders: &[Order]) -> Result<(), OrderBookError> {
+        if matches!(self.state, TradingState::Closed | TradingState::Suspended) {
+            return Err(OrderBookError::InvalidState(self.state));
+        }
+        if orders.len() == 2 && quote.bid_px >= quote.ask_px {
+            return Err(OrderBookError::OrderCannotBePlaced(
+                "quote bid must be below the ask".to_string(),
+            ));
+        }
+        if let [bid, ask] = orders {
+            if bid.id == ask.id {
+                return Err(OrderBookError::DuplicateOrderId(ask.id));
+            }
+        }
+        // orders of the previous quote are cancelled before the new ones are placed
+        let previous = orders
+            .first()
+            .and_then(|order| order.owner)
+            .map(|owner| self.quote_orders(owner))
+            .unwrap_or_default();
+        for order in orders {
+            if !previous.contains(&order.id) {
+                self.check_duplicate_id(order.id)?;
+            }
+            self.check_instrument_spec(order.price, order.volume)?;
+            if let Some(price) = order.price {
+                self.check_price_bands(price)?;
+                // aggressive orders are rejected while the book is halted
+                if self.state == TradingState::Halted
+                    && self.crossing_price(order.side, price).is_some()
+                {
+                    return Err(OrderBookError::InvalidState(self.state));
+                }
+            }
+            self.check_risk(order)
+                .map_err(|reason| OrderBookError::RiskRejected(order.id, reason))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_quote {
+    use crate::*;
+
+    #[test]
+    fn test_quote_replaces_previous_quote() {
+        let owner = OwnerId::new(1);
+        let mut order_book = OrderBook::default();
+        let quote = Quote::new(9.0.into(), 10.into(), 11.0.into(), 10.into());
+        let report = order_book
+            .submit_quote(owner, quote, (Oid::new(1), Oid::new(2)), Timestamp::new(1))
+            .unwrap();
+        assert!(report.cancelled.is_empty());
+        assert_eq!(order_book.quote(owner), Some(quote));
+        assert_eq!(order_book.get_best_buy(), Some(Price::new(9.0)));
+
+        // crossed quote is rejected and the previous quote stays on the book
+        let crossed = Quote::new(11.0.into(), 10.into(), 10.0.into(), 10.into());
+        assert!(order_book
+            .submit_quote(
+                owner,
+                crossed,
+                (Oid::new(3), Oid::new(4)),
+                Timestamp::new(2)
+            )
+            .is_err());
+        assert_eq!(order_book.order_count(), 2);
+
+        // one sided quote replaces both sides
+        let quote = Quote::new(9.5.into(), 5.into(), Price::ZERO, Volume::ZERO);
+        let report = order_book
+            .submit_quote(owner, quote, (Oid::new(5), Oid::new(6)), Timestamp::new(3))
+            .unwrap();
+        assert_eq!(report.cancelled.len(), 2);
+        assert_eq!(report.trades.len(), 1);
+        assert_eq!(order_book.get_best_buy(), Some(Price::new(9.5)));
+        assert_eq!(order_book.get_best_sell(), None);
+
+        let seller = Order::new_market(Oid::new(7), OrderSide::Sell, Timestamp::new(4), 5.into());
+        order_book.execute(&seller).unwrap();
+        assert_eq!(order_book.quote(owner), None);
+        assert!(order_book.cancel_quote(owner).is_empty());
+    }
+
+    #[test]
+    fn test_failed_quote_leaves_no_orphan_order() {
+        let owner = OwnerId::new(1);
+        let mut order_book = OrderBook::default();
+        let quote = Quote::new(9.0.into(), 10.into(), 11.0.into(), 10.into());
+        order_book
+            .submit_quote(owner, quote, (Oid::new(1), Oid::new(2)), Timestamp::new(1))
+            .unwrap();
+
+        // same id for both sides is rejected before the previous quote is cancelled
+        let next = Quote::new(9.5.into(), 10.into(), 10.5.into(), 10.into());
+        assert_eq!(
+            order_book
+                .submit_quote(owner, next, (Oid::new(5), Oid::new(5)), Timestamp::new(2))
+                .unwrap_err(),
+            OrderBookError::DuplicateOrderId(Oid::new(5))
+        );
+        assert_eq!(order_book.quote(owner), Some(quote));
+        assert_eq!(order_book.order_count(), 2);
+
+        // ask is checked against the collar moved by the bid, so the bid is pulled
+        let mut order_book =
+            OrderBook::default().with_risk_validator(PriceCollar { percentage: 10.0 });
+        let wide = Quote::new(9.0.into(), 10.into(), 20.0.into(), 10.into());
+        assert!(matches!(
+            order_book.submit_quote(owner, wide, (Oid::new(1), Oid::new(2)), Timestamp::new(1)),
+            Err(OrderBookError::RiskRejected(..))
+        ));
+        assert_eq!(order_book.order_count(), 0);
+        assert_eq!(order_book.quote(owner), None);
+        assert!(order_book.cancel_quote(owner).is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+//!
+//! Last trade and reference price of the book. Last trade is updated by every fill,
+//! reference price is the previous close or the last auction price and can be set by the user.
+//! Moving the reference price moves the dynamic price bands with it.
+//!
+
+use crate::{BookEvent, LevelStore, OrderBook, Price, Volume};
+
+impl<S: LevelStore> OrderBook<S> {
+    /// start with the reference price, i.e. the previous close
+    pub fn with_reference_price(mut self, price: Price) -> Self {
+        self.set_reference_price(Some(price));
+        self
+    }
+
+    /// set the reference price, dynamic price bands are moved to it
+    pub fn set_reference_price(&mut self, price: Option<Price>) {
+        self.logged(
+            || BookEvent::SetReferencePrice(price),
+            |book| {
+                book.reference_price = price;
+                if let (Some(price), Some(bands)) = (price, book.price_bands.as_mut()) {
+                    if bands.percentage.is_some() {
+                        bands.set_reference_price(price);
+                    }
+                }
+            },
+        )
+    }
+
+    pub fn reference_price(&self) -> Option<Price> {
+        self.reference_price
+    }
+
+    /// price of the most recent fill
+    pub fn last_trade_price(&self) -> Option<Price> {
+        self.last_trade.map(|(price, _)| price)
+    }
+
+    /// volume of the most recent fill
+    pub fn last_trade_volume(&self) -> Option<Volume> {
+        self.last_trade.map(|(_, volume)| volume)
+    }
+
+    /// change of the last trade price from the reference price in percent
+    pub fn price_change_percent(&self) -> Option<f64> {
+        let reference = self.reference_price?.to_f64();
+        let last = self.last_trade_price()?.to_f64();
+        (reference != 0.0).then(|| (last - reference) / reference * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests_reference {
+    use crate::*;
+
+    #[test]
+    fn test_last_trade_and_reference_price() {
+        let mut order_book = OrderBook::default()
+            .with_price_bands(PriceBands::percentage(10.0.into(), 10.0))
+            .with_reference_price(10.0.into());
+        assert_eq!(order_book.last_trade_price(), None);
+        assert_eq!(order_book.price_change_percent(), None);
+
+        let orders = [
+            Order::new_limit(
+                Oid::new(1),
+                OrderSide::Sell,
+                Timestamp::new(1),
+                10.5.into(),
+                5.into(),
+            ),
+            Order::new_market(Oid::new(2), OrderSide::Buy, Timestamp::new(2), 3.into()),
+        ];
+        for order in &orders {
+            order_book.execute(order).unwrap();
+        }
+        assert_eq!(order_book.last_trade_price(), Some(Price::new(10.5)));
+        assert_eq!(order_book.last_trade_volume(), Some(3.into()));
+        assert_eq!(order_book.price_change_percent(), Some(5.0));
+
+        // dynamic bands follow the reference price
+        order_book.set_reference_price(Some(12.0.into()));
+        assert_eq!(
+            order_book.price_bands().unwrap().reference_price,
+            Some(Price::new(12.0))
+        );
+
+        // closing price becomes the reference for the next session
+        order_book.set_reference_price(None);
+        order_book.close(Timestamp::new(3)).unwrap();
+        assert_eq!(order_book.reference_price(), Some(Price::new(10.5)));
+    }
+}
@@ -0,0 +1,178 @@
+//!
+//! Reject taxonomy. Every error returned for an order maps to a reject code with a stable
+//! number and a short message, so gateways can map rejects without parsing error strings.
+//! Numbers are never reused, new codes are added at the end.
+//!
+
+use core::fmt::{Display, Formatter};
+
+use crate::{AmendOrderError, CancelOrderError, MatchingEngineError, OrderBookError, TradingState};
+
+/// Reason the order or the request was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u16)]
+pub enum RejectCode {
+    /// order is not valid, i.e. unsupported combination of instructions
+    InvalidOrder = 1,
+    /// nothing on the opposite side to match against
+    NoLiquidity = 2,
+    UnknownOrder = 3,
+    AlreadyCancelled = 4,
+    /// amended volume is not above the filled volume
+    VolumeNotAboveFilled = 5,
+    PostOnlyWouldCross = 6,
+    BookHalted = 7,
+    /// request is not allowed in the trading state of the book
+    InvalidState = 8,
+    InvalidStateTransition = 9,
+    PriceOutOfBand = 10,
+    InvalidTickSize = 11,
+    InvalidLotSize = 12,
+    VolumeBelowMinimum = 13,
+    RiskRejected = 14,
+    DuplicateOrderId = 15,
+    ZeroVolume = 16,
+    MissingPrice = 17,
+    OrderExpired = 18,
+    Throttled = 19,
+    ArithmeticOverflow = 20,
+    /// book is inconsistent
+    Internal = 99,
+}
+
+impl RejectCode {
+    /// stable number of the code
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            RejectCode::InvalidOrder => "invalid order",
+            RejectCode::NoLiquidity => "no liquidity",
+            RejectCode::UnknownOrder => "unknown order",
+            RejectCode::AlreadyCancelled => "order already cancelled",
+            RejectCode::VolumeNotAboveFilled => "volume not above filled volume",
+            RejectCode::PostOnlyWouldCross => "post only order would cross",
+            RejectCode::BookHalted => "book halted",
+            RejectCode::InvalidState => "not allowed in the current trading state",
+            RejectCode::InvalidStateTransition => "invalid trading state transition",
+            RejectCode::PriceOutOfBand => "price out of band",
+            RejectCode::InvalidTickSize => "price not on a tick",
+            RejectCode::InvalidLotSize => "volume not a multiple of the lot size",
+            RejectCode::VolumeBelowMinimum => "volume below minimum",
+            RejectCode::RiskRejected => "rejected by risk check",
+            RejectCode::DuplicateOrderId => "duplicate order id",
+            RejectCode::ZeroVolume => "zero volume",
+            RejectCode::MissingPrice => "missing price",
+            RejectCode::OrderExpired => "order expired",
+            RejectCode::Throttled => "throttled",
+            RejectCode::ArithmeticOverflow => "arithmetic overflow",
+            RejectCode::Internal => "internal error",
+        }
+    }
+}
+
+impl Display for RejectCode {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{} {}", self.code(), self.message())
+    }
+}
+
+impl OrderBookError {
+    pub fn reject_code(&self) -> RejectCode {
+        match self {
+            OrderBookError::OrderCannotBePlaced(_) => RejectCode::InvalidOrder,
+            OrderBookError::NoOrderToMatch | OrderBookError::LevelHasNoValidOrders => {
+                RejectCode::NoLiquidity
+            }
+            OrderBookError::CancelOrderError(error) => error.reject_code(),
+            OrderBookError::AmendOrderError(error) => error.reject_code(),
+            OrderBookError::PostOnlyWouldCross(_) => RejectCode::PostOnlyWouldCross,
+            OrderBookError::InvalidState(TradingState::Halted) => RejectCode::BookHalted,
+            OrderBookError::InvalidState(_) => RejectCode::InvalidState,
+            OrderBookError::InvalidStateTransition { .. } => RejectCode::InvalidStateTransition,
+            OrderBookError::PriceOutOfBand(_) => RejectCode::PriceOutOfBand,
+            OrderBookError::InvalidTickSize(_) => RejectCode::InvalidTickSize,
+            OrderBookError::InvalidLotSize(_) => RejectCode::InvalidLotSize,
+            OrderBookError::VolumeBelowMinimum(_) => RejectCode::VolumeBelowMinimum,
+            OrderBookError::RiskRejected(..) => RejectCode::RiskRejected,
+            OrderBookError::DuplicateOrderId(_) => RejectCode::DuplicateOrderId,
+            OrderBookError::ZeroVolume(_) => RejectCode::ZeroVolume,
+            OrderBookError::MissingPrice(_) => RejectCode::MissingPrice,
+            OrderBookError::OrderExpired(_) => RejectCode::OrderExpired,
+            OrderBookError::UnknownOrder(_) => RejectCode::UnknownOrder,
+            OrderBookError::ArithmeticOverflow => RejectCode::ArithmeticOverflow,
+            OrderBookError::Corrupted(_) => RejectCode::Internal,
+        }
+    }
+}
+
+impl CancelOrderError {
+    pub fn reject_code(&self) -> RejectCode {
+        match self {
+            CancelOrderError::NotFound(_) => RejectCode::UnknownOrder,
+            CancelOrderError::AlreadyCancelled(_) => RejectCode::AlreadyCancelled,
+        }
+    }
+}
+
+impl AmendOrderError {
+    pub fn reject_code(&self) -> RejectCode {
+        match self {
+            AmendOrderError::NotFound(_) => RejectCode::UnknownOrder,
+            AmendOrderError::VolumeNotAboveFilled(_) => RejectCode::VolumeNotAboveFilled,
+        }
+    }
+}
+
+impl MatchingEngineError {
+    pub fn reject_code(&self) -> RejectCode {
+        match self {
+            MatchingEngineError::OrderBookError(error) => error.reject_code(),
+            MatchingEngineError::MissingPrice(_) => RejectCode::MissingPrice,
+            MatchingEngineError::ZeroVolume(_) => RejectCode::ZeroVolume,
+            MatchingEngineError::DuplicateOrderId(_) => RejectCode::DuplicateOrderId,
+            MatchingEngineError::NoMarketOrders | MatchingEngineError::NoOrdersToMatch => {
+                RejectCode::NoLiquidity
+            }
+            MatchingEngineError::Throttled(_) => RejectCode::Throttled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_reject {
+    use crate::*;
+
+    #[test]
+    fn test_reject_codes() {
+        let mut order_book = OrderBook::default();
+        let order = |id: u64, volume: u64| {
+            Order::new_limit(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                10.0.into(),
+                volume.into(),
+            )
+        };
+        let error = order_book.execute(&order(1, 0)).unwrap_err();
+        assert_eq!(error, OrderBookError::ZeroVolume(Oid::new(1)));
+        assert_eq!(error.reject_code().code(), 16);
+
+        order_book.execute(&order(1, 5)).unwrap();
+        let error = order_book.execute(&order(1, 5)).unwrap_err();
+        assert_eq!(error.reject_code(), RejectCode::DuplicateOrderId);
+
+        let error = OrderBookError::from(order_book.cancel_order(Oid::new(9)).unwrap_err());
+        assert_eq!(error.reject_code(), RejectCode::UnknownOrder);
+        assert_eq!(error.reject_code().to_string(), "3 unknown order");
+
+        order_book.halt(Timestamp::new(2)).unwrap();
+        let market = Order::new_market(Oid::new(2), OrderSide::Sell, Timestamp::new(2), 1.into());
+        let error = order_book.execute(&market).unwrap_err();
+        assert_eq!(error.reject_code(), RejectCode::BookHalted);
+    }
+}
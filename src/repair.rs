@@ -0,0 +1,234 @@
+//!
+//! Repair of the cached best levels. The best level of each side is cached with its price
+//! and volume, removing the best level clears it and the top of the book update after each
+//! call of the book finds the next one. Code changing the levels directly, i.e. the integrity
+//! tooling or feed handlers, can check the cache of both sides and repair it on demand.
+//! Level left without volume at the top is rebuilt from the orders still queued at it,
+//! it is removed only once none of them is on the book.
+//!
+
+use crate::{
+    LevelStore, Limits, OrderBook, OrderMap, OrderSide, Price, PriceLike, QuantityLike, Volume,
+};
+
+/// Validity of the cached best level of each side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BestValidity {
+    pub bids: bool,
+    pub asks: bool,
+}
+
+impl BestValidity {
+    pub fn is_valid(&self) -> bool {
+        self.bids && self.asks
+    }
+}
+
+impl<P: PriceLike, V: QuantityLike, S: LevelStore<P>> Limits<P, V, S> {
+    /// best is the highest bid or lowest ask, it has volume and its price and volume are cached
+    fn is_best_valid(&self, side: OrderSide) -> bool {
+        let top = match side {
+            OrderSide::Buy => self.store.last(),
+            OrderSide::Sell => self.store.first(),
+        };
+        let level = self.best.and_then(|index| self.levels.get(index));
+        match (top, level) {
+            (None, None) => self.best.is_none() && self.best_price.is_none(),
+            (Some((price, index)), Some(level)) => {
+                self.best == Some(index)
+                    && !level.total_volume.is_zero()
+                    && self.best_price == Some(price)
+                    && self.best_volume == Some(level.total_volume)
+            }
+            _ => false,
+        }
+    }
+
+    /// best level is missing or has no volume left, cheap check run after every change
+    pub(crate) fn is_best_stale(&self) -> bool {
+        match self.best {
+            None => !self.store.is_empty(),
+            Some(index) => self
+                .levels
+                .get(index)
+                .is_none_or(|level| level.total_volume.is_zero()),
+        }
+    }
+}
+
+impl<S: LevelStore> Limits<Price, Volume, S> {
+    /// rebuild the volume of the levels without volume at the top of the side from the orders
+    /// still on the book, drop the levels left without any, and find the best level again
+    fn repair_best(&mut self, side: OrderSide, orders: &OrderMap) {
+        loop {
+            let top = match side {
+                OrderSide::Buy => self.store.last(),
+                OrderSide::Sell => self.store.first(),
+            };
+            let Some((price, index)) = top else {
+                break;
+            };
+            match self.levels.get_mut(index) {
+                Some(level) if !level.total_volume.is_zero() => break,
+                Some(level) => {
+                    let mut volume = Volume::ZERO;
+                    level.orders.retain(|slot, handle| {
+                        let order = orders.get_by_handle(handle).filter(|order| {
+                            order.side == side
+                                && order.price == price
+                                && order.queue_slot == Some(slot)
+                        });
+                        if let Some(order) = order {
+                            volume += order.visible_volume();
+                        }
+                        order.is_some()
+                    });
+                    level.total_volume = volume;
+                    if volume.is_zero() {
+                        self.remove_level(price, index);
+                    }
+                }
+                // level is gone, there is nothing to keep for reuse
+                None => {
+                    self.store.remove(&price);
+                }
+            }
+            self.touched.push(price);
+        }
+        self.update_best(side);
+    }
+
+    pub(crate) fn heal_best(&mut self, side: OrderSide, orders: &OrderMap) {
+        if self.is_best_stale() {
+            self.repair_best(side, orders);
+        }
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// validity of the cached best levels of both sides
+    pub fn best_validity(&self) -> BestValidity {
+        BestValidity {
+            bids: self.bids.is_best_valid(OrderSide::Buy),
+            asks: self.asks.is_best_valid(OrderSide::Sell),
+        }
+    }
+
+    /// find the best level of each side again if its cache is not valid,
+    /// levels without volume at the top are rebuilt from their orders or removed on the way
+    /// returns the validity before the repair
+    pub fn refresh_best(&mut self) -> BestValidity {
+        let validity = self.best_validity();
+        if validity.is_valid() {
+            return validity;
+        }
+        for (side, valid) in [
+            (OrderSide::Buy, validity.bids),
+            (OrderSide::Sell, validity.asks),
+        ] {
+            if !valid {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(side = %side, "stale best level repaired");
+                let limits: &mut Limits<Price, Volume, S> = match side {
+                    OrderSide::Buy => &mut self.bids,
+                    OrderSide::Sell => &mut self.asks,
+                };
+                limits.repair_best(side, &self.orders);
+            }
+        }
+        self.update_top_of_book();
+        validity
+    }
+}
+
+#[cfg(test)]
+mod tests_repair {
+    use crate::*;
+
+    #[test]
+    fn test_stale_best_is_repaired() {
+        let mut order_book = OrderBook::default();
+        for (id, side, price) in [
+            (1, OrderSide::Buy, 10.0),
+            (2, OrderSide::Buy, 9.0),
+            (3, OrderSide::Sell, 11.0),
+            (4, OrderSide::Sell, 12.0),
+        ] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+        assert!(order_book.best_validity().is_valid());
+
+        // best cleared and best ask level drained behind the back of the book
+        order_book.bids.set_best(None);
+        order_book
+            .asks
+            .level_at_mut(&Price::new(11.0))
+            .unwrap()
+            .total_volume = Volume::ZERO;
+        order_book.asks.refresh_best_volume();
+        assert_eq!(order_book.get_best_buy(), None);
+        assert_eq!(
+            order_book.best_validity(),
+            BestValidity {
+                bids: false,
+                asks: false
+            }
+        );
+
+        assert!(!order_book.refresh_best().is_valid());
+        assert!(order_book.best_validity().is_valid());
+        assert_eq!(order_book.get_best_buy(), Some(10.0.into()));
+        // drained level is rebuilt from the order still resting at it
+        assert_eq!(order_book.get_best_sell(), Some(11.0.into()));
+        assert_eq!(order_book.get_best_sell_volume(), Some(5.into()));
+        assert_eq!(order_book.best_bid_ask().ask_price, Some(11.0.into()));
+        assert!(order_book.check_integrity().is_ok());
+        assert!(order_book.refresh_best().is_valid());
+
+        // level is dropped once none of its orders is on the book
+        order_book.orders.remove(&Oid::new(3));
+        order_book
+            .asks
+            .level_at_mut(&Price::new(11.0))
+            .unwrap()
+            .total_volume = Volume::ZERO;
+        order_book.refresh_best();
+        assert_eq!(order_book.get_best_sell(), Some(12.0.into()));
+        assert_eq!(order_book.level_count(OrderSide::Sell), 1);
+    }
+
+    #[test]
+    fn test_best_is_found_after_the_best_order_is_cancelled() {
+        let mut order_book = OrderBook::default();
+        for (id, price) in [(1, 10.0), (2, 9.0), (3, 9.0), (4, 8.0)] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+        order_book.cancel_order(Oid::new(1)).unwrap();
+        assert_eq!(order_book.get_best_buy(), Some(9.0.into()));
+        assert_eq!(order_book.get_best_buy_volume(), Some(10.into()));
+        assert!(order_book.best_validity().is_valid());
+
+        order_book.cancel_order(Oid::new(2)).unwrap();
+        assert_eq!(order_book.get_best_buy_volume(), Some(5.into()));
+        order_book.cancel_order(Oid::new(3)).unwrap();
+        assert_eq!(order_book.get_best_buy(), Some(8.0.into()));
+        order_book.cancel_order(Oid::new(4)).unwrap();
+        assert_eq!(order_book.get_best_buy(), None);
+        assert!(order_book.best_validity().is_valid());
+        assert!(order_book.check_integrity().is_ok());
+    }
+}
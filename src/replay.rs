@@ -0,0 +1,349 @@
+//!
+//! Replay of historical order actions into the book.
+//! Actions are read from CSV, or JSON lines with the `serde` feature, and applied in order,
+//! either as fast as possible or paced by their timestamps (milliseconds) against the wall clock.
+//!
+//! CSV columns are `timestamp,action,id,side,price,volume`, where action is one of `new`,
+//! `cancel` or `amend`. New order without price is a market order, cancel only needs the id,
+//! amend needs the id, the new price and the new volume.
+//!
+
+use std::io::BufRead;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::{
+    Execution, Oid, Order, OrderBook, OrderBookError, OrderSide, Price, Timestamp, Trade, Volume,
+};
+
+/// Replay error
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("Failed to read actions: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid action on line {line}: {reason}")]
+    InvalidLine { line: usize, reason: String },
+}
+
+/// Timestamped order action
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "action", rename_all = "lowercase")
+)]
+pub enum ReplayAction {
+    New(Order),
+    Cancel {
+        id: Oid,
+        timestamp: Timestamp,
+    },
+    Amend {
+        id: Oid,
+        timestamp: Timestamp,
+        price: Price,
+        volume: Volume,
+    },
+}
+
+impl ReplayAction {
+    pub fn timestamp(&self) -> Timestamp {
+        match self {
+            ReplayAction::New(order) => order.timestamp,
+            ReplayAction::Cancel { timestamp, .. } | ReplayAction::Amend { timestamp, .. } => {
+                *timestamp
+            }
+        }
+    }
+
+    /// parse a single CSV record
+    pub fn from_csv(record: &str) -> Result<Self, String> {
+        let fields = record.split(',').map(str::trim).collect::<Vec<_>>();
+        let field = |i: usize| fields.get(i).copied().unwrap_or_default();
+        let timestamp = Timestamp::new(
+            field(0)
+                .parse()
+                .map_err(|_| format!("invalid timestamp '{}'", field(0)))?,
+        );
+        let id = Oid::new(
+            field(2)
+                .parse()
+                .map_err(|_| format!("invalid id '{}'", field(2)))?,
+        );
+        let price = || -> Result<Option<Price>, String> {
+            match field(4) {
+                "" => Ok(None),
+                price => price
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("invalid price '{}'", price)),
+            }
+        };
+        let volume = || -> Result<Volume, String> {
+            field(5)
+                .parse()
+                .map(Volume::new)
+                .map_err(|_| format!("invalid volume '{}'", field(5)))
+        };
+
+        match field(1).to_ascii_lowercase().as_str() {
+            "new" => {
+                let side = match field(3).to_ascii_lowercase().as_str() {
+                    "buy" | "b" => OrderSide::Buy,
+                    "sell" | "s" => OrderSide::Sell,
+                    side => return Err(format!("invalid side '{}'", side)),
+                };
+                let order = match price()? {
+                    Some(price) => Order::new_limit(id, side, timestamp, price, volume()?),
+                    None => Order::new_market(id, side, timestamp, volume()?),
+                };
+                Ok(ReplayAction::New(order))
+            }
+            "cancel" => Ok(ReplayAction::Cancel { id, timestamp }),
+            "amend" => Ok(ReplayAction::Amend {
+                id,
+                timestamp,
+                price: price()?.ok_or("amend requires a price")?,
+                volume: volume()?,
+            }),
+            action => Err(format!("unknown action '{}'", action)),
+        }
+    }
+}
+
+/// read actions from CSV, empty lines, comments starting with `#` and the header are skipped
+pub fn read_csv<R: BufRead>(reader: R) -> impl Iterator<Item = Result<ReplayAction, ReplayError>> {
+    reader.lines().enumerate().filter_map(|(i, line)| {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let record = line.trim();
+        if record.is_empty() || record.starts_with('#') || record.starts_with("timestamp") {
+            return None;
+        }
+        Some(
+            ReplayAction::from_csv(record).map_err(|reason| ReplayError::InvalidLine {
+                line: i + 1,
+                reason,
+            }),
+        )
+    })
+}
+
+/// read actions from JSON lines, one action object per line
+#[cfg(feature = "serde")]
+pub fn read_jsonl<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<ReplayAction, ReplayError>> {
+    reader.lines().enumerate().filter_map(|(i, line)| {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e.into())),
+        };
+        if line.trim().is_empty() {
+            return None;
+        }
+        Some(
+            serde_json::from_str(&line).map_err(|e| ReplayError::InvalidLine {
+                line: i + 1,
+                reason: e.to_string(),
+            }),
+        )
+    })
+}
+
+/// Pace of the replay
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReplaySpeed {
+    #[default]
+    AsFastAsPossible,
+    /// actions are applied when the wall clock reaches their timestamp,
+    /// the multiplier speeds up (above 1.0) or slows down (below 1.0) the replay
+    WallClock(f64),
+}
+
+/// Outcome of the replay
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    /// number of actions applied to the book
+    pub actions: usize,
+    /// trades of the executed new orders
+    pub trades: Vec<Trade>,
+    /// actions the book rejected
+    pub rejected: Vec<(Oid, OrderBookError)>,
+}
+
+impl ReplayReport {
+    /// executions of all trades in the order they happened
+    pub fn fills(&self) -> impl Iterator<Item = &Execution> {
+        self.trades.iter().flat_map(|trade| trade.executions.iter())
+    }
+}
+
+/// Drives order actions into the book
+#[derive(Debug)]
+pub struct Replay {
+    book: OrderBook,
+    speed: ReplaySpeed,
+}
+
+impl Replay {
+    pub fn new(book: OrderBook) -> Self {
+        Replay {
+            book,
+            speed: ReplaySpeed::default(),
+        }
+    }
+
+    pub fn with_speed(mut self, speed: ReplaySpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub fn into_book(self) -> OrderBook {
+        self.book
+    }
+
+    /// apply the actions to the book, stops at the first action that cannot be read
+    /// actions rejected by the book are recorded in the report and the replay continues
+    pub fn run<I>(&mut self, actions: I) -> Result<ReplayReport, ReplayError>
+    where
+        I: IntoIterator<Item = Result<ReplayAction, ReplayError>>,
+    {
+        let mut report = ReplayReport::default();
+        let mut clock: Option<(Instant, u64)> = None;
+        for action in actions {
+            let action = action?;
+            if let ReplaySpeed::WallClock(multiplier) = self.speed {
+                let timestamp = u64::from(action.timestamp());
+                let (start, first) = *clock.get_or_insert((Instant::now(), timestamp));
+                let elapsed = Duration::from_millis(timestamp.saturating_sub(first))
+                    .div_f64(multiplier.max(f64::MIN_POSITIVE));
+                if let Some(wait) = (start + elapsed).checked_duration_since(Instant::now()) {
+                    std::thread::sleep(wait);
+                }
+            }
+            self.apply(action, &mut report);
+            report.actions += 1;
+        }
+        Ok(report)
+    }
+
+    fn apply(&mut self, action: ReplayAction, report: &mut ReplayReport) {
+        let result = match action {
+            ReplayAction::New(order) => self
+                .book
+                .execute(&order)
+                .map(|trade| report.trades.push(trade))
+                .map_err(|e| (order.id, e)),
+            ReplayAction::Cancel { id, .. } => self
+                .book
+                .cancel_order(id)
+                .map(|_| ())
+                .map_err(|e| (id, e.into())),
+            ReplayAction::Amend {
+                id, price, volume, ..
+            } => self
+                .book
+                .amend_order(id, price, volume)
+                .map(|_| ())
+                .map_err(|e| (id, e)),
+        };
+        if let Err(rejected) = result {
+            report.rejected.push(rejected);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_replay {
+    use super::*;
+
+    const ACTIONS: &str = "timestamp,action,id,side,price,volume
+1,new,1,buy,10.0,5
+2,new,2,buy,10.5,5
+# reprice the first bid
+3,amend,1,,10.5,4
+4,new,3,sell,10.5,7
+5,cancel,9,,,
+6,new,4,sell,,2
+";
+
+    #[test]
+    fn test_replay_csv() {
+        let mut replay = Replay::new(OrderBook::default());
+        let report = replay.run(read_csv(ACTIONS.as_bytes())).unwrap();
+        assert_eq!(report.actions, 6);
+        let fills = report
+            .fills()
+            .map(|e| (e.order_id, e.volume))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fills,
+            vec![
+                (Oid::new(2), 5.into()),
+                (Oid::new(1), 2.into()),
+                (Oid::new(1), 2.into())
+            ]
+        );
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].0, Oid::new(9));
+        assert!(replay.book().is_empty());
+
+        let error = Replay::new(OrderBook::default())
+            .run(read_csv("1,new,1,up,10.0,5".as_bytes()))
+            .unwrap_err();
+        assert!(matches!(error, ReplayError::InvalidLine { line: 1, .. }));
+    }
+
+    #[test]
+    fn test_replay_wall_clock() {
+        let actions = "0,new,1,buy,10.0,5\n40,new,2,sell,10.0,5\n";
+        let start = Instant::now();
+        let report = Replay::new(OrderBook::default())
+            .with_speed(ReplaySpeed::WallClock(2.0))
+            .run(read_csv(actions.as_bytes()))
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(report.fills().count(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_replay_jsonl() {
+        let actions = [
+            ReplayAction::New(Order::new_limit(
+                Oid::new(1),
+                OrderSide::Sell,
+                Timestamp::new(1),
+                11.0.into(),
+                3.into(),
+            )),
+            ReplayAction::Amend {
+                id: Oid::new(1),
+                timestamp: Timestamp::new(2),
+                price: 11.0.into(),
+                volume: 2.into(),
+            },
+        ];
+        let lines = actions
+            .iter()
+            .map(|a| serde_json::to_string(a).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let parsed = read_jsonl(lines.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(parsed, actions);
+
+        let mut replay = Replay::new(OrderBook::default());
+        replay.run(parsed.into_iter().map(Ok)).unwrap();
+        assert_eq!(replay.book().total_volume(OrderSide::Sell), 2.into());
+    }
+}
@@ -0,0 +1,330 @@
+//!
+//! Pre-trade risk checks. Validators registered on the book are run in order before an order
+//! is added or executed, the first validator that rejects the order stops the chain.
+//!
+
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use thiserror::Error;
+
+use crate::{HashedLevels, LevelStore, Order, OrderBook, OrderSide, OwnerId, Price, Volume};
+
+/// Reason the order was rejected by a risk check
+#[derive(Error, Debug, PartialEq, PartialOrd, Clone)]
+pub enum RejectReason {
+    #[error("Volume {volume:?} is above the maximum order size {max:?}")]
+    MaxOrderSize { volume: Volume, max: Volume },
+    #[error("Notional {notional:?} is above the maximum notional {max:?}")]
+    MaxNotional { notional: Price, max: Price },
+    #[error("Open notional {notional:?} of owner {owner} is above the maximum {max:?}")]
+    MaxOpenNotional {
+        owner: OwnerId,
+        notional: Price,
+        max: Price,
+    },
+    #[error("Price {price:?} is too far from the reference price {reference:?}")]
+    PriceCollar { price: Price, reference: Price },
+    /// rejected by a custom validator
+    #[error("{0}")]
+    Other(String),
+}
+
+/// Pre-trade risk check of the book with the level store
+pub trait RiskValidator<S = HashedLevels>: core::fmt::Debug + Send + Sync {
+    /// check the order before it is added to the book or executed
+    fn validate(&self, order: &Order, book: &OrderBook<S>) -> Result<(), RejectReason>;
+}
+
+/// Reject orders with volume above the maximum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaxOrderSize(pub Volume);
+
+impl<S: LevelStore> RiskValidator<S> for MaxOrderSize {
+    fn validate(&self, order: &Order, _book: &OrderBook<S>) -> Result<(), RejectReason> {
+        if order.volume > self.0 {
+            return Err(RejectReason::MaxOrderSize {
+                volume: order.volume,
+                max: self.0,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// notional of the order at its price, market order is valued at the worst price
+/// it would sweep to, None if there is no liquidity for the market order
+fn order_notional<S: LevelStore>(order: &Order, book: &OrderBook<S>) -> Option<Price> {
+    let price = match order.price {
+        Some(price) => price,
+        None => {
+            book.estimate_fill_price(order.side, order.volume)?
+                .worst_price
+        }
+    };
+    Some(book.notional(price, order.volume))
+}
+
+/// Reject orders with price times volume above the maximum,
+/// market order is valued at the worst price it would sweep to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaxNotional(pub Price);
+
+impl<S: LevelStore> RiskValidator<S> for MaxNotional {
+    fn validate(&self, order: &Order, book: &OrderBook<S>) -> Result<(), RejectReason> {
+        let Some(notional) = order_notional(order, book) else {
+            return Ok(());
+        };
+        if notional > self.0 {
+            return Err(RejectReason::MaxNotional {
+                notional,
+                max: self.0,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reject orders that would take the open notional of their owner on the side of the book
+/// above the maximum, orders without owner are not checked.
+/// Order replacing the resting order with the same id is counted instead of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaxOpenNotional(pub Price);
+
+impl<S: LevelStore> RiskValidator<S> for MaxOpenNotional {
+    fn validate(&self, order: &Order, book: &OrderBook<S>) -> Result<(), RejectReason> {
+        let Some(owner) = order.owner else {
+            return Ok(());
+        };
+        let Some(notional) = order_notional(order, book) else {
+            return Ok(());
+        };
+        let replaced = book
+            .orders
+            .get(&order.id)
+            .filter(|resting| resting.owner == Some(owner) && resting.side == order.side)
+            .map_or(Price::ZERO, |resting| {
+                book.notional(resting.price, resting.remaining_volume())
+            });
+        let notional = book
+            .open_notional(owner, order.side)
+            .saturating_sub(replaced)
+            .saturating_add(notional);
+        if notional > self.0 {
+            return Err(RejectReason::MaxOpenNotional {
+                owner,
+                notional,
+                max: self.0,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reject limit orders priced more than the percentage away from the reference price,
+/// reference is the mid price, or the best opposite price when one side of the book is empty.
+/// Orders are not checked while there is no reference price.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceCollar {
+    /// allowed distance from the reference price in percent, i.e. 5.0 is 5%
+    pub percentage: f64,
+}
+
+impl<S: LevelStore> RiskValidator<S> for PriceCollar {
+    fn validate(&self, order: &Order, book: &OrderBook<S>) -> Result<(), RejectReason> {
+        let Some(price) = order.price else {
+            return Ok(());
+        };
+        let opposite = match order.side {
+            OrderSide::Buy => book.get_best_sell(),
+            OrderSide::Sell => book.get_best_buy(),
+        };
+        let Some(reference) = book.mid_price().or(opposite) else {
+            return Ok(());
+        };
+        let distance = (price.to_f64() - reference.to_f64()).abs();
+        if distance > reference.to_f64().abs() * self.percentage / 100.0 {
+            return Err(RejectReason::PriceCollar { price, reference });
+        }
+        Ok(())
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// add the risk check run before orders are added or executed
+    pub fn with_risk_validator(mut self, validator: impl RiskValidator<S> + 'static) -> Self {
+        self.add_risk_validator(validator);
+        self
+    }
+
+    pub fn add_risk_validator(&mut self, validator: impl RiskValidator<S> + 'static) {
+        self.risk_validators.push(Arc::new(validator));
+    }
+
+    /// run the risk checks in order, the first rejection is returned
+    pub(crate) fn check_risk(&self, order: &Order) -> Result<(), RejectReason> {
+        self.risk_validators
+            .iter()
+            .try_for_each(|validator| validator.validate(order, self))
+    }
+}
+
+#[cfg(test)]
+mod tests_risk {
+    use crate::*;
+
+    fn limit(id: u64, side: OrderSide, price: f64, volume: u64) -> Order {
+        Order::new_limit(
+            Oid::new(id),
+            side,
+            Timestamp::new(id),
+            price.into(),
+            volume.into(),
+        )
+    }
+
+    #[test]
+    fn test_risk_validators() {
+        let mut order_book = OrderBook::default()
+            .with_risk_validator(MaxOrderSize(100.into()))
+            .with_risk_validator(MaxNotional(Price::new(900.0)))
+            .with_risk_validator(PriceCollar { percentage: 10.0 });
+
+        order_book
+            .execute(&limit(1, OrderSide::Sell, 10.0, 50))
+            .unwrap();
+        assert_eq!(
+            order_book
+                .execute(&limit(2, OrderSide::Sell, 10.0, 101))
+                .unwrap_err(),
+            OrderBookError::RiskRejected(
+                Oid::new(2),
+                RejectReason::MaxOrderSize {
+                    volume: 101.into(),
+                    max: 100.into()
+                }
+            )
+        );
+        assert!(matches!(
+            order_book.execute(&limit(3, OrderSide::Buy, 10.5, 99)),
+            Err(OrderBookError::RiskRejected(
+                _,
+                RejectReason::MaxNotional { .. }
+            ))
+        ));
+        let market = Order::new_market(Oid::new(4), OrderSide::Buy, Timestamp::new(4), 100.into());
+        assert!(order_book.execute(&market).is_err());
+
+        // best ask is the reference while there are no bids
+        let order = LimitOrder::new(
+            Oid::new(5),
+            OrderSide::Buy,
+            Timestamp::new(5),
+            8.0.into(),
+            10.into(),
+        );
+        assert_eq!(
+            order_book.add_order(order).unwrap_err(),
+            OrderBookError::RiskRejected(
+                Oid::new(5),
+                RejectReason::PriceCollar {
+                    price: 8.0.into(),
+                    reference: 10.0.into()
+                }
+            )
+        );
+        order_book
+            .execute(&limit(6, OrderSide::Buy, 9.5, 10))
+            .unwrap();
+        assert_eq!(order_book.order_count(), 2);
+    }
+
+    #[test]
+    fn test_max_open_notional() {
+        let owner = OwnerId::new(1);
+        let mut order_book = OrderBook::default()
+            .with_contract_multiplier(10)
+            .with_risk_validator(MaxOpenNotional(Price::new(1_000.0)));
+        order_book
+            .execute(&limit(1, OrderSide::Buy, 10.0, 6).with_owner(owner))
+            .unwrap();
+        // 600 is open, another 500 would take it above the maximum
+        assert_eq!(
+            order_book
+                .execute(&limit(2, OrderSide::Buy, 10.0, 5).with_owner(owner))
+                .unwrap_err(),
+            OrderBookError::RiskRejected(
+                Oid::new(2),
+                RejectReason::MaxOpenNotional {
+                    owner,
+                    notional: Price::new(1_100.0),
+                    max: Price::new(1_000.0)
+                }
+            )
+        );
+        // other side and orders without owner are counted separately
+        order_book
+            .execute(&limit(3, OrderSide::Sell, 11.0, 9).with_owner(owner))
+            .unwrap();
+        order_book
+            .execute(&limit(4, OrderSide::Buy, 10.0, 5))
+            .unwrap();
+        assert_eq!(
+            order_book.open_notional(owner, OrderSide::Buy),
+            Price::new(600.0)
+        );
+
+        // replaced order is counted instead of the resting one
+        order_book
+            .amend_order(Oid::new(1), 10.0.into(), 9.into())
+            .unwrap();
+        assert!(matches!(
+            order_book.amend_order(Oid::new(1), 10.0.into(), 11.into()),
+            Err(OrderBookError::RiskRejected(
+                _,
+                RejectReason::MaxOpenNotional { .. }
+            ))
+        ));
+        assert_eq!(
+            order_book.open_notional(owner, OrderSide::Buy),
+            Price::new(900.0)
+        );
+    }
+
+    #[test]
+    fn test_risk_is_checked_once() {
+        let mut order_book = OrderBook::default().with_risk_validator(MaxOrderSize(100.into()));
+        order_book
+            .execute(&limit(1, OrderSide::Buy, 10.0, 80))
+            .unwrap();
+        // rejected amend leaves the resting order as it was
+        assert!(matches!(
+            order_book.amend_order(Oid::new(1), 10.0.into(), 150.into()),
+            Err(OrderBookError::RiskRejected(
+                _,
+                RejectReason::MaxOrderSize { .. }
+            ))
+        ));
+        assert_eq!(order_book.order_count(), 1);
+        assert_eq!(order_book.get_best_buy_volume(), Some(80.into()));
+
+        // remainder is not checked again after the fills moved the reference price
+        let mut order_book = OrderBook::default();
+        order_book
+            .execute(&limit(1, OrderSide::Buy, 9.0, 1))
+            .unwrap();
+        order_book
+            .execute(&limit(2, OrderSide::Sell, 10.0, 5))
+            .unwrap();
+        order_book
+            .execute(&limit(3, OrderSide::Sell, 20.0, 5))
+            .unwrap();
+        order_book.add_risk_validator(PriceCollar { percentage: 20.0 });
+        let trade = order_book
+            .execute(&limit(4, OrderSide::Buy, 11.0, 10))
+            .unwrap();
+        assert_eq!(trade.filled_volume, 5.into());
+        assert_eq!(order_book.get_best_buy(), Some(11.0.into()));
+        assert_eq!(order_book.get_best_buy_volume(), Some(5.into()));
+    }
+}
@@ -0,0 +1,501 @@
+//!
+//! Zero-copy binary encoding of events, following Simple Binary Encoding conventions.
+//! Every message starts with the standard 8 byte header (block length, template id, schema id,
+//! version) followed by a fixed size little endian block. Encoders write into a caller provided
+//! buffer and decoders are flyweights that read fields directly from the received bytes,
+//! so neither side allocates. Volumes are scaled integers, every block carries the number
+//! of their decimal places and messages of a build with other volume decimals are rejected.
+//!
+
+use thiserror::Error;
+
+use crate::utils::VOLUME_DECIMALS;
+use crate::{
+    BookDelta, DeltaEvent, DepthLevel, DepthSnapshot, Fill, Oid, OrderSide, Price, Volume,
+};
+
+pub const SCHEMA_ID: u16 = 1;
+pub const SCHEMA_VERSION: u16 = 4;
+pub const HEADER_LENGTH: usize = 8;
+
+pub const FILL_TEMPLATE_ID: u16 = 1;
+pub const BOOK_DELTA_TEMPLATE_ID: u16 = 2;
+pub const DEPTH_SNAPSHOT_TEMPLATE_ID: u16 = 3;
+
+const FILL_BLOCK_LENGTH: u16 = 64;
+const BOOK_DELTA_BLOCK_LENGTH: u16 = 48;
+const DEPTH_SNAPSHOT_BLOCK_LENGTH: u16 = 16;
+const DEPTH_LEVEL_LENGTH: usize = 24;
+
+/// Encoding error
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum SbeError {
+    #[error("Buffer of {available} bytes is too short, {required} bytes required")]
+    BufferTooShort { required: usize, available: usize },
+    #[error("Unexpected template id {0}")]
+    UnexpectedTemplate(u16),
+    #[error("Unsupported schema {schema_id} version {version}")]
+    UnsupportedSchema { schema_id: u16, version: u16 },
+    #[error("Invalid value of field {0}")]
+    InvalidValue(&'static str),
+    #[error("Volumes have {found} decimal places, expected {expected}")]
+    VolumeDecimals { expected: u8, found: u8 },
+}
+
+fn check_len(buffer: &[u8], required: usize) -> Result<(), SbeError> {
+    if buffer.len() < required {
+        return Err(SbeError::BufferTooShort {
+            required,
+            available: buffer.len(),
+        });
+    }
+    Ok(())
+}
+
+fn put_u16(buffer: &mut [u8], offset: usize, value: u16) {
+    buffer[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut [u8], offset: usize, value: u32) {
+    buffer[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buffer: &mut [u8], offset: usize, value: u64) {
+    buffer[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_i64(buffer: &mut [u8], offset: usize, value: i64) {
+    buffer[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn get_u16(buffer: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buffer[offset], buffer[offset + 1]])
+}
+
+fn get_u32(buffer: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buffer[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn get_u64(buffer: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buffer[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn get_i64(buffer: &[u8], offset: usize) -> i64 {
+    get_u64(buffer, offset) as i64
+}
+
+fn put_header(buffer: &mut [u8], block_length: u16, template_id: u16) {
+    put_u16(buffer, 0, block_length);
+    put_u16(buffer, 2, template_id);
+    put_u16(buffer, 4, SCHEMA_ID);
+    put_u16(buffer, 6, SCHEMA_VERSION);
+}
+
+/// check the header and return the message block
+fn wrap(buffer: &[u8], template_id: u16, block_length: u16) -> Result<&[u8], SbeError> {
+    check_len(buffer, HEADER_LENGTH)?;
+    if get_u16(buffer, 2) != template_id {
+        return Err(SbeError::UnexpectedTemplate(get_u16(buffer, 2)));
+    }
+    let (schema_id, version) = (get_u16(buffer, 4), get_u16(buffer, 6));
+    if schema_id != SCHEMA_ID || version != SCHEMA_VERSION {
+        return Err(SbeError::UnsupportedSchema { schema_id, version });
+    }
+    check_len(buffer, HEADER_LENGTH + block_length as usize)?;
+    Ok(&buffer[HEADER_LENGTH..])
+}
+
+/// check that the volumes of the block have the decimal places of this build
+fn check_volume_decimals(block: &[u8], offset: usize) -> Result<(), SbeError> {
+    let (expected, found) = (VOLUME_DECIMALS as u8, block[offset]);
+    if found != expected {
+        return Err(SbeError::VolumeDecimals { expected, found });
+    }
+    Ok(())
+}
+
+/// template id of the encoded message, used to pick the decoder
+pub fn template_id(buffer: &[u8]) -> Result<u16, SbeError> {
+    check_len(buffer, HEADER_LENGTH)?;
+    Ok(get_u16(buffer, 2))
+}
+
+fn side_code(side: OrderSide) -> u8 {
+    match side {
+        OrderSide::Buy => 0,
+        OrderSide::Sell => 1,
+    }
+}
+
+fn side_from_code(code: u8) -> Result<OrderSide, SbeError> {
+    match code {
+        0 => Ok(OrderSide::Buy),
+        1 => Ok(OrderSide::Sell),
+        _ => Err(SbeError::InvalidValue("side")),
+    }
+}
+
+// fill block: buy and sell order id u64, buy and sell order price i64, volume u64,
+// trade price i64, sequence u64, volume decimals u8, 7 bytes padding
+
+/// encode the fill, returns the number of bytes written
+pub fn encode_fill(fill: &Fill, buffer: &mut [u8]) -> Result<usize, SbeError> {
+    let len = HEADER_LENGTH + FILL_BLOCK_LENGTH as usize;
+    check_len(buffer, len)?;
+    put_header(buffer, FILL_BLOCK_LENGTH, FILL_TEMPLATE_ID);
+    let block = &mut buffer[HEADER_LENGTH..len];
+    block.fill(0);
+    put_u64(block, 0, fill.buy_order_id.into());
+    put_u64(block, 8, fill.sell_order_id.into());
+    put_i64(block, 16, fill.buy_order_price.mantissa());
+    put_i64(block, 24, fill.sell_order_price.mantissa());
+    put_u64(block, 32, fill.volume.mantissa());
+    put_i64(block, 40, fill.trade_price.mantissa());
+    put_u64(block, 48, fill.sequence);
+    block[56] = VOLUME_DECIMALS as u8;
+    Ok(len)
+}
+
+/// Flyweight over an encoded fill
+#[derive(Debug, Clone, Copy)]
+pub struct FillDecoder<'a> {
+    block: &'a [u8],
+}
+
+impl<'a> FillDecoder<'a> {
+    pub fn wrap(buffer: &'a [u8]) -> Result<Self, SbeError> {
+        let block = wrap(buffer, FILL_TEMPLATE_ID, FILL_BLOCK_LENGTH)?;
+        check_volume_decimals(block, 56)?;
+        Ok(FillDecoder { block })
+    }
+
+    pub fn buy_order_id(&self) -> Oid {
+        Oid::new(get_u64(self.block, 0))
+    }
+
+    pub fn sell_order_id(&self) -> Oid {
+        Oid::new(get_u64(self.block, 8))
+    }
+
+    pub fn buy_order_price(&self) -> Price {
+        Price::from_mantissa(get_i64(self.block, 16))
+    }
+
+    pub fn sell_order_price(&self) -> Price {
+        Price::from_mantissa(get_i64(self.block, 24))
+    }
+
+    pub fn volume(&self) -> Volume {
+        Volume::from_mantissa(get_u64(self.block, 32))
+    }
+
+    pub fn trade_price(&self) -> Price {
+        Price::from_mantissa(get_i64(self.block, 40))
+    }
+
+    pub fn sequence(&self) -> u64 {
+        get_u64(self.block, 48)
+    }
+
+    pub fn to_fill(&self) -> Fill {
+        Fill {
+            buy_order_id: self.buy_order_id(),
+            sell_order_id: self.sell_order_id(),
+            buy_order_price: self.buy_order_price(),
+            sell_order_price: self.sell_order_price(),
+            trade_price: self.trade_price(),
+            volume: self.volume(),
+            sequence: self.sequence(),
+        }
+    }
+}
+
+// book delta block: sequence u64, kind u8, side u8, volume decimals u8, 5 bytes padding,
+// price i64, volume u64, order count u32, 4 bytes padding, event sequence u64
+const DELTA_LEVEL_ADDED: u8 = 0;
+const DELTA_LEVEL_UPDATED: u8 = 1;
+const DELTA_LEVEL_REMOVED: u8 = 2;
+const DELTA_TRADE: u8 = 3;
+
+/// encode the book delta, returns the number of bytes written
+pub fn encode_delta(delta: &BookDelta, buffer: &mut [u8]) -> Result<usize, SbeError> {
+    let len = HEADER_LENGTH + BOOK_DELTA_BLOCK_LENGTH as usize;
+    check_len(buffer, len)?;
+    put_header(buffer, BOOK_DELTA_BLOCK_LENGTH, BOOK_DELTA_TEMPLATE_ID);
+    let (kind, side, price, volume, order_count) = match delta.event {
+        DeltaEvent::LevelAdded {
+            side,
+            price,
+            volume,
+            order_count,
+        } => (DELTA_LEVEL_ADDED, side, price, volume, order_count),
+        DeltaEvent::LevelUpdated {
+            side,
+            price,
+            volume,
+            order_count,
+        } => (DELTA_LEVEL_UPDATED, side, price, volume, order_count),
+        DeltaEvent::LevelRemoved { side, price } => {
+            (DELTA_LEVEL_REMOVED, side, price, Volume::ZERO, 0)
+        }
+        DeltaEvent::Trade {
+            aggressor_side,
+            price,
+            volume,
+        } => (DELTA_TRADE, aggressor_side, price, volume, 0),
+    };
+    let block = &mut buffer[HEADER_LENGTH..len];
+    block.fill(0);
+    put_u64(block, 0, delta.sequence);
+    block[8] = kind;
+    block[9] = side_code(side);
+    block[10] = VOLUME_DECIMALS as u8;
+    put_i64(block, 16, price.mantissa());
+    put_u64(block, 24, volume.mantissa());
+    put_u32(block, 32, order_count as u32);
+    put_u64(block, 40, delta.event_sequence);
+    Ok(len)
+}
+
+/// Flyweight over an encoded book delta
+#[derive(Debug, Clone, Copy)]
+pub struct DeltaDecoder<'a> {
+    block: &'a [u8],
+}
+
+impl<'a> DeltaDecoder<'a> {
+    pub fn wrap(buffer: &'a [u8]) -> Result<Self, SbeError> {
+        let block = wrap(buffer, BOOK_DELTA_TEMPLATE_ID, BOOK_DELTA_BLOCK_LENGTH)?;
+        check_volume_decimals(block, 10)?;
+        Ok(DeltaDecoder { block })
+    }
+
+    pub fn sequence(&self) -> u64 {
+        get_u64(self.block, 0)
+    }
+
+    pub fn event_sequence(&self) -> u64 {
+        get_u64(self.block, 40)
+    }
+
+    pub fn side(&self) -> Result<OrderSide, SbeError> {
+        side_from_code(self.block[9])
+    }
+
+    pub fn price(&self) -> Price {
+        Price::from_mantissa(get_i64(self.block, 16))
+    }
+
+    pub fn volume(&self) -> Volume {
+        Volume::from_mantissa(get_u64(self.block, 24))
+    }
+
+    pub fn order_count(&self) -> usize {
+        get_u32(self.block, 32) as usize
+    }
+
+    pub fn event(&self) -> Result<DeltaEvent, SbeError> {
+        let side = self.side()?;
+        let (price, volume, order_count) = (self.price(), self.volume(), self.order_count());
+        match self.block[8] {
+            DELTA_LEVEL_ADDED => Ok(DeltaEvent::LevelAdded {
+                side,
+                price,
+                volume,
+                order_count,
+            }),
+            DELTA_LEVEL_UPDATED => Ok(DeltaEvent::LevelUpdated {
+                side,
+                price,
+                volume,
+                order_count,
+            }),
+            DELTA_LEVEL_REMOVED => Ok(DeltaEvent::LevelRemoved { side, price }),
+            DELTA_TRADE => Ok(DeltaEvent::Trade {
+                aggressor_side: side,
+                price,
+                volume,
+            }),
+            _ => Err(SbeError::InvalidValue("delta kind")),
+        }
+    }
+
+    pub fn to_delta(&self) -> Result<BookDelta, SbeError> {
+        Ok(BookDelta {
+            sequence: self.sequence(),
+            event_sequence: self.event_sequence(),
+            event: self.event()?,
+        })
+    }
+}
+
+/// bytes required to encode the depth snapshot
+pub fn depth_encoded_len(snapshot: &DepthSnapshot) -> usize {
+    HEADER_LENGTH
+        + DEPTH_SNAPSHOT_BLOCK_LENGTH as usize
+        + (snapshot.bids.len() + snapshot.asks.len()) * DEPTH_LEVEL_LENGTH
+}
+
+/// encode the depth snapshot, block holds the bid and ask level counts and the volume decimals,
+/// it is followed by the bid and then the ask levels
+pub fn encode_depth(snapshot: &DepthSnapshot, buffer: &mut [u8]) -> Result<usize, SbeError> {
+    let len = depth_encoded_len(snapshot);
+    check_len(buffer, len)?;
+    put_header(
+        buffer,
+        DEPTH_SNAPSHOT_BLOCK_LENGTH,
+        DEPTH_SNAPSHOT_TEMPLATE_ID,
+    );
+    let block = &mut buffer[HEADER_LENGTH..len];
+    block[..DEPTH_SNAPSHOT_BLOCK_LENGTH as usize].fill(0);
+    put_u32(block, 0, snapshot.bids.len() as u32);
+    put_u32(block, 4, snapshot.asks.len() as u32);
+    block[8] = VOLUME_DECIMALS as u8;
+    let levels = snapshot.bids.iter().chain(&snapshot.asks);
+    for (i, level) in levels.enumerate() {
+        let offset = DEPTH_SNAPSHOT_BLOCK_LENGTH as usize + i * DEPTH_LEVEL_LENGTH;
+        put_i64(block, offset, level.price.mantissa());
+        put_u64(block, offset + 8, level.volume.mantissa());
+        put_u32(block, offset + 16, level.order_count as u32);
+        put_u32(block, offset + 20, 0);
+    }
+    Ok(len)
+}
+
+/// Flyweight over an encoded depth snapshot
+#[derive(Debug, Clone, Copy)]
+pub struct DepthDecoder<'a> {
+    block: &'a [u8],
+}
+
+impl<'a> DepthDecoder<'a> {
+    pub fn wrap(buffer: &'a [u8]) -> Result<Self, SbeError> {
+        let block = wrap(
+            buffer,
+            DEPTH_SNAPSHOT_TEMPLATE_ID,
+            DEPTH_SNAPSHOT_BLOCK_LENGTH,
+        )?;
+        check_volume_decimals(block, 8)?;
+        let decoder = DepthDecoder { block };
+        let levels = decoder.bid_count() + decoder.ask_count();
+        check_len(
+            block,
+            DEPTH_SNAPSHOT_BLOCK_LENGTH as usize + levels * DEPTH_LEVEL_LENGTH,
+        )?;
+        Ok(decoder)
+    }
+
+    pub fn bid_count(&self) -> usize {
+        get_u32(self.block, 0) as usize
+    }
+
+    pub fn ask_count(&self) -> usize {
+        get_u32(self.block, 4) as usize
+    }
+
+    fn level(&self, i: usize) -> DepthLevel {
+        let offset = DEPTH_SNAPSHOT_BLOCK_LENGTH as usize + i * DEPTH_LEVEL_LENGTH;
+        DepthLevel {
+            price: Price::from_mantissa(get_i64(self.block, offset)),
+            volume: Volume::from_mantissa(get_u64(self.block, offset + 8)),
+            order_count: get_u32(self.block, offset + 16) as usize,
+        }
+    }
+
+    /// bid levels from the best price
+    pub fn bids(&self) -> impl Iterator<Item = DepthLevel> + 'a {
+        let decoder = *self;
+        (0..self.bid_count()).map(move |i| decoder.level(i))
+    }
+
+    /// ask levels from the best price
+    pub fn asks(&self) -> impl Iterator<Item = DepthLevel> + 'a {
+        let decoder = *self;
+        let bid_count = self.bid_count();
+        (0..self.ask_count()).map(move |i| decoder.level(bid_count + i))
+    }
+}
+
+#[cfg(test)]
+mod tests_sbe {
+    use super::*;
+
+    #[test]
+    fn test_fill_roundtrip() {
+        let fill = Fill {
+            buy_order_id: Oid::new(1),
+            sell_order_id: Oid::new(2),
+            buy_order_price: Price::new(10.5),
+            sell_order_price: Price::new(10.25),
+            trade_price: Price::new(10.25),
+            volume: Volume::new(7),
+            sequence: 4,
+        };
+        let mut buffer = [0u8; 72];
+        let len = encode_fill(&fill, &mut buffer).unwrap();
+        assert_eq!(template_id(&buffer[..len]), Ok(FILL_TEMPLATE_ID));
+        let decoder = FillDecoder::wrap(&buffer[..len]).unwrap();
+        assert_eq!(decoder.sell_order_price(), Price::new(10.25));
+        assert_eq!(decoder.volume(), Volume::new(7));
+        assert_eq!(decoder.trade_price(), Price::new(10.25));
+        assert_eq!(decoder.sequence(), 4);
+        assert!(DeltaDecoder::wrap(&buffer[..len]).is_err());
+        assert_eq!(
+            encode_fill(&fill, &mut buffer[..10]),
+            Err(SbeError::BufferTooShort {
+                required: 72,
+                available: 10
+            })
+        );
+
+        // volumes of a build with other volume decimals are not misread
+        buffer[HEADER_LENGTH + 56] = VOLUME_DECIMALS as u8 + 1;
+        assert_eq!(
+            FillDecoder::wrap(&buffer[..len]).unwrap_err(),
+            SbeError::VolumeDecimals {
+                expected: VOLUME_DECIMALS as u8,
+                found: VOLUME_DECIMALS as u8 + 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_delta_and_depth_roundtrip() {
+        let delta = BookDelta {
+            sequence: 9,
+            event_sequence: 12,
+            event: DeltaEvent::LevelUpdated {
+                side: OrderSide::Sell,
+                price: Price::new(11.0),
+                volume: Volume::new(3),
+                order_count: 2,
+            },
+        };
+        let mut buffer = [0u8; 64];
+        let len = encode_delta(&delta, &mut buffer).unwrap();
+        assert_eq!(
+            DeltaDecoder::wrap(&buffer[..len]).unwrap().to_delta(),
+            Ok(delta)
+        );
+
+        let level = |price: f64, volume: u64| DepthLevel {
+            price: Price::new(price),
+            volume: Volume::new(volume),
+            order_count: 1,
+        };
+        let snapshot = DepthSnapshot {
+            bids: vec![level(10.0, 5), level(9.0, 6)],
+            asks: vec![level(11.0, 7)],
+        };
+        let mut buffer = vec![0u8; depth_encoded_len(&snapshot)];
+        encode_depth(&snapshot, &mut buffer).unwrap();
+        let decoder = DepthDecoder::wrap(&buffer).unwrap();
+        assert_eq!(decoder.bids().collect::<Vec<_>>(), snapshot.bids);
+        assert_eq!(decoder.asks().collect::<Vec<_>>(), snapshot.asks);
+        assert!(DepthDecoder::wrap(&buffer[..buffer.len() - 1]).is_err());
+    }
+}
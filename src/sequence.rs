@@ -0,0 +1,80 @@
+//!
+//! Event sequence of the book. Every accepted order, execution, fill, cancellation and amend
+//! takes the next number of a single counter, which is included in its report, so consumers
+//! can detect gaps and order the events deterministically. Level changes published on the
+//! delta feed carry the sequence of the event that caused them.
+//!
+
+use crate::{LevelStore, OrderBook};
+
+impl<S: LevelStore> OrderBook<S> {
+    /// sequence number of the last accepted event, zero before the first event
+    pub fn event_sequence(&self) -> u64 {
+        self.event_sequence
+    }
+
+    pub(crate) fn next_event_sequence(&mut self) -> u64 {
+        self.event_sequence += 1;
+        self.event_sequence
+    }
+}
+
+#[cfg(test)]
+mod tests_sequence {
+    use crate::*;
+
+    #[test]
+    fn test_events_are_sequenced() {
+        let mut order_book = OrderBook::default().with_delta_feed();
+        let orders = [
+            Order::new_limit(
+                Oid::new(1),
+                OrderSide::Sell,
+                Timestamp::new(1),
+                10.0.into(),
+                5.into(),
+            ),
+            Order::new_limit(
+                Oid::new(2),
+                OrderSide::Sell,
+                Timestamp::new(2),
+                10.0.into(),
+                5.into(),
+            ),
+            Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(3), 7.into()),
+        ];
+        let trades = orders
+            .iter()
+            .map(|order| order_book.execute(order).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(trades[0].sequence, 1);
+        assert_eq!(trades[1].sequence, 2);
+        assert_eq!(trades[2].sequence, 3);
+        assert_eq!(
+            trades[2]
+                .executions
+                .iter()
+                .map(|e| e.sequence)
+                .collect::<Vec<_>>(),
+            vec![4, 5]
+        );
+
+        let amend = order_book
+            .amend_order(Oid::new(2), 10.0.into(), 4.into())
+            .unwrap();
+        assert_eq!(amend.sequence, 6);
+        let cancel = order_book.cancel_order(Oid::new(2)).unwrap();
+        assert_eq!(cancel.sequence(), 7);
+        assert_eq!(order_book.event_sequence(), 7);
+
+        // rejected commands are not sequenced
+        assert!(order_book.cancel_order(Oid::new(2)).is_err());
+        assert_eq!(order_book.event_sequence(), 7);
+
+        let deltas = order_book.drain_deltas();
+        assert!(deltas
+            .windows(2)
+            .all(|pair| pair[0].event_sequence <= pair[1].event_sequence));
+        assert_eq!(deltas.last().map(|d| d.event_sequence), Some(7));
+    }
+}
@@ -0,0 +1,130 @@
+//!
+//! Asynchronous front-end of the book.
+//! The book is owned by a single tokio task that consumes commands from a channel and streams
+//! the execution reports back in the order the commands were applied, so the book itself never
+//! needs to be shared or locked.
+//!
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::{Command, ExecutionReport, OrderBook};
+
+/// Handle to the book running in its own task
+#[derive(Debug)]
+pub struct BookService {
+    commands: mpsc::Sender<Command>,
+    reports: mpsc::Receiver<ExecutionReport>,
+    task: JoinHandle<OrderBook>,
+}
+
+impl BookService {
+    /// spawn the task owning the book, capacity bounds both the command and the report channel
+    /// must be called from within a tokio runtime
+    pub fn spawn(mut book: OrderBook, capacity: usize) -> Self {
+        let (commands, mut command_rx) = mpsc::channel(capacity);
+        let (report_tx, reports) = mpsc::channel(capacity);
+        let task = tokio::spawn(async move {
+            while let Some(command) = command_rx.recv().await {
+                let mut pending = Vec::new();
+                book.apply(command, |report| pending.push(report));
+                for report in pending {
+                    // reports are dropped once the handle is gone, commands are still applied
+                    let _ = report_tx.send(report).await;
+                }
+            }
+            book
+        });
+        BookService {
+            commands,
+            reports,
+            task,
+        }
+    }
+
+    /// sender that can be shared with other tasks feeding the book
+    pub fn commands(&self) -> mpsc::Sender<Command> {
+        self.commands.clone()
+    }
+
+    /// queue the command, waits while the command channel is full
+    pub async fn send(&self, command: Command) -> Result<(), mpsc::error::SendError<Command>> {
+        self.commands.send(command).await
+    }
+
+    /// next execution report, none once the task has stopped and all reports were received
+    pub async fn recv(&mut self) -> Option<ExecutionReport> {
+        self.reports.recv().await
+    }
+
+    /// stop accepting commands and return the book once all queued commands were applied
+    /// reports not yet received are discarded
+    pub async fn shutdown(self) -> Result<OrderBook, tokio::task::JoinError> {
+        let BookService {
+            commands,
+            reports,
+            task,
+        } = self;
+        drop(commands);
+        drop(reports);
+        task.await
+    }
+}
+
+#[cfg(test)]
+mod tests_service {
+    use crate::*;
+
+    #[test]
+    fn test_book_service() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut service = BookService::spawn(OrderBook::default(), 8);
+            let sender = service.commands();
+            sender
+                .send(Command::Execute(Order::new_limit(
+                    Oid::new(1),
+                    OrderSide::Sell,
+                    Timestamp::new(1),
+                    10.0.into(),
+                    5.into(),
+                )))
+                .await
+                .unwrap();
+            service
+                .send(Command::Execute(Order::new_market(
+                    Oid::new(2),
+                    OrderSide::Buy,
+                    Timestamp::new(2),
+                    2.into(),
+                )))
+                .await
+                .unwrap();
+            service.send(Command::Cancel(Oid::new(3))).await.unwrap();
+
+            for _ in 0..3 {
+                assert!(matches!(
+                    service.recv().await,
+                    Some(ExecutionReport::Accepted { .. } | ExecutionReport::PartialFill { .. })
+                ));
+            }
+            let Some(ExecutionReport::Filled {
+                order_id, volume, ..
+            }) = service.recv().await
+            else {
+                panic!("expected fill");
+            };
+            assert_eq!((order_id, volume), (Oid::new(2), 2.into()));
+            assert!(matches!(
+                service.recv().await,
+                Some(ExecutionReport::Rejected { order_id, .. }) if order_id == Oid::new(3)
+            ));
+
+            drop(sender);
+            let book = service.shutdown().await.unwrap();
+            assert_eq!(book.total_volume(OrderSide::Sell), 3.into());
+        });
+    }
+}
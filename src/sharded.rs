@@ -0,0 +1,312 @@
+//!
+//! Order books of many instruments partitioned across worker threads.
+//! Each book is owned by exactly one shard thread, so books are never shared or locked,
+//! commands are routed to the shard of their symbol over a channel. Execution reports of all
+//! shards are merged into one stream, reports of one symbol are in the order its commands
+//! were applied. The manager itself is `Send + Sync` and can be shared by the gateway threads.
+//!
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::thread::JoinHandle;
+
+use hashbrown::HashMap;
+
+use crate::{Command, ExecutionReport, Instrument, ManagerError, OrderBook, Symbol};
+
+type BookQuery = Box<dyn FnOnce(&OrderBook) + Send>;
+
+enum ShardMessage {
+    AddBook(Symbol, Box<OrderBook>),
+    RemoveBook(Symbol, Sender<OrderBook>),
+    Apply(Symbol, Command),
+    Query(Symbol, BookQuery),
+}
+
+#[derive(Debug)]
+struct Shard {
+    messages: Sender<ShardMessage>,
+    worker: JoinHandle<BTreeMap<Symbol, OrderBook>>,
+}
+
+/// Books partitioned across shard threads
+#[derive(Debug)]
+pub struct ShardedBookManager {
+    shards: Vec<Shard>,
+    // shard of each symbol
+    routes: RwLock<HashMap<Symbol, usize>>,
+    reports: Mutex<Receiver<(Symbol, ExecutionReport)>>,
+}
+
+impl ShardedBookManager {
+    /// start the given number of shard threads, at least one
+    pub fn new(shards: usize) -> Self {
+        let (report_tx, reports) = mpsc::channel();
+        let shards = (0..shards.max(1))
+            .map(|index| {
+                let (messages, message_rx) = mpsc::channel();
+                let report_tx = report_tx.clone();
+                let worker = std::thread::Builder::new()
+                    .name(format!("lob-shard-{index}"))
+                    .spawn(move || run_shard(message_rx, report_tx))
+                    .expect("failed to spawn shard thread");
+                Shard { messages, worker }
+            })
+            .collect();
+        ShardedBookManager {
+            shards,
+            routes: RwLock::new(HashMap::new()),
+            reports: Mutex::new(reports),
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// shard owning the book of the symbol
+    pub fn shard_of(&self, symbol: &Symbol) -> Option<usize> {
+        self.routes
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(symbol)
+            .copied()
+    }
+
+    /// symbols in alphabetical order
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let mut symbols = self
+            .routes
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        symbols.sort();
+        symbols
+    }
+
+    /// move the book of the symbol to the shard with the fewest books
+    pub fn add_book(&self, symbol: Symbol, book: OrderBook) -> Result<usize, ManagerError> {
+        let mut routes = self.routes.write().unwrap_or_else(PoisonError::into_inner);
+        if routes.contains_key(&symbol) {
+            return Err(ManagerError::SymbolExists(symbol));
+        }
+        let mut books = vec![0; self.shards.len()];
+        for shard in routes.values() {
+            books[*shard] += 1;
+        }
+        let shard = (0..books.len())
+            .min_by_key(|index| books[*index])
+            .unwrap_or_default();
+        self.dispatch(shard, ShardMessage::AddBook(symbol.clone(), Box::new(book)))?;
+        routes.insert(symbol, shard);
+        Ok(shard)
+    }
+
+    /// add an empty book trading by the spec of the instrument
+    pub fn add_instrument(&self, instrument: &Instrument) -> Result<usize, ManagerError> {
+        let book = OrderBook::default()
+            .with_instrument_spec(instrument.spec)
+            .with_contract_multiplier(instrument.multiplier);
+        self.add_book(instrument.symbol.clone(), book)
+    }
+
+    /// take the book of the symbol back from its shard
+    pub fn remove_book(&self, symbol: &Symbol) -> Result<OrderBook, ManagerError> {
+        let shard = self
+            .routes
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(symbol)
+            .ok_or_else(|| ManagerError::UnknownSymbol(symbol.clone()))?;
+        let (book_tx, book_rx) = mpsc::channel();
+        self.dispatch(shard, ShardMessage::RemoveBook(symbol.clone(), book_tx))?;
+        book_rx
+            .recv()
+            .map_err(|_| ManagerError::UnknownSymbol(symbol.clone()))
+    }
+
+    /// queue the command on the shard of the symbol, its reports are sent to the report stream
+    pub fn send(&self, symbol: &Symbol, command: Command) -> Result<(), ManagerError> {
+        let shard = self.route(symbol)?;
+        self.dispatch(shard, ShardMessage::Apply(symbol.clone(), command))
+    }
+
+    /// run the query on the book of the symbol in its shard and wait for the result,
+    /// all commands queued for the symbol before the query have been applied
+    pub fn query<R: Send + 'static>(
+        &self,
+        symbol: &Symbol,
+        query: impl FnOnce(&OrderBook) -> R + Send + 'static,
+    ) -> Result<R, ManagerError> {
+        let shard = self.route(symbol)?;
+        let (result_tx, result_rx) = mpsc::channel();
+        let query: BookQuery = Box::new(move |book| {
+            let _ = result_tx.send(query(book));
+        });
+        self.dispatch(shard, ShardMessage::Query(symbol.clone(), query))?;
+        result_rx
+            .recv()
+            .map_err(|_| ManagerError::UnknownSymbol(symbol.clone()))
+    }
+
+    /// next report of any shard, waits until one is available
+    /// none once all shards have stopped and every report was received
+    pub fn recv(&self) -> Option<(Symbol, ExecutionReport)> {
+        self.reports
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .recv()
+            .ok()
+    }
+
+    /// reports available now
+    pub fn drain_reports(&self) -> Vec<(Symbol, ExecutionReport)> {
+        self.reports
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .try_iter()
+            .collect()
+    }
+
+    /// stop the shards once the queued commands were applied and return all books
+    pub fn shutdown(self) -> BTreeMap<Symbol, OrderBook> {
+        let mut books = BTreeMap::new();
+        for Shard { messages, worker } in self.shards {
+            drop(messages);
+            if let Ok(shard_books) = worker.join() {
+                books.extend(shard_books);
+            }
+        }
+        books
+    }
+
+    fn route(&self, symbol: &Symbol) -> Result<usize, ManagerError> {
+        self.shard_of(symbol)
+            .ok_or_else(|| ManagerError::UnknownSymbol(symbol.clone()))
+    }
+
+    fn dispatch(&self, shard: usize, message: ShardMessage) -> Result<(), ManagerError> {
+        self.shards[shard]
+            .messages
+            .send(message)
+            .map_err(|_| ManagerError::ShardStopped(shard))
+    }
+}
+
+/// apply the messages to the books of the shard until the manager is gone
+fn run_shard(
+    messages: Receiver<ShardMessage>,
+    reports: Sender<(Symbol, ExecutionReport)>,
+) -> BTreeMap<Symbol, OrderBook> {
+    let mut books = BTreeMap::new();
+    for message in messages {
+        match message {
+            ShardMessage::AddBook(symbol, book) => {
+                books.insert(symbol, *book);
+            }
+            ShardMessage::RemoveBook(symbol, book_tx) => {
+                if let Some(book) = books.remove(&symbol) {
+                    let _ = book_tx.send(book);
+                }
+            }
+            ShardMessage::Apply(symbol, command) => {
+                if let Some(book) = books.get_mut(&symbol) {
+                    // reports are dropped once the manager is gone
+                    book.apply(command, |report| {
+                        let _ = reports.send((symbol.clone(), report));
+                    });
+                }
+            }
+            ShardMessage::Query(symbol, query) => {
+                if let Some(book) = books.get(&symbol) {
+                    query(book);
+                }
+            }
+        }
+    }
+    books
+}
+
+#[cfg(test)]
+mod tests_sharded {
+    use std::sync::Arc;
+
+    use crate::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_books_are_spread_across_shards() {
+        assert_send_sync::<ShardedBookManager>();
+        let manager = Arc::new(ShardedBookManager::new(2));
+        let symbols = ["AAA", "BBB", "CCC", "DDD"].map(Symbol::from);
+        for symbol in &symbols {
+            manager
+                .add_book(symbol.clone(), OrderBook::default())
+                .unwrap();
+        }
+        assert_eq!(
+            manager.add_book(symbols[0].clone(), OrderBook::default()),
+            Err(ManagerError::SymbolExists(symbols[0].clone()))
+        );
+        assert_eq!(manager.shard_of(&symbols[0]), Some(0));
+        assert_eq!(manager.shard_of(&symbols[1]), Some(1));
+
+        // gateway threads send to all books concurrently
+        std::thread::scope(|scope| {
+            for (index, symbol) in symbols.iter().enumerate() {
+                let manager = manager.clone();
+                scope.spawn(move || {
+                    let id = index as u64 * 10;
+                    let orders = [
+                        Order::new_limit(
+                            Oid::new(id + 1),
+                            OrderSide::Sell,
+                            Timestamp::new(1),
+                            10.0.into(),
+                            5.into(),
+                        ),
+                        Order::new_market(
+                            Oid::new(id + 2),
+                            OrderSide::Buy,
+                            Timestamp::new(2),
+                            2.into(),
+                        ),
+                    ];
+                    for order in orders {
+                        manager.send(symbol, Command::Execute(order)).unwrap();
+                    }
+                });
+            }
+        });
+
+        for symbol in &symbols {
+            let volume = manager
+                .query(symbol, |book| book.get_best_sell_volume())
+                .unwrap();
+            assert_eq!(volume, Some(3.into()));
+        }
+        let reports = manager.drain_reports();
+        // accept and fill of both orders on each book
+        assert_eq!(reports.len(), 16);
+        let first = reports
+            .iter()
+            .find(|(symbol, _)| *symbol == symbols[2])
+            .unwrap();
+        assert!(matches!(first.1, ExecutionReport::Accepted { .. }));
+
+        assert_eq!(
+            manager.send(&Symbol::from("EEE"), Command::Cancel(Oid::new(1))),
+            Err(ManagerError::UnknownSymbol(Symbol::from("EEE")))
+        );
+        let removed = manager.remove_book(&symbols[3]).unwrap();
+        assert_eq!(removed.order_count(), 1);
+
+        let manager = Arc::into_inner(manager).unwrap();
+        let books = manager.shutdown();
+        assert_eq!(books.len(), 3);
+    }
+}
@@ -0,0 +1,534 @@
+//!
+//! Snapshot of the book state. Resting orders are stored in price-time priority,
+//! so the book rebuilt from the snapshot matches orders in the same sequence.
+//!
+//! Binary snapshot layout, all integers are little endian:
+//! magic `LOB1`, version u16, volume decimals u8, trading state u8, then bid and ask sides.
+//! Each side is a u32 level count followed by the levels, each level is its price i64,
+//! u32 order count and the orders in time priority.
+//! Version 2 added the optional owner of the order, version 3 the optional minimum quantity,
+//! version 4 the volume decimals of the build that wrote it, a snapshot written with other
+//! decimals is rejected. Snapshots of older versions can still be restored and are read
+//! as written with the volume decimals of this build.
+//!
+
+use alloc::vec::Vec;
+
+use hashbrown::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::utils::VOLUME_DECIMALS;
+use crate::{
+    LevelStore, LimitOrder, Oid, OrderBook, OrderSide, OwnerId, PostOnly, Price, TimeInForce,
+    Timestamp, TradingState, Volume,
+};
+
+const SNAPSHOT_MAGIC: &[u8; 4] = b"LOB1";
+/// version of the binary snapshot format written by this version of the crate
+pub const SNAPSHOT_VERSION: u16 = 4;
+
+/// Snapshot decoding error
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum SnapshotError {
+    #[error("Snapshot is truncated")]
+    UnexpectedEnd,
+    #[error("Data is not an order book snapshot")]
+    InvalidMagic,
+    #[error("Snapshot version {0} is not supported")]
+    UnsupportedVersion(u16),
+    #[error("Snapshot contains invalid {0}")]
+    InvalidValue(&'static str),
+    #[error("Snapshot volumes have {found} decimal places, expected {expected}")]
+    VolumeDecimals { expected: u8, found: u8 },
+}
+
+/// Resting orders and trading state of the book
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BookSnapshot {
+    pub state: TradingState,
+    /// bid orders from the best price, in time priority within a level
+    pub bids: Vec<LimitOrder>,
+    /// ask orders from the best price, in time priority within a level
+    pub asks: Vec<LimitOrder>,
+}
+
+impl OrderBook {
+    /// rebuild the book from the snapshot
+    pub fn from_book_snapshot(snapshot: BookSnapshot) -> Self {
+        OrderBook::empty().with_book_snapshot(snapshot)
+    }
+
+    /// rebuild the book from the binary snapshot, snapshot is validated before it is restored
+    pub fn restore(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        BookSnapshot::decode(bytes).map(OrderBook::from_book_snapshot)
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// snapshot of the resting orders and trading state
+    /// price bands and instrument spec are configuration and are not part of the snapshot
+    pub fn book_snapshot(&self) -> BookSnapshot {
+        BookSnapshot {
+            state: self.state,
+            bids: self.snapshot_orders(OrderSide::Buy),
+            asks: self.snapshot_orders(OrderSide::Sell),
+        }
+    }
+
+    fn snapshot_orders(&self, side: OrderSide) -> Vec<LimitOrder> {
+        self.iter_orders(side)
+            .map(|order| LimitOrder {
+                // position in the queue is given by the order in the snapshot
+                queue_slot: None,
+                ..order.clone()
+            })
+            .collect()
+    }
+
+    /// put the resting orders and trading state of the snapshot on the book
+    /// orders are restored as they were, without validation and matching,
+    /// snapshot from an untrusted source is checked with `BookSnapshot::validate` first
+    pub fn with_book_snapshot(mut self, snapshot: BookSnapshot) -> Self {
+        self.state = snapshot.state;
+        for order in snapshot.bids.into_iter().chain(snapshot.asks) {
+            self.restore_order(order);
+        }
+        self.update_top_of_book();
+        self
+    }
+
+    /// put the order on the book as it is, visible and hidden volume are kept
+    /// order that would overflow the volume of its level is dropped
+    pub(crate) fn restore_order(&mut self, order: LimitOrder) {
+        let (id, side, timestamp) = (order.id, order.side, order.timestamp);
+        let handle = self.orders.insert(order);
+        if let Some(order) = self.orders.get_by_handle_mut(handle) {
+            let added = match side {
+                OrderSide::Buy => self.bids.add_order(order, handle),
+                OrderSide::Sell => self.asks.add_order(order, handle),
+            };
+            match added {
+                Ok(()) => self.note_accept_time(timestamp),
+                Err(_) => {
+                    self.orders.remove(&id);
+                }
+            }
+        }
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// encode the resting orders and trading state using the versioned binary format
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes(SNAPSHOT_MAGIC);
+        writer.u16(SNAPSHOT_VERSION);
+        writer.u8(VOLUME_DECIMALS as u8);
+        writer.u8(match self.state {
+            TradingState::PreOpen => 0,
+            TradingState::Open => 1,
+            TradingState::Halted => 2,
+            TradingState::Closed => 3,
+            TradingState::Suspended => 4,
+        });
+        for side in [OrderSide::Buy, OrderSide::Sell] {
+            let limits = self.limits(side);
+            writer.u32(limits.level_count() as u32);
+            for level in limits.iter_levels(side) {
+                writer.i64(level.price.mantissa());
+                writer.u32(level.orders.len() as u32);
+                for handle in level.orders.iter() {
+                    if let Some(order) = self.orders.get_by_handle(handle) {
+                        writer.order(order);
+                    }
+                }
+            }
+        }
+        writer.buffer
+    }
+}
+
+impl BookSnapshot {
+    /// decode the binary snapshot
+    pub fn decode(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = Reader { bytes, version: 0 };
+        if reader.take(4)? != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::InvalidMagic);
+        }
+        reader.version = reader.u16()?;
+        if !(1..=SNAPSHOT_VERSION).contains(&reader.version) {
+            return Err(SnapshotError::UnsupportedVersion(reader.version));
+        }
+        if reader.version >= 4 {
+            let (expected, found) = (VOLUME_DECIMALS as u8, reader.u8()?);
+            if found != expected {
+                return Err(SnapshotError::VolumeDecimals { expected, found });
+            }
+        }
+        let state = match reader.u8()? {
+            0 => TradingState::PreOpen,
+            1 => TradingState::Open,
+            2 => TradingState::Halted,
+            3 => TradingState::Closed,
+            4 => TradingState::Suspended,
+            _ => return Err(SnapshotError::InvalidValue("trading state")),
+        };
+        let mut snapshot = BookSnapshot {
+            state,
+            ..BookSnapshot::default()
+        };
+        for side in [OrderSide::Buy, OrderSide::Sell] {
+            for _ in 0..reader.u32()? {
+                let price = Price::from_mantissa(reader.i64()?);
+                for _ in 0..reader.u32()? {
+                    let order = reader.order(side, price)?;
+                    match side {
+                        OrderSide::Buy => snapshot.bids.push(order),
+                        OrderSide::Sell => snapshot.asks.push(order),
+                    }
+                }
+            }
+        }
+        if !reader.bytes.is_empty() {
+            return Err(SnapshotError::InvalidValue("trailing data"));
+        }
+        snapshot.validate()?;
+        Ok(snapshot)
+    }
+
+    /// check that every order can be restored, order ids are unique
+    /// and the volume of each level does not overflow
+    pub fn validate(&self) -> Result<(), SnapshotError> {
+        let mut ids = HashSet::new();
+        let mut levels = HashMap::new();
+        for order in self.bids.iter().chain(&self.asks) {
+            if !ids.insert(order.id) {
+                return Err(SnapshotError::InvalidValue("duplicate order id"));
+            }
+            let volume = levels
+                .entry((order.side, order.price))
+                .or_insert(Volume::ZERO);
+            *volume = volume
+                .checked_add(order.visible_volume())
+                .ok_or(SnapshotError::InvalidValue("level volume"))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn i64(&mut self, value: i64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn optional_u64(&mut self, value: Option<u64>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.u64(value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn order(&mut self, order: &LimitOrder) {
+        self.u64(order.id.into());
+        self.u64(order.timestamp.into());
+        self.u64(order.volume.mantissa());
+        self.optional_u64(order.filled_volume.map(|volume| volume.mantissa()));
+        self.u8(match order.time_in_force {
+            TimeInForce::GoodTillCancel => 0,
+            TimeInForce::ImmediateOrCancel => 1,
+        });
+        self.optional_u64(order.expiry.map(u64::from));
+        self.optional_u64(order.display_volume.map(|volume| volume.mantissa()));
+        self.u64(order.hidden_volume.mantissa());
+        match order.post_only {
+            None => self.u8(0),
+            Some(PostOnly::Reject) => self.u8(1),
+            Some(PostOnly::Slide { tick_size }) => {
+                self.u8(2);
+                self.i64(tick_size.mantissa());
+            }
+        }
+        self.optional_u64(order.owner.map(u64::from));
+        self.optional_u64(order.min_qty.map(|volume| volume.mantissa()));
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    // version of the snapshot being read
+    version: u16,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], SnapshotError> {
+        if self.bytes.len() < len {
+            return Err(SnapshotError::UnexpectedEnd);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, SnapshotError> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    fn optional_u64(&mut self) -> Result<Option<u64>, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => self.u64().map(Some),
+            _ => Err(SnapshotError::InvalidValue("optional flag")),
+        }
+    }
+
+    fn order(&mut self, side: OrderSide, price: Price) -> Result<LimitOrder, SnapshotError> {
+        let mut order = LimitOrder::new(
+            Oid::new(self.u64()?),
+            side,
+            Timestamp::new(self.u64()?),
+            price,
+            Volume::from_mantissa(self.u64()?),
+        );
+        order.filled_volume = self.optional_u64()?.map(Volume::from_mantissa);
+        order.time_in_force = match self.u8()? {
+            0 => TimeInForce::GoodTillCancel,
+            1 => TimeInForce::ImmediateOrCancel,
+            _ => return Err(SnapshotError::InvalidValue("time in force")),
+        };
+        order.expiry = self.optional_u64()?.map(Timestamp::new);
+        order.display_volume = self.optional_u64()?.map(Volume::from_mantissa);
+        order.hidden_volume = Volume::from_mantissa(self.u64()?);
+        order.post_only = match self.u8()? {
+            0 => None,
+            1 => Some(PostOnly::Reject),
+            2 => Some(PostOnly::Slide {
+                tick_size: Price::from_mantissa(self.i64()?),
+            }),
+            _ => return Err(SnapshotError::InvalidValue("post only")),
+        };
+        if self.version >= 2 {
+            order.owner = self.optional_u64()?.map(OwnerId::new);
+        }
+        if self.version >= 3 {
+            order.min_qty = self.optional_u64()?.map(Volume::from_mantissa);
+        }
+        if order.remaining_volume() < order.hidden_volume {
+            return Err(SnapshotError::InvalidValue("order volume"));
+        }
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests_snapshot {
+    use crate::*;
+
+    #[test]
+    fn test_restore_book_snapshot() {
+        let mut order_book = OrderBook::default();
+        let orders = [
+            (1, OrderSide::Buy, 10.0, 5),
+            (2, OrderSide::Buy, 10.0, 6),
+            (3, OrderSide::Buy, 11.0, 7),
+            (4, OrderSide::Sell, 12.0, 8),
+        ];
+        for (id, side, price, volume) in orders {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                volume.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        let snapshot = order_book.book_snapshot();
+        let restored = OrderBook::from_book_snapshot(snapshot.clone());
+
+        assert_eq!(restored.book_snapshot(), snapshot);
+        assert_eq!(restored.l3_snapshot(), order_book.l3_snapshot());
+        assert_eq!(restored.best_bid_ask(), order_book.best_bid_ask());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_book_snapshot() {
+        let order = LimitOrder::new(
+            Oid::new(1),
+            OrderSide::Buy,
+            Timestamp::new(1),
+            10.0.into(),
+            5.into(),
+        );
+        let mut order_book = OrderBook::default();
+        order_book.add_order(order).unwrap();
+
+        let json = serde_json::to_string(&order_book.book_snapshot()).unwrap();
+        let snapshot: BookSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, order_book.book_snapshot());
+    }
+
+    #[test]
+    fn test_binary_snapshot_roundtrip() {
+        let mut order_book = OrderBook::default();
+        let iceberg = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            12.0.into(),
+            100.into(),
+        )
+        .with_display_volume(30.into())
+        .with_expiry(Timestamp::new(99));
+        order_book.execute(&iceberg).unwrap();
+        let post_only = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Buy,
+            Timestamp::new(2),
+            10.0.into(),
+            10.into(),
+        )
+        .with_post_only(PostOnly::Slide {
+            tick_size: 0.01.into(),
+        })
+        .with_owner(OwnerId::new(7))
+        .with_min_qty(5.into());
+        order_book.execute(&post_only).unwrap();
+        let taker = Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(3), 20.into());
+        order_book.execute(&taker).unwrap();
+
+        let bytes = order_book.snapshot();
+        let restored = OrderBook::restore(&bytes).unwrap();
+        assert_eq!(restored.book_snapshot(), order_book.book_snapshot());
+        assert_eq!(restored.best_bid_ask(), order_book.best_bid_ask());
+        assert_eq!(restored.snapshot(), bytes);
+        assert_eq!(restored.open_orders(OwnerId::new(7)).len(), 1);
+
+        assert_eq!(
+            OrderBook::restore(&bytes[..bytes.len() - 1]).err(),
+            Some(SnapshotError::UnexpectedEnd)
+        );
+        assert_eq!(
+            OrderBook::restore(b"nope").err(),
+            Some(SnapshotError::InvalidMagic)
+        );
+
+        // snapshot written with other volume decimals
+        let mut bytes = OrderBook::default().snapshot();
+        bytes[6] += 1;
+        assert_eq!(
+            OrderBook::restore(&bytes).err(),
+            Some(SnapshotError::VolumeDecimals {
+                expected: bytes[6] - 1,
+                found: bytes[6]
+            })
+        );
+
+        // version 1 snapshots have no volume decimals and their orders no owner
+        bytes.remove(6);
+        bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+        assert!(OrderBook::restore(&bytes).unwrap().is_empty());
+        bytes[4..6].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            OrderBook::restore(&bytes).err(),
+            Some(SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn test_invalid_snapshot_is_rejected() {
+        let (first, second) = (0xABCD_0001u64, 0xABCD_0002u64);
+        let mut order_book = OrderBook::default();
+        for (id, price) in [(first, 10.0), (second, 9.0)] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(1),
+                price.into(),
+                5.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        // same order id at two levels
+        let mut bytes = order_book.snapshot();
+        let position = bytes
+            .windows(8)
+            .position(|window| window == second.to_le_bytes())
+            .unwrap();
+        bytes[position..position + 8].copy_from_slice(&first.to_le_bytes());
+        assert_eq!(
+            OrderBook::restore(&bytes).err(),
+            Some(SnapshotError::InvalidValue("duplicate order id"))
+        );
+
+        let order = |id: u64| {
+            LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                10.0.into(),
+                Volume::from_mantissa(u64::MAX / 2 + 1),
+            )
+        };
+        let snapshot = BookSnapshot {
+            asks: vec![order(1), order(2)],
+            ..BookSnapshot::default()
+        };
+        assert_eq!(
+            snapshot.validate(),
+            Err(SnapshotError::InvalidValue("level volume"))
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//!
+//! Lock-free driver of the book for users running it on a pinned core.
+//! Gateway thread pushes commands to a single producer single consumer ring buffer, the driver
+//! applies them to the book and pushes the responses to another ring buffer, without locks
+//! or an async runtime.
+//!
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rtrb::{Consumer, Producer, PushError, RingBuffer};
+
+use crate::{Command, ExecutionReport, OrderBook};
+
+/// Response of the driver
+#[derive(Debug, Clone)]
+pub enum Response {
+    Report(ExecutionReport),
+    /// all reports of the command with the given sequence number were pushed,
+    /// commands are numbered from 1 in the order they were consumed
+    Completed(u64),
+}
+
+/// Applies the commands from the ring buffer to the book
+#[derive(Debug)]
+pub struct SpscDriver {
+    book: OrderBook,
+    commands: Consumer<Command>,
+    responses: Producer<Response>,
+    sequence: u64,
+}
+
+/// create the book driver with its command and response ring buffers of the given capacity
+/// returns the command producer and the response consumer for the gateway thread
+pub fn spsc_driver(
+    book: OrderBook,
+    capacity: usize,
+) -> (Producer<Command>, Consumer<Response>, SpscDriver) {
+    let (command_tx, command_rx) = RingBuffer::new(capacity);
+    let (response_tx, response_rx) = RingBuffer::new(capacity);
+    (
+        command_tx,
+        response_rx,
+        SpscDriver::new(book, command_rx, response_tx),
+    )
+}
+
+impl SpscDriver {
+    pub fn new(
+        book: OrderBook,
+        commands: Consumer<Command>,
+        responses: Producer<Response>,
+    ) -> Self {
+        SpscDriver {
+            book,
+            commands,
+            responses,
+            sequence: 0,
+        }
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub fn into_book(self) -> OrderBook {
+        self.book
+    }
+
+    /// apply all queued commands, returns the number of commands applied
+    /// spins while the response buffer is full, so the gateway must keep consuming responses
+    pub fn poll(&mut self) -> usize {
+        let mut applied = 0;
+        while let Ok(command) = self.commands.pop() {
+            self.sequence += 1;
+            let responses = &mut self.responses;
+            self.book
+                .apply(command, |report| push(responses, Response::Report(report)));
+            push(responses, Response::Completed(self.sequence));
+            applied += 1;
+        }
+        applied
+    }
+
+    /// busy poll the commands until running is cleared, queued commands are applied before returning
+    pub fn run(&mut self, running: &AtomicBool) {
+        while running.load(Ordering::Acquire) {
+            if self.poll() == 0 {
+                std::hint::spin_loop();
+            }
+        }
+        self.poll();
+    }
+}
+
+fn push(responses: &mut Producer<Response>, mut response: Response) {
+    while let Err(PushError::Full(rejected)) = responses.push(response) {
+        response = rejected;
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests_spsc {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn test_spsc_driver() {
+        let (mut commands, mut responses, mut driver) = spsc_driver(OrderBook::default(), 2);
+        let running = AtomicBool::new(true);
+        std::thread::scope(|scope| {
+            let handle = scope.spawn(|| {
+                driver.run(&running);
+                driver.into_book()
+            });
+
+            for id in 1..=3 {
+                let mut command = Command::Execute(Order::new_limit(
+                    Oid::new(id),
+                    OrderSide::Sell,
+                    Timestamp::new(id),
+                    10.0.into(),
+                    1.into(),
+                ));
+                while let Err(PushError::Full(rejected)) = commands.push(command) {
+                    command = rejected;
+                    // drain responses so the driver never blocks on a full buffer
+                    while responses.pop().is_ok() {}
+                }
+            }
+            let mut command = Command::Execute(Order::new_market(
+                Oid::new(4),
+                OrderSide::Buy,
+                Timestamp::new(4),
+                2.into(),
+            ));
+            while let Err(PushError::Full(rejected)) = commands.push(command) {
+                command = rejected;
+                while responses.pop().is_ok() {}
+            }
+
+            let mut filled = Volume::ZERO;
+            loop {
+                match responses.pop() {
+                    Ok(Response::Report(
+                        ExecutionReport::PartialFill {
+                            order_id, volume, ..
+                        }
+                        | ExecutionReport::Filled {
+                            order_id, volume, ..
+                        },
+                    )) if order_id == Oid::new(4) => filled += volume,
+                    Ok(Response::Completed(4)) => break,
+                    _ => std::hint::spin_loop(),
+                }
+            }
+            assert_eq!(filled, 2.into());
+
+            running.store(false, Ordering::Release);
+            let book = handle.join().unwrap();
+            assert_eq!(book.order_count(), 1);
+        });
+    }
+}
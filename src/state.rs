@@ -0,0 +1,252 @@
+//!
+//! Trading state machine of the book.
+//!
+//! Book moves from closed to pre-open (call auction), then to open (continuous trading).
+//! Open or pre-open book can be halted and resumed, and book in any state can be closed.
+//! Transitions purge expired orders, transition from pre-open to open uncrosses the book.
+//! Kill switch cancels all resting orders and suspends the book until it is explicitly resumed.
+//!
+
+use alloc::vec::Vec;
+
+use crate::{
+    AuctionResult, BookEvent, CancellationReport, CancellationStatus, LevelStore, OrderBook,
+    OrderBookError, Timestamp,
+};
+
+/// Trading state of the book
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TradingState {
+    /// Call auction, orders are accepted but not matched until the book is uncrossed
+    PreOpen,
+    /// Continuous trading, orders are matched on arrival
+    #[default]
+    Open,
+    /// Matching is suspended, aggressive orders are rejected, cancels are allowed
+    Halted,
+    /// No new orders are accepted, cancels are allowed
+    Closed,
+    /// Kill switch was triggered, only cancels are accepted until the book is resumed
+    Suspended,
+}
+
+/// Actions caused by the state transition
+#[derive(Debug, Clone)]
+pub struct StateTransition {
+    pub from: TradingState,
+    pub to: TradingState,
+    /// orders that expired at the time of the transition
+    pub expired: Vec<CancellationReport>,
+    /// result of the auction when the book opens after pre-open
+    pub auction: Option<AuctionResult>,
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    pub fn state(&self) -> TradingState {
+        self.state
+    }
+
+    /// move the book to the new trading state
+    /// now is used to remove orders that expired before the transition
+    pub fn transition(
+        &mut self,
+        to: TradingState,
+        now: Timestamp,
+    ) -> Result<StateTransition, OrderBookError> {
+        self.logged(
+            || BookEvent::Transition { to, now },
+            |book| book.change_state(to, now),
+        )
+    }
+
+    fn change_state(
+        &mut self,
+        to: TradingState,
+        now: Timestamp,
+    ) -> Result<StateTransition, OrderBookError> {
+        let from = self.state;
+        let allowed = match (from, to) {
+            (TradingState::Closed, TradingState::PreOpen) => true,
+            (TradingState::Open, TradingState::PreOpen) => true,
+            (TradingState::Halted, TradingState::PreOpen) => true,
+            (TradingState::PreOpen, TradingState::Open) => true,
+            (TradingState::Halted, TradingState::Open) => true,
+            (TradingState::Suspended, TradingState::PreOpen) => true,
+            (TradingState::Suspended, TradingState::Open) => true,
+            (TradingState::PreOpen, TradingState::Halted) => true,
+            (TradingState::Open, TradingState::Halted) => true,
+            (from, TradingState::Closed) => from != TradingState::Closed,
+            _ => false,
+        };
+        if !allowed {
+            return Err(OrderBookError::InvalidStateTransition { from, to });
+        }
+
+        let expired = self.purge_expired(now);
+
+        let auction = if from == TradingState::PreOpen && to == TradingState::Open {
+            match self.uncross() {
+                Ok(result) => Some(result),
+                Err(OrderBookError::NoOrderToMatch) => None,
+                Err(e) => return Err(e),
+            }
+        } else {
+            None
+        };
+        // auction and closing prices become the reference price
+        if let Some(auction) = &auction {
+            self.set_reference_price(Some(auction.price));
+        } else if to == TradingState::Closed && self.last_trade.is_some() {
+            self.set_reference_price(self.last_trade_price());
+        }
+
+        self.state = to;
+        Ok(StateTransition {
+            from,
+            to,
+            expired,
+            auction,
+        })
+    }
+
+    /// suspend matching
+    pub fn halt(&mut self, now: Timestamp) -> Result<StateTransition, OrderBookError> {
+        self.transition(TradingState::Halted, now)
+    }
+
+    /// resume continuous trading, book opened after pre-open is uncrossed first
+    pub fn open(&mut self, now: Timestamp) -> Result<StateTransition, OrderBookError> {
+        self.transition(TradingState::Open, now)
+    }
+
+    /// stop accepting new orders
+    pub fn close(&mut self, now: Timestamp) -> Result<StateTransition, OrderBookError> {
+        self.transition(TradingState::Closed, now)
+    }
+
+    /// cancel all resting orders and suspend the book, only cancels are accepted until
+    /// the book is resumed by transition to pre-open or open
+    pub fn kill_switch(&mut self) -> Vec<CancellationReport> {
+        self.logged(
+            || BookEvent::KillSwitch,
+            |book| {
+                let order_ids = book
+                    .orders
+                    .values()
+                    .map(|order| order.id)
+                    .collect::<Vec<_>>();
+                let reports = book.remove_orders(order_ids, CancellationStatus::Cancelled);
+                book.state = TradingState::Suspended;
+                reports
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_state {
+    use crate::*;
+
+    #[test]
+    fn test_halted_book_rejects_aggressive_orders() {
+        let mut order_book = OrderBook::default();
+        let order = Order::new_limit(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            21.0.into(),
+            100.into(),
+        );
+        order_book.execute(&order).unwrap();
+        let order = Order::new_limit(
+            Oid::new(2),
+            OrderSide::Sell,
+            Timestamp::new(2),
+            22.0.into(),
+            100.into(),
+        )
+        .with_expiry(Timestamp::new(5));
+        order_book.execute(&order).unwrap();
+
+        let transition = order_book.halt(Timestamp::new(10)).unwrap();
+        assert_eq!(transition.from, TradingState::Open);
+        assert_eq!(transition.to, TradingState::Halted);
+        assert_eq!(transition.expired.len(), 1);
+
+        let order = Order::new_market(Oid::new(3), OrderSide::Buy, Timestamp::new(11), 10.into());
+        assert_eq!(
+            order_book.execute(&order).unwrap_err(),
+            OrderBookError::InvalidState(TradingState::Halted)
+        );
+        let order = Order::new_limit(
+            Oid::new(4),
+            OrderSide::Buy,
+            Timestamp::new(12),
+            21.0.into(),
+            10.into(),
+        );
+        assert!(order_book.execute(&order).is_err());
+
+        // passive orders and cancels are accepted
+        let order = Order::new_limit(
+            Oid::new(5),
+            OrderSide::Buy,
+            Timestamp::new(13),
+            20.0.into(),
+            10.into(),
+        );
+        order_book.execute(&order).unwrap();
+        order_book.cancel_order(Oid::new(5)).unwrap();
+
+        assert_eq!(
+            order_book.close(Timestamp::new(20)).unwrap().to,
+            TradingState::Closed
+        );
+        assert_eq!(
+            order_book.open(Timestamp::new(30)).unwrap_err(),
+            OrderBookError::InvalidStateTransition {
+                from: TradingState::Closed,
+                to: TradingState::Open
+            }
+        );
+    }
+
+    #[test]
+    fn test_kill_switch_suspends_the_book() {
+        let mut order_book = OrderBook::default();
+        for (id, side, price) in [(1, OrderSide::Buy, 10.0), (2, OrderSide::Sell, 11.0)] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+
+        let reports = order_book.kill_switch();
+        assert_eq!(reports.len(), 2);
+        assert!(order_book.is_empty());
+        assert_eq!(order_book.best_bid_ask(), Bbo::default());
+        assert_eq!(order_book.state(), TradingState::Suspended);
+
+        let order = Order::new_limit(
+            Oid::new(3),
+            OrderSide::Buy,
+            Timestamp::new(3),
+            10.0.into(),
+            5.into(),
+        );
+        assert_eq!(
+            order_book.execute(&order).unwrap_err(),
+            OrderBookError::InvalidState(TradingState::Suspended)
+        );
+        assert!(order_book.halt(Timestamp::new(4)).is_err());
+
+        order_book.open(Timestamp::new(5)).unwrap();
+        order_book.execute(&order).unwrap();
+        assert_eq!(order_book.order_count(), 1);
+    }
+}
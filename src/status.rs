@@ -0,0 +1,171 @@
+//!
+//! Order status. Resting orders are looked up on the book, orders that were filled or cancelled
+//! are remembered for a limited number of orders, so their final state can still be queried.
+//!
+
+use alloc::collections::VecDeque;
+
+use hashbrown::HashMap;
+
+use crate::{LevelStore, LimitOrder, Oid, OrderBook, OrderSide, OwnerId, Price, Volume};
+
+/// number of filled and cancelled orders remembered by default
+pub const DEFAULT_FINISHED_ORDERS_CAPACITY: usize = 10_000;
+
+/// State of the order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderState {
+    /// Order rests on the book, nothing has been filled yet
+    New,
+    /// Order rests on the book and part of it has been filled
+    PartiallyFilled,
+    /// Order has been completely filled
+    Filled,
+    /// Order has been cancelled or expired, it may have been partially filled before
+    Cancelled,
+}
+
+/// Status of the order
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderView {
+    pub id: Oid,
+    pub side: OrderSide,
+    pub price: Price,
+    /// original volume of the order
+    pub volume: Volume,
+    pub filled_volume: Volume,
+    /// volume still open on the book, zero once the order is filled or cancelled
+    pub remaining_volume: Volume,
+    pub state: OrderState,
+    pub owner: Option<OwnerId>,
+}
+
+impl OrderView {
+    /// status of the order resting on the book
+    pub(crate) fn resting(order: &LimitOrder) -> Self {
+        let filled_volume = order.filled_volume.unwrap_or(Volume::ZERO);
+        OrderView {
+            id: order.id,
+            side: order.side,
+            price: order.price,
+            volume: order.volume,
+            filled_volume,
+            remaining_volume: order.remaining_volume(),
+            state: if filled_volume.is_zero() {
+                OrderState::New
+            } else {
+                OrderState::PartiallyFilled
+            },
+            owner: order.owner,
+        }
+    }
+
+    /// status of the order that has been removed from the book
+    pub(crate) fn finished(order: &LimitOrder, state: OrderState) -> Self {
+        OrderView {
+            remaining_volume: Volume::ZERO,
+            state,
+            ..OrderView::resting(order)
+        }
+    }
+}
+
+/// bounded history of orders that are no longer on the book, oldest are forgotten first
+#[derive(Debug, Clone)]
+pub(crate) struct FinishedOrders {
+    views: HashMap<Oid, OrderView>,
+    order: VecDeque<Oid>,
+    capacity: usize,
+}
+
+impl Default for FinishedOrders {
+    fn default() -> Self {
+        FinishedOrders::with_capacity(DEFAULT_FINISHED_ORDERS_CAPACITY)
+    }
+}
+
+impl FinishedOrders {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        FinishedOrders {
+            views: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub(crate) fn record(&mut self, view: OrderView) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.views.remove(&oldest);
+            }
+        }
+        self.order.push_back(view.id);
+        self.views.insert(view.id, view);
+    }
+
+    pub(crate) fn get(&self, id: &Oid) -> Option<&OrderView> {
+        self.views.get(id)
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// set how many filled and cancelled orders are remembered for status queries
+    pub fn with_finished_orders_capacity(mut self, capacity: usize) -> Self {
+        self.finished_orders = FinishedOrders::with_capacity(capacity);
+        self
+    }
+
+    /// status of the order, none if the order is unknown or has been forgotten
+    pub fn get_order(&self, id: Oid) -> Option<OrderView> {
+        self.orders
+            .get(&id)
+            .map(OrderView::resting)
+            .or_else(|| self.finished_orders.get(&id).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests_status {
+    use crate::*;
+
+    #[test]
+    fn test_get_order() {
+        let mut order_book = OrderBook::default().with_finished_orders_capacity(1);
+        for id in 1..=3 {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                10.0.into(),
+                10.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        assert_eq!(
+            order_book.get_order(Oid::new(1)).unwrap().state,
+            OrderState::New
+        );
+
+        let order = Order::new_market(Oid::new(4), OrderSide::Buy, Timestamp::new(4), 15.into());
+        order_book.execute(&order).unwrap();
+        let filled = order_book.get_order(Oid::new(1)).unwrap();
+        assert_eq!(filled.state, OrderState::Filled);
+        assert_eq!(filled.filled_volume, 10.into());
+
+        let partial = order_book.get_order(Oid::new(2)).unwrap();
+        assert_eq!(partial.state, OrderState::PartiallyFilled);
+        assert_eq!(partial.remaining_volume, 5.into());
+
+        order_book.cancel_order(Oid::new(2)).unwrap();
+        let cancelled = order_book.get_order(Oid::new(2)).unwrap();
+        assert_eq!(cancelled.state, OrderState::Cancelled);
+        assert_eq!(cancelled.filled_volume, 5.into());
+
+        // only the last finished order is remembered
+        assert_eq!(order_book.get_order(Oid::new(1)), None);
+        assert_eq!(order_book.get_order(Oid::new(5)), None);
+    }
+}
@@ -0,0 +1,632 @@
+//!
+//! Storage of the active price levels of one side of the book.
+//! Levels themselves are kept in a stable vec by the limits, the store maps the price of each
+//! level with volume to its index and keeps them ordered by price, so the best level and the
+//! next level can be found. The book is generic over the store, so the trade-off between
+//! the lookup by price and the memory can be picked for the instrument,
+//! i.e. sparse books of equities vs dense books of futures.
+//!
+
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::ops::Bound;
+
+use itertools::Either;
+
+use crate::primitives::LevelMap;
+use crate::{LevelIndex, OrderBook, Price, PriceLike};
+
+/// Index of the active levels of one side of the book by price
+pub trait LevelStore<P = Price>: Debug + Clone + Default {
+    /// index of the level at the price
+    fn get(&self, price: &P) -> Option<LevelIndex>;
+
+    /// add the level at the price, level already stored at the price is replaced
+    fn insert(&mut self, price: P, index: LevelIndex);
+
+    fn remove(&mut self, price: &P) -> Option<LevelIndex>;
+
+    /// number of stored levels
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// level with the lowest price
+    fn first(&self) -> Option<(P, LevelIndex)>;
+
+    /// level with the highest price
+    fn last(&self) -> Option<(P, LevelIndex)>;
+
+    /// level with the highest price below the price
+    fn below(&self, price: P) -> Option<(P, LevelIndex)>;
+
+    /// level with the lowest price above the price
+    fn above(&self, price: P) -> Option<(P, LevelIndex)>;
+
+    /// levels in ascending price order
+    fn iter(&self) -> impl DoubleEndedIterator<Item = (P, LevelIndex)> + '_;
+
+    /// make room for the number of additional levels
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// release unused memory
+    fn shrink_to_fit(&mut self) {}
+
+    /// prices whose levels are indexed inconsistently by the store, checked by the integrity check
+    fn inconsistent_prices(&self) -> Vec<P> {
+        Vec::new()
+    }
+}
+
+/// Levels looked up by price in a hash map and ordered in a b-tree,
+/// lookup by price is O(1) at the cost of keeping both indices, default store of the book
+#[derive(Debug, Clone, Default)]
+pub struct HashedLevels<P = Price> {
+    level_map: LevelMap<P>,
+    sorted_levels: BTreeMap<P, LevelIndex>,
+}
+
+impl<P: PriceLike> LevelStore<P> for HashedLevels<P> {
+    fn get(&self, price: &P) -> Option<LevelIndex> {
+        self.level_map.get(price).copied()
+    }
+
+    fn insert(&mut self, price: P, index: LevelIndex) {
+        self.level_map.insert(price, index);
+        self.sorted_levels.insert(price, index);
+    }
+
+    fn remove(&mut self, price: &P) -> Option<LevelIndex> {
+        self.sorted_levels.remove(price);
+        self.level_map.remove(price)
+    }
+
+    fn len(&self) -> usize {
+        self.sorted_levels.len()
+    }
+
+    fn first(&self) -> Option<(P, LevelIndex)> {
+        first(&self.sorted_levels)
+    }
+
+    fn last(&self) -> Option<(P, LevelIndex)> {
+        last(&self.sorted_levels)
+    }
+
+    fn below(&self, price: P) -> Option<(P, LevelIndex)> {
+        below(&self.sorted_levels, price)
+    }
+
+    fn above(&self, price: P) -> Option<(P, LevelIndex)> {
+        above(&self.sorted_levels, price)
+    }
+
+    fn iter(&self) -> impl DoubleEndedIterator<Item = (P, LevelIndex)> + '_ {
+        self.sorted_levels
+            .iter()
+            .map(|(price, index)| (*price, *index))
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.level_map.reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.level_map.shrink_to_fit();
+    }
+
+    fn inconsistent_prices(&self) -> Vec<P> {
+        let mut prices = self
+            .level_map
+            .iter()
+            .filter(|(price, index)| self.sorted_levels.get(price) != Some(index))
+            .map(|(price, _)| *price)
+            .collect::<Vec<_>>();
+        prices.extend(
+            self.sorted_levels
+                .keys()
+                .filter(|price| !self.level_map.contains_key(*price)),
+        );
+        prices
+    }
+}
+
+/// Levels kept only in a b-tree, lookup by price is O(log n) with half of the memory
+/// of the hashed levels, suits sparse books with few levels
+#[derive(Debug, Clone, Default)]
+pub struct SortedLevels<P = Price> {
+    levels: BTreeMap<P, LevelIndex>,
+}
+
+impl<P: PriceLike> LevelStore<P> for SortedLevels<P> {
+    fn get(&self, price: &P) -> Option<LevelIndex> {
+        self.levels.get(price).copied()
+    }
+
+    fn insert(&mut self, price: P, index: LevelIndex) {
+        self.levels.insert(price, index);
+    }
+
+    fn remove(&mut self, price: &P) -> Option<LevelIndex> {
+        self.levels.remove(price)
+    }
+
+    fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    fn first(&self) -> Option<(P, LevelIndex)> {
+        first(&self.levels)
+    }
+
+    fn last(&self) -> Option<(P, LevelIndex)> {
+        last(&self.levels)
+    }
+
+    fn below(&self, price: P) -> Option<(P, LevelIndex)> {
+        below(&self.levels, price)
+    }
+
+    fn above(&self, price: P) -> Option<(P, LevelIndex)> {
+        above(&self.levels, price)
+    }
+
+    fn iter(&self) -> impl DoubleEndedIterator<Item = (P, LevelIndex)> + '_ {
+        self.levels.iter().map(|(price, index)| (*price, *index))
+    }
+}
+
+/// Levels of an instrument with a known price range and tick kept in a flat vec indexed by
+/// the tick offset from the lowest price, with a bitset of the slots holding a level.
+/// Insert, remove and lookup by price are O(1) and the next level is found by bit scans.
+/// Prices outside of the range or off the tick are kept in a b-tree, so the book stays correct
+/// when the price leaves the range, default store has no range and keeps every level there
+#[derive(Debug, Clone, Default)]
+pub struct DenseLevels {
+    // mantissa of the lowest price of the range and of the tick
+    low: i64,
+    tick: i64,
+    slots: Vec<LevelIndex>,
+    // bit of each slot holding a level
+    occupied: Vec<u64>,
+    dense_len: usize,
+    outside: BTreeMap<Price, LevelIndex>,
+}
+
+impl DenseLevels {
+    /// slot for each tick from the low to the high price, both included
+    pub fn new(low: Price, high: Price, tick_size: Price) -> Self {
+        let tick = tick_size.mantissa();
+        let slot_count = if tick > 0 && high >= low {
+            ((high.mantissa() - low.mantissa()) / tick + 1) as usize
+        } else {
+            0
+        };
+        DenseLevels {
+            low: low.mantissa(),
+            tick,
+            slots: vec![LevelIndex(0); slot_count],
+            occupied: vec![0; slot_count.div_ceil(64)],
+            dense_len: 0,
+            outside: BTreeMap::new(),
+        }
+    }
+
+    /// number of ticks covered by the range
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn slot(&self, price: &Price) -> Option<usize> {
+        let offset = price.mantissa().checked_sub(self.low)?;
+        if offset < 0 || self.tick <= 0 || offset % self.tick != 0 {
+            return None;
+        }
+        let slot = (offset / self.tick) as usize;
+        (slot < self.slots.len()).then_some(slot)
+    }
+
+    fn price(&self, slot: usize) -> Price {
+        Price::from_mantissa(self.low + slot as i64 * self.tick)
+    }
+
+    fn is_occupied(&self, slot: usize) -> bool {
+        self.occupied[slot / 64] & (1 << (slot % 64)) != 0
+    }
+
+    fn level(&self, slot: usize) -> (Price, LevelIndex) {
+        (self.price(slot), self.slots[slot])
+    }
+
+    /// highest occupied slot up to the slot
+    fn scan_down(&self, slot: usize) -> Option<usize> {
+        let mut word = slot / 64;
+        let mut bits = self.occupied[word] & (u64::MAX >> (63 - slot % 64));
+        loop {
+            if bits != 0 {
+                return Some(word * 64 + 63 - bits.leading_zeros() as usize);
+            }
+            word = word.checked_sub(1)?;
+            bits = self.occupied[word];
+        }
+    }
+
+    /// lowest occupied slot from the slot
+    fn scan_up(&self, slot: usize) -> Option<usize> {
+        let mut word = slot / 64;
+        let mut bits = *self.occupied.get(word)? & (u64::MAX << (slot % 64));
+        loop {
+            if bits != 0 {
+                return Some(word * 64 + bits.trailing_zeros() as usize);
+            }
+            word += 1;
+            bits = *self.occupied.get(word)?;
+        }
+    }
+
+    fn dense_levels(&self) -> impl DoubleEndedIterator<Item = (Price, LevelIndex)> + '_ {
+        self.occupied
+            .iter()
+            .enumerate()
+            .flat_map(|(word, bits)| SetBits {
+                bits: *bits,
+                base: word * 64,
+            })
+            .map(|slot| self.level(slot))
+    }
+}
+
+impl LevelStore for DenseLevels {
+    fn get(&self, price: &Price) -> Option<LevelIndex> {
+        match self.slot(price) {
+            Some(slot) => self.is_occupied(slot).then(|| self.slots[slot]),
+            None => self.outside.get(price).copied(),
+        }
+    }
+
+    fn insert(&mut self, price: Price, index: LevelIndex) {
+        let Some(slot) = self.slot(&price) else {
+            self.outside.insert(price, index);
+            return;
+        };
+        if !self.is_occupied(slot) {
+            self.occupied[slot / 64] |= 1 << (slot % 64);
+            self.dense_len += 1;
+        }
+        self.slots[slot] = index;
+    }
+
+    fn remove(&mut self, price: &Price) -> Option<LevelIndex> {
+        let Some(slot) = self.slot(price) else {
+            return self.outside.remove(price);
+        };
+        if !self.is_occupied(slot) {
+            return None;
+        }
+        self.occupied[slot / 64] &= !(1 << (slot % 64));
+        self.dense_len -= 1;
+        Some(self.slots[slot])
+    }
+
+    fn len(&self) -> usize {
+        self.dense_len + self.outside.len()
+    }
+
+    fn first(&self) -> Option<(Price, LevelIndex)> {
+        let dense = self.scan_up(0).map(|slot| self.level(slot));
+        lower(dense, first(&self.outside))
+    }
+
+    fn last(&self) -> Option<(Price, LevelIndex)> {
+        let dense = self
+            .slots
+            .len()
+            .checked_sub(1)
+            .and_then(|slot| self.scan_down(slot))
+            .map(|slot| self.level(slot));
+        higher(dense, last(&self.outside))
+    }
+
+    fn below(&self, price: Price) -> Option<(Price, LevelIndex)> {
+        // highest slot with a price below the price
+        let dense = match price.mantissa().saturating_sub(self.low) {
+            offset if offset <= 0 || self.tick <= 0 || self.slots.is_empty() => None,
+            offset => {
+                let slot = ((offset - 1) / self.tick) as usize;
+                self.scan_down(slot.min(self.slots.len() - 1))
+            }
+        };
+        let dense = dense.map(|slot| self.level(slot));
+        higher(dense, below(&self.outside, price))
+    }
+
+    fn above(&self, price: Price) -> Option<(Price, LevelIndex)> {
+        // lowest slot with a price above the price
+        let dense = match price.mantissa().saturating_sub(self.low) {
+            _ if self.tick <= 0 => None,
+            offset if offset < 0 => self.scan_up(0),
+            offset => self.scan_up((offset / self.tick + 1) as usize),
+        };
+        let dense = dense.map(|slot| self.level(slot));
+        lower(dense, above(&self.outside, price))
+    }
+
+    fn iter(&self) -> impl DoubleEndedIterator<Item = (Price, LevelIndex)> + '_ {
+        if self.outside.is_empty() {
+            return Either::Left(self.dense_levels());
+        }
+        let mut levels = self.dense_levels().collect::<Vec<_>>();
+        levels.extend(self.outside.iter().map(|(price, index)| (*price, *index)));
+        levels.sort_unstable_by_key(|(price, _)| *price);
+        Either::Right(levels.into_iter())
+    }
+}
+
+/// indices of the set bits of a word, from the lowest
+struct SetBits {
+    bits: u64,
+    base: usize,
+}
+
+impl Iterator for SetBits {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.bits == 0 {
+            return None;
+        }
+        let bit = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Some(self.base + bit)
+    }
+}
+
+impl DoubleEndedIterator for SetBits {
+    fn next_back(&mut self) -> Option<usize> {
+        if self.bits == 0 {
+            return None;
+        }
+        let bit = 63 - self.bits.leading_zeros() as usize;
+        self.bits &= !(1 << bit);
+        Some(self.base + bit)
+    }
+}
+
+fn lower<P: PriceLike>(
+    level: Option<(P, LevelIndex)>,
+    other: Option<(P, LevelIndex)>,
+) -> Option<(P, LevelIndex)> {
+    match (level, other) {
+        (Some(level), Some(other)) => Some(if other.0 < level.0 { other } else { level }),
+        (level, other) => level.or(other),
+    }
+}
+
+fn higher<P: PriceLike>(
+    level: Option<(P, LevelIndex)>,
+    other: Option<(P, LevelIndex)>,
+) -> Option<(P, LevelIndex)> {
+    match (level, other) {
+        (Some(level), Some(other)) => Some(if other.0 > level.0 { other } else { level }),
+        (level, other) => level.or(other),
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// store the levels of both sides in the given store, i.e. the dense levels with
+    /// the price range of the instrument, levels already on the book are moved to it
+    pub fn with_level_store(mut self, store: S) -> Self {
+        for limits in [&mut self.bids, &mut self.asks] {
+            let mut levels = store.clone();
+            for (price, index) in limits.store.iter() {
+                levels.insert(price, index);
+            }
+            limits.store = levels;
+        }
+        self
+    }
+}
+
+fn first<P: PriceLike>(levels: &BTreeMap<P, LevelIndex>) -> Option<(P, LevelIndex)> {
+    levels
+        .first_key_value()
+        .map(|(price, index)| (*price, *index))
+}
+
+fn last<P: PriceLike>(levels: &BTreeMap<P, LevelIndex>) -> Option<(P, LevelIndex)> {
+    levels
+        .last_key_value()
+        .map(|(price, index)| (*price, *index))
+}
+
+fn below<P: PriceLike>(levels: &BTreeMap<P, LevelIndex>, price: P) -> Option<(P, LevelIndex)> {
+    levels
+        .range(..price)
+        .next_back()
+        .map(|(price, index)| (*price, *index))
+}
+
+fn above<P: PriceLike>(levels: &BTreeMap<P, LevelIndex>, price: P) -> Option<(P, LevelIndex)> {
+    levels
+        .range((Bound::Excluded(price), Bound::Unbounded))
+        .next()
+        .map(|(price, index)| (*price, *index))
+}
+
+#[cfg(test)]
+mod tests_store {
+    use crate::*;
+
+    fn check_store<S: LevelStore<i64>>() {
+        let mut store = S::default();
+        assert!(store.is_empty());
+        for (price, index) in [(105, 0), (101, 1), (110, 2)] {
+            store.insert(price, LevelIndex(index));
+        }
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(&101), Some(LevelIndex(1)));
+        assert_eq!(store.first(), Some((101, LevelIndex(1))));
+        assert_eq!(store.last(), Some((110, LevelIndex(2))));
+        assert_eq!(store.below(105), Some((101, LevelIndex(1))));
+        assert_eq!(store.above(105), Some((110, LevelIndex(2))));
+        assert_eq!(store.above(110), None);
+        assert_eq!(
+            store
+                .iter()
+                .rev()
+                .map(|(price, _)| price)
+                .collect::<Vec<_>>(),
+            vec![110, 105, 101]
+        );
+        assert_eq!(store.remove(&105), Some(LevelIndex(0)));
+        assert_eq!(store.get(&105), None);
+        assert_eq!(store.below(110), Some((101, LevelIndex(1))));
+        assert!(store.inconsistent_prices().is_empty());
+    }
+
+    #[test]
+    fn test_level_stores() {
+        check_store::<HashedLevels<i64>>();
+        check_store::<SortedLevels<i64>>();
+    }
+
+    #[test]
+    fn test_dense_levels() {
+        let price = |price: f64| Price::new(price);
+        let mut store = DenseLevels::new(price(9.0), price(11.0), price(0.01));
+        assert_eq!(store.capacity(), 201);
+        // two words of the bitset apart, outside of the range and off the tick
+        for (index, value) in [(0, 9.5), (1, 10.5), (2, 12.0), (3, 8.0), (4, 10.005)] {
+            store.insert(price(value), LevelIndex(index));
+        }
+        assert_eq!(store.len(), 5);
+        assert_eq!(store.get(&price(10.5)), Some(LevelIndex(1)));
+        assert_eq!(store.get(&price(10.4)), None);
+        assert_eq!(store.get(&price(10.005)), Some(LevelIndex(4)));
+        assert_eq!(store.first(), Some((price(8.0), LevelIndex(3))));
+        assert_eq!(store.last(), Some((price(12.0), LevelIndex(2))));
+        assert_eq!(
+            store.below(price(10.5)),
+            Some((price(10.005), LevelIndex(4)))
+        );
+        assert_eq!(store.below(price(10.0)), Some((price(9.5), LevelIndex(0))));
+        assert_eq!(store.below(price(9.5)), Some((price(8.0), LevelIndex(3))));
+        assert_eq!(store.below(price(20.0)), Some((price(12.0), LevelIndex(2))));
+        assert_eq!(store.above(price(5.0)), Some((price(8.0), LevelIndex(3))));
+        assert_eq!(
+            store.above(price(9.5)),
+            Some((price(10.005), LevelIndex(4)))
+        );
+        assert_eq!(store.above(price(10.5)), Some((price(12.0), LevelIndex(2))));
+        assert_eq!(
+            store.iter().map(|(price, _)| price).collect::<Vec<_>>(),
+            [8.0, 9.5, 10.005, 10.5, 12.0].map(price)
+        );
+
+        for value in [12.0, 8.0, 10.005] {
+            store.remove(&price(value));
+        }
+        assert_eq!(store.remove(&price(10.4)), None);
+        assert_eq!(store.len(), 2);
+        assert_eq!(
+            store
+                .iter()
+                .rev()
+                .map(|(price, _)| price)
+                .collect::<Vec<_>>(),
+            [10.5, 9.5].map(price)
+        );
+        assert_eq!(store.above(price(10.5)), None);
+        assert_eq!(store.below(price(9.5)), None);
+
+        // without a range every level is kept outside
+        check_store_prices::<DenseLevels>();
+    }
+
+    fn check_store_prices<S: LevelStore>() {
+        let mut store = S::default();
+        store.insert(Price::new(10.0), LevelIndex(0));
+        store.insert(Price::new(9.0), LevelIndex(1));
+        assert_eq!(store.first(), Some((Price::new(9.0), LevelIndex(1))));
+        assert_eq!(
+            store.above(Price::new(9.0)),
+            Some((Price::new(10.0), LevelIndex(0)))
+        );
+        assert_eq!(store.remove(&Price::new(9.0)), Some(LevelIndex(1)));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_book_with_dense_levels() {
+        let mut order_book = OrderBook::<DenseLevels>::empty();
+        order_book
+            .execute(&Order::new_limit(
+                Oid::new(1),
+                OrderSide::Sell,
+                Timestamp::new(1),
+                10.5.into(),
+                5.into(),
+            ))
+            .unwrap();
+        // level already on the book is moved to the new store
+        let mut order_book = order_book.with_level_store(DenseLevels::new(
+            Price::new(9.0),
+            Price::new(11.0),
+            Price::new(0.5),
+        ));
+        for (id, side, price) in [
+            (2, OrderSide::Buy, 10.0),
+            (3, OrderSide::Buy, 9.5),
+            (4, OrderSide::Sell, 11.0),
+            (5, OrderSide::Sell, 13.0),
+        ] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+        let buy = Order::new_market(Oid::new(6), OrderSide::Buy, Timestamp::new(6), 12.into());
+        let trade = order_book.execute(&buy).unwrap();
+        assert_eq!(trade.filled_volume, 12.into());
+        assert_eq!(order_book.get_best_sell(), Some(13.0.into()));
+        order_book.cancel_order(Oid::new(2)).unwrap();
+        assert_eq!(order_book.get_best_buy(), Some(9.5.into()));
+        assert!(order_book.check_integrity().is_ok());
+    }
+
+    #[test]
+    fn test_book_with_sorted_levels() {
+        let mut order_book = OrderBook::<SortedLevels>::empty();
+        for (id, side, price) in [
+            (1, OrderSide::Buy, 10.0),
+            (2, OrderSide::Buy, 9.0),
+            (3, OrderSide::Sell, 11.0),
+            (4, OrderSide::Sell, 12.0),
+        ] {
+            let order = Order::new_limit(
+                Oid::new(id),
+                side,
+                Timestamp::new(id),
+                price.into(),
+                5.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+        let buy = Order::new_market(Oid::new(5), OrderSide::Buy, Timestamp::new(5), 7.into());
+        let trade = order_book.execute(&buy).unwrap();
+        assert_eq!(trade.filled_volume, 7.into());
+        order_book.cancel_order(Oid::new(1)).unwrap();
+        assert_eq!(order_book.get_best_buy(), Some(9.0.into()));
+        assert_eq!(order_book.get_best_sell(), Some(12.0.into()));
+        assert_eq!(order_book.level_count(OrderSide::Sell), 1);
+        assert!(order_book.check_integrity().is_ok());
+    }
+}
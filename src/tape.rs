@@ -0,0 +1,318 @@
+//!
+//! Trade tape. Bounded record of the most recent trades on the book,
+//! once full the oldest trade is dropped.
+//! Volume and time weighted average prices are computed over the trades on the tape
+//! within a window that ends at the most recent trade.
+//!
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::{Execution, LevelStore, Oid, Order, OrderBook, OrderSide, Price, Timestamp, Volume};
+
+/// Trade printed on the tape
+#[derive(Debug, Clone, PartialEq)]
+pub struct TapeEntry {
+    pub timestamp: Timestamp,
+    /// side of the order that took liquidity
+    pub aggressor_side: OrderSide,
+    pub price: Price,
+    pub volume: Volume,
+    pub buy_order_id: Oid,
+    pub sell_order_id: Oid,
+    /// event sequence of the fill
+    pub sequence: u64,
+}
+
+/// Ring buffer of the most recent trades
+#[derive(Debug, Clone)]
+pub struct TradeTape {
+    entries: VecDeque<TapeEntry>,
+    capacity: usize,
+}
+
+impl TradeTape {
+    pub fn new(capacity: usize) -> Self {
+        TradeTape {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// trades from the oldest to the most recent
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &TapeEntry> {
+        self.entries.iter()
+    }
+
+    /// most recent trade
+    pub fn last(&self) -> Option<&TapeEntry> {
+        self.entries.back()
+    }
+
+    /// volume weighted average price of the trades within the window
+    pub fn vwap(&self, window: u64) -> Option<Price> {
+        let start = self.window_start(window)?;
+        let (mut notional, mut volume) = (0i128, 0i128);
+        for entry in self
+            .entries
+            .iter()
+            .filter(|e| u64::from(e.timestamp) >= start)
+        {
+            notional += entry.price.mantissa() as i128 * *entry.volume as i128;
+            volume += *entry.volume as i128;
+        }
+        (volume > 0).then(|| Price::from_mantissa((notional / volume) as i64))
+    }
+
+    /// time weighted average price within the window, each price is weighted by the time
+    /// until the next trade, price of the last trade before the window holds at its start.
+    /// Trades at a single instant are averaged with equal weights.
+    pub fn twap(&self, window: u64) -> Option<Price> {
+        let start = self.window_start(window)?;
+        let first = self
+            .entries
+            .iter()
+            .rposition(|e| u64::from(e.timestamp) < start)
+            .unwrap_or(0);
+        let entries = self.entries.range(first..).collect::<Vec<_>>();
+
+        let (mut weighted, mut duration) = (0i128, 0i128);
+        for pair in entries.windows(2) {
+            let from = u64::from(pair[0].timestamp).max(start);
+            let to = u64::from(pair[1].timestamp).max(start);
+            let held = to.saturating_sub(from) as i128;
+            weighted += pair[0].price.mantissa() as i128 * held;
+            duration += held;
+        }
+        if duration > 0 {
+            return Some(Price::from_mantissa((weighted / duration) as i64));
+        }
+        let in_window = entries
+            .iter()
+            .filter(|e| u64::from(e.timestamp) >= start)
+            .map(|e| e.price.mantissa() as i128)
+            .collect::<Vec<_>>();
+        let sum = in_window.iter().sum::<i128>();
+        Some(Price::from_mantissa((sum / in_window.len() as i128) as i64))
+    }
+
+    /// start of the window ending at the most recent trade
+    fn window_start(&self, window: u64) -> Option<u64> {
+        let end = u64::from(self.last()?.timestamp);
+        Some(end.saturating_sub(window))
+    }
+
+    pub(crate) fn record(&mut self, entry: TapeEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
+
+impl TapeEntry {
+    /// trade of the aggressive order against the resting order
+    pub(crate) fn from_execution(order: &Order, execution: &Execution) -> Self {
+        let (buy_order_id, sell_order_id) = match order.side {
+            OrderSide::Buy => (order.id, execution.order_id),
+            OrderSide::Sell => (execution.order_id, order.id),
+        };
+        TapeEntry {
+            timestamp: order.timestamp,
+            aggressor_side: order.side,
+            price: execution.price,
+            volume: execution.volume,
+            buy_order_id,
+            sell_order_id,
+            sequence: execution.sequence,
+        }
+    }
+}
+
+impl<S: LevelStore> OrderBook<S> {
+    /// record trades on a tape that keeps at most capacity most recent trades
+    pub fn with_trade_tape(mut self, capacity: usize) -> Self {
+        self.trade_tape = Some(TradeTape::new(capacity));
+        self
+    }
+
+    pub fn trade_tape(&self) -> Option<&TradeTape> {
+        self.trade_tape.as_ref()
+    }
+
+    /// publish the trade to the tape, the delta feed and the candles
+    pub(crate) fn record_trade(&mut self, entry: TapeEntry) {
+        self.last_trade = Some((entry.price, entry.volume));
+        if let Some(feed) = &mut self.delta_feed {
+            feed.record_trade(&entry);
+        }
+        if let Some(candles) = &mut self.candles {
+            candles.record(&entry);
+        }
+        if let Some(tape) = &mut self.trade_tape {
+            tape.record(entry);
+        }
+    }
+
+    /// sequence and publish trades of the aggressive order, resting orders were already filled
+    pub(crate) fn record_executions(&mut self, order: &Order, executions: &mut [Execution]) {
+        let mut leaves = order.volume;
+        for i in 0..executions.len() {
+            let execution = &mut executions[i];
+            execution.sequence = self.next_event_sequence();
+            let execution = execution.clone();
+            self.record_trade(TapeEntry::from_execution(order, &execution));
+
+            // resting order can be executed again by the same order, i.e. next iceberg tranche
+            let later = executions[i + 1..]
+                .iter()
+                .filter(|e| e.order_id == execution.order_id)
+                .map(|e| e.volume)
+                .sum::<Volume>();
+            let resting_leaves = self
+                .orders
+                .get(&execution.order_id)
+                .map_or(Volume::ZERO, |resting| resting.remaining_volume())
+                + later;
+            let (price, volume, sequence) = (execution.price, execution.volume, execution.sequence);
+            self.record_fill(execution.order_id, price, volume, resting_leaves, sequence);
+            leaves -= volume;
+            self.record_fill(order.id, price, volume, leaves, sequence);
+        }
+    }
+
+    /// volume weighted average price of the trades on the tape within the window
+    pub fn vwap(&self, window: u64) -> Option<Price> {
+        self.trade_tape.as_ref()?.vwap(window)
+    }
+
+    /// time weighted average price of the trades on the tape within the window
+    pub fn twap(&self, window: u64) -> Option<Price> {
+        self.trade_tape.as_ref()?.twap(window)
+    }
+
+    /// at most n most recent trades, from the oldest to the most recent
+    pub fn recent_trades(&self, n: usize) -> Vec<&TapeEntry> {
+        let Some(tape) = &self.trade_tape else {
+            return Vec::new();
+        };
+        tape.iter().skip(tape.len().saturating_sub(n)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests_tape {
+    use crate::*;
+
+    #[test]
+    fn test_trade_tape() {
+        let mut order_book = OrderBook::default().with_trade_tape(2);
+        for id in 1..=3 {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                (10.0 + id as f64).into(),
+                5.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        let order = Order::new_market(Oid::new(4), OrderSide::Buy, Timestamp::new(4), 15.into());
+        order_book.execute(&order).unwrap();
+
+        let trades = order_book.recent_trades(5);
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].price, Price::new(12.0));
+        assert_eq!(trades[1].price, Price::new(13.0));
+        assert_eq!(trades[1].aggressor_side, OrderSide::Buy);
+        assert_eq!(trades[1].buy_order_id, Oid::new(4));
+        assert_eq!(trades[1].sell_order_id, Oid::new(3));
+        assert_eq!(order_book.recent_trades(1)[0].price, Price::new(13.0));
+    }
+
+    #[test]
+    fn test_trade_tape_records_crossed_book_fills() {
+        let mut order_book = OrderBook::default().with_trade_tape(10);
+        order_book
+            .transition(TradingState::Halted, Timestamp::new(0))
+            .unwrap();
+        let sell = LimitOrder::new(
+            Oid::new(1),
+            OrderSide::Sell,
+            Timestamp::new(1),
+            10.0.into(),
+            5.into(),
+        );
+        let buy = LimitOrder::new(
+            Oid::new(2),
+            OrderSide::Buy,
+            Timestamp::new(2),
+            11.0.into(),
+            5.into(),
+        );
+        order_book.add_order(sell).unwrap();
+        order_book.add_order(buy).unwrap();
+        order_book
+            .transition(TradingState::Open, Timestamp::new(3))
+            .unwrap();
+        order_book.find_and_fill_best_orders().unwrap();
+
+        let trade = order_book.trade_tape().unwrap().last().unwrap();
+        assert_eq!(trade.aggressor_side, OrderSide::Buy);
+        assert_eq!(trade.price, Price::new(10.0));
+        assert_eq!(trade.timestamp, Timestamp::new(2));
+    }
+
+    #[test]
+    fn test_vwap_and_twap() {
+        let mut order_book = OrderBook::default().with_trade_tape(10);
+        assert_eq!(order_book.vwap(100), None);
+        for (id, price) in [(1, 10.0), (2, 11.0), (3, 12.0), (4, 16.0)] {
+            let order = LimitOrder::new(
+                Oid::new(id),
+                OrderSide::Sell,
+                Timestamp::new(id),
+                price.into(),
+                id.into(),
+            );
+            order_book.add_order(order).unwrap();
+        }
+        for (id, timestamp, volume) in [(5, 0, 1), (6, 100, 2), (7, 130, 3), (8, 200, 4)] {
+            let order = Order::new_market(
+                Oid::new(id),
+                OrderSide::Buy,
+                Timestamp::new(timestamp),
+                volume.into(),
+            );
+            order_book.execute(&order).unwrap();
+        }
+
+        // trades at 100, 130 and 200
+        assert_eq!(
+            order_book.vwap(100),
+            Some(Price::from_mantissa(1_355_555_555))
+        );
+        // 11 holds from 110 to 130, 12 from 130 to 200
+        assert_eq!(
+            order_book.twap(90),
+            Some(Price::from_mantissa(1_177_777_777))
+        );
+        // only the last trade is in the window
+        assert_eq!(order_book.twap(0), Some(Price::new(16.0)));
+    }
+}
@@ -0,0 +1,312 @@
+//!
+//! Property based testing support.
+//! `Arbitrary` implementations generate orders on a narrow price grid, so generated orders
+//! often cross and rest at the same levels. `ReferenceBook` is a slow but obviously correct
+//! model of the book, `check_against_reference` applies the same commands to the book and to
+//! the model and reports the first difference.
+//!
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use proptest::prelude::*;
+
+use crate::utils::PRICE_SCALE;
+use crate::{
+    Command, DepthLevel, DepthSnapshot, Execution, Oid, Order, OrderBook, OrderSide, OrderType,
+    Price, TimeInForce, Timestamp, Trade, Volume,
+};
+
+/// number of ticks of the generated prices
+const PRICE_TICKS: i64 = 21;
+/// generated prices are from 9.90 to 10.10 with 0.01 tick
+const PRICE_TICK: i64 = PRICE_SCALE / 100;
+const MIN_PRICE: i64 = 990 * PRICE_TICK;
+const MAX_VOLUME: u64 = 100;
+
+impl Arbitrary for Price {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Price>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (0..PRICE_TICKS)
+            .prop_map(|tick| Price::from_mantissa(MIN_PRICE + tick * PRICE_TICK))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Volume {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Volume>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (1..=MAX_VOLUME).prop_map(Volume::new).boxed()
+    }
+}
+
+impl Arbitrary for OrderSide {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<OrderSide>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![Just(OrderSide::Buy), Just(OrderSide::Sell)].boxed()
+    }
+}
+
+impl Arbitrary for Order {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Order>;
+
+    /// good till cancel and immediate or cancel limit orders, and market orders
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<u64>(),
+            any::<OrderSide>(),
+            any::<Price>(),
+            any::<Volume>(),
+            0..4u8,
+        )
+            .prop_map(|(id, side, price, volume, kind)| {
+                let (id, timestamp) = (Oid::new(id), Timestamp::new(id));
+                match kind {
+                    0 => Order::new_market(id, side, timestamp, volume),
+                    1 => Order::new_limit(id, side, timestamp, price, volume)
+                        .with_time_in_force(TimeInForce::ImmediateOrCancel),
+                    _ => Order::new_limit(id, side, timestamp, price, volume),
+                }
+            })
+            .boxed()
+    }
+}
+
+/// sequence of up to max_len commands, orders have ids and timestamps increasing from 1,
+/// cancels and amends target ids of any order of the sequence, including unknown ones
+pub fn commands(max_len: usize) -> impl Strategy<Value = Vec<Command>> {
+    let command = prop_oneof![
+        6 => any::<Order>().prop_map(Command::Execute),
+        2 => (1..=max_len as u64).prop_map(|id| Command::Cancel(Oid::new(id))),
+        2 => (1..=max_len as u64, any::<Price>(), any::<Volume>()).prop_map(
+            |(id, price, volume)| Command::Amend {
+                order_id: Oid::new(id),
+                price,
+                volume,
+            }
+        ),
+    ];
+    proptest::collection::vec(command, 0..=max_len).prop_map(|mut commands| {
+        for (i, command) in commands.iter_mut().enumerate() {
+            if let Command::Execute(order) = command {
+                order.id = Oid::new(i as u64 + 1);
+                order.timestamp = Timestamp::new(i as u64 + 1);
+            }
+        }
+        commands
+    })
+}
+
+#[derive(Debug, Clone)]
+struct RestingOrder {
+    id: Oid,
+    side: OrderSide,
+    price: Price,
+    volume: Volume,
+    filled: Volume,
+}
+
+impl RestingOrder {
+    fn remaining(&self) -> Volume {
+        self.volume - self.filled
+    }
+}
+
+/// Reference model of the book
+/// resting orders are kept in a vec in time priority and every match scans all of them
+#[derive(Debug, Default, Clone)]
+pub struct ReferenceBook {
+    orders: Vec<RestingOrder>,
+}
+
+impl ReferenceBook {
+    pub fn new() -> Self {
+        ReferenceBook::default()
+    }
+
+    /// execute the order, supports plain limit and market orders
+    pub fn execute(&mut self, order: &Order) -> Trade {
+        let mut trade = Trade::new(order.id, order.volume);
+        while !trade.remaining_volume().is_zero() {
+            let crosses = |resting: &RestingOrder| match (order.side, order.price) {
+                (_, None) => true,
+                (OrderSide::Buy, Some(price)) => resting.price <= price,
+                (OrderSide::Sell, Some(price)) => resting.price >= price,
+            };
+            // best price first, then the earliest order
+            let best = self
+                .orders
+                .iter()
+                .enumerate()
+                .filter(|(_, resting)| resting.side != order.side && crosses(resting))
+                .min_by_key(|(i, resting)| match order.side {
+                    OrderSide::Buy => (resting.price, *i),
+                    OrderSide::Sell => (Price::MAX - resting.price, *i),
+                })
+                .map(|(i, _)| i);
+            let Some(i) = best else {
+                break;
+            };
+            let resting = &mut self.orders[i];
+            let volume = resting.remaining().min(trade.remaining_volume());
+            trade.add_execution(Execution::new(resting.id, resting.price, volume));
+            resting.filled += volume;
+            if resting.remaining().is_zero() {
+                self.orders.remove(i);
+            }
+        }
+        let rests =
+            order.kind == OrderType::Limit && order.time_in_force == TimeInForce::GoodTillCancel;
+        match order.price {
+            Some(price) if rests && !trade.remaining_volume().is_zero() => {
+                self.orders.push(RestingOrder {
+                    id: order.id,
+                    side: order.side,
+                    price,
+                    volume: order.volume,
+                    filled: trade.filled_volume,
+                })
+            }
+            _ => trade.cancel_remaining(),
+        }
+        trade
+    }
+
+    /// returns false if the order is not on the book
+    pub fn cancel(&mut self, id: Oid) -> bool {
+        let before = self.orders.len();
+        self.orders.retain(|o| o.id != id);
+        self.orders.len() != before
+    }
+
+    /// reducing volume at the same price keeps the time priority, otherwise the order moves
+    /// to the back, returns false if the order is not on the book or volume is not above filled
+    pub fn amend(&mut self, id: Oid, price: Price, volume: Volume) -> bool {
+        let Some(i) = self.orders.iter().position(|o| o.id == id) else {
+            return false;
+        };
+        let order = &mut self.orders[i];
+        if volume <= order.filled {
+            return false;
+        }
+        let keeps_priority = price == order.price && volume <= order.volume;
+        order.price = price;
+        order.volume = volume;
+        if !keeps_priority {
+            let order = self.orders.remove(i);
+            self.orders.push(order);
+        }
+        true
+    }
+
+    /// every level of both sides
+    pub fn depth(&self) -> DepthSnapshot {
+        let levels = |side: OrderSide| {
+            let mut levels: Vec<DepthLevel> = Vec::new();
+            for order in self.orders.iter().filter(|o| o.side == side) {
+                match levels.iter_mut().find(|l| l.price == order.price) {
+                    Some(level) => {
+                        level.volume += order.remaining();
+                        level.order_count += 1;
+                    }
+                    None => levels.push(DepthLevel {
+                        price: order.price,
+                        volume: order.remaining(),
+                        order_count: 1,
+                    }),
+                }
+            }
+            levels.sort_by_key(|l| l.price);
+            if side == OrderSide::Buy {
+                levels.reverse();
+            }
+            levels
+        };
+        DepthSnapshot {
+            bids: levels(OrderSide::Buy),
+            asks: levels(OrderSide::Sell),
+        }
+    }
+}
+
+/// apply the commands to a new book and to the reference model,
+/// returns the description of the first step where they differ
+pub fn check_against_reference(commands: &[Command]) -> Result<(), String> {
+    let mut book = OrderBook::default();
+    let mut reference = ReferenceBook::new();
+    for (step, command) in commands.iter().enumerate() {
+        match command {
+            Command::Execute(order) => {
+                let expected = reference.execute(order);
+                let trade = book
+                    .execute(order)
+                    .map_err(|e| format!("step {step}: {command:?} failed with {e}"))?;
+                // model does not sequence the events
+                let unsequenced = |executions: &[Execution]| {
+                    executions
+                        .iter()
+                        .map(|e| (e.order_id, e.price, e.volume, e.hidden))
+                        .collect::<Vec<_>>()
+                };
+                if unsequenced(&trade.executions) != unsequenced(&expected.executions)
+                    || trade.cancelled_volume != expected.cancelled_volume
+                {
+                    return Err(format!(
+                        "step {step}: {command:?} traded {trade:?}, expected {expected:?}"
+                    ));
+                }
+            }
+            Command::Cancel(id) => {
+                let (cancelled, expected) = (book.cancel_order(*id).is_ok(), reference.cancel(*id));
+                if cancelled != expected {
+                    return Err(format!(
+                        "step {step}: {command:?} succeeded {cancelled}, expected {expected}"
+                    ));
+                }
+            }
+            Command::Amend {
+                order_id,
+                price,
+                volume,
+            } => {
+                let amended = book.amend_order(*order_id, *price, *volume).is_ok();
+                let expected = reference.amend(*order_id, *price, *volume);
+                if amended != expected {
+                    return Err(format!(
+                        "step {step}: {command:?} succeeded {amended}, expected {expected}"
+                    ));
+                }
+            }
+            Command::PurgeExpired(_) => {}
+        }
+        let (depth, expected) = (book.depth(usize::MAX), reference.depth());
+        if depth != expected {
+            return Err(format!(
+                "step {step}: {command:?} left depth {depth:?}, expected {expected:?}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_testing {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_book_matches_reference(commands in commands(60)) {
+            if let Err(difference) = check_against_reference(&commands) {
+                prop_assert!(false, "{}", difference);
+            }
+        }
+    }
+}
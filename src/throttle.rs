@@ -0,0 +1,118 @@
+//!
+//! Rate limiting of order entry. Every owner has a token bucket that is refilled at the
+//! configured rate, each message takes one token and messages without a token are throttled.
+//! Time is taken from the order timestamps, which are expected to be in milliseconds.
+//!
+
+use hashbrown::HashMap;
+
+use crate::{OwnerId, Timestamp};
+
+// tokens are kept in thousandths, so refill of partial tokens is exact with millisecond timestamps
+const TOKEN: u64 = 1_000;
+
+/// Rate limit of each owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub messages_per_second: u64,
+    /// number of messages that can be sent at once after a quiet period
+    pub burst: u64,
+}
+
+impl RateLimit {
+    pub fn new(messages_per_second: u64, burst: u64) -> Self {
+        RateLimit {
+            messages_per_second,
+            burst,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: u64,
+    updated: u64,
+}
+
+/// Token buckets of all owners
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: HashMap<OwnerId, Bucket>,
+    throttled: HashMap<OwnerId, u64>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        RateLimiter {
+            limit,
+            buckets: HashMap::new(),
+            throttled: HashMap::new(),
+        }
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// take a token of the owner, returns false if the message is throttled
+    pub fn try_acquire(&mut self, owner: OwnerId, now: Timestamp) -> bool {
+        let now = u64::from(now);
+        let capacity = self.limit.burst.saturating_mul(TOKEN);
+        let bucket = self.buckets.entry(owner).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        // timestamps going backwards do not refill the bucket
+        let elapsed = now.saturating_sub(bucket.updated);
+        let refill = elapsed.saturating_mul(self.limit.messages_per_second);
+        bucket.tokens = bucket.tokens.saturating_add(refill).min(capacity);
+        bucket.updated = bucket.updated.max(now);
+
+        if bucket.tokens < TOKEN {
+            *self.throttled.entry(owner).or_default() += 1;
+            return false;
+        }
+        bucket.tokens -= TOKEN;
+        true
+    }
+
+    /// number of throttled messages of the owner
+    pub fn throttled(&self, owner: OwnerId) -> u64 {
+        self.throttled.get(&owner).copied().unwrap_or(0)
+    }
+
+    /// number of throttled messages of all owners
+    pub fn total_throttled(&self) -> u64 {
+        self.throttled.values().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests_throttle {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let (alice, bob) = (OwnerId::new(1), OwnerId::new(2));
+        let mut limiter = RateLimiter::new(RateLimit::new(10, 2));
+        assert!(limiter.try_acquire(alice, Timestamp::new(1_000)));
+        assert!(limiter.try_acquire(alice, Timestamp::new(1_000)));
+        assert!(!limiter.try_acquire(alice, Timestamp::new(1_050)));
+        // other owners have their own bucket
+        assert!(limiter.try_acquire(bob, Timestamp::new(1_050)));
+
+        // one token is refilled every 100 ms
+        assert!(limiter.try_acquire(alice, Timestamp::new(1_100)));
+        assert!(!limiter.try_acquire(alice, Timestamp::new(1_150)));
+        assert!(!limiter.try_acquire(alice, Timestamp::new(900)));
+        // bucket does not grow above the burst
+        assert!(limiter.try_acquire(alice, Timestamp::new(60_000)));
+        assert!(limiter.try_acquire(alice, Timestamp::new(60_000)));
+        assert!(!limiter.try_acquire(alice, Timestamp::new(60_000)));
+
+        assert_eq!(limiter.throttled(alice), 4);
+        assert_eq!(limiter.throttled(bob), 0);
+        assert_eq!(limiter.total_throttled(), 4);
+    }
+}
@@ -0,0 +1,135 @@
+//!
+//! Helpers for fixed point decimal numbers
+//!
+
+use core::fmt::{Formatter, Result as FmtResult};
+
+/// number of decimal places of the price
+pub const PRICE_DECIMALS: u32 = 8;
+/// price is stored as integer multiplied by this scale
+pub const PRICE_SCALE: i64 = 10i64.pow(PRICE_DECIMALS);
+
+/// number of decimal places of the volume, volumes are whole units
+/// unless the `fractional-volume` feature is enabled
+#[cfg(not(feature = "fractional-volume"))]
+pub const VOLUME_DECIMALS: u32 = 0;
+#[cfg(feature = "fractional-volume")]
+pub const VOLUME_DECIMALS: u32 = 8;
+/// volume is stored as integer multiplied by this scale
+pub const VOLUME_SCALE: u64 = 10u64.pow(VOLUME_DECIMALS);
+
+/// convert f64 to scaled integer, rounding to the nearest value
+/// values out of range saturate to i64 min/max
+pub fn scale_f64(value: f64, scale: i64) -> i64 {
+    // f64::round is not available without std, round half away from zero by hand
+    let scaled = value * scale as f64;
+    let truncated = scaled as i64;
+    let fraction = scaled - truncated as f64;
+    if fraction >= 0.5 {
+        truncated.saturating_add(1)
+    } else if fraction <= -0.5 {
+        truncated.saturating_sub(1)
+    } else {
+        truncated
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseScaledError {
+    Invalid,
+    TooManyDecimals,
+    OutOfRange,
+}
+
+/// parse decimal string into integer scaled by 10^decimals
+pub fn parse_scaled(s: &str, decimals: u32) -> Result<i64, ParseScaledError> {
+    let s = s.trim();
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(ParseScaledError::Invalid);
+    }
+    if !integer
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(ParseScaledError::Invalid);
+    }
+    if fraction.len() > decimals as usize {
+        return Err(ParseScaledError::TooManyDecimals);
+    }
+
+    let mut value: i64 = 0;
+    let fraction_padded = fraction
+        .chars()
+        .chain(core::iter::repeat('0'))
+        .take(decimals as usize);
+    for c in integer.chars().chain(fraction_padded) {
+        let digit = c.to_digit(10).ok_or(ParseScaledError::Invalid)? as i64;
+        value = value
+            .checked_mul(10)
+            .and_then(|v| v.checked_add(digit))
+            .ok_or(ParseScaledError::OutOfRange)?;
+    }
+    Ok(if negative { -value } else { value })
+}
+
+/// write integer scaled by 10^decimals as a decimal string
+/// trailing zeros of the fraction are trimmed, unless the precision of the formatter is given,
+/// then the value is rounded half away from zero to that many decimal places
+pub fn fmt_scaled(f: &mut Formatter, value: i128, decimals: u32) -> FmtResult {
+    // precision above the number of decimal places only adds zeros, so it is capped
+    let (value, decimals) = match f.precision().map(|precision| precision.min(18) as u32) {
+        Some(precision) if precision < decimals => {
+            let divisor = 10i128.pow(decimals - precision);
+            let half = divisor / 2;
+            let rounded = if value < 0 {
+                (value - half) / divisor
+            } else {
+                (value + half) / divisor
+            };
+            (rounded, precision)
+        }
+        Some(precision) => (value * 10i128.pow(precision - decimals), precision),
+        None => (value, decimals),
+    };
+    let sign = if value < 0 { "-" } else { "" };
+    let scale = 10i128.pow(decimals);
+    let (units, fraction) = (value.abs() / scale, value.abs() % scale);
+    if decimals == 0 {
+        return write!(f, "{sign}{units}");
+    }
+    let digits = alloc::format!("{fraction:0width$}", width = decimals as usize);
+    let digits = match f.precision() {
+        Some(_) => digits.as_str(),
+        None => digits.trim_end_matches('0'),
+    };
+    if digits.is_empty() {
+        write!(f, "{sign}{units}")
+    } else {
+        write!(f, "{sign}{units}.{digits}")
+    }
+}
+
+#[cfg(test)]
+mod tests_utils {
+    use super::*;
+
+    #[test]
+    fn test_parse_scaled() {
+        assert_eq!(parse_scaled("21.0453", 4), Ok(210453));
+        assert_eq!(parse_scaled("-1.5", 2), Ok(-150));
+        assert_eq!(parse_scaled("100", 2), Ok(10000));
+        assert_eq!(parse_scaled(".5", 1), Ok(5));
+        assert_eq!(
+            parse_scaled("1.555", 2),
+            Err(ParseScaledError::TooManyDecimals)
+        );
+        assert_eq!(parse_scaled("1a", 2), Err(ParseScaledError::Invalid));
+        assert_eq!(parse_scaled("", 2), Err(ParseScaledError::Invalid));
+    }
+}
@@ -0,0 +1,207 @@
+//!
+//! JavaScript bindings of the book, built with `wasm-bindgen` for `wasm32-unknown-unknown`.
+//! Prices and volumes are JavaScript numbers, order ids and timestamps are BigInts.
+//! Build with `cargo rustc --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib`.
+//!
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Oid, Order, OrderBook, OrderBookError, OrderSide, Price, Timestamp, Trade, Volume};
+
+/// Order book exported to JavaScript as `OrderBook`
+#[wasm_bindgen(js_name = OrderBook)]
+#[derive(Debug, Default)]
+pub struct WasmOrderBook {
+    book: OrderBook,
+}
+
+/// Top of the book exported to JavaScript as `Bbo`
+#[wasm_bindgen(js_name = Bbo)]
+#[derive(Debug, Clone, Copy)]
+pub struct WasmBbo {
+    #[wasm_bindgen(js_name = bidPrice)]
+    pub bid_price: Option<f64>,
+    #[wasm_bindgen(js_name = bidVolume)]
+    pub bid_volume: f64,
+    #[wasm_bindgen(js_name = askPrice)]
+    pub ask_price: Option<f64>,
+    #[wasm_bindgen(js_name = askVolume)]
+    pub ask_volume: f64,
+}
+
+/// Outcome of the order exported to JavaScript as `Trade`
+#[wasm_bindgen(js_name = Trade)]
+#[derive(Debug, Clone, Copy)]
+pub struct WasmTrade {
+    #[wasm_bindgen(js_name = filledVolume)]
+    pub filled_volume: f64,
+    /// volume of a market or immediate order that could not be filled
+    #[wasm_bindgen(js_name = cancelledVolume)]
+    pub cancelled_volume: f64,
+    #[wasm_bindgen(js_name = executionCount)]
+    pub execution_count: usize,
+}
+
+impl From<Trade> for WasmTrade {
+    fn from(trade: Trade) -> Self {
+        WasmTrade {
+            filled_volume: trade.filled_volume.to_f64(),
+            cancelled_volume: trade.cancelled_volume.to_f64(),
+            execution_count: trade.executions.len(),
+        }
+    }
+}
+
+fn side(is_buy: bool) -> OrderSide {
+    if is_buy {
+        OrderSide::Buy
+    } else {
+        OrderSide::Sell
+    }
+}
+
+/// volume must be a non negative multiple of the smallest volume increment
+fn volume(value: f64) -> Result<Volume, OrderBookError> {
+    let volume = Volume::from_f64(value);
+    if value < 0.0 || volume.to_f64() != value {
+        return Err(OrderBookError::OrderCannotBePlaced(format!(
+            "volume must be a non negative multiple of {}",
+            Volume::from_mantissa(1)
+        )));
+    }
+    Ok(volume)
+}
+
+fn js_error(error: OrderBookError) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+impl WasmOrderBook {
+    fn execute(&mut self, order: Order) -> Result<WasmTrade, OrderBookError> {
+        self.book.execute(&order).map(WasmTrade::from)
+    }
+
+    /// flat list of price, volume and order count of the top n levels
+    fn depth_levels(&self, side: OrderSide, n: usize) -> Vec<f64> {
+        self.book
+            .iter_levels(side)
+            .take(n)
+            .flat_map(|level| {
+                [
+                    level.price().to_f64(),
+                    level.total_volume().to_f64(),
+                    level.order_count() as f64,
+                ]
+            })
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_class = OrderBook)]
+impl WasmOrderBook {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        WasmOrderBook::default()
+    }
+
+    /// execute the limit order, the remainder rests on the book
+    #[wasm_bindgen(js_name = addLimitOrder)]
+    pub fn add_limit_order(
+        &mut self,
+        id: u64,
+        is_buy: bool,
+        price: f64,
+        volume: f64,
+        timestamp: u64,
+    ) -> Result<WasmTrade, JsError> {
+        let order = Order::new_limit(
+            Oid::new(id),
+            side(is_buy),
+            Timestamp::new(timestamp),
+            Price::new(price),
+            self::volume(volume).map_err(js_error)?,
+        );
+        self.execute(order).map_err(js_error)
+    }
+
+    /// execute the market order, the unfilled remainder is cancelled
+    #[wasm_bindgen(js_name = addMarketOrder)]
+    pub fn add_market_order(
+        &mut self,
+        id: u64,
+        is_buy: bool,
+        volume: f64,
+        timestamp: u64,
+    ) -> Result<WasmTrade, JsError> {
+        let order = Order::new_market(
+            Oid::new(id),
+            side(is_buy),
+            Timestamp::new(timestamp),
+            self::volume(volume).map_err(js_error)?,
+        );
+        self.execute(order).map_err(js_error)
+    }
+
+    pub fn cancel(&mut self, id: u64) -> Result<(), JsError> {
+        self.book
+            .cancel_order(Oid::new(id))
+            .map(|_| ())
+            .map_err(|e| js_error(e.into()))
+    }
+
+    pub fn bbo(&self) -> WasmBbo {
+        let bbo = self.book.best_bid_ask();
+        WasmBbo {
+            bid_price: bbo.bid_price.map(|price| price.to_f64()),
+            bid_volume: bbo.bid_volume.to_f64(),
+            ask_price: bbo.ask_price.map(|price| price.to_f64()),
+            ask_volume: bbo.ask_volume.to_f64(),
+        }
+    }
+
+    /// top n bid levels as a flat list of price, volume and order count
+    #[wasm_bindgen(js_name = depthBids)]
+    pub fn depth_bids(&self, n: usize) -> Vec<f64> {
+        self.depth_levels(OrderSide::Buy, n)
+    }
+
+    /// top n ask levels as a flat list of price, volume and order count
+    #[wasm_bindgen(js_name = depthAsks)]
+    pub fn depth_asks(&self, n: usize) -> Vec<f64> {
+        self.depth_levels(OrderSide::Sell, n)
+    }
+
+    #[wasm_bindgen(js_name = orderCount)]
+    pub fn order_count(&self) -> usize {
+        self.book.order_count()
+    }
+}
+
+#[cfg(test)]
+mod tests_wasm {
+    use super::*;
+
+    #[test]
+    fn test_wasm_order_book() {
+        let mut book = WasmOrderBook::new();
+        book.add_limit_order(1, true, 10.5, 5.0, 1).unwrap();
+        book.add_limit_order(2, true, 10.0, 3.0, 2).unwrap();
+        book.add_limit_order(3, false, 11.0, 4.0, 3).unwrap();
+        let trade = book.add_market_order(4, false, 6.0, 4).unwrap();
+        assert_eq!(trade.filled_volume, 6.0);
+        assert_eq!(trade.execution_count, 2);
+
+        let bbo = book.bbo();
+        assert_eq!(bbo.bid_price, Some(10.0));
+        assert_eq!(bbo.bid_volume, 2.0);
+        assert_eq!(bbo.ask_price, Some(11.0));
+        assert_eq!(book.depth_asks(5), vec![11.0, 4.0, 1.0]);
+        book.cancel(3).unwrap();
+        assert!(book.depth_asks(5).is_empty());
+        assert_eq!(book.order_count(), 1);
+    }
+}